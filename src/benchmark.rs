@@ -0,0 +1,980 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{app::AppExit, prelude::*, utils::Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera_controller::{cut_to, CameraController, CameraTransition, SmoothCuts},
+    frame_pacing::FrameLimiter,
+    gpu_timing::GpuFrameTime,
+    loading::SceneReady,
+    render_features::{FeatureToggle, RenderFeatures},
+    render_scale::RenderScale,
+    scene_preset::ScenePreset,
+    system_info::SystemInfo,
+};
+
+/// Summary of a set of frame times, all in milliseconds.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct FrameStats {
+    pub avg: f32,
+    pub min: f32,
+    pub max: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+}
+
+impl FrameStats {
+    pub fn from_samples(samples: &[f32]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        // Nearest-rank percentile
+        let percentile = |p: f32| {
+            let rank = ((p / 100.0) * sorted.len() as f32).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        Some(Self {
+            avg: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+        })
+    }
+}
+
+impl std::fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "avg {:.2}ms, min {:.2}ms, max {:.2}ms, p50 {:.2}ms, p95 {:.2}ms, p99 {:.2}ms",
+            self.avg, self.min, self.max, self.p50, self.p95, self.p99
+        )
+    }
+}
+
+/// Two sided 95% Student's t critical values for 1 to 30 degrees of freedom, above that the
+/// normal distribution's 1.96 is close enough.
+const T_95: [f32; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// How a metric varies between benchmark runs, from one average per run, in milliseconds.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RunStats {
+    pub mean: f32,
+    /// Sample standard deviation of the per run averages.
+    pub std_dev: f32,
+    /// Half the width of the 95% confidence interval of the mean.
+    pub ci95: f32,
+}
+
+impl RunStats {
+    /// Needs at least two runs for the spread.
+    pub fn from_runs(values: &[f32]) -> Option<Self> {
+        if values.len() < 2 {
+            return None;
+        }
+        let n = values.len() as f32;
+        let mean = values.iter().sum::<f32>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / (n - 1.0);
+        let std_dev = variance.sqrt();
+        let t = T_95.get(values.len() - 2).copied().unwrap_or(1.96);
+        Some(Self {
+            mean,
+            std_dev,
+            ci95: t * std_dev / n.sqrt(),
+        })
+    }
+}
+
+impl std::fmt::Display for RunStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "mean {:.2}ms ± {:.2}ms (95% CI, {:.1}%), std dev {:.2}ms",
+            self.mean,
+            self.ci95,
+            self.ci95 / self.mean * 100.0,
+            self.std_dev
+        )
+    }
+}
+
+/// Results of the benchmark runs since startup, written with `--bench-output` and compared with
+/// `--bench-baseline`.
+#[derive(Default, Serialize, Deserialize)]
+pub struct BenchmarkResults {
+    /// Missing from results written before it was recorded.
+    #[serde(default)]
+    pub system: Option<SystemInfo>,
+    pub configurations: Vec<ConfigurationResults>,
+}
+
+/// Results of one benchmark run, or of one pass of the matrix.
+#[derive(Serialize, Deserialize)]
+pub struct ConfigurationResults {
+    /// Features and render scale of a matrix pass, like "ssao on, taa off", otherwise "default".
+    pub label: String,
+    pub render_scale: f32,
+    /// Lights in the scene, including the ones added by `--many-lights`. Missing from results
+    /// written before it was recorded.
+    #[serde(default)]
+    pub lights: usize,
+    /// Rendered with `--deferred`.
+    #[serde(default)]
+    pub deferred: bool,
+    pub positions: Vec<PositionResults>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PositionResults {
+    pub name: String,
+    pub cpu: Option<FrameStats>,
+    pub gpu: Option<FrameStats>,
+    /// Spread of the average between runs, with `--bench-runs`.
+    pub cpu_runs: Option<RunStats>,
+    pub gpu_runs: Option<RunStats>,
+}
+
+impl BenchmarkResults {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Results of an earlier run to compare against, see `--bench-baseline`.
+#[derive(Resource)]
+pub struct BenchmarkBaseline {
+    pub results: BenchmarkResults,
+    /// Percentage a metric can get slower by before it counts as a regression.
+    pub threshold_percent: f32,
+}
+
+/// Per frame times recorded at a single camera position.
+#[derive(Default)]
+pub struct StepSamples {
+    pub cpu: Vec<f32>,
+    pub gpu: Vec<f32>,
+    /// Frame times while the camera was moving to this position, with `--smooth-cuts`.
+    pub transition_cpu: Vec<f32>,
+    pub transition_gpu: Vec<f32>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Moving to the camera position.
+    Transition,
+    Warmup,
+    Measure,
+}
+
+impl Phase {
+    fn name(&self) -> &'static str {
+        match self {
+            Phase::Transition => "transition",
+            Phase::Warmup => "warmup",
+            Phase::Measure => "measure",
+        }
+    }
+}
+
+/// A single frame of a benchmark run, written to the `--bench-trace` CSV.
+struct TraceFrame {
+    /// Which of the `--bench-runs` sweeps the frame is from, counting from zero.
+    run: usize,
+    step: usize,
+    /// Seconds since the benchmark started.
+    time: f32,
+    phase: Phase,
+    cpu: f32,
+    gpu: Option<f32>,
+}
+
+struct BenchmarkRun {
+    /// Index of the camera position currently being measured.
+    step: usize,
+    /// Seconds spent at the current camera position, including warmup.
+    step_elapsed: f32,
+    elapsed: f32,
+    steps: Vec<StepSamples>,
+    /// Samples of the sweeps finished so far, with `--bench-runs`.
+    completed: Vec<Vec<StepSamples>>,
+    trace: Vec<TraceFrame>,
+}
+
+/// State of the benchmark sweep, started with the B key.
+#[derive(Resource)]
+pub struct Benchmark {
+    /// Seconds to render at each camera position before recording, so shader compilation and
+    /// texture uploads triggered by the new view don't end up in the results.
+    pub warmup_secs: f32,
+    /// Seconds to record at each camera position after the warmup.
+    pub step_secs: f32,
+    /// Write every frame of the run, including warmup frames, to this CSV file.
+    pub trace_path: Option<PathBuf>,
+    /// Times to sweep through the camera positions. With more than one the first is discarded
+    /// as a warmup, and the spread of the others is reported.
+    pub runs: usize,
+    /// Configuration the next run is recorded under, set by the matrix.
+    pub label: String,
+    /// Write the results to this JSON file once the benchmark exits.
+    pub output_path: Option<PathBuf>,
+    pub results: BenchmarkResults,
+    run: Option<BenchmarkRun>,
+    /// Samples of the last completed run, until taken with [`Benchmark::take_results`].
+    finished: Option<Vec<StepSamples>>,
+}
+
+impl Default for Benchmark {
+    fn default() -> Self {
+        Self::new(1.0, 2.0)
+    }
+}
+
+impl Benchmark {
+    pub fn new(warmup_secs: f32, step_secs: f32) -> Self {
+        Self {
+            warmup_secs: warmup_secs.max(0.0),
+            step_secs: step_secs.max(0.0),
+            trace_path: None,
+            runs: 1,
+            label: String::from("default"),
+            output_path: None,
+            results: default(),
+            run: None,
+            finished: None,
+        }
+    }
+
+    pub fn start(&mut self) {
+        println!(
+            "Starting Benchmark with {:.1}s warmup and {:.1}s per step, {} runs",
+            self.warmup_secs, self.step_secs, self.runs
+        );
+        self.run = Some(BenchmarkRun {
+            step: 0,
+            step_elapsed: 0.0,
+            elapsed: 0.0,
+            steps: Vec::new(),
+            completed: Vec::new(),
+            trace: Vec::new(),
+        });
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+
+    pub fn take_results(&mut self) -> Option<Vec<StepSamples>> {
+        self.finished.take()
+    }
+}
+
+/// Starts the benchmark once the scene is ready and exits when it finishes, see `--bench`.
+#[derive(Resource, Default)]
+pub struct AutoBenchmark {
+    started: bool,
+}
+
+/// Runs the benchmark once for every on/off combination of a set of features and prints a
+/// table comparing them, then exits. See `--bench-matrix`.
+#[derive(Resource)]
+pub struct BenchmarkMatrix {
+    features: Vec<FeatureToggle>,
+    /// Render scales to run every feature combination at, none to keep the current scale.
+    pub scales: Vec<f32>,
+    /// Features and render scale before the matrix started, restored when it's done.
+    initial: Option<(RenderFeatures, RenderScale)>,
+    /// Index of the combination being benchmarked.
+    pass: usize,
+    /// Features of the current pass, like "SSAO on, TAA off".
+    label: String,
+    /// Configuration, average cpu frame time, and average gpu frame time of each finished pass.
+    results: Vec<(String, Option<f32>, Option<f32>)>,
+}
+
+impl BenchmarkMatrix {
+    pub fn new(features: Vec<FeatureToggle>) -> Self {
+        Self {
+            features,
+            scales: Vec::new(),
+            initial: None,
+            pass: 0,
+            label: String::new(),
+            results: Vec::new(),
+        }
+    }
+
+    /// Parses a comma separated list of feature names, like `ssao,taa,shadows`.
+    pub fn from_arg(arg: &str) -> anyhow::Result<Self> {
+        let features = arg
+            .split(',')
+            .map(|name| {
+                FeatureToggle::from_arg(name)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown feature {name} in --bench-matrix"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self::new(features))
+    }
+
+    /// Parses a comma separated list of render scales, like `0.5,1.0`.
+    pub fn with_scales(mut self, arg: &str) -> anyhow::Result<Self> {
+        self.scales = arg
+            .split(',')
+            .map(|scale| {
+                scale.trim().parse().map_err(|_| {
+                    anyhow::anyhow!("Invalid render scale {scale} in --bench-matrix-scales")
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(self)
+    }
+
+    fn pass_count(&self) -> usize {
+        (1 << self.features.len()) * self.scales.len().max(1)
+    }
+
+    /// Sets the features and render scale for the current pass. Every feature is on in the first
+    /// pass, and every combination of features runs at each scale.
+    fn configure(&self, features: &mut RenderFeatures, render_scale: &mut RenderScale) -> String {
+        let mut label = Vec::new();
+        for (i, feature) in self.features.iter().enumerate() {
+            let on = self.pass & (1 << i) == 0;
+            feature.set(features, on);
+            label.push(format!(
+                "{} {}",
+                feature.name(),
+                if on { "on" } else { "off" }
+            ));
+        }
+        if let Some(scale) = self.scales.get(self.pass >> self.features.len()) {
+            *render_scale = RenderScale::new(*scale);
+            label.push(format!("scale {:.2}", render_scale.scale));
+        }
+        label.join(", ")
+    }
+}
+
+pub struct BenchmarkPlugin;
+impl Plugin for BenchmarkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Benchmark>().add_systems(
+            Update,
+            (auto_benchmark, benchmark_matrix, benchmark).chain(),
+        );
+    }
+}
+
+/// Writes the results with `--bench-output` and compares them with `--bench-baseline`, then
+/// exits, with an error if the results couldn't be written or anything regressed.
+fn finish_benchmark(
+    bench: &Benchmark,
+    baseline: Option<&BenchmarkBaseline>,
+    app_exit: &mut EventWriter<AppExit>,
+) {
+    let mut failed = false;
+    if let Some(path) = &bench.output_path {
+        match bench.results.save(path) {
+            Ok(_) => println!("Wrote benchmark results to {}", path.display()),
+            Err(e) => {
+                println!(
+                    "Failed to write benchmark results to {}: {e}",
+                    path.display()
+                );
+                failed = true;
+            }
+        }
+    }
+    if let Some(baseline) = baseline {
+        failed |= !compare_results(
+            &baseline.results,
+            &bench.results,
+            baseline.threshold_percent,
+        );
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    app_exit.send(AppExit);
+}
+
+fn auto_benchmark(
+    auto: Option<ResMut<AutoBenchmark>>,
+    scene_ready: Res<SceneReady>,
+    mut bench: ResMut<Benchmark>,
+    baseline: Option<Res<BenchmarkBaseline>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let Some(mut auto) = auto else {
+        return;
+    };
+    if !auto.started {
+        if scene_ready.ready && !bench.is_running() {
+            bench.start();
+            auto.started = true;
+        }
+    } else if !bench.is_running() {
+        finish_benchmark(&bench, baseline.as_deref(), &mut app_exit);
+    }
+}
+
+fn benchmark_matrix(
+    matrix: Option<ResMut<BenchmarkMatrix>>,
+    scene_ready: Res<SceneReady>,
+    mut bench: ResMut<Benchmark>,
+    mut features: ResMut<RenderFeatures>,
+    mut render_scale: ResMut<RenderScale>,
+    baseline: Option<Res<BenchmarkBaseline>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let Some(mut matrix) = matrix else {
+        return;
+    };
+    if !scene_ready.ready || bench.is_running() {
+        return;
+    }
+    if matrix.initial.is_none() {
+        matrix.initial = Some((*features, *render_scale));
+    } else {
+        let Some(steps) = bench.take_results() else {
+            return;
+        };
+        let average = |samples: Vec<f32>| FrameStats::from_samples(&samples).map(|s| s.avg);
+        let cpu = average(steps.iter().flat_map(|s| s.cpu.clone()).collect());
+        let gpu = average(steps.iter().flat_map(|s| s.gpu.clone()).collect());
+        let label = std::mem::take(&mut matrix.label);
+        matrix.results.push((label, cpu, gpu));
+        matrix.pass += 1;
+    }
+    if matrix.pass < matrix.pass_count() {
+        matrix.label = matrix.configure(&mut features, &mut render_scale);
+        println!(
+            "Benchmark matrix pass {}/{}: {}",
+            matrix.pass + 1,
+            matrix.pass_count(),
+            matrix.label
+        );
+        bench.label = matrix.label.clone();
+        bench.start();
+    } else {
+        print_matrix(&matrix.results);
+        if let Some((initial_features, initial_scale)) = matrix.initial {
+            *features = initial_features;
+            *render_scale = initial_scale;
+        }
+        finish_benchmark(&bench, baseline.as_deref(), &mut app_exit);
+    }
+}
+
+fn print_matrix(results: &[(String, Option<f32>, Option<f32>)]) {
+    let width = results
+        .iter()
+        .map(|(label, ..)| label.len())
+        .max()
+        .unwrap_or(0)
+        .max("Configuration".len());
+    let ms = |time: &Option<f32>| time.map_or("n/a".to_string(), |t| format!("{t:.2}ms"));
+    println!(
+        "{:width$}  {:>9}  {:>9}",
+        "Configuration", "cpu avg", "gpu avg"
+    );
+    for (label, cpu, gpu) in results {
+        println!("{label:width$}  {:>9}  {:>9}", ms(cpu), ms(gpu));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn benchmark(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut camera: Query<
+        (
+            Entity,
+            &mut Transform,
+            Option<&CameraController>,
+            Has<CameraTransition>,
+        ),
+        With<Camera3d>,
+    >,
+    mut bench: ResMut<Benchmark>,
+    preset: Res<ScenePreset>,
+    gpu_frame_time: Res<GpuFrameTime>,
+    smooth_cuts: Option<Res<SmoothCuts>>,
+    render_scale: Res<RenderScale>,
+    limiter: Option<Res<FrameLimiter>>,
+    system_info: Option<Res<SystemInfo>>,
+    lights: Query<(), Or<(With<PointLight>, With<SpotLight>, With<DirectionalLight>)>>,
+    features: Res<RenderFeatures>,
+    time: Res<Time>,
+    mut last_frame: Local<Option<Instant>>,
+) {
+    // Frame times come from the wall clock, time only advances by a fixed step per frame with
+    // `--fixed-timestep`
+    let now = Instant::now();
+    let frame_time = last_frame.replace(now).map(|last| now - last);
+    let Ok((entity, mut transform, controller, moving)) = camera.get_single_mut() else {
+        return;
+    };
+    let gamepad_start = controller.is_some_and(|controller| {
+        gamepads.iter().any(|gamepad| {
+            gamepad_buttons.just_pressed(GamepadButton::new(gamepad, controller.gamepad_benchmark))
+        })
+    });
+    if (input.just_pressed(KeyCode::KeyB) || gamepad_start) && !bench.is_running() {
+        bench.start();
+    }
+    let (warmup_secs, step_secs, runs) = (bench.warmup_secs, bench.step_secs, bench.runs);
+    let trace_path = bench.trace_path.clone();
+    let label = bench.label.clone();
+    let Some(run) = &mut bench.run else {
+        return;
+    };
+    if run.steps.len() <= run.step {
+        if let Some((_, position)) = preset.cameras.get(run.step) {
+            cut_to(
+                &mut commands,
+                entity,
+                &mut transform,
+                *position,
+                smooth_cuts.as_deref(),
+            );
+            run.steps.push(StepSamples::default());
+            run.step_elapsed = 0.0;
+        } else if run.completed.len() + 1 < runs {
+            let steps = std::mem::take(&mut run.steps);
+            run.completed.push(steps);
+            run.step = 0;
+            println!("Finished benchmark run {}/{runs}", run.completed.len());
+        } else {
+            let steps = std::mem::take(&mut run.steps);
+            run.completed.push(steps);
+            // The first run also warms up caches and clocks, so it's left out when there are more
+            let kept = &run.completed[(run.completed.len() > 1) as usize..];
+            let steps = merge_runs(kept);
+            if let Some(system_info) = &system_info {
+                println!("System: {}", **system_info);
+            }
+            println!("Render scale: {:.2}", render_scale.scale);
+            println!("Lights: {}", lights.iter().count());
+            println!(
+                "Renderer: {}",
+                if features.deferred {
+                    "deferred"
+                } else {
+                    "forward"
+                }
+            );
+            let late_threshold = limiter.as_ref().map(|limiter| limiter.late_threshold_ms());
+            print_results(&preset, &steps, late_threshold);
+            let positions = position_results(&preset, &steps, kept);
+            if kept.len() > 1 {
+                print_run_stats(&positions, kept);
+            }
+            let configuration = ConfigurationResults {
+                label,
+                render_scale: render_scale.scale,
+                lights: lights.iter().count(),
+                deferred: features.deferred,
+                positions,
+            };
+            if let Some(path) = trace_path {
+                match write_trace(&path, &preset, &run.trace, system_info.as_deref()) {
+                    Ok(_) => println!("Wrote frame trace to {}", path.display()),
+                    Err(e) => println!("Failed to write frame trace to {}: {e}", path.display()),
+                }
+            }
+            bench.run = None;
+            bench.results.system = system_info.as_deref().cloned();
+            bench.results.configurations.push(configuration);
+            bench.finished = Some(steps);
+            if let Some((_, position)) = preset.cameras.first() {
+                cut_to(&mut commands, entity, &mut transform, *position, None);
+            }
+        }
+        // The frame time of the frame the camera moved on still reflects the previous position
+        return;
+    }
+    run.elapsed += time.delta_seconds();
+    let phase = if moving {
+        Phase::Transition
+    } else {
+        run.step_elapsed += time.delta_seconds();
+        if run.step_elapsed <= warmup_secs {
+            Phase::Warmup
+        } else {
+            Phase::Measure
+        }
+    };
+    let cpu = frame_time.unwrap_or(time.delta()).as_secs_f32() * 1000.0;
+    let gpu = gpu_frame_time.get();
+    if let Some(samples) = run.steps.last_mut() {
+        let recorded = match phase {
+            Phase::Transition => Some((&mut samples.transition_cpu, &mut samples.transition_gpu)),
+            Phase::Warmup => None,
+            Phase::Measure => Some((&mut samples.cpu, &mut samples.gpu)),
+        };
+        if let Some((cpu_samples, gpu_samples)) = recorded {
+            cpu_samples.push(cpu);
+            if let Some(gpu) = gpu {
+                gpu_samples.push(gpu);
+            }
+        }
+    }
+    run.trace.push(TraceFrame {
+        run: run.completed.len(),
+        step: run.step,
+        time: run.elapsed,
+        phase,
+        cpu,
+        gpu,
+    });
+    if run.step_elapsed >= warmup_secs + step_secs {
+        run.step += 1;
+    }
+}
+
+/// Frames that took longer than `threshold_ms`.
+fn late_frames(samples: &[f32], threshold_ms: f32) -> usize {
+    samples.iter().filter(|time| **time > threshold_ms).count()
+}
+
+/// `late_threshold` is the frame time past which a frame counts as late, without a frame cap
+/// frames that take more than 1.5x the median are late.
+fn print_results(preset: &ScenePreset, steps: &[StepSamples], late_threshold: Option<f32>) {
+    for ((name, _), samples) in preset.cameras.iter().zip(steps) {
+        if let Some(cpu) = FrameStats::from_samples(&samples.cpu) {
+            println!("{name} cpu: {cpu}");
+            let late = late_frames(&samples.cpu, late_threshold.unwrap_or(cpu.p50 * 1.5));
+            println!(
+                "{name} late frames: {late}/{} ({:.1}%)",
+                samples.cpu.len(),
+                late as f32 / samples.cpu.len() as f32 * 100.0
+            );
+        }
+        match FrameStats::from_samples(&samples.gpu) {
+            Some(gpu) => println!("{name} gpu: {gpu}"),
+            None => println!("{name} gpu: n/a"),
+        }
+        if let Some(cpu) = FrameStats::from_samples(&samples.transition_cpu) {
+            println!("{name} transition cpu: {cpu}");
+        }
+        if let Some(gpu) = FrameStats::from_samples(&samples.transition_gpu) {
+            println!("{name} transition gpu: {gpu}");
+        }
+    }
+    let all_cpu: Vec<f32> = steps.iter().flat_map(|s| s.cpu.iter().copied()).collect();
+    if let Some(cpu) = FrameStats::from_samples(&all_cpu) {
+        println!("Benchmark avg cpu frame time: {:.2}ms", cpu.avg);
+    }
+}
+
+/// Samples of every run combined per camera position.
+fn merge_runs(runs: &[Vec<StepSamples>]) -> Vec<StepSamples> {
+    let mut merged: Vec<StepSamples> = Vec::new();
+    for steps in runs {
+        merged.resize_with(merged.len().max(steps.len()), StepSamples::default);
+        for (merged, samples) in merged.iter_mut().zip(steps) {
+            merged.cpu.extend_from_slice(&samples.cpu);
+            merged.gpu.extend_from_slice(&samples.gpu);
+            merged
+                .transition_cpu
+                .extend_from_slice(&samples.transition_cpu);
+            merged
+                .transition_gpu
+                .extend_from_slice(&samples.transition_gpu);
+        }
+    }
+    merged
+}
+
+/// Frame time stats of each camera position, with their spread between `runs`.
+fn position_results(
+    preset: &ScenePreset,
+    steps: &[StepSamples],
+    runs: &[Vec<StepSamples>],
+) -> Vec<PositionResults> {
+    let average = |samples: &[f32]| FrameStats::from_samples(samples).map(|stats| stats.avg);
+    preset
+        .cameras
+        .iter()
+        .zip(steps)
+        .enumerate()
+        .map(|(i, ((name, _), samples))| {
+            let per_run = |gpu: bool| -> Vec<f32> {
+                runs.iter()
+                    .filter_map(|steps| {
+                        let samples = steps.get(i)?;
+                        average(if gpu { &samples.gpu } else { &samples.cpu })
+                    })
+                    .collect()
+            };
+            PositionResults {
+                name: name.clone(),
+                cpu: FrameStats::from_samples(&samples.cpu),
+                gpu: FrameStats::from_samples(&samples.gpu),
+                cpu_runs: RunStats::from_runs(&per_run(false)),
+                gpu_runs: RunStats::from_runs(&per_run(true)),
+            }
+        })
+        .collect()
+}
+
+/// Spread of each camera position's average frame time between runs.
+fn print_run_stats(positions: &[PositionResults], runs: &[Vec<StepSamples>]) {
+    println!("Across {} runs, the first run discarded:", runs.len());
+    for position in positions {
+        if let Some(cpu) = &position.cpu_runs {
+            println!("{} cpu: {cpu}", position.name);
+        }
+        if let Some(gpu) = &position.gpu_runs {
+            println!("{} gpu: {gpu}", position.name);
+        }
+    }
+    let totals: Vec<f32> = runs
+        .iter()
+        .filter_map(|steps| {
+            let all: Vec<f32> = steps.iter().flat_map(|s| s.cpu.iter().copied()).collect();
+            FrameStats::from_samples(&all).map(|stats| stats.avg)
+        })
+        .collect();
+    if let Some(cpu) = RunStats::from_runs(&totals) {
+        println!("Benchmark cpu frame time: {cpu}");
+    }
+}
+
+/// Prints how each position's frame times changed from `baseline`, in red where they got slower
+/// by more than `threshold_percent` and green where they got faster by as much. Returns false
+/// if anything got slower by more than that.
+fn compare_results(
+    baseline: &BenchmarkResults,
+    current: &BenchmarkResults,
+    threshold_percent: f32,
+) -> bool {
+    const RED: &str = "\x1b[31m";
+    const GREEN: &str = "\x1b[32m";
+    const RESET: &str = "\x1b[0m";
+    let mut rows = Vec::new();
+    for configuration in &current.configurations {
+        let Some(base_configuration) = baseline.configurations.iter().find(|base| {
+            base.label == configuration.label && base.render_scale == configuration.render_scale
+        }) else {
+            println!(
+                "No baseline for {} at render scale {:.2}",
+                configuration.label, configuration.render_scale
+            );
+            continue;
+        };
+        for position in &configuration.positions {
+            let Some(base_position) = base_configuration
+                .positions
+                .iter()
+                .find(|base| base.name == position.name)
+            else {
+                continue;
+            };
+            let metrics = [
+                (
+                    "cpu avg",
+                    position.cpu.map(|s| s.avg),
+                    base_position.cpu.map(|s| s.avg),
+                ),
+                (
+                    "cpu p95",
+                    position.cpu.map(|s| s.p95),
+                    base_position.cpu.map(|s| s.p95),
+                ),
+                (
+                    "gpu avg",
+                    position.gpu.map(|s| s.avg),
+                    base_position.gpu.map(|s| s.avg),
+                ),
+            ];
+            for (metric, current, base) in metrics {
+                if let (Some(current), Some(base)) = (current, base) {
+                    let label = format!("{} {}", configuration.label, position.name);
+                    rows.push((label, metric, base, current, (current / base - 1.0) * 100.0));
+                }
+            }
+        }
+    }
+
+    let width = rows
+        .iter()
+        .map(|(label, ..)| label.len())
+        .max()
+        .unwrap_or(0)
+        .max("Position".len());
+    println!(
+        "{:width$}  {:7}  {:>9}  {:>9}  {:>8}",
+        "Position", "Metric", "Baseline", "Current", "Delta"
+    );
+    let mut regressed = 0;
+    for (label, metric, base, current, delta) in &rows {
+        let color = if *delta > threshold_percent {
+            regressed += 1;
+            RED
+        } else if *delta < -threshold_percent {
+            GREEN
+        } else {
+            ""
+        };
+        let reset = if color.is_empty() { "" } else { RESET };
+        println!(
+            "{label:width$}  {metric:7}  {:>7.2}ms  {:>7.2}ms  {color}{:>+7.1}%{reset}",
+            base, current, delta
+        );
+    }
+    if regressed > 0 {
+        println!("{regressed} metrics regressed by more than {threshold_percent}%");
+    } else {
+        println!("No regressions over {threshold_percent}%");
+    }
+    regressed == 0
+}
+
+/// The system info goes in `#` comment lines before the header.
+fn write_trace(
+    path: &Path,
+    preset: &ScenePreset,
+    trace: &[TraceFrame],
+    system_info: Option<&SystemInfo>,
+) -> anyhow::Result<()> {
+    let mut csv = String::new();
+    if let Some(info) = system_info {
+        writeln!(csv, "# gpu: {}", info.gpu)?;
+        writeln!(csv, "# driver: {}", info.driver)?;
+        writeln!(csv, "# backend: {}", info.backend)?;
+        writeln!(csv, "# os: {}", info.os)?;
+        writeln!(csv, "# cpu: {}", info.cpu)?;
+        writeln!(csv, "# memory_gb: {:.1}", info.memory_gb)?;
+        writeln!(
+            csv,
+            "# git_hash: {}",
+            info.git_hash.as_deref().unwrap_or_default()
+        )?;
+    }
+    csv.push_str("frame,time_s,position,phase,cpu_ms,gpu_ms,run\n");
+    for (frame, f) in trace.iter().enumerate() {
+        let position = preset.cameras.get(f.step).map(|(name, _)| name.as_str());
+        let gpu = f.gpu.map(|gpu| format!("{gpu:.3}")).unwrap_or_default();
+        writeln!(
+            csv,
+            "{frame},{:.4},{},{},{:.3},{gpu},{}",
+            f.time,
+            position.unwrap_or_default(),
+            f.phase.name(),
+            f.cpu,
+            f.run,
+        )?;
+    }
+    fs::write(path, csv)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(label: &str, cpu_ms: f32, gpu_ms: Option<f32>) -> BenchmarkResults {
+        BenchmarkResults {
+            system: None,
+            configurations: vec![ConfigurationResults {
+                label: label.to_string(),
+                render_scale: 1.0,
+                lights: 0,
+                deferred: false,
+                positions: vec![PositionResults {
+                    name: "atrium".to_string(),
+                    cpu: FrameStats::from_samples(&[cpu_ms]),
+                    gpu: gpu_ms.and_then(|ms| FrameStats::from_samples(&[ms])),
+                    cpu_runs: None,
+                    gpu_runs: None,
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn same_results_pass() {
+        let baseline = results("default", 10.0, Some(8.0));
+        assert!(compare_results(
+            &baseline,
+            &results("default", 10.0, Some(8.0)),
+            5.0
+        ));
+    }
+
+    #[test]
+    fn slowdown_within_threshold_passes() {
+        let baseline = results("default", 10.0, Some(8.0));
+        assert!(compare_results(
+            &baseline,
+            &results("default", 10.4, Some(8.3)),
+            5.0
+        ));
+    }
+
+    #[test]
+    fn slowdown_over_threshold_fails() {
+        let baseline = results("default", 10.0, Some(8.0));
+        assert!(!compare_results(
+            &baseline,
+            &results("default", 12.0, Some(8.0)),
+            5.0
+        ));
+        assert!(!compare_results(
+            &baseline,
+            &results("default", 10.0, Some(9.0)),
+            5.0
+        ));
+    }
+
+    #[test]
+    fn speedup_passes() {
+        let baseline = results("default", 10.0, Some(8.0));
+        assert!(compare_results(
+            &baseline,
+            &results("default", 5.0, Some(4.0)),
+            5.0
+        ));
+    }
+
+    #[test]
+    fn missing_gpu_times_are_skipped() {
+        let baseline = results("default", 10.0, None);
+        assert!(compare_results(
+            &baseline,
+            &results("default", 10.0, Some(50.0)),
+            5.0
+        ));
+    }
+
+    #[test]
+    fn configurations_without_a_baseline_are_skipped() {
+        let baseline = results("ssao on", 10.0, Some(8.0));
+        assert!(compare_results(
+            &baseline,
+            &results("ssao off", 20.0, Some(16.0)),
+            5.0
+        ));
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    camera_controller::{cut_to, SmoothCuts},
+    scene_preset::ScenePreset,
+};
+
+pub const BOOKMARKS_PATH: &str = "camera_bookmarks.ron";
+
+pub const BOOKMARK_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// Camera transforms bound to the number keys. Ctrl+number stores the current camera transform,
+/// number recalls it. Bookmarks are saved per scene preset in [`BOOKMARKS_PATH`] and start out
+/// as the preset's camera positions.
+#[derive(Resource)]
+pub struct CameraBookmarks {
+    pub path: PathBuf,
+    pub scene: String,
+    pub slots: Vec<Option<Transform>>,
+}
+
+type BookmarkFile = BTreeMap<String, Vec<Option<Transform>>>;
+
+impl CameraBookmarks {
+    pub fn load(path: impl Into<PathBuf>, preset: &ScenePreset) -> Self {
+        let path = path.into();
+        let mut slots = read_bookmark_file(&path)
+            .remove(&preset.name)
+            .unwrap_or_else(|| preset.cameras.iter().map(|(_, t)| Some(*t)).collect());
+        slots.resize(BOOKMARK_KEYS.len(), None);
+        Self {
+            path,
+            scene: preset.name.clone(),
+            slots,
+        }
+    }
+
+    /// Writes the bookmarks for this scene, keeping any other scenes already in the file.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let mut file = read_bookmark_file(&self.path);
+        file.insert(self.scene.clone(), self.slots.clone());
+        fs::write(
+            &self.path,
+            ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())?,
+        )?;
+        Ok(())
+    }
+}
+
+fn read_bookmark_file(path: &Path) -> BookmarkFile {
+    match fs::read_to_string(path) {
+        Ok(contents) => ron::from_str(&contents).unwrap_or_else(|e| {
+            warn!(
+                "Ignoring invalid camera bookmarks in {}: {}",
+                path.display(),
+                e
+            );
+            BookmarkFile::default()
+        }),
+        Err(_) => BookmarkFile::default(),
+    }
+}
+
+pub struct CameraBookmarksPlugin;
+impl Plugin for CameraBookmarksPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_bookmarks)
+            .add_systems(Update, bookmark_input);
+    }
+}
+
+fn load_bookmarks(mut commands: Commands, preset: Res<ScenePreset>) {
+    commands.insert_resource(CameraBookmarks::load(BOOKMARKS_PATH, &preset));
+}
+
+fn bookmark_input(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    mut camera: Query<(Entity, &mut Transform), With<Camera3d>>,
+    bookmarks: Option<ResMut<CameraBookmarks>>,
+    smooth_cuts: Option<Res<SmoothCuts>>,
+) {
+    let Some(mut bookmarks) = bookmarks else {
+        return;
+    };
+    let Ok((entity, mut transform)) = camera.get_single_mut() else {
+        return;
+    };
+    let ctrl = input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    for (i, key) in BOOKMARK_KEYS.iter().enumerate() {
+        if !input.just_pressed(*key) {
+            continue;
+        }
+        if ctrl {
+            bookmarks.slots[i] = Some(*transform);
+            match bookmarks.save() {
+                Ok(_) => info!("Saved camera bookmark {}", i + 1),
+                Err(e) => warn!("Failed to save camera bookmarks: {}", e),
+            }
+        } else if let Some(bookmark) = bookmarks.slots[i] {
+            cut_to(
+                &mut commands,
+                entity,
+                &mut transform,
+                bookmark,
+                smooth_cuts.as_deref(),
+            );
+        }
+    }
+}
@@ -1,16 +1,46 @@
 // Copied from https://github.com/DGriffin91/bevy_basic_camera
 
+use std::{fs, path::Path};
+
 use bevy::{
     input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
     prelude::*,
+    window::{CursorGrabMode, PrimaryWindow},
 };
+use serde::{Deserialize, Serialize};
+
+/// Vertical field of view range in degrees that [`adjust_fov`] stays within.
+const MIN_FOV: f32 = 10.0;
+const MAX_FOV: f32 = 120.0;
+
+/// Mouse movement is turned into rotation at the rate the controller originally had at 60 fps,
+/// before it stopped depending on the frame rate.
+const MOUSE_SCALE: f32 = 1.0 / 60.0;
+
+/// Range the scroll wheel can scale the movement speed within.
+const MIN_SPEED_MULTIPLIER: f32 = 0.01;
+const MAX_SPEED_MULTIPLIER: f32 = 100.0;
+/// Seconds the movement speed stays on screen after it changes.
+const SPEED_INDICATOR_SECS: f32 = 1.5;
 
 /// Provides basic movement functionality to the attached camera
 #[derive(Component, Clone)]
 pub struct CameraController {
     pub enabled: bool,
     pub initialized: bool,
+    /// Look speed of the mouse and gamepad.
     pub sensitivity: f32,
+    pub invert_y: bool,
+    /// Seconds the mouse movement is smoothed over, 0 turns smoothing off.
+    pub mouse_smoothing: f32,
+    /// Extra sensitivity per 1000 pixels per second of mouse movement, so fast flicks turn
+    /// further than slow ones over the same distance. 0 turns acceleration off.
+    pub mouse_acceleration: f32,
+    /// Capture the cursor on click instead of only looking around while the button is held.
+    /// Escape releases it.
+    pub grab_cursor: bool,
+    /// Smoothed mouse movement in pixels per second.
+    pub mouse_velocity: Vec2,
     pub key_forward: KeyCode,
     pub key_back: KeyCode,
     pub key_left: KeyCode,
@@ -18,18 +48,44 @@ pub struct CameraController {
     pub key_up: KeyCode,
     pub key_down: KeyCode,
     pub key_run: KeyCode,
+    pub key_slow: KeyCode,
+    pub key_record_path: KeyCode,
+    pub key_orbit: KeyCode,
+    pub key_fov_narrower: KeyCode,
+    pub key_fov_wider: KeyCode,
+    /// Degrees the field of view changes by per press.
+    pub fov_step: f32,
     pub mouse_key_enable_mouse: MouseButton,
     pub keyboard_key_enable_mouse: KeyCode,
     pub walk_speed: f32,
-    pub run_speed: f32,
+    /// Speed multiplier while the run key is held.
+    pub run_multiplier: f32,
+    /// Speed multiplier while the slow key is held, for close ups.
+    pub slow_multiplier: f32,
+    /// Scales the walk speed, adjusted with the scroll wheel outside orbit mode.
+    pub speed_multiplier: f32,
+    /// Factor the speed changes by per scroll wheel notch.
+    pub scroll_speed_step: f32,
     pub friction: f32,
     pub pitch: f32,
     pub yaw: f32,
     pub velocity: Vec3,
     pub orbit_focus: Vec3,
     pub orbit_mode: bool,
+    /// Distance in front of the camera to place the focus point when orbit mode is turned on.
+    pub orbit_distance: f32,
+    /// Radians per second to turn around the focus point in orbit mode, for unattended demos.
+    pub orbit_auto_rotate_speed: f32,
     pub scroll_wheel_speed: f32,
     pub lock_y: bool,
+    /// Stick deflections smaller than this are ignored.
+    pub gamepad_deadzone: f32,
+    /// Look speed of the right stick at full deflection.
+    pub gamepad_sensitivity: f32,
+    pub gamepad_up: GamepadButtonType,
+    pub gamepad_down: GamepadButtonType,
+    pub gamepad_run: GamepadButtonType,
+    pub gamepad_benchmark: GamepadButtonType,
 }
 
 impl CameraController {
@@ -46,7 +102,17 @@ impl CameraController {
     {:?} - Up
     {:?} - Down
     {:?} - Run
+    {:?} - Slow
+    Scroll Wheel - Movement Speed
+    {:?} - Start/Stop Recording Camera Path
+    {:?} - Toggle Orbit Mode
+    {:?}/{:?} - Narrower/Wider Field of View
     {:?}/{:?} - EnableMouse
+    Escape - Release Mouse
+    Gamepad: Left Stick - Move, Right Stick - Look
+    {:?}/{:?} - Up/Down
+    {:?} - Run
+    {:?} - Run Benchmark
 ",
             self.key_forward,
             self.key_back,
@@ -55,11 +121,31 @@ impl CameraController {
             self.key_up,
             self.key_down,
             self.key_run,
+            self.key_slow,
+            self.key_record_path,
+            self.key_orbit,
+            self.key_fov_narrower,
+            self.key_fov_wider,
             self.mouse_key_enable_mouse,
             self.keyboard_key_enable_mouse,
+            self.gamepad_up,
+            self.gamepad_down,
+            self.gamepad_run,
+            self.gamepad_benchmark,
         );
         self
     }
+
+    /// Start in orbit mode, turning at `speed` radians per second.
+    pub fn auto_rotate(mut self, speed: f32) -> Self {
+        self.orbit_mode = true;
+        self.orbit_auto_rotate_speed = speed;
+        self
+    }
+
+    fn focus_ahead(&mut self, transform: &Transform) {
+        self.orbit_focus = transform.translation + *transform.forward() * self.orbit_distance;
+    }
 }
 
 impl Default for CameraController {
@@ -68,6 +154,11 @@ impl Default for CameraController {
             enabled: true,
             initialized: false,
             sensitivity: 0.25,
+            invert_y: false,
+            mouse_smoothing: 0.0,
+            mouse_acceleration: 0.0,
+            grab_cursor: false,
+            mouse_velocity: Vec2::ZERO,
             key_forward: KeyCode::KeyW,
             key_back: KeyCode::KeyS,
             key_left: KeyCode::KeyA,
@@ -75,30 +166,53 @@ impl Default for CameraController {
             key_up: KeyCode::KeyE,
             key_down: KeyCode::KeyQ,
             key_run: KeyCode::ShiftLeft,
+            key_slow: KeyCode::AltLeft,
+            key_record_path: KeyCode::KeyR,
+            key_orbit: KeyCode::KeyO,
+            key_fov_narrower: KeyCode::KeyZ,
+            key_fov_wider: KeyCode::KeyX,
+            fov_step: 5.0,
             mouse_key_enable_mouse: MouseButton::Left,
             keyboard_key_enable_mouse: KeyCode::KeyM,
             walk_speed: 5.0,
-            run_speed: 15.0,
+            run_multiplier: 3.0,
+            slow_multiplier: 0.2,
+            speed_multiplier: 1.0,
+            scroll_speed_step: 1.2,
             friction: 0.5,
             pitch: 0.0,
             yaw: 0.0,
             velocity: Vec3::ZERO,
             orbit_focus: Vec3::ZERO,
             orbit_mode: false,
+            orbit_distance: 5.0,
+            orbit_auto_rotate_speed: 0.0,
             scroll_wheel_speed: 0.1,
             lock_y: false,
+            gamepad_deadzone: 0.15,
+            gamepad_sensitivity: 10.0,
+            gamepad_up: GamepadButtonType::RightTrigger,
+            gamepad_down: GamepadButtonType::LeftTrigger,
+            gamepad_run: GamepadButtonType::LeftTrigger2,
+            gamepad_benchmark: GamepadButtonType::Start,
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn camera_controller(
     time: Res<Time>,
     mut mouse_events: EventReader<MouseMotion>,
     mouse_button_input: Res<ButtonInput<MouseButton>>,
     mut scroll_evr: EventReader<MouseWheel>,
     key_input: Res<ButtonInput<KeyCode>>,
-    mut move_toggled: Local<bool>,
-    mut query: Query<(&mut Transform, &mut CameraController), With<Camera>>,
+    gamepads: Res<Gamepads>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    ui: Query<&Interaction>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut cursor_grabbed: Local<bool>,
+    mut query: Query<(&mut Transform, &mut CameraController), With<Camera3d>>,
 ) {
     let dt = time.delta_seconds();
 
@@ -108,6 +222,9 @@ pub fn camera_controller(
             options.yaw = yaw;
             options.pitch = pitch;
             options.initialized = true;
+            if options.orbit_mode {
+                options.focus_ahead(&transform);
+            }
         }
         if !options.enabled {
             return;
@@ -124,6 +241,12 @@ pub fn camera_controller(
                 MouseScrollUnit::Pixel => (),
             }
         }
+        // Orbit mode zooms with the scroll wheel instead
+        if !options.orbit_mode && scroll_distance != 0.0 {
+            options.speed_multiplier = (options.speed_multiplier
+                * options.scroll_speed_step.powf(scroll_distance))
+            .clamp(MIN_SPEED_MULTIPLIER, MAX_SPEED_MULTIPLIER);
+        }
 
         // Handle key input
         let mut axis_input = Vec3::ZERO;
@@ -145,18 +268,76 @@ pub fn camera_controller(
         if key_input.pressed(options.key_down) {
             axis_input.y -= 1.0;
         }
-        if key_input.just_pressed(options.keyboard_key_enable_mouse) {
-            *move_toggled = !*move_toggled;
+        let ui_pressed = ui.iter().any(|i| *i == Interaction::Pressed);
+        let grab = if key_input.just_pressed(options.keyboard_key_enable_mouse) {
+            Some(!*cursor_grabbed)
+        } else if key_input.just_pressed(KeyCode::Escape) && *cursor_grabbed {
+            Some(false)
+        } else if options.grab_cursor
+            && !*cursor_grabbed
+            && mouse_button_input.just_pressed(options.mouse_key_enable_mouse)
+            && !ui_pressed
+        {
+            Some(true)
+        } else {
+            None
+        };
+        if let Some(grab) = grab {
+            *cursor_grabbed = grab;
+            if let Ok(mut window) = windows.get_single_mut() {
+                set_cursor_grab(&mut window, grab);
+            }
+        }
+        if key_input.just_pressed(options.key_orbit) {
+            options.orbit_mode = !options.orbit_mode;
+            if options.orbit_mode {
+                options.focus_ahead(&transform);
+            }
+        }
+
+        // Handle gamepad input
+        let mut gamepad_look = Vec2::ZERO;
+        let mut gamepad_run = false;
+        for gamepad in gamepads.iter() {
+            let axis = |axis_type| {
+                let value = gamepad_axes
+                    .get(GamepadAxis::new(gamepad, axis_type))
+                    .unwrap_or(0.0);
+                if value.abs() < options.gamepad_deadzone {
+                    0.0
+                } else {
+                    value
+                }
+            };
+            let pressed =
+                |button_type| gamepad_buttons.pressed(GamepadButton::new(gamepad, button_type));
+            axis_input.x += axis(GamepadAxisType::LeftStickX);
+            axis_input.z += axis(GamepadAxisType::LeftStickY);
+            if pressed(options.gamepad_up) {
+                axis_input.y += 1.0;
+            }
+            if pressed(options.gamepad_down) {
+                axis_input.y -= 1.0;
+            }
+            gamepad_run |= pressed(options.gamepad_run);
+            gamepad_look += Vec2::new(
+                axis(GamepadAxisType::RightStickX),
+                -axis(GamepadAxisType::RightStickY),
+            );
         }
 
         // Apply movement update
         if axis_input != Vec3::ZERO {
-            let max_speed = if key_input.pressed(options.key_run) {
-                options.run_speed
+            let modifier = if key_input.pressed(options.key_run) || gamepad_run {
+                options.run_multiplier
+            } else if key_input.pressed(options.key_slow) {
+                options.slow_multiplier
             } else {
-                options.walk_speed
+                1.0
             };
-            options.velocity = axis_input.normalize() * max_speed;
+            let max_speed = options.walk_speed * options.speed_multiplier * modifier;
+            // Keeps partial stick deflections, keys always add up to at least 1
+            options.velocity = axis_input.clamp_length_max(1.0) * max_speed;
         } else {
             let friction = options.friction.clamp(0.0, 1.0);
             options.velocity *= 1.0 - friction;
@@ -182,28 +363,50 @@ pub fn camera_controller(
         transform.translation += translation_delta + scroll_translation;
         options.orbit_focus += translation_delta;
 
-        // Handle mouse input
+        // Handle mouse input, unless it's dragging something in the UI
         let mut mouse_delta = Vec2::ZERO;
-        if mouse_button_input.pressed(options.mouse_key_enable_mouse) || *move_toggled {
+        if (mouse_button_input.pressed(options.mouse_key_enable_mouse) && !ui_pressed)
+            || *cursor_grabbed
+        {
             for mouse_event in mouse_events.read() {
                 mouse_delta += mouse_event.delta;
             }
         } else {
             mouse_events.clear();
         }
+        if options.invert_y {
+            mouse_delta.y = -mouse_delta.y;
+        }
+        if dt > 0.0 {
+            let speed = mouse_delta.length() / dt;
+            mouse_delta *= 1.0 + options.mouse_acceleration * speed / 1000.0;
+            if options.mouse_smoothing > 0.0 {
+                let blend = 1.0 - (-dt / options.mouse_smoothing).exp();
+                options.mouse_velocity = options.mouse_velocity.lerp(mouse_delta / dt, blend);
+                mouse_delta = options.mouse_velocity * dt;
+            }
+        }
+        // Mouse movement is already a distance, only the gamepad's stick is a speed
+        let look = mouse_delta * MOUSE_SCALE + gamepad_look * options.gamepad_sensitivity * dt;
 
-        if mouse_delta != Vec2::ZERO {
+        let auto_rotate = if options.orbit_mode {
+            options.orbit_auto_rotate_speed * dt
+        } else {
+            0.0
+        };
+
+        if look != Vec2::ZERO || auto_rotate != 0.0 {
             let sensitivity = if options.orbit_mode {
                 options.sensitivity * 2.0
             } else {
                 options.sensitivity
             };
             let (pitch, yaw) = (
-                (options.pitch - mouse_delta.y * 0.5 * sensitivity * dt).clamp(
+                (options.pitch - look.y * 0.5 * sensitivity).clamp(
                     -0.99 * std::f32::consts::FRAC_PI_2,
                     0.99 * std::f32::consts::FRAC_PI_2,
                 ),
-                options.yaw - mouse_delta.x * sensitivity * dt,
+                options.yaw - look.x * sensitivity - auto_rotate,
             );
 
             // Apply look update
@@ -224,6 +427,444 @@ pub fn camera_controller(
     }
 }
 
+/// Hides the cursor and keeps it in the window while the mouse looks around.
+fn set_cursor_grab(window: &mut Window, grab: bool) {
+    window.cursor.visible = !grab;
+    window.cursor.grab_mode = if !grab {
+        CursorGrabMode::None
+    } else if cfg!(target_os = "macos") {
+        // macOS can only lock the cursor, Windows and X11 can only confine it
+        CursorGrabMode::Locked
+    } else {
+        CursorGrabMode::Confined
+    };
+}
+
+/// A single recorded camera pose, `time` is in seconds from the start of the path.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct CameraKeyframe {
+    pub time: f32,
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+}
+
+impl CameraKeyframe {
+    pub fn new(time: f32, transform: &Transform) -> Self {
+        Self {
+            time,
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+        }
+    }
+}
+
+/// How a path speeds up and slows down between two control points.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathEase {
+    #[default]
+    Linear,
+    In,
+    Out,
+    InOut,
+}
+
+impl PathEase {
+    pub const ALL: [PathEase; 4] = [
+        PathEase::Linear,
+        PathEase::In,
+        PathEase::Out,
+        PathEase::InOut,
+    ];
+
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            PathEase::Linear => t,
+            PathEase::In => t * t,
+            PathEase::Out => t * (2.0 - t),
+            PathEase::InOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// A point placed in the path editor, the path runs through these on a Catmull-Rom spline.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct PathControlPoint {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    /// Meters per second on the way to the next point.
+    pub speed: f32,
+    /// Easing on the way to the next point.
+    pub ease: PathEase,
+}
+
+impl PathControlPoint {
+    pub fn new(transform: &Transform) -> Self {
+        Self {
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            speed: 2.0,
+            ease: PathEase::Linear,
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(Vec3::from_array(self.translation))
+            .with_rotation(Quat::from_array(self.rotation))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct CameraPath {
+    pub keyframes: Vec<CameraKeyframe>,
+    /// Points the keyframes were baked from when the path was made in the path editor, kept so
+    /// it can be edited again. Playback only uses the keyframes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub control_points: Vec<PathControlPoint>,
+}
+
+impl CameraPath {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        fs::write(
+            path,
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?,
+        )?;
+        Ok(())
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map(|k| k.time).unwrap_or(0.0)
+    }
+
+    /// Bakes keyframes `interval` seconds apart from control points, taking each segment at its
+    /// starting point's speed and easing.
+    pub fn from_control_points(points: &[PathControlPoint], interval: f32) -> Self {
+        let mut keyframes = Vec::new();
+        let mut time = 0.0;
+        for i in 0..points.len().saturating_sub(1) {
+            let point = points[i];
+            let segment = |u: f32| {
+                let [p0, p1, p2, p3] = [
+                    points[i.saturating_sub(1)],
+                    points[i],
+                    points[i + 1],
+                    points[(i + 2).min(points.len() - 1)],
+                ]
+                .map(|p| Vec3::from_array(p.translation));
+                let translation = catmull_rom(p0, p1, p2, p3, u);
+                let rotation = Quat::from_array(point.rotation)
+                    .slerp(Quat::from_array(points[i + 1].rotation), u);
+                Transform::from_translation(translation).with_rotation(rotation)
+            };
+            // Approximate the arc length to time the segment
+            let length: f32 = (0..16)
+                .map(|s| {
+                    segment(s as f32 / 16.0)
+                        .translation
+                        .distance(segment((s + 1) as f32 / 16.0).translation)
+                })
+                .sum();
+            let duration = (length / point.speed.max(0.01)).max(interval);
+            let steps = (duration / interval).ceil() as usize;
+            for step in 0..steps {
+                let t = step as f32 / steps as f32;
+                let transform = segment(point.ease.apply(t));
+                keyframes.push(CameraKeyframe::new(time + t * duration, &transform));
+            }
+            time += duration;
+        }
+        if let Some(last) = points.last() {
+            keyframes.push(CameraKeyframe::new(time, &last.transform()));
+        }
+        Self {
+            keyframes,
+            control_points: points.to_vec(),
+        }
+    }
+
+    /// Samples the path at `time` using a Catmull-Rom spline for translation and slerp for rotation.
+    pub fn sample(&self, time: f32) -> Option<Transform> {
+        let keys = &self.keyframes;
+        let last = keys.len().checked_sub(1)?;
+        let i = keys
+            .iter()
+            .rposition(|k| k.time <= time)
+            .unwrap_or(0)
+            .min(last.saturating_sub(1));
+        let k1 = keys[i];
+        let k2 = keys[(i + 1).min(last)];
+        let k0 = keys[i.saturating_sub(1)];
+        let k3 = keys[(i + 2).min(last)];
+        let span = k2.time - k1.time;
+        let u = if span > 0.0 {
+            ((time - k1.time) / span).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let [p0, p1, p2, p3] = [k0, k1, k2, k3].map(|k| Vec3::from_array(k.translation));
+        let translation = catmull_rom(p0, p1, p2, p3, u);
+        let rotation = Quat::from_array(k1.rotation).slerp(Quat::from_array(k2.rotation), u);
+        Some(Transform::from_translation(translation).with_rotation(rotation))
+    }
+}
+
+/// Point `u` of the way from `p1` to `p2` on a Catmull-Rom spline.
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, u: f32) -> Vec3 {
+    0.5 * ((2.0 * p1)
+        + (p2 - p0) * u
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * u * u
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * u * u * u)
+}
+
+/// Records the camera transform while active, toggled with [`CameraController::key_record_path`].
+#[derive(Resource)]
+pub struct CameraPathRecorder {
+    pub output: String,
+    /// Seconds between recorded keyframes.
+    pub interval: f32,
+    recording: Option<(f32, CameraPath)>,
+}
+
+impl Default for CameraPathRecorder {
+    fn default() -> Self {
+        Self {
+            output: String::from("camera_path.ron"),
+            interval: 0.25,
+            recording: None,
+        }
+    }
+}
+
+pub fn record_camera_path(
+    time: Res<Time>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    mut recorder: ResMut<CameraPathRecorder>,
+    query: Query<(&Transform, &CameraController), With<Camera3d>>,
+) {
+    let Ok((transform, options)) = query.get_single() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    if key_input.just_pressed(options.key_record_path) {
+        if let Some((_, path)) = recorder.recording.take() {
+            match path.save(&recorder.output) {
+                Ok(_) => info!(
+                    "Saved camera path with {} keyframes to {}",
+                    path.keyframes.len(),
+                    recorder.output
+                ),
+                Err(e) => warn!("Failed to save camera path: {}", e),
+            }
+            return;
+        }
+        info!("Recording camera path");
+        recorder.recording = Some((now, CameraPath::default()));
+    }
+    let interval = recorder.interval;
+    if let Some((start, path)) = &mut recorder.recording {
+        let t = now - *start;
+        if !matches!(path.keyframes.last(), Some(k) if t - k.time < interval) {
+            path.keyframes.push(CameraKeyframe::new(t, transform));
+        }
+    }
+}
+
+/// Plays back a [`CameraPath`] on the camera, looping at the end.
+#[derive(Resource)]
+pub struct CameraPathPlayback {
+    pub path: CameraPath,
+    pub elapsed: f32,
+    pub looping: bool,
+}
+
+impl CameraPathPlayback {
+    pub fn new(path: CameraPath) -> Self {
+        Self {
+            path,
+            elapsed: 0.0,
+            looping: true,
+        }
+    }
+}
+
+pub fn play_camera_path(
+    time: Res<Time>,
+    playback: Option<ResMut<CameraPathPlayback>>,
+    mut query: Query<(&mut Transform, Option<&mut CameraController>), With<Camera3d>>,
+) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+    let Ok((mut transform, options)) = query.get_single_mut() else {
+        return;
+    };
+    playback.elapsed += time.delta_seconds();
+    let duration = playback.path.duration();
+    if playback.elapsed > duration {
+        if !playback.looping {
+            return;
+        }
+        playback.elapsed = if duration > 0.0 {
+            playback.elapsed % duration
+        } else {
+            0.0
+        };
+    }
+    if let Some(sampled) = playback.path.sample(playback.elapsed) {
+        *transform = sampled;
+        // Pick up the new orientation if the user takes over
+        if let Some(mut options) = options {
+            options.initialized = false;
+        }
+    }
+}
+
+/// Shows the movement speed for a moment after the scroll wheel changes it.
+#[derive(Component)]
+struct SpeedIndicator;
+
+fn spawn_speed_indicator(mut commands: Commands) {
+    let mut text = TextBundle::from_section(
+        "",
+        TextStyle {
+            font_size: 20.0,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        bottom: Val::Px(40.0),
+        left: Val::Percent(45.0),
+        ..default()
+    })
+    .with_background_color(Color::rgba(0.1, 0.1, 0.1, 0.8));
+    text.visibility = Visibility::Hidden;
+    commands.spawn((text, SpeedIndicator));
+}
+
+fn update_speed_indicator(
+    time: Res<Time>,
+    controllers: Query<&CameraController>,
+    mut indicator: Query<(&mut Text, &mut Visibility), With<SpeedIndicator>>,
+    mut last_multiplier: Local<Option<f32>>,
+    mut shown_for: Local<f32>,
+) {
+    let (Ok(options), Ok((mut text, mut visibility))) =
+        (controllers.get_single(), indicator.get_single_mut())
+    else {
+        return;
+    };
+    if last_multiplier.is_some_and(|last| last != options.speed_multiplier) {
+        text.sections[0].value = format!(
+            "Speed x{:.2} ({:.1} m/s)",
+            options.speed_multiplier,
+            options.walk_speed * options.speed_multiplier
+        );
+        *visibility = Visibility::Visible;
+        *shown_for = 0.0;
+    }
+    *last_multiplier = Some(options.speed_multiplier);
+    *shown_for += time.delta_seconds();
+    if *shown_for > SPEED_INDICATOR_SECS {
+        *visibility = Visibility::Hidden;
+    }
+}
+
+/// Narrows or widens the field of view by [`CameraController::fov_step`] degrees per press.
+pub fn adjust_fov(
+    key_input: Res<ButtonInput<KeyCode>>,
+    mut query: Query<(&mut Projection, &CameraController)>,
+) {
+    for (mut projection, options) in &mut query {
+        let step = if key_input.just_pressed(options.key_fov_wider) {
+            options.fov_step
+        } else if key_input.just_pressed(options.key_fov_narrower) {
+            -options.fov_step
+        } else {
+            continue;
+        };
+        let Projection::Perspective(perspective) = &mut *projection else {
+            continue;
+        };
+        let degrees = (perspective.fov.to_degrees() + step).clamp(MIN_FOV, MAX_FOV);
+        perspective.fov = degrees.to_radians();
+        println!("Field of view: {degrees:.0}°");
+    }
+}
+
+/// When present, jumps between bookmarks and benchmark positions ease the camera to the target
+/// over `duration` seconds instead of cutting, see `--smooth-cuts`.
+#[derive(Resource, Clone, Copy)]
+pub struct SmoothCuts {
+    pub duration: f32,
+}
+
+/// An in progress move of the camera between two transforms, removed once it arrives.
+#[derive(Component, Clone, Copy)]
+pub struct CameraTransition {
+    pub from: Transform,
+    pub to: Transform,
+    pub elapsed: f32,
+    pub duration: f32,
+}
+
+/// Moves the camera to `target`, easing there with a [`CameraTransition`] if [`SmoothCuts`] are on.
+pub fn cut_to(
+    commands: &mut Commands,
+    camera: Entity,
+    transform: &mut Transform,
+    target: Transform,
+    smooth_cuts: Option<&SmoothCuts>,
+) {
+    match smooth_cuts {
+        Some(smooth_cuts) if smooth_cuts.duration > 0.0 => {
+            commands.entity(camera).insert(CameraTransition {
+                from: *transform,
+                to: target,
+                elapsed: 0.0,
+                duration: smooth_cuts.duration,
+            });
+        }
+        _ => {
+            commands.entity(camera).remove::<CameraTransition>();
+            *transform = target;
+        }
+    }
+}
+
+pub fn camera_transition(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(
+        Entity,
+        &mut Transform,
+        &mut CameraTransition,
+        Option<&mut CameraController>,
+    )>,
+) {
+    for (entity, mut transform, mut transition, options) in &mut query {
+        transition.elapsed += time.delta_seconds();
+        let t = (transition.elapsed / transition.duration).clamp(0.0, 1.0);
+        // Smoothstep so the camera eases in and out
+        let t = t * t * (3.0 - 2.0 * t);
+        transform.translation = transition
+            .from
+            .translation
+            .lerp(transition.to.translation, t);
+        transform.rotation = transition.from.rotation.slerp(transition.to.rotation, t);
+        if let Some(mut options) = options {
+            options.initialized = false;
+        }
+        if transition.elapsed >= transition.duration {
+            commands.entity(entity).remove::<CameraTransition>();
+        }
+    }
+}
+
 /// Simple flying camera plugin.
 /// In order to function, the [`CameraController`] component should be attached to the camera entity.
 #[derive(Default)]
@@ -231,6 +872,18 @@ pub struct CameraControllerPlugin;
 
 impl Plugin for CameraControllerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, camera_controller);
+        app.init_resource::<CameraPathRecorder>()
+            .add_systems(Startup, spawn_speed_indicator)
+            .add_systems(
+                Update,
+                (
+                    update_speed_indicator.after(camera_controller),
+                    camera_controller,
+                    record_camera_path,
+                    play_camera_path.after(camera_controller),
+                    camera_transition.after(camera_controller),
+                    adjust_fov,
+                ),
+            );
     }
 }
@@ -0,0 +1,103 @@
+use bevy::{prelude::*, utils::HashMap};
+
+/// Replaces every `StandardMaterial` in the scene with a uniform grey one, so lighting can be
+/// judged without the albedo getting in the way. Toggled with F9, see `--clay`.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ClayMode {
+    pub enabled: bool,
+    /// Keep each material's normal map, so surface detail still shows up in the lighting.
+    pub keep_normal_maps: bool,
+    pub color: Color,
+    pub perceptual_roughness: f32,
+}
+
+impl Default for ClayMode {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keep_normal_maps: false,
+            color: Color::rgb(0.5, 0.5, 0.5),
+            perceptual_roughness: 0.6,
+        }
+    }
+}
+
+impl ClayMode {
+    fn material(&self, original: &StandardMaterial) -> StandardMaterial {
+        StandardMaterial {
+            base_color: self.color,
+            perceptual_roughness: self.perceptual_roughness,
+            metallic: 0.0,
+            normal_map_texture: if self.keep_normal_maps {
+                original.normal_map_texture.clone()
+            } else {
+                None
+            },
+            flip_normal_map_y: original.flip_normal_map_y,
+            double_sided: original.double_sided,
+            cull_mode: original.cull_mode,
+            ..default()
+        }
+    }
+}
+
+/// The material an entity had before clay mode replaced it.
+#[derive(Component)]
+struct ClayOriginal(Handle<StandardMaterial>);
+
+/// Clay materials already made for each original material, so toggling back is instant.
+#[derive(Resource, Default)]
+struct ClayMaterials(HashMap<AssetId<StandardMaterial>, Handle<StandardMaterial>>);
+
+pub struct ClayPlugin;
+impl Plugin for ClayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClayMode>()
+            .init_resource::<ClayMaterials>()
+            .add_systems(Update, (toggle_clay, apply_clay).chain());
+    }
+}
+
+fn toggle_clay(input: Res<ButtonInput<KeyCode>>, mut clay: ResMut<ClayMode>) {
+    if input.just_pressed(KeyCode::F9) {
+        clay.enabled = !clay.enabled;
+    }
+}
+
+fn apply_clay(
+    mut commands: Commands,
+    clay: Res<ClayMode>,
+    original: Query<(Entity, &Handle<StandardMaterial>), Without<ClayOriginal>>,
+    replaced: Query<(Entity, &ClayOriginal)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut cache: ResMut<ClayMaterials>,
+) {
+    if !clay.enabled {
+        if clay.is_changed() {
+            for (entity, original) in &replaced {
+                commands
+                    .entity(entity)
+                    .remove::<ClayOriginal>()
+                    .insert(original.0.clone());
+            }
+        }
+        return;
+    }
+    // Runs every frame so scenes that finish loading later are covered too
+    for (entity, handle) in &original {
+        let clay_handle = match cache.0.get(&handle.id()) {
+            Some(clay_handle) => clay_handle.clone(),
+            None => {
+                let Some(material) = materials.get(handle).map(|m| clay.material(m)) else {
+                    continue;
+                };
+                let clay_handle = materials.add(material);
+                cache.0.insert(handle.id(), clay_handle.clone());
+                clay_handle
+            }
+        };
+        commands
+            .entity(entity)
+            .insert((clay_handle, ClayOriginal(handle.clone())));
+    }
+}
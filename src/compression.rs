@@ -0,0 +1,233 @@
+// Web builds can't encode, they only keep the format names and texture formats
+#![cfg_attr(target_arch = "wasm32", allow(dead_code))]
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use bevy::{prelude::*, render::render_resource::TextureFormat};
+#[cfg(not(target_arch = "wasm32"))]
+use intel_tex_2::{astc, bc1, bc5, bc7, RgSurface, RgbaSurface};
+use wgpu::{AstcBlock, AstcChannel, Features};
+
+/// GPU compressed formats that generated mip chains can be encoded to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompressionFormat {
+    /// Half the size of BC7 and quick to encode, but blocky on smooth gradients and alpha is
+    /// only on or off. Supported on desktop GPUs.
+    Bc1,
+    /// High quality, supported on desktop GPUs.
+    Bc7,
+    /// Two channels at the quality of BC7, for normal maps. The material rebuilds Z from X and
+    /// Y. Picked for normal maps by [`CompressionFormat::for_normal_map`], not on its own.
+    Bc5,
+    /// High quality ASTC, supported on mobile GPUs and Apple silicon.
+    Astc4x4,
+    /// Lower quality, but a quarter the size of ASTC 4x4.
+    Astc8x8,
+}
+
+impl FromStr for CompressionFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bc1" => Ok(CompressionFormat::Bc1),
+            "bc7" => Ok(CompressionFormat::Bc7),
+            "astc" | "astc4x4" => Ok(CompressionFormat::Astc4x4),
+            "astc8x8" => Ok(CompressionFormat::Astc8x8),
+            _ => Err(anyhow!(
+                "Unknown texture format {s}, expected bc1, bc7, astc, or astc8x8"
+            )),
+        }
+    }
+}
+
+impl CompressionFormat {
+    /// Name of the format on the kram command line.
+    pub fn kram_format(&self) -> &'static str {
+        match self {
+            CompressionFormat::Bc1 => "bc1",
+            CompressionFormat::Bc7 => "bc7",
+            CompressionFormat::Bc5 => "bc5",
+            CompressionFormat::Astc4x4 => "astc4x4",
+            CompressionFormat::Astc8x8 => "astc8x8",
+        }
+    }
+
+    pub fn block_size(&self) -> (u32, u32) {
+        match self {
+            CompressionFormat::Bc1
+            | CompressionFormat::Bc7
+            | CompressionFormat::Bc5
+            | CompressionFormat::Astc4x4 => (4, 4),
+            CompressionFormat::Astc8x8 => (8, 8),
+        }
+    }
+
+    pub fn required_features(&self) -> Features {
+        match self {
+            CompressionFormat::Bc1 | CompressionFormat::Bc7 | CompressionFormat::Bc5 => {
+                Features::TEXTURE_COMPRESSION_BC
+            }
+            CompressionFormat::Astc4x4 | CompressionFormat::Astc8x8 => {
+                Features::TEXTURE_COMPRESSION_ASTC
+            }
+        }
+    }
+
+    pub fn texture_format(&self, srgb: bool) -> TextureFormat {
+        let channel = if srgb {
+            AstcChannel::UnormSrgb
+        } else {
+            AstcChannel::Unorm
+        };
+        match self {
+            CompressionFormat::Bc1 if srgb => TextureFormat::Bc1RgbaUnormSrgb,
+            CompressionFormat::Bc1 => TextureFormat::Bc1RgbaUnorm,
+            CompressionFormat::Bc7 if srgb => TextureFormat::Bc7RgbaUnormSrgb,
+            CompressionFormat::Bc7 => TextureFormat::Bc7RgbaUnorm,
+            CompressionFormat::Bc5 => TextureFormat::Bc5RgUnorm,
+            CompressionFormat::Astc4x4 => TextureFormat::Astc {
+                block: AstcBlock::B4x4,
+                channel,
+            },
+            CompressionFormat::Astc8x8 => TextureFormat::Astc {
+                block: AstcBlock::B8x8,
+                channel,
+            },
+        }
+    }
+
+    /// Format to encode normal maps to when the rest of the textures use this one. BC1 and BC7
+    /// blocks share their endpoints between the channels, which shows up as blocky shading on
+    /// normal maps, BC5 encodes X and Y separately.
+    pub fn for_normal_map(&self) -> CompressionFormat {
+        match self {
+            CompressionFormat::Bc1 | CompressionFormat::Bc7 => CompressionFormat::Bc5,
+            format => *format,
+        }
+    }
+
+    /// Encodes with settings fast enough to run while the scene loads, or with the slow ones
+    /// that give the best quality when `high_quality` is set.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn compress_surface(&self, surface: &RgbaSurface, high_quality: bool) -> Vec<u8> {
+        match self {
+            CompressionFormat::Bc1 => bc1::compress_blocks(surface),
+            CompressionFormat::Bc7 if high_quality => {
+                bc7::compress_blocks(&bc7::alpha_slow_settings(), surface)
+            }
+            CompressionFormat::Bc7 => bc7::compress_blocks(&bc7::alpha_basic_settings(), surface),
+            CompressionFormat::Bc5 => {
+                let rg: Vec<u8> = surface
+                    .data
+                    .chunks_exact(4)
+                    .flat_map(|texel| [texel[0], texel[1]])
+                    .collect();
+                bc5::compress_blocks(&RgSurface {
+                    data: &rg,
+                    width: surface.width,
+                    height: surface.height,
+                    stride: surface.width * 2,
+                })
+            }
+            CompressionFormat::Astc4x4 | CompressionFormat::Astc8x8 => {
+                let (width, height) = self.block_size();
+                let settings = if high_quality {
+                    astc::alpha_slow_settings(width, height)
+                } else {
+                    astc::alpha_fast_settings(width, height)
+                };
+                astc::compress_blocks(&settings, surface)
+            }
+        }
+    }
+}
+
+/// Pads an RGBA8 image out to `padded_width` x `padded_height` by repeating the edge pixels.
+#[cfg(not(target_arch = "wasm32"))]
+fn pad_rgba(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    padded_width: u32,
+    padded_height: u32,
+) -> Cow<[u8]> {
+    if width == padded_width && height == padded_height {
+        return Cow::Borrowed(pixels);
+    }
+    let mut padded = Vec::with_capacity((padded_width * padded_height * 4) as usize);
+    for y in 0..padded_height {
+        let row = (y.min(height - 1) * width * 4) as usize;
+        padded.extend_from_slice(&pixels[row..row + (width * 4) as usize]);
+        let last = &pixels[row + ((width - 1) * 4) as usize..row + (width * 4) as usize];
+        for _ in width..padded_width {
+            padded.extend_from_slice(last);
+        }
+    }
+    Cow::Owned(padded)
+}
+
+/// Encodes every mip level of an RGBA8 image to `format`. Not available on the web, where the
+/// encoders can't be built.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn compress_mips(
+    image: &mut Image,
+    format: CompressionFormat,
+    high_quality: bool,
+) -> anyhow::Result<()> {
+    let descriptor = &image.texture_descriptor;
+    let srgb = match descriptor.format {
+        TextureFormat::Rgba8UnormSrgb => true,
+        TextureFormat::Rgba8Unorm => false,
+        texture_format => {
+            return Err(anyhow!(
+                "Compression not supported for {:?}.",
+                texture_format
+            ))
+        }
+    };
+    let (block_width, block_height) = format.block_size();
+    let size = descriptor.size;
+    if size.width % block_width != 0 || size.height % block_height != 0 {
+        return Err(anyhow!(
+            "Image size {}x{} is not a multiple of the {:?} block size.",
+            size.width,
+            size.height,
+            format
+        ));
+    }
+
+    let mut compressed = Vec::new();
+    let mut offset = 0;
+    for level in 0..descriptor.mip_level_count {
+        let width = (size.width >> level).max(1);
+        let height = (size.height >> level).max(1);
+        let level_len = (width * height * 4) as usize;
+        let padded_width = width.div_ceil(block_width) * block_width;
+        let padded_height = height.div_ceil(block_height) * block_height;
+        let pixels = pad_rgba(
+            &image.data[offset..offset + level_len],
+            width,
+            height,
+            padded_width,
+            padded_height,
+        );
+        compressed.extend(format.compress_surface(
+            &RgbaSurface {
+                data: &pixels,
+                width: padded_width,
+                height: padded_height,
+                stride: padded_width * 4,
+            },
+            high_quality,
+        ));
+        offset += level_len;
+    }
+
+    image.data = compressed;
+    image.texture_descriptor.format = format.texture_format(srgb);
+    Ok(())
+}
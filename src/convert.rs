@@ -1,71 +1,799 @@
-use threadpool::ThreadPool;
-
-use std::{fs, io::Write, process::Command, thread::available_parallelism};
-
-pub fn change_gltf_to_use_ktx2() {
-    for path in [
-        "./assets/main_sponza/NewSponza_Main_glTF_002.gltf",
-        "./assets/PKG_A_Curtains/NewSponza_Curtains_glTF.gltf",
-    ] {
-        let contents = fs::read_to_string(path).unwrap();
-        let new = contents.replace(".png", ".ktx2");
-        let mut file = fs::OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(path)
-            .unwrap();
-        let _ = file.write(new.as_bytes()).unwrap();
-    }
-}
-
-pub fn convert_images_to_ktx2() {
-    for path in [
-        "./assets/PKG_A_Curtains/textures",
-        "./assets/main_sponza/textures",
-    ] {
-        let pool = ThreadPool::new(available_parallelism().unwrap().get());
-        for path in fs::read_dir(path).unwrap() {
-            pool.execute(move || {
-                if let Ok(path) = path {
-                    let path = path.path();
-                    if path.is_file() && path.extension().unwrap() == "png" {
-                        let path_string = path.to_string_lossy().to_string();
-                        let new_path_string =
-                            path.with_extension("ktx2").to_string_lossy().to_string();
-                        let name = path.file_stem().unwrap().to_string_lossy().to_lowercase();
-                        let nor = name.contains("normal");
-                        let metal_rough = name.contains("roughness") && name.contains("metalness");
-                        let color = name.contains("basecolor");
-                        let decal = name.contains("decal");
-
-                        if !nor && !metal_rough && !color && !decal {
-                            return;
-                        }
-
-                        let mut cmd = Command::new("kram");
-                        cmd.arg("encode").arg("-f");
-                        // should be able to use bc5 for nor and rough+metal, but they looked bad
-                        cmd.arg("bc7");
-                        if nor {
-                            cmd.arg("-normal");
-                        }
-                        cmd.arg("-type")
-                            .arg("2d")
-                            .arg("-mipmin")
-                            .arg("2")
-                            .arg("-srgb")
-                            .arg("-zstd")
-                            .arg("0")
-                            .arg("-i")
-                            .arg(path_string)
-                            .arg("-o")
-                            .arg(new_path_string);
-                        dbg!(&cmd);
-                        cmd.output().expect("ls command failed to start");
-                    }
+use anyhow::{anyhow, Context};
+use bevy::{
+    prelude::{default, Image},
+    render::render_resource::TextureFormat,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Condvar, Mutex,
+    },
+    thread::available_parallelism,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    compression::{compress_mips, CompressionFormat},
+    gltf_file::{GltfFile, ImageSource},
+    ktx2_writer::write_ktx2,
+    mip_filter::{MipFilter, TexelLayout},
+    mipmap_generator::{adaptive_memory_budget, generate_mips},
+};
+
+/// Scene folders in the asset directory, and the glTF file in each that references the textures
+/// in its `textures` folder.
+pub const SCENES: [(&str, &str); 2] = [
+    ("main_sponza", "NewSponza_Main_glTF_002.gltf"),
+    ("PKG_A_Curtains", "NewSponza_Curtains_glTF.gltf"),
+];
+
+/// Extensions of the image files that can be converted.
+const SOURCE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// The Sponza glTF files, relative to the asset directory.
+pub fn sponza_gltfs() -> Vec<PathBuf> {
+    SCENES
+        .iter()
+        .map(|(scene, gltf)| Path::new(scene).join(gltf))
+        .collect()
+}
+
+/// Mip chains written by `--convert` stop once the shorter side gets this small.
+pub const SMALLEST_MIP_SIZE: u32 = 4;
+
+/// zstd level the KTX2 textures are supercompressed at unless `--ktx2-zstd` says otherwise. 0
+/// leaves the level to the encoder.
+pub const DEFAULT_ZSTD_LEVEL: u32 = 0;
+/// zstd's own default, for toktx which doesn't take 0.
+const ZSTD_DEFAULT_LEVEL: u32 = 3;
+
+/// Written to the output directory after a conversion, see [`ConvertManifest`].
+const MANIFEST_FILE: &str = "ktx2_manifest.ron";
+/// Bumped when the same settings give different textures, so they're converted again.
+const MANIFEST_VERSION: u32 = 1;
+
+/// How the materials in a glTF file use a texture, which decides its color space and format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureRole {
+    /// Base color and emissive, stored as sRGB.
+    Color,
+    /// Stored linear and encoded as BC5 where the format allows.
+    NormalMap,
+    /// Metallic-roughness, occlusion, and anything else that isn't a color, stored linear.
+    Data,
+}
+
+impl TextureRole {
+    pub fn srgb(&self) -> bool {
+        *self == TextureRole::Color
+    }
+}
+
+/// What the textures are encoded to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConvertTarget {
+    /// A GPU format, encoded with kram. The textures only load on GPUs that support it.
+    Gpu(CompressionFormat),
+    /// UASTC in a Basis Universal KTX2, encoded with toktx. Transcoded to a format the GPU
+    /// supports when loaded, BCn on desktop and ASTC or ETC2 on mobile, which needs the
+    /// `basis_universal` feature.
+    Uastc,
+}
+
+impl FromStr for ConvertTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uastc" => Ok(ConvertTarget::Uastc),
+            _ => s.parse().map(ConvertTarget::Gpu).map_err(|_| {
+                anyhow!("Unknown texture format {s}, expected bc1, bc7, astc, astc8x8, or uastc")
+            }),
+        }
+    }
+}
+
+impl ConvertTarget {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConvertTarget::Gpu(format) => format.kram_format(),
+            ConvertTarget::Uastc => "uastc",
+        }
+    }
+
+    /// Name of the format normal maps are encoded to.
+    fn normal_map_name(&self) -> &'static str {
+        match self {
+            ConvertTarget::Gpu(format) => format.for_normal_map().kram_format(),
+            ConvertTarget::Uastc => "uastc",
+        }
+    }
+}
+
+/// Trades conversion time for texture quality, see `--convert-quality`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConvertQuality {
+    /// BC1 instead of BC7, at kram's lowest quality. For iterating on the scene, the textures
+    /// are half the size but blocky on smooth gradients.
+    Fast,
+    /// kram's default quality, with its box filtered mips.
+    #[default]
+    Default,
+    /// Mips filtered with a Kaiser window and encoded with the slowest encoder settings. kram
+    /// can't change its mip filter, so these are encoded in process instead.
+    High,
+}
+
+impl FromStr for ConvertQuality {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fast" => Ok(ConvertQuality::Fast),
+            "default" => Ok(ConvertQuality::Default),
+            "high" => Ok(ConvertQuality::High),
+            _ => Err(anyhow!(
+                "Unknown conversion quality {s}, expected fast, default, or high"
+            )),
+        }
+    }
+}
+
+impl ConvertQuality {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConvertQuality::Fast => "fast",
+            ConvertQuality::Default => "default",
+            ConvertQuality::High => "high",
+        }
+    }
+
+    /// What the textures are encoded to when `requested` was asked for.
+    pub fn target(&self, requested: ConvertTarget) -> ConvertTarget {
+        match (self, requested) {
+            (ConvertQuality::Fast, ConvertTarget::Gpu(CompressionFormat::Bc7)) => {
+                ConvertTarget::Gpu(CompressionFormat::Bc1)
+            }
+            _ => requested,
+        }
+    }
+
+    /// Filter to generate the mips with in process, `None` leaves them to kram.
+    fn mip_filter(&self) -> Option<MipFilter> {
+        match self {
+            ConvertQuality::High => Some(MipFilter::Kaiser),
+            _ => None,
+        }
+    }
+
+    /// kram's `-quality`, `None` for its default.
+    fn kram_quality(&self) -> Option<u32> {
+        match self {
+            ConvertQuality::Fast => Some(0),
+            _ => None,
+        }
+    }
+
+    /// toktx's `--uastc_quality` and mip filter, `None` for its default Lanczos filter.
+    fn uastc_settings(&self) -> (u32, Option<&'static str>) {
+        match self {
+            ConvertQuality::Fast => (0, Some("box")),
+            ConvertQuality::Default => (2, None),
+            ConvertQuality::High => (4, Some("kaiser")),
+        }
+    }
+}
+
+/// What the textures in a directory were converted with, so `--validate` can catch a mismatch
+/// and a conversion with different settings redoes textures that look up to date.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConvertManifest {
+    pub quality: ConvertQuality,
+    /// Name of the format the textures were encoded to, see [`ConvertTarget::name`].
+    pub format: String,
+    /// And the one normal maps were encoded to, empty from before they got their own.
+    #[serde(default)]
+    pub normal_format: String,
+    #[serde(default)]
+    pub version: u32,
+}
+
+impl ConvertManifest {
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(MANIFEST_FILE)
+    }
+
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        Ok(ron::from_str(&fs::read_to_string(Self::path(dir))?)?)
+    }
+
+    pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        fs::write(Self::path(dir), ron::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ConvertSettings {
+    /// Directory holding the glTF files.
+    pub input_dir: PathBuf,
+    /// Directory the KTX2 textures and updated glTF files are written to. When it's not the
+    /// input directory, the buffers and images that weren't converted are copied over so it can
+    /// be used as is.
+    pub output_dir: PathBuf,
+    /// .gltf or .glb files to convert, relative to `input_dir`.
+    pub gltfs: Vec<PathBuf>,
+    pub format: ConvertTarget,
+    pub quality: ConvertQuality,
+    /// Number of images to encode at once.
+    pub threads: usize,
+    /// Upper bound on the estimated memory used by all running encoder processes.
+    pub max_memory_bytes: u64,
+    /// zstd supercompression level of the KTX2 output, from 1 to 22, or 0 for the encoder's
+    /// default.
+    pub zstd_level: u32,
+}
+
+impl Default for ConvertSettings {
+    fn default() -> Self {
+        Self {
+            input_dir: PathBuf::from("assets"),
+            output_dir: PathBuf::from("assets"),
+            gltfs: sponza_gltfs(),
+            format: ConvertTarget::Gpu(CompressionFormat::Bc7),
+            quality: ConvertQuality::Default,
+            threads: available_parallelism().map(|n| n.get()).unwrap_or(1),
+            max_memory_bytes: adaptive_memory_budget(),
+            zstd_level: DEFAULT_ZSTD_LEVEL,
+        }
+    }
+}
+
+pub fn parse_zstd_level(value: &str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(level) if level <= 22 => Ok(level),
+        _ => Err(format!(
+            "Invalid zstd level {value}, expected 1 to 22, or 0 for the encoder's default"
+        )),
+    }
+}
+
+/// Blocks encoder tasks until their estimated memory fits in the budget.
+/// A task is always allowed to start when nothing else is running.
+struct MemoryBudget {
+    max_bytes: u64,
+    in_use: Mutex<u64>,
+    released: Condvar,
+}
+
+impl MemoryBudget {
+    fn new(max_bytes: u64) -> Self {
+        Self {
+            max_bytes,
+            in_use: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    fn acquire(&self, bytes: u64) {
+        let mut in_use = self.in_use.lock().unwrap();
+        while *in_use > 0 && *in_use + bytes > self.max_bytes {
+            in_use = self.released.wait(in_use).unwrap();
+        }
+        *in_use += bytes;
+    }
+
+    fn release(&self, bytes: u64) {
+        *self.in_use.lock().unwrap() -= bytes;
+        self.released.notify_all();
+    }
+}
+
+/// Rough memory used by kram to encode an image: the decoded RGBA8 source, its mips,
+/// and the working copies used while encoding.
+fn estimate_encode_memory(path: &Path) -> u64 {
+    match image::image_dimensions(path) {
+        Ok((width, height)) => width as u64 * height as u64 * 4 * 3,
+        Err(_) => file_size(path) * 4,
+    }
+}
+
+/// Converts the textures and points the glTF files at them, without needing the Bevy app. Images
+/// that failed to encode keep referencing their source image, and fail the conversion.
+pub fn prepare_assets(settings: &ConvertSettings) -> anyhow::Result<()> {
+    let failed = convert_images_to_ktx2(settings)?;
+    change_gltf_to_use_ktx2(settings, &failed)?;
+    if !failed.is_empty() {
+        return Err(anyhow!(
+            "{} images failed to convert, the glTF files still use their source images",
+            failed.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Points the glTF files at the KTX2 textures, except the ones in `failed`, by output path.
+pub fn change_gltf_to_use_ktx2(
+    settings: &ConvertSettings,
+    failed: &HashSet<PathBuf>,
+) -> anyhow::Result<()> {
+    for gltf in &settings.gltfs {
+        let input_path = settings.input_dir.join(gltf);
+        let output_path = settings.output_dir.join(gltf);
+        let input_dir = input_path.parent().unwrap_or(Path::new(""));
+        let output_dir = output_path.parent().unwrap_or(Path::new(""));
+        let mut doc = read_source_gltf(&input_path)?;
+        let images: Vec<_> = plan_images(settings, gltf, &doc)
+            .into_iter()
+            .filter(|image| !failed.contains(&image.output))
+            .collect();
+        if input_path != output_path {
+            // Buffers and images that stay as they are
+            let converted: HashSet<usize> = images.iter().map(|image| image.index).collect();
+            let unconverted = (0..doc.image_count())
+                .filter(|index| !converted.contains(index))
+                .filter_map(|index| match doc.image_source(index, input_dir) {
+                    Ok(ImageSource::Uri(uri)) => Some(uri),
+                    _ => None,
+                });
+            for uri in doc.buffer_uris().into_iter().chain(unconverted) {
+                let output = output_dir.join(&uri);
+                fs::create_dir_all(output.parent().unwrap_or(output_dir))?;
+                fs::copy(input_dir.join(&uri), &output)
+                    .with_context(|| format!("Failed to copy {}", uri.display()))?;
+            }
+        }
+        for image in &images {
+            doc.set_image_uri(image.index, &image.uri);
+        }
+        fs::create_dir_all(output_dir)?;
+        // Keep the original referencing the source images around for --revert
+        let backup = backup_path(&output_path);
+        if output_path.exists() && !backup.exists() {
+            fs::copy(&output_path, &backup)?;
+        }
+        doc.write(&output_path)?;
+    }
+    Ok(())
+}
+
+/// The glTF file to convert from. After an in place conversion that's the original kept next to
+/// it, which still references the source images.
+fn read_source_gltf(path: &Path) -> anyhow::Result<GltfFile> {
+    let backup = backup_path(path);
+    GltfFile::read(if backup.exists() { &backup } else { path })
+}
+
+/// An image of a glTF file to convert.
+struct ConvertImage {
+    index: usize,
+    /// Image file to encode. Embedded images are written out next to their KTX2 first.
+    source: PathBuf,
+    embedded: Option<Vec<u8>>,
+    output: PathBuf,
+    /// `output` relative to the glTF file.
+    uri: String,
+    role: TextureRole,
+}
+
+/// The images of `gltf` that its materials use, and where their KTX2 files go. Embedded images
+/// go in a folder named after the glTF file.
+fn plan_images(settings: &ConvertSettings, gltf: &Path, doc: &GltfFile) -> Vec<ConvertImage> {
+    let input_path = settings.input_dir.join(gltf);
+    let input_dir = input_path.parent().unwrap_or(Path::new(""));
+    let output_path = settings.output_dir.join(gltf);
+    let output_dir = output_path.parent().unwrap_or(Path::new(""));
+    let stem = gltf.file_stem().unwrap_or_default().to_string_lossy();
+    let mut images = Vec::new();
+    for (index, role) in texture_roles(&doc.json) {
+        let (source, embedded, uri) = match doc.image_source(index, input_dir) {
+            Ok(ImageSource::Uri(uri)) => {
+                let extension = uri.extension().unwrap_or_default().to_string_lossy();
+                if !SOURCE_EXTENSIONS.contains(&extension.to_lowercase().as_str()) {
+                    continue;
+                }
+                (input_dir.join(&uri), None, uri)
+            }
+            Ok(ImageSource::Embedded(bytes, mime_type)) => {
+                let extension = match mime_type.as_str() {
+                    "image/png" => "png",
+                    "image/jpeg" => "jpg",
+                    _ => continue,
+                };
+                let uri = Path::new(&format!("{stem}_textures"))
+                    .join(format!("image_{index}.{extension}"));
+                (output_dir.join(&uri), Some(bytes), uri)
+            }
+            Err(e) => {
+                println!("Skipping image {index} of {}: {e:#}", gltf.display());
+                continue;
+            }
+        };
+        let uri = uri.with_extension("ktx2");
+        images.push(ConvertImage {
+            index,
+            source,
+            embedded,
+            output: output_dir.join(&uri),
+            uri: uri.to_string_lossy().replace('\\', "/"),
+            role,
+        });
+    }
+    images.sort_by_key(|image| image.index);
+    images
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Restores the original glTF files saved by [`change_gltf_to_use_ktx2`], so the scene loads the
+/// source images again. The KTX2 textures are left in place for the next conversion.
+pub fn revert_gltf(dir: &Path, gltfs: &[PathBuf]) -> anyhow::Result<()> {
+    for gltf in gltfs {
+        let path = dir.join(gltf);
+        let backup = backup_path(&path);
+        if !backup.exists() {
+            println!("No original to restore for {}", path.display());
+            continue;
+        }
+        fs::copy(&backup, &path)
+            .with_context(|| format!("Failed to restore {}", path.display()))?;
+        println!("Restored {}", path.display());
+    }
+    Ok(())
+}
+
+/// Roles of the images the materials in a glTF document use, by image index.
+pub fn texture_roles(gltf: &serde_json::Value) -> HashMap<usize, TextureRole> {
+    let mut roles = HashMap::new();
+    for material in gltf["materials"].as_array().into_iter().flatten() {
+        let pbr = &material["pbrMetallicRoughness"];
+        for (texture, role) in [
+            (&pbr["baseColorTexture"], TextureRole::Color),
+            (&material["emissiveTexture"], TextureRole::Color),
+            (&material["normalTexture"], TextureRole::NormalMap),
+            (&pbr["metallicRoughnessTexture"], TextureRole::Data),
+            (&material["occlusionTexture"], TextureRole::Data),
+        ] {
+            let image = texture["index"]
+                .as_u64()
+                .and_then(|texture| gltf["textures"][texture as usize]["source"].as_u64());
+            if let Some(image) = image {
+                roles.insert(image as usize, role);
+            }
+        }
+    }
+    roles
+}
+
+#[derive(Default)]
+struct ConversionProgress {
+    completed: AtomicUsize,
+    input_bytes: AtomicU64,
+    output_bytes: AtomicU64,
+}
+
+/// Returns true if `output` is missing or older than `input`.
+fn needs_conversion(input: &Path, output: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(input), modified(output)) {
+        (Some(input), Some(output)) => output < input,
+        _ => true,
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}m{:02}s", secs / 60, secs % 60)
+}
+
+fn progress_bar(completed: usize, total: usize) -> String {
+    const WIDTH: usize = 30;
+    let filled = (completed * WIDTH) / total.max(1);
+    format!("[{}{}]", "#".repeat(filled), "-".repeat(WIDTH - filled))
+}
+
+/// Encodes the images the glTF files use, returning the output paths of the ones that failed.
+/// The manifest is only written when they all succeeded.
+pub fn convert_images_to_ktx2(settings: &ConvertSettings) -> anyhow::Result<HashSet<PathBuf>> {
+    let target = settings.quality.target(settings.format);
+    let manifest = ConvertManifest {
+        quality: settings.quality,
+        format: target.name().to_string(),
+        normal_format: target.normal_map_name().to_string(),
+        version: MANIFEST_VERSION,
+    };
+    // Without a manifest the textures are from before they were recorded, trust the timestamps
+    let changed =
+        ConvertManifest::load(&settings.output_dir).is_ok_and(|previous| previous != manifest);
+    if changed {
+        println!("Conversion settings changed, converting every image again");
+    }
+
+    let mut jobs = Vec::new();
+    let mut queued = HashSet::new();
+    let mut skipped = 0;
+    for gltf in &settings.gltfs {
+        let doc = read_source_gltf(&settings.input_dir.join(gltf))?;
+        // Images no material uses stay as they are
+        for image in plan_images(settings, gltf, &doc) {
+            if !queued.insert(image.output.clone()) {
+                continue;
+            }
+            fs::create_dir_all(image.output.parent().unwrap_or(Path::new("")))?;
+            if let Some(bytes) = &image.embedded {
+                // Only written when it changed, so the timestamps still skip it next time
+                if fs::read(&image.source).ok().as_ref() != Some(bytes) {
+                    fs::write(&image.source, bytes)?;
                 }
-            });
+            }
+            if !changed && !needs_conversion(&image.source, &image.output) {
+                skipped += 1;
+                continue;
+            }
+            jobs.push((image.source, image.output, image.role));
         }
-        pool.join();
+    }
+
+    let total = jobs.len();
+    println!(
+        "Converting {total} images to {} KTX2 at {} quality on {} threads, {skipped} already up to date",
+        target.name(),
+        settings.quality.name(),
+        settings.threads
+    );
+    match settings.zstd_level {
+        0 => println!("Using zstd supercompression at the encoder's default level"),
+        level => println!("Using zstd supercompression level {level}"),
+    }
+    let progress = ConversionProgress::default();
+    let failed = Mutex::new(HashSet::new());
+    let budget = MemoryBudget::new(settings.max_memory_bytes);
+    let start = Instant::now();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(settings.threads)
+        .build()?;
+    pool.install(|| {
+        jobs.par_iter().for_each(|(path, new_path, role)| {
+            let task_bytes = estimate_encode_memory(path);
+            budget.acquire(task_bytes);
+            let result = convert_image(path, new_path, *role, target, settings);
+            budget.release(task_bytes);
+            if let Err(e) = result {
+                println!("Failed to convert {}: {e:#}", path.display());
+                failed.lock().unwrap().insert(new_path.clone());
+            }
+
+            let input_bytes = file_size(path);
+            let output_bytes = file_size(new_path);
+            progress
+                .input_bytes
+                .fetch_add(input_bytes, Ordering::Relaxed);
+            progress
+                .output_bytes
+                .fetch_add(output_bytes, Ordering::Relaxed);
+            let completed = progress.completed.fetch_add(1, Ordering::Relaxed) + 1;
+            let elapsed = start.elapsed();
+            let eta = elapsed.mul_f64((total - completed) as f64 / completed as f64);
+            println!(
+                "{} {completed}/{total} ({}%) ETA {} - {} ({:.1}MB -> {:.1}MB)",
+                progress_bar(completed, total),
+                completed * 100 / total,
+                format_duration(eta),
+                path.file_name().unwrap().to_string_lossy(),
+                input_bytes as f64 / 1_000_000.0,
+                output_bytes as f64 / 1_000_000.0,
+            );
+        });
+    });
+
+    let failed = failed.into_inner().unwrap();
+    println!(
+        "Converted {} images in {}: {:.1}MB of source images -> {:.1}MB of KTX2",
+        total - failed.len(),
+        format_duration(start.elapsed()),
+        progress.input_bytes.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        progress.output_bytes.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+    );
+    if failed.is_empty() {
+        manifest.save(&settings.output_dir)?;
+    }
+    Ok(failed)
+}
+
+/// Encodes a single image to `new_path`. The encoders write to a temporary file that's only
+/// renamed to `new_path` once they succeed, so an interrupted conversion doesn't leave a partial
+/// KTX2 behind that looks up to date.
+fn convert_image(
+    path: &Path,
+    new_path: &Path,
+    role: TextureRole,
+    target: ConvertTarget,
+    settings: &ConvertSettings,
+) -> anyhow::Result<()> {
+    // kram picks the container from the extension, so it has to stay .ktx2
+    let partial = new_path.with_extension("partial.ktx2");
+    let result = encode(path, &partial, role, target, settings).and_then(|_| {
+        fs::rename(&partial, new_path)
+            .with_context(|| format!("Failed to move the output to {}", new_path.display()))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Encodes an image with kram, or in process if the quality preset needs a different mip
+/// filter.
+fn encode(
+    path: &Path,
+    new_path: &Path,
+    role: TextureRole,
+    target: ConvertTarget,
+    settings: &ConvertSettings,
+) -> anyhow::Result<()> {
+    let format = match target {
+        ConvertTarget::Gpu(format) => format,
+        ConvertTarget::Uastc => return encode_uastc(path, new_path, role, settings),
+    };
+    let format = if role == TextureRole::NormalMap {
+        format.for_normal_map()
+    } else {
+        format
+    };
+    if let Some(filter) = settings.quality.mip_filter() {
+        return encode_image(path, new_path, role, format, filter, settings.zstd_level);
+    }
+    // kram only reads PNG
+    let png = new_path.with_extension("source.png");
+    let input = if path.extension().unwrap_or_default() == "png" {
+        path
+    } else {
+        image::open(path)
+            .and_then(|image| image.to_rgba8().save(&png))
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        &png
+    };
+    let mut cmd = Command::new("kram");
+    cmd.arg("encode").arg("-f");
+    cmd.arg(format.kram_format());
+    if role == TextureRole::NormalMap {
+        cmd.arg("-normal");
+    }
+    if let Some(quality) = settings.quality.kram_quality() {
+        cmd.arg("-quality").arg(quality.to_string());
+    }
+    cmd.arg("-type").arg("2d").arg("-mipmin").arg("2");
+    if role.srgb() {
+        cmd.arg("-srgb");
+    }
+    cmd.arg("-zstd").arg(settings.zstd_level.to_string());
+    cmd.arg("-i").arg(input).arg("-o").arg(new_path);
+    let output = cmd.output();
+    if input == png {
+        let _ = fs::remove_file(&png);
+    }
+    let output = output.context("kram failed to start, is it in your path?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "kram failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Generates the mips of an image with `filter` and encodes them with the slow encoder settings.
+fn encode_image(
+    path: &Path,
+    new_path: &Path,
+    role: TextureRole,
+    format: CompressionFormat,
+    filter: MipFilter,
+    zstd_level: u32,
+) -> anyhow::Result<()> {
+    let source = image::open(path)?.to_rgba8();
+    let (width, height) = source.dimensions();
+    let layout = TexelLayout {
+        channels: 4,
+        srgb: role.srgb(),
+        normal_map: role == TextureRole::NormalMap,
+    };
+    let mut data = source.into_raw();
+    let mip_level_count = generate_mips(
+        &mut data,
+        width,
+        height,
+        layout,
+        SMALLEST_MIP_SIZE,
+        u32::MAX,
+        filter,
+    )?;
+    let mut image = Image { data, ..default() };
+    let descriptor = &mut image.texture_descriptor;
+    descriptor.size.width = width;
+    descriptor.size.height = height;
+    descriptor.mip_level_count = mip_level_count;
+    descriptor.format = if role.srgb() {
+        TextureFormat::Rgba8UnormSrgb
+    } else {
+        TextureFormat::Rgba8Unorm
+    };
+    compress_mips(&mut image, format, true)?;
+    write_ktx2(new_path, &image, format, Some(zstd_level))
+}
+
+/// Encodes an image to a UASTC KTX2 with toktx, from KTX-Software.
+fn encode_uastc(
+    path: &Path,
+    new_path: &Path,
+    role: TextureRole,
+    settings: &ConvertSettings,
+) -> anyhow::Result<()> {
+    let (quality, filter) = settings.quality.uastc_settings();
+    let mut cmd = Command::new("toktx");
+    cmd.arg("--t2")
+        .arg("--encode")
+        .arg("uastc")
+        .arg("--uastc_quality")
+        .arg(quality.to_string())
+        .arg("--genmipmap");
+    if let Some(filter) = filter {
+        cmd.arg("--filter").arg(filter);
+    }
+    // Normal maps keep all three channels, toktx's two channel normal mode stores Y in alpha,
+    // which Bevy doesn't rebuild Z from
+    cmd.arg("--assign_oetf")
+        .arg(if role.srgb() { "srgb" } else { "linear" });
+    let level = match settings.zstd_level {
+        0 => ZSTD_DEFAULT_LEVEL,
+        level => level,
+    };
+    cmd.arg("--zcmp").arg(level.to_string());
+    cmd.arg(new_path).arg(path);
+    let output = cmd
+        .output()
+        .context("toktx failed to start, is KTX-Software installed?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "toktx failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zstd_levels_in_range_parse() {
+        assert_eq!(parse_zstd_level("0"), Ok(0));
+        assert_eq!(parse_zstd_level("1"), Ok(1));
+        assert_eq!(parse_zstd_level("22"), Ok(22));
+    }
+
+    #[test]
+    fn zstd_levels_out_of_range_fail() {
+        assert!(parse_zstd_level("23").is_err());
+        assert!(parse_zstd_level("-1").is_err());
+        assert!(parse_zstd_level("").is_err());
+        assert!(parse_zstd_level("fast").is_err());
     }
 }
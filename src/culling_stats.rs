@@ -0,0 +1,40 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    render::view::VisibilitySystems,
+};
+
+pub const DRAWN_MESHES: DiagnosticPath = DiagnosticPath::const_new("drawn_meshes");
+pub const CULLED_MESHES: DiagnosticPath = DiagnosticPath::const_new("culled_meshes");
+
+/// Reports how many mesh entities were drawn and how many were culled each frame, shown with the
+/// diagnostics (F3). A mesh counts as drawn when any view sees it, including the shadow maps, so
+/// toggling frustum culling in the features panel (F2) shows what it saves.
+pub struct CullingStatsPlugin;
+impl Plugin for CullingStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(DRAWN_MESHES).with_max_history_length(20))
+            .register_diagnostic(Diagnostic::new(CULLED_MESHES).with_max_history_length(20))
+            .add_systems(
+                PostUpdate,
+                culling_stats.after(VisibilitySystems::CheckVisibility),
+            );
+    }
+}
+
+fn culling_stats(
+    mut diagnostics: Diagnostics,
+    meshes: Query<(&InheritedVisibility, &ViewVisibility), With<Handle<Mesh>>>,
+) {
+    let (mut drawn, mut culled) = (0, 0);
+    // Hidden meshes were never candidates, so they don't count as culled
+    for (inherited, view) in &meshes {
+        if view.get() {
+            drawn += 1;
+        } else if inherited.get() {
+            culled += 1;
+        }
+    }
+    diagnostics.add_measurement(&DRAWN_MESHES, || drawn as f64);
+    diagnostics.add_measurement(&CULLED_MESHES, || culled as f64);
+}
@@ -0,0 +1,82 @@
+use bevy::{diagnostic::DiagnosticsStore, prelude::*};
+
+/// How often to refresh the text, so the numbers are readable.
+const UPDATE_INTERVAL_SECS: f32 = 0.25;
+
+/// Marks the on-screen diagnostics text, shown/hidden with F3.
+#[derive(Component)]
+pub struct DebugText;
+
+/// Shows the smoothed value of every registered diagnostic in the corner of the window.
+pub struct DebugTextPlugin;
+impl Plugin for DebugTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_debug_text)
+            .add_systems(Update, (toggle_debug_text, update_debug_text));
+    }
+}
+
+fn spawn_debug_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            ..default()
+        })
+        .with_background_color(Color::rgba(0.1, 0.1, 0.1, 0.8)),
+        DebugText,
+    ));
+}
+
+fn toggle_debug_text(
+    input: Res<ButtonInput<KeyCode>>,
+    mut text: Query<&mut Visibility, With<DebugText>>,
+) {
+    if !input.just_pressed(KeyCode::F3) {
+        return;
+    }
+    for mut visibility in &mut text {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Visible,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_debug_text(
+    time: Res<Time>,
+    diagnostics: Res<DiagnosticsStore>,
+    mut text: Query<&mut Text, With<DebugText>>,
+    mut since_update: Local<f32>,
+) {
+    *since_update += time.delta_seconds();
+    if *since_update < UPDATE_INTERVAL_SECS {
+        return;
+    }
+    *since_update = 0.0;
+
+    let mut lines = Vec::new();
+    for diagnostic in diagnostics.iter() {
+        if let (true, Some(value)) = (diagnostic.is_enabled, diagnostic.smoothed()) {
+            lines.push(format!(
+                "{}: {:.2}{}",
+                diagnostic.path(),
+                value,
+                diagnostic.suffix
+            ));
+        }
+    }
+    lines.sort();
+    for mut text in &mut text {
+        text.sections[0].value = lines.join("\n");
+    }
+}
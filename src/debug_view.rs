@@ -0,0 +1,186 @@
+use bevy::{
+    asset::load_internal_asset,
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef, ShaderType},
+    utils::HashMap,
+};
+
+const DEBUG_VIEW_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(30781295174624891046281937513);
+
+/// Material channels and render statistics that can be shown instead of the lit scene, cycled
+/// with F8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DebugView {
+    /// Every surface is drawn additively without depth testing, bright areas are drawn many
+    /// times over.
+    Overdraw,
+    Depth,
+    /// World space vertex normals.
+    Normals,
+    BaseColor,
+    /// Metallic in red, roughness in green.
+    MetallicRoughness,
+    UvChecker,
+}
+
+impl DebugView {
+    const ALL: [DebugView; 6] = [
+        DebugView::Overdraw,
+        DebugView::Depth,
+        DebugView::Normals,
+        DebugView::BaseColor,
+        DebugView::MetallicRoughness,
+        DebugView::UvChecker,
+    ];
+
+    /// Index of the view in debug_view.wgsl.
+    fn shader_mode(&self) -> u32 {
+        Self::ALL.iter().position(|view| view == self).unwrap() as u32
+    }
+}
+
+/// The debug view replacing the scene's materials, `None` to show the lit scene.
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct ActiveDebugView(pub Option<DebugView>);
+
+#[derive(Clone, Copy, Default, ShaderType)]
+struct DebugViewParams {
+    base_color: Vec4,
+    metallic: f32,
+    roughness: f32,
+    mode: u32,
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+pub struct DebugViewMaterial {
+    #[uniform(0)]
+    params: DebugViewParams,
+    #[texture(1)]
+    #[sampler(2)]
+    base_color_texture: Option<Handle<Image>>,
+    #[texture(3)]
+    #[sampler(4)]
+    metallic_roughness_texture: Option<Handle<Image>>,
+    alpha_mode: AlphaMode,
+}
+
+impl DebugViewMaterial {
+    fn new(material: &StandardMaterial, view: DebugView) -> Self {
+        Self {
+            params: DebugViewParams {
+                base_color: material.base_color.as_linear_rgba_f32().into(),
+                metallic: material.metallic,
+                roughness: material.perceptual_roughness,
+                mode: view.shader_mode(),
+            },
+            base_color_texture: material.base_color_texture.clone(),
+            metallic_roughness_texture: material.metallic_roughness_texture.clone(),
+            alpha_mode: if view == DebugView::Overdraw {
+                AlphaMode::Add
+            } else {
+                AlphaMode::Opaque
+            },
+        }
+    }
+}
+
+impl Material for DebugViewMaterial {
+    fn fragment_shader() -> ShaderRef {
+        DEBUG_VIEW_SHADER_HANDLE.into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+}
+
+/// The material an entity had before a debug view replaced it.
+#[derive(Component)]
+struct DebugViewOriginal(Handle<StandardMaterial>);
+
+/// Debug materials already made for each original material and view, so switching views
+/// doesn't make new ones each time.
+#[derive(Resource, Default)]
+struct DebugViewMaterials(
+    HashMap<(AssetId<StandardMaterial>, DebugView), Handle<DebugViewMaterial>>,
+);
+
+pub struct DebugViewPlugin;
+impl Plugin for DebugViewPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            DEBUG_VIEW_SHADER_HANDLE,
+            "debug_view.wgsl",
+            Shader::from_wgsl
+        );
+        app.add_plugins(MaterialPlugin::<DebugViewMaterial>::default())
+            .init_resource::<ActiveDebugView>()
+            .init_resource::<DebugViewMaterials>()
+            .add_systems(Update, (cycle_debug_view, apply_debug_view).chain());
+    }
+}
+
+fn cycle_debug_view(input: Res<ButtonInput<KeyCode>>, mut active: ResMut<ActiveDebugView>) {
+    if !input.just_pressed(KeyCode::F8) {
+        return;
+    }
+    active.0 = match active.0 {
+        None => Some(DebugView::ALL[0]),
+        Some(view) => DebugView::ALL.get(view.shader_mode() as usize + 1).copied(),
+    };
+    match active.0 {
+        Some(view) => println!("Debug view: {view:?}"),
+        None => println!("Debug view: off"),
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_debug_view(
+    mut commands: Commands,
+    active: Res<ActiveDebugView>,
+    standard: Query<(Entity, &Handle<StandardMaterial>)>,
+    replaced: Query<(Entity, &DebugViewOriginal)>,
+    standard_materials: Res<Assets<StandardMaterial>>,
+    mut debug_materials: ResMut<Assets<DebugViewMaterial>>,
+    mut cache: ResMut<DebugViewMaterials>,
+) {
+    let Some(view) = active.0 else {
+        if active.is_changed() {
+            for (entity, original) in &replaced {
+                commands
+                    .entity(entity)
+                    .remove::<(Handle<DebugViewMaterial>, DebugViewOriginal)>()
+                    .insert(original.0.clone());
+            }
+        }
+        return;
+    };
+    let mut debug_material = |original: &Handle<StandardMaterial>| {
+        let material = standard_materials.get(original)?;
+        Some(
+            cache
+                .0
+                .entry((original.id(), view))
+                .or_insert_with(|| debug_materials.add(DebugViewMaterial::new(material, view)))
+                .clone(),
+        )
+    };
+    if active.is_changed() {
+        for (entity, original) in &replaced {
+            if let Some(handle) = debug_material(&original.0) {
+                commands.entity(entity).insert(handle);
+            }
+        }
+    }
+    // Also covers scenes that finish loading while a debug view is active
+    for (entity, original) in &standard {
+        if let Some(handle) = debug_material(original) {
+            commands
+                .entity(entity)
+                .remove::<Handle<StandardMaterial>>()
+                .insert((handle, DebugViewOriginal(original.clone())));
+        }
+    }
+}
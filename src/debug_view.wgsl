@@ -0,0 +1,62 @@
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings::view,
+}
+
+struct DebugViewParams {
+    base_color: vec4<f32>,
+    metallic: f32,
+    roughness: f32,
+    mode: u32,
+};
+
+@group(2) @binding(0) var<uniform> params: DebugViewParams;
+@group(2) @binding(1) var base_color_texture: texture_2d<f32>;
+@group(2) @binding(2) var base_color_sampler: sampler;
+@group(2) @binding(3) var metallic_roughness_texture: texture_2d<f32>;
+@group(2) @binding(4) var metallic_roughness_sampler: sampler;
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+#ifdef VERTEX_UVS
+    let uv = in.uv;
+#else
+    let uv = vec2(0.0);
+#endif
+    switch params.mode {
+        // DebugView::Overdraw
+        case 0u: {
+            // Blended additively, so each layer adds up
+            return vec4(0.08, 0.03, 0.01, 1.0);
+        }
+        // DebugView::Depth
+        case 1u: {
+            let distance = length(in.world_position.xyz - view.world_position);
+            return vec4(vec3(1.0 - distance / (distance + 10.0)), 1.0);
+        }
+        // DebugView::Normals
+        case 2u: {
+            return vec4(normalize(in.world_normal) * 0.5 + 0.5, 1.0);
+        }
+        // DebugView::BaseColor
+        case 3u: {
+            return params.base_color * textureSample(base_color_texture, base_color_sampler, uv);
+        }
+        // DebugView::MetallicRoughness
+        case 4u: {
+            // glTF packs roughness in green and metallic in blue, shown as red metallic
+            // and green roughness
+            let texel = textureSample(metallic_roughness_texture, metallic_roughness_sampler, uv);
+            return vec4(params.metallic * texel.b, params.roughness * texel.g, 0.0, 1.0);
+        }
+        // DebugView::UvChecker
+        case 5u: {
+            let cells = floor(uv * 16.0);
+            let checker = abs(cells.x + cells.y) % 2.0;
+            return vec4(fract(uv) * mix(0.4, 1.0, checker), checker * 0.5, 1.0);
+        }
+        default: {
+            return vec4(1.0, 0.0, 1.0, 1.0);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    fs,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Lists the asset packages fetched by `--download-assets`.
+pub const DOWNLOADS_MANIFEST: &str = "assets/downloads.ron";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Download {
+    pub name: String,
+    pub url: String,
+    /// Lowercase hex SHA-256 of the downloaded file.
+    pub sha256: Option<String>,
+    /// Directory to extract a zip archive into, or the path to write any other file to.
+    pub destination: PathBuf,
+}
+
+impl Download {
+    /// Destinations without an extension are directories to extract into.
+    fn is_zip(&self) -> bool {
+        self.destination.extension().is_none()
+    }
+
+    /// A directory counts as present if it has anything in it.
+    fn is_present(&self) -> bool {
+        if self.is_zip() {
+            fs::read_dir(&self.destination).is_ok_and(|mut dir| dir.next().is_some())
+        } else {
+            self.destination.exists()
+        }
+    }
+}
+
+/// Fetches, verifies, and unpacks every package in the manifest that isn't already on disk.
+pub fn download_assets(manifest: &Path) -> anyhow::Result<()> {
+    let contents = fs::read_to_string(manifest)
+        .with_context(|| format!("Failed to read {}", manifest.display()))?;
+    let downloads: Vec<Download> = ron::from_str(&contents)?;
+    for download in &downloads {
+        if download.is_present() {
+            println!(
+                "{} is already in {}",
+                download.name,
+                download.destination.display()
+            );
+            continue;
+        }
+        if download.url.is_empty() {
+            println!(
+                "{} has no download link, add one to {} or place it in {} by hand",
+                download.name,
+                manifest.display(),
+                download.destination.display()
+            );
+            continue;
+        }
+        println!("Downloading {} from {}", download.name, download.url);
+        let bytes = fetch(&download.url)?;
+        let hash = format!("{:x}", Sha256::digest(&bytes));
+        match &download.sha256 {
+            Some(expected) if !expected.eq_ignore_ascii_case(&hash) => {
+                return Err(anyhow!(
+                    "Checksum mismatch for {}, expected {expected} but got {hash}",
+                    download.name
+                ));
+            }
+            Some(_) => (),
+            None => println!("{} has sha256 {hash}", download.name),
+        }
+        if download.is_zip() {
+            fs::create_dir_all(&download.destination)?;
+            zip::ZipArchive::new(Cursor::new(bytes))?.extract(&download.destination)?;
+        } else {
+            if let Some(parent) = download.destination.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&download.destination, bytes)?;
+        }
+        println!(
+            "Saved {} to {}",
+            download.name,
+            download.destination.display()
+        );
+    }
+    Ok(())
+}
+
+fn fetch(url: &str) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ureq::get(url)
+        .call()
+        .with_context(|| format!("Failed to download {url}"))?
+        .into_reader()
+        .read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
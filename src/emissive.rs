@@ -0,0 +1,146 @@
+use bevy::{prelude::*, render::primitives::Aabb, utils::HashMap};
+
+/// Added by the [`crate::scene_rules::SceneRule::BoostEmissive`] rule to meshes whose material has
+/// an emissive texture.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct EmissiveMesh {
+    pub strength: f32,
+    /// Spawn a point light of this intensity at the center of the mesh.
+    pub light_intensity: Option<f32>,
+}
+
+/// A point light standing in for an emissive mesh.
+#[derive(Component)]
+pub struct EmissiveLight;
+
+/// Whether the emissive boost is applied, toggled with L.
+#[derive(Resource)]
+pub struct EmissiveBoost {
+    pub enabled: bool,
+    /// Emissive color each boosted material was loaded with, and how much to boost it by.
+    originals: HashMap<AssetId<StandardMaterial>, (Color, f32)>,
+}
+
+impl Default for EmissiveBoost {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            originals: HashMap::new(),
+        }
+    }
+}
+
+pub struct EmissivePlugin;
+impl Plugin for EmissivePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EmissiveBoost>().add_systems(
+            Update,
+            (
+                boost_emissive_materials,
+                spawn_emissive_lights,
+                toggle_emissive_boost,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn boost_emissive_materials(
+    meshes: Query<(&Handle<StandardMaterial>, &EmissiveMesh), Added<EmissiveMesh>>,
+    mut boost: ResMut<EmissiveBoost>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (handle, emissive) in &meshes {
+        if boost.originals.contains_key(&handle.id()) {
+            continue;
+        }
+        let Some(material) = materials.get_mut(handle) else {
+            continue;
+        };
+        boost
+            .originals
+            .insert(handle.id(), (material.emissive, emissive.strength));
+        if boost.enabled {
+            material.emissive = material.emissive * emissive.strength;
+        }
+    }
+}
+
+/// Waits for the bounds of the mesh to be calculated, then places the light at its center.
+fn spawn_emissive_lights(
+    mut commands: Commands,
+    meshes: Query<(
+        Entity,
+        &EmissiveMesh,
+        &Handle<StandardMaterial>,
+        &Aabb,
+        &GlobalTransform,
+    )>,
+    boost: Res<EmissiveBoost>,
+) {
+    for (entity, emissive, handle, aabb, transform) in &meshes {
+        commands.entity(entity).remove::<EmissiveMesh>();
+        let Some(intensity) = emissive.light_intensity else {
+            continue;
+        };
+        let color = boost
+            .originals
+            .get(&handle.id())
+            .map(|(color, _)| color.with_a(1.0))
+            .unwrap_or(Color::WHITE);
+        commands.spawn((
+            PointLightBundle {
+                transform: Transform::from_translation(
+                    transform.transform_point(aabb.center.into()),
+                ),
+                point_light: PointLight {
+                    color,
+                    intensity,
+                    range: 4.0,
+                    radius: aabb.half_extents.min_element(),
+                    shadows_enabled: false,
+                    ..default()
+                },
+                visibility: if boost.enabled {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                },
+                ..default()
+            },
+            EmissiveLight,
+        ));
+    }
+}
+
+fn toggle_emissive_boost(
+    input: Res<ButtonInput<KeyCode>>,
+    mut boost: ResMut<EmissiveBoost>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut lights: Query<&mut Visibility, With<EmissiveLight>>,
+) {
+    if !input.just_pressed(KeyCode::KeyL) {
+        return;
+    }
+    boost.enabled = !boost.enabled;
+    info!(
+        "Emissive boost {}",
+        if boost.enabled { "on" } else { "off" }
+    );
+    for (id, (original, strength)) in &boost.originals {
+        if let Some(material) = materials.get_mut(*id) {
+            material.emissive = if boost.enabled {
+                *original * *strength
+            } else {
+                *original
+            };
+        }
+    }
+    for mut visibility in &mut lights {
+        *visibility = if boost.enabled {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
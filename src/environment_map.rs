@@ -0,0 +1,115 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Lists the environment maps that can be cycled through, relative to the assets folder.
+pub const ENVIRONMENT_MAPS_MANIFEST: &str = "assets/environment_maps/environment_maps.ron";
+
+/// A prefiltered diffuse/specular cubemap pair, see `assets/environment_maps/info.txt`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnvironmentMapEntry {
+    pub name: String,
+    pub diffuse: String,
+    pub specular: String,
+    /// Each map is captured at a different exposure, so it needs its own intensity.
+    pub intensity: f32,
+    /// Equirectangular HDR to show as the skybox, converted to a cubemap once loaded. Without one
+    /// the specular map is shown.
+    #[serde(default)]
+    pub skybox: Option<String>,
+}
+
+impl EnvironmentMapEntry {
+    /// A map following the naming of the bundled maps: `{path}_diffuse_rgb9e5_zstd.ktx2` and
+    /// `{path}_specular_rgb9e5_zstd.ktx2`.
+    pub fn from_path(path: &str) -> Self {
+        Self {
+            name: path.to_string(),
+            diffuse: format!("{path}_diffuse_rgb9e5_zstd.ktx2"),
+            specular: format!("{path}_specular_rgb9e5_zstd.ktx2"),
+            intensity: 250.0,
+            skybox: None,
+        }
+    }
+
+    pub fn light(&self, asset_server: &AssetServer) -> EnvironmentMapLight {
+        EnvironmentMapLight {
+            diffuse_map: asset_server.load(&self.diffuse),
+            specular_map: asset_server.load(&self.specular),
+            intensity: self.intensity,
+        }
+    }
+}
+
+/// The environment maps from the manifest, cycled with N. Changing `current` updates the camera.
+#[derive(Resource, Clone, Debug)]
+pub struct EnvironmentMaps {
+    pub maps: Vec<EnvironmentMapEntry>,
+    pub current: usize,
+}
+
+impl EnvironmentMaps {
+    /// Reads the manifest, falling back to the bundled Pisa map. `selected` picks a map by name,
+    /// or is treated as a path prefix if it isn't in the manifest.
+    pub fn load(selected: Option<&str>) -> Self {
+        let mut maps = match fs::read_to_string(ENVIRONMENT_MAPS_MANIFEST) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring invalid {}: {}", ENVIRONMENT_MAPS_MANIFEST, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        if maps.is_empty() {
+            maps.push(EnvironmentMapEntry {
+                name: String::from("pisa"),
+                ..EnvironmentMapEntry::from_path("environment_maps/pisa")
+            });
+        }
+        let current = match selected {
+            Some(selected) => match maps.iter().position(|map| map.name == selected) {
+                Some(i) => i,
+                None => {
+                    maps.push(EnvironmentMapEntry::from_path(selected));
+                    maps.len() - 1
+                }
+            },
+            None => 0,
+        };
+        Self { maps, current }
+    }
+
+    pub fn current(&self) -> &EnvironmentMapEntry {
+        &self.maps[self.current]
+    }
+}
+
+pub struct EnvironmentMapPlugin;
+impl Plugin for EnvironmentMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (cycle_environment_map, apply_environment_map).chain(),
+        );
+    }
+}
+
+fn cycle_environment_map(input: Res<ButtonInput<KeyCode>>, mut env_maps: ResMut<EnvironmentMaps>) {
+    if input.just_pressed(KeyCode::KeyN) && env_maps.maps.len() > 1 {
+        env_maps.current = (env_maps.current + 1) % env_maps.maps.len();
+        info!("Environment map: {}", env_maps.current().name);
+    }
+}
+
+fn apply_environment_map(
+    asset_server: Res<AssetServer>,
+    env_maps: Res<EnvironmentMaps>,
+    mut cameras: Query<&mut EnvironmentMapLight, With<Camera3d>>,
+) {
+    if !env_maps.is_changed() {
+        return;
+    }
+    for mut light in &mut cameras {
+        *light = env_maps.current().light(&asset_server);
+    }
+}
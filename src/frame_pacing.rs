@@ -0,0 +1,89 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+
+/// Sleeping is only accurate to about a millisecond, the rest of the wait is spun.
+const SPIN_TIME: Duration = Duration::from_millis(1);
+
+/// Caps the frame rate by waiting at the end of each frame until the frame time has passed, see
+/// `--max-fps`. Keeps laptops from heating up and throttling partway through long benchmarks.
+#[derive(Resource)]
+pub struct FrameLimiter {
+    pub frame_time: Duration,
+    frame_start: Option<Instant>,
+}
+
+impl FrameLimiter {
+    pub fn new(max_fps: f32) -> Self {
+        Self {
+            frame_time: Duration::from_secs_f32(1.0 / max_fps.max(1.0)),
+            frame_start: None,
+        }
+    }
+
+    /// Frames that take longer than this many milliseconds missed the cap.
+    pub fn late_threshold_ms(&self) -> f32 {
+        self.frame_time.as_secs_f32() * 1000.0 * 1.5
+    }
+}
+
+/// Advances time by `frame_time` every frame instead of by the wall clock, so camera paths,
+/// benchmark transitions, the time of day, and the wind play out over the same frames on every
+/// machine. See `--fixed-timestep`.
+#[derive(Resource, Clone, Copy)]
+pub struct FixedTimestep {
+    pub frame_time: Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(fps: f32) -> Self {
+        Self {
+            frame_time: Duration::from_secs_f32(1.0 / fps.max(1.0)),
+        }
+    }
+
+    /// How time should advance, with or without a fixed timestep.
+    pub fn strategy(fixed: Option<&FixedTimestep>) -> TimeUpdateStrategy {
+        match fixed {
+            Some(fixed) => TimeUpdateStrategy::ManualDuration(fixed.frame_time),
+            None => TimeUpdateStrategy::Automatic,
+        }
+    }
+}
+
+pub struct FramePacingPlugin;
+impl Plugin for FramePacingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, apply_fixed_timestep)
+            .add_systems(Last, limit_frame_rate);
+    }
+}
+
+fn apply_fixed_timestep(mut commands: Commands, fixed: Option<Res<FixedTimestep>>) {
+    if let Some(fixed) = fixed {
+        commands.insert_resource(FixedTimestep::strategy(Some(&fixed)));
+    }
+}
+
+fn limit_frame_rate(limiter: Option<ResMut<FrameLimiter>>) {
+    let Some(mut limiter) = limiter else {
+        return;
+    };
+    if let Some(start) = limiter.frame_start {
+        let deadline = start + limiter.frame_time;
+        let now = Instant::now();
+        if deadline > now + SPIN_TIME {
+            thread::sleep(deadline - now - SPIN_TIME);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+        // Late frames start the next one right away instead of trying to catch up
+        limiter.frame_start = Some(deadline.max(now));
+    } else {
+        limiter.frame_start = Some(Instant::now());
+    }
+}
@@ -0,0 +1,297 @@
+use std::{f32::consts::PI, path::PathBuf};
+
+use bevy::{prelude::*, render::primitives::Aabb, utils::HashMap};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    irradiance_volume::IrradianceVolumeData,
+    loading::SceneReady,
+    sky::{sky_radiance, SKY_BRIGHTNESS},
+    walk::SceneColliders,
+    GrifLight,
+};
+
+/// Rays traced from each probe, spread evenly over the sphere.
+const RAYS_PER_PROBE: usize = 128;
+const MAX_RAY_DISTANCE: f32 = 200.0;
+/// Probes that see the back of this many faces are inside a wall and get their neighbors' light.
+const MAX_BACKFACE_FRACTION: f32 = 0.25;
+/// Rough share of the sky visible from surfaces inside the scene, lighting them on top of the sun.
+const SKY_VISIBILITY: f32 = 0.2;
+/// Frames to wait after the colliders are built, so they're in Rapier's query pipeline.
+const COLLIDER_SETTLE_FRAMES: u32 = 3;
+
+/// Bakes a single bounce of sun and sky light into an irradiance volume covering the scene, and
+/// writes it to `path` for `--irradiance-volume`. Only active when the resource is present, see
+/// `--bake-gi`. Needs the `physics` feature, the rays are traced against its colliders.
+#[derive(Resource, Clone, Debug)]
+pub struct GiBake {
+    pub path: PathBuf,
+    /// Distance between probes, in meters.
+    pub spacing: f32,
+    settled_frames: u32,
+    done: bool,
+}
+
+impl GiBake {
+    pub fn new(path: PathBuf, spacing: f32) -> Self {
+        Self {
+            path,
+            spacing,
+            settled_frames: 0,
+            done: false,
+        }
+    }
+}
+
+pub struct GiBakePlugin;
+impl Plugin for GiBakePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, bake_gi);
+    }
+}
+
+/// Directions spread evenly over the sphere, on a Fibonacci spiral.
+fn sphere_directions(count: usize) -> Vec<Vec3> {
+    let golden_angle = PI * (3.0 - 5.0f32.sqrt());
+    (0..count)
+        .map(|i| {
+            let y = 1.0 - (i as f32 + 0.5) / count as f32 * 2.0;
+            let radius = (1.0 - y * y).sqrt();
+            let theta = golden_angle * i as f32;
+            Vec3::new(theta.cos() * radius, y, theta.sin() * radius)
+        })
+        .collect()
+}
+
+/// Linear average color of an uncompressed 8 bit texture, the bake only needs rough albedos.
+fn average_color(image: &Image) -> Option<Vec3> {
+    let srgb = match image.texture_descriptor.format {
+        bevy::render::render_resource::TextureFormat::Rgba8UnormSrgb => true,
+        bevy::render::render_resource::TextureFormat::Rgba8Unorm => false,
+        _ => return None,
+    };
+    let size = image.texture_descriptor.size;
+    let texels = (size.width * size.height) as usize;
+    // Every few texels of the first mip is plenty
+    let step = (texels / 4096).max(1);
+    let mut sum = Vec3::ZERO;
+    let mut count = 0;
+    for texel in image.data[..texels * 4].chunks_exact(4).step_by(step) {
+        let color = Vec3::new(texel[0] as f32, texel[1] as f32, texel[2] as f32) / 255.0;
+        sum += if srgb { color.powf(2.2) } else { color };
+        count += 1;
+    }
+    (count > 0).then(|| sum / count as f32)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn bake_gi(
+    mut commands: Commands,
+    bake: Option<ResMut<GiBake>>,
+    scene_ready: Res<SceneReady>,
+    mut colliders: ResMut<SceneColliders>,
+    rapier: Res<RapierContext>,
+    suns: Query<(&DirectionalLight, &GlobalTransform), With<GrifLight>>,
+    meshes: Query<(&Aabb, &GlobalTransform, Option<&Handle<StandardMaterial>>), With<Collider>>,
+    standard_materials: Res<Assets<StandardMaterial>>,
+    images: Res<Assets<Image>>,
+) {
+    let Some(mut bake) = bake else {
+        return;
+    };
+    if bake.done || !scene_ready.ready {
+        return;
+    }
+    colliders.wanted = true;
+    if !colliders.built {
+        return;
+    }
+    bake.settled_frames += 1;
+    if bake.settled_frames < COLLIDER_SETTLE_FRAMES {
+        return;
+    }
+    bake.done = true;
+
+    // Cover everything that has a collider
+    let (mut min, mut max) = (Vec3::MAX, Vec3::MIN);
+    for (aabb, transform, _) in &meshes {
+        for corner in 0..8 {
+            let sign = Vec3::new(
+                if corner & 1 == 0 { -1.0 } else { 1.0 },
+                if corner & 2 == 0 { -1.0 } else { 1.0 },
+                if corner & 4 == 0 { -1.0 } else { 1.0 },
+            );
+            let point = transform
+                .transform_point(Vec3::from(aabb.center) + Vec3::from(aabb.half_extents) * sign);
+            min = min.min(point);
+            max = max.max(point);
+        }
+    }
+    if min.cmpgt(max).any() {
+        warn!("Nothing to bake, the scene has no colliders");
+        return;
+    }
+    let size = max - min;
+    let resolution = (size / bake.spacing)
+        .ceil()
+        .as_uvec3()
+        .clamp(UVec3::ONE, UVec3::splat(64));
+    let transform = Transform::from_translation((min + max) * 0.5).with_scale(size);
+
+    let (sun_direction, sun_illuminance) = suns
+        .iter()
+        .next()
+        .map(|(light, transform)| (-transform.forward(), light.illuminance))
+        .unwrap_or((Vec3::Y, 0.0));
+    let sky = |dir: Vec3| sky_radiance(dir, sun_direction) * SKY_BRIGHTNESS;
+    let sky_irradiance = sky(Vec3::Y) * PI * SKY_VISIBILITY;
+
+    let mut albedos = HashMap::new();
+    let mut albedo = |entity: Entity| -> Vec3 {
+        let Some(handle) = meshes.get(entity).ok().and_then(|(_, _, handle)| handle) else {
+            return Vec3::splat(0.5);
+        };
+        *albedos.entry(handle.id()).or_insert_with(|| {
+            let Some(material) = standard_materials.get(handle) else {
+                return Vec3::splat(0.5);
+            };
+            let base = material.base_color.as_rgba_linear();
+            let texture = material
+                .base_color_texture
+                .as_ref()
+                .and_then(|texture| images.get(texture))
+                .and_then(average_color)
+                .unwrap_or(Vec3::ONE);
+            Vec3::new(base.r(), base.g(), base.b()) * texture
+        })
+    };
+
+    let probe_count = resolution.x * resolution.y * resolution.z;
+    info!(
+        "Baking {}x{}x{} irradiance probes, this can take a while",
+        resolution.x, resolution.y, resolution.z
+    );
+    let directions = sphere_directions(RAYS_PER_PROBE);
+    let filter = QueryFilter::default();
+    let mut voxels = Vec::with_capacity(probe_count as usize);
+    let mut valid = Vec::with_capacity(probe_count as usize);
+    for z in 0..resolution.z {
+        for y in 0..resolution.y {
+            for x in 0..resolution.x {
+                let probe = IrradianceVolumeData::probe_position(
+                    &transform,
+                    resolution,
+                    UVec3::new(x, y, z),
+                );
+                let mut cube = [Vec3::ZERO; 6];
+                let mut weights = [0.0; 6];
+                let mut backfaces = 0;
+                for &dir in &directions {
+                    let radiance = match rapier.cast_ray_and_get_normal(
+                        probe,
+                        dir,
+                        MAX_RAY_DISTANCE,
+                        false,
+                        filter,
+                    ) {
+                        Some((_, hit)) if hit.normal.dot(dir) > 0.0 => {
+                            backfaces += 1;
+                            Vec3::ZERO
+                        }
+                        Some((entity, hit)) => {
+                            let n_dot_l = hit.normal.dot(sun_direction).max(0.0);
+                            let sunlit = n_dot_l > 0.0
+                                && rapier
+                                    .cast_ray(
+                                        hit.point + hit.normal * 0.01,
+                                        sun_direction,
+                                        MAX_RAY_DISTANCE,
+                                        true,
+                                        filter,
+                                    )
+                                    .is_none();
+                            let irradiance = sky_irradiance
+                                + if sunlit {
+                                    Vec3::splat(sun_illuminance * n_dot_l)
+                                } else {
+                                    Vec3::ZERO
+                                };
+                            albedo(entity) * irradiance / PI
+                        }
+                        None => sky(dir),
+                    };
+                    // Cosine weighted onto the faces of the ambient cube
+                    for (face, axis) in [
+                        Vec3::X,
+                        Vec3::NEG_X,
+                        Vec3::Y,
+                        Vec3::NEG_Y,
+                        Vec3::Z,
+                        Vec3::NEG_Z,
+                    ]
+                    .into_iter()
+                    .enumerate()
+                    {
+                        let weight = dir.dot(axis).max(0.0);
+                        cube[face] += radiance * weight;
+                        weights[face] += weight;
+                    }
+                }
+                for (value, weight) in cube.iter_mut().zip(weights) {
+                    *value /= weight.max(f32::EPSILON);
+                }
+                voxels.push(cube);
+                valid.push((backfaces as f32) < RAYS_PER_PROBE as f32 * MAX_BACKFACE_FRACTION);
+            }
+        }
+    }
+
+    // Probes stuck inside walls would leak darkness, borrow the light of their neighbors instead
+    let index = |v: IVec3| (v.x + resolution.x as i32 * (v.y + resolution.y as i32 * v.z)) as usize;
+    let snapshot = voxels.clone();
+    for z in 0..resolution.z as i32 {
+        for y in 0..resolution.y as i32 {
+            for x in 0..resolution.x as i32 {
+                let voxel = IVec3::new(x, y, z);
+                if valid[index(voxel)] {
+                    continue;
+                }
+                let neighbors: Vec<_> = [
+                    IVec3::X,
+                    IVec3::NEG_X,
+                    IVec3::Y,
+                    IVec3::NEG_Y,
+                    IVec3::Z,
+                    IVec3::NEG_Z,
+                ]
+                .into_iter()
+                .map(|offset| voxel + offset)
+                .filter(|v| v.cmpge(IVec3::ZERO).all() && v.cmplt(resolution.as_ivec3()).all())
+                .filter(|v| valid[index(*v)])
+                .collect();
+                let mut cube = [Vec3::ZERO; 6];
+                for neighbor in &neighbors {
+                    for (value, other) in cube.iter_mut().zip(snapshot[index(*neighbor)]) {
+                        *value += other / neighbors.len() as f32;
+                    }
+                }
+                voxels[index(voxel)] = cube;
+            }
+        }
+    }
+
+    let volume = IrradianceVolumeData {
+        transform,
+        resolution,
+        voxels,
+    };
+    match volume.save(&bake.path) {
+        Ok(()) => info!("Saved irradiance volume to {}", bake.path.display()),
+        Err(e) => error!(
+            "Failed to save irradiance volume to {}: {e}",
+            bake.path.display()
+        ),
+    }
+    commands.insert_resource(volume);
+}
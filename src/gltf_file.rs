@@ -0,0 +1,166 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F534A;
+const CHUNK_BIN: u32 = 0x004E4942;
+
+/// A glTF document read from a .gltf file or a .glb container, edited as JSON so extensions and
+/// anything else this doesn't know about are kept as they are.
+pub struct GltfFile {
+    pub json: serde_json::Value,
+    /// The binary chunk of a .glb, which is the buffer without a uri.
+    pub bin: Option<Vec<u8>>,
+    pub glb: bool,
+}
+
+/// Where the bytes of an image come from.
+pub enum ImageSource {
+    /// A file, relative to the glTF file.
+    Uri(PathBuf),
+    /// Bytes embedded in a buffer, and their MIME type.
+    Embedded(Vec<u8>, String),
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().unwrap(),
+    ))
+}
+
+impl GltfFile {
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if !bytes.starts_with(GLB_MAGIC) {
+            let json = serde_json::from_slice(&bytes)
+                .with_context(|| format!("Invalid {}", path.display()))?;
+            return Ok(Self {
+                json,
+                bin: None,
+                glb: false,
+            });
+        }
+        let (mut json, mut bin) = (None, None);
+        let mut offset = 12;
+        while let (Some(len), Some(kind)) = (read_u32(&bytes, offset), read_u32(&bytes, offset + 4))
+        {
+            let data = bytes
+                .get(offset + 8..offset + 8 + len as usize)
+                .ok_or_else(|| anyhow!("Truncated chunk in {}", path.display()))?;
+            match kind {
+                CHUNK_JSON => {
+                    json = Some(
+                        serde_json::from_slice(data)
+                            .with_context(|| format!("Invalid {}", path.display()))?,
+                    )
+                }
+                CHUNK_BIN => bin = Some(data.to_vec()),
+                _ => (),
+            }
+            offset += 8 + len as usize;
+        }
+        Ok(Self {
+            json: json.ok_or_else(|| anyhow!("No JSON chunk in {}", path.display()))?,
+            bin,
+            glb: true,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        if !self.glb {
+            fs::write(path, serde_json::to_vec_pretty(&self.json)?)?;
+            return Ok(());
+        }
+        // Chunks are 4 byte aligned, JSON is padded with spaces and binary with zeros
+        let mut json = serde_json::to_vec(&self.json)?;
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut chunks = vec![(CHUNK_JSON, json)];
+        if let Some(bin) = &self.bin {
+            let mut bin = bin.clone();
+            bin.resize(bin.len().next_multiple_of(4), 0);
+            chunks.push((CHUNK_BIN, bin));
+        }
+        let len = 12 + chunks.iter().map(|(_, data)| 8 + data.len()).sum::<usize>();
+        let mut bytes = GLB_MAGIC.to_vec();
+        bytes.extend_from_slice(&GLB_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(len as u32).to_le_bytes());
+        for (kind, data) in chunks {
+            bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&kind.to_le_bytes());
+            bytes.extend_from_slice(&data);
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    pub fn image_count(&self) -> usize {
+        self.json["images"].as_array().map_or(0, Vec::len)
+    }
+
+    /// Files the buffers are read from, relative to the glTF file.
+    pub fn buffer_uris(&self) -> Vec<PathBuf> {
+        self.json["buffers"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|buffer| buffer["uri"].as_str())
+            .filter(|uri| !uri.starts_with("data:"))
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    /// Where image `index` gets its bytes. `dir` is the glTF file's directory, for images
+    /// embedded in an external buffer.
+    pub fn image_source(&self, index: usize, dir: &Path) -> anyhow::Result<ImageSource> {
+        let image = &self.json["images"][index];
+        if let Some(uri) = image["uri"].as_str() {
+            if uri.starts_with("data:") {
+                return Err(anyhow!("Images in data URIs aren't supported"));
+            }
+            return Ok(ImageSource::Uri(PathBuf::from(uri)));
+        }
+        let view = image["bufferView"]
+            .as_u64()
+            .map(|view| &self.json["bufferViews"][view as usize])
+            .ok_or_else(|| anyhow!("Image has neither a uri nor a buffer view"))?;
+        let offset = view["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let len = view["byteLength"].as_u64().unwrap_or(0) as usize;
+        let buffer = view["buffer"]
+            .as_u64()
+            .map(|buffer| &self.json["buffers"][buffer as usize])
+            .ok_or_else(|| anyhow!("Buffer view has no buffer"))?;
+        let external;
+        let data = match buffer["uri"].as_str() {
+            None => self
+                .bin
+                .as_ref()
+                .ok_or_else(|| anyhow!("Buffer has no uri and there's no binary chunk"))?,
+            Some(uri) if !uri.starts_with("data:") => {
+                external = fs::read(dir.join(uri))
+                    .with_context(|| format!("Failed to read buffer {uri}"))?;
+                &external
+            }
+            Some(_) => return Err(anyhow!("Buffers in data URIs aren't supported")),
+        };
+        let bytes = data
+            .get(offset..offset + len)
+            .ok_or_else(|| anyhow!("Buffer view is out of bounds"))?;
+        let mime_type = image["mimeType"].as_str().unwrap_or("image/png");
+        Ok(ImageSource::Embedded(bytes.to_vec(), mime_type.to_string()))
+    }
+
+    /// Points image `index` at a file relative to the glTF file. An embedded copy's bytes are
+    /// left in the buffer, only the reference to them is dropped.
+    pub fn set_image_uri(&mut self, index: usize, uri: &str) {
+        if let Some(image) = self.json["images"][index].as_object_mut() {
+            image.remove("bufferView");
+            image.remove("mimeType");
+            image.insert("uri".to_string(), uri.into());
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+/// Luminous efficacy Blender uses to turn watts into lumens when exporting lights.
+const WATTS_TO_LUMENS: f32 = 683.0;
+
+/// Keeps the lights that came with the glTF instead of despawning them. Only active when the
+/// resource is present, see `--use-gltf-lights`.
+///
+/// Bevy already reads point and spot intensities as candela and converts them to lumens, but
+/// scenes exported from Blender without its "Standard" lighting mode store the raw watts, so the
+/// lights come out hundreds of times too dim. They're rescaled as if they were watts, then by
+/// `scale` for any remaining tuning.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct GltfLights {
+    pub scale: f32,
+    /// How many of the brightest lights cast shadows, the rest have them turned off.
+    pub shadow_count: usize,
+}
+
+/// A light that was loaded from the glTF and kept, see [`GltfLights`].
+#[derive(Component)]
+pub struct GltfLight;
+
+pub struct GltfLightsPlugin;
+impl Plugin for GltfLightsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (rescale_gltf_lights, limit_gltf_light_shadows).chain(),
+        );
+    }
+}
+
+fn rescale_gltf_lights(
+    settings: Option<Res<GltfLights>>,
+    mut directional: Query<&mut DirectionalLight, Added<GltfLight>>,
+    mut spots: Query<&mut SpotLight, Added<GltfLight>>,
+    mut points: Query<&mut PointLight, Added<GltfLight>>,
+) {
+    let Some(settings) = settings else {
+        return;
+    };
+    for mut light in &mut directional {
+        light.illuminance *= WATTS_TO_LUMENS * settings.scale;
+    }
+    // Watts to candela, Bevy's loader already multiplied by 4 PI to get lumens
+    for mut light in &mut spots {
+        light.intensity *= WATTS_TO_LUMENS / (4.0 * PI) * settings.scale;
+    }
+    for mut light in &mut points {
+        light.intensity *= WATTS_TO_LUMENS / (4.0 * PI) * settings.scale;
+    }
+}
+
+/// Shadows are expensive, so only the brightest lights get them. Directional lights come first
+/// since they light the whole scene.
+fn limit_gltf_light_shadows(
+    settings: Option<Res<GltfLights>>,
+    added: Query<(), Added<GltfLight>>,
+    mut directional: Query<(Entity, &mut DirectionalLight), With<GltfLight>>,
+    mut spots: Query<(Entity, &mut SpotLight), With<GltfLight>>,
+    mut points: Query<(Entity, &mut PointLight), With<GltfLight>>,
+) {
+    let Some(settings) = settings else {
+        return;
+    };
+    if added.is_empty() {
+        return;
+    }
+    let mut ranked: Vec<(Entity, f32)> = spots
+        .iter()
+        .map(|(entity, light)| (entity, light.intensity))
+        .chain(
+            points
+                .iter()
+                .map(|(entity, light)| (entity, light.intensity)),
+        )
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut ranked: Vec<Entity> = ranked.into_iter().map(|(entity, _)| entity).collect();
+    ranked.splice(0..0, directional.iter().map(|(entity, _)| entity));
+    ranked.truncate(settings.shadow_count);
+
+    for (entity, mut light) in &mut directional {
+        light.shadows_enabled = ranked.contains(&entity);
+    }
+    for (entity, mut light) in &mut spots {
+        light.shadows_enabled = ranked.contains(&entity);
+    }
+    for (entity, mut light) in &mut points {
+        light.shadows_enabled = ranked.contains(&entity);
+    }
+    info!(
+        "Kept {} glTF lights, {} with shadows",
+        directional.iter().count() + spots.iter().count() + points.iter().count(),
+        ranked.len()
+    );
+}
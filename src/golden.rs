@@ -0,0 +1,271 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    app::AppExit, prelude::*, render::view::screenshot::ScreenshotManager, window::PrimaryWindow,
+};
+use image::{imageops, GrayImage};
+
+use crate::{
+    camera_controller::cut_to, loading::SceneReady, scene_preset::ScenePreset,
+    screenshot::CAPTURE_DELAY_FRAMES,
+};
+
+/// Side of the square windows SSIM is computed over.
+const SSIM_WINDOW: u32 = 8;
+
+/// Outcome of comparing one camera position against its golden image.
+#[derive(Clone, Debug)]
+enum GoldenResult {
+    Ssim(f32),
+    /// There was no golden image yet, the capture was saved as the new one.
+    Created,
+    Error(String),
+}
+
+/// Renders each of the scene's camera positions and compares it against a golden PNG of the same
+/// name in `dir`, see `--compare-golden`. Prints a report and exits, with an error if any
+/// position scored below `min_ssim`.
+#[derive(Resource)]
+pub struct GoldenComparison {
+    pub dir: PathBuf,
+    pub min_ssim: f32,
+    step: usize,
+    frames_at_step: u32,
+    pending: Option<Arc<Mutex<Option<GoldenResult>>>>,
+    results: Vec<(String, GoldenResult)>,
+}
+
+impl GoldenComparison {
+    pub fn new(dir: impl Into<PathBuf>, min_ssim: f32) -> Self {
+        Self {
+            dir: dir.into(),
+            min_ssim,
+            step: 0,
+            frames_at_step: 0,
+            pending: None,
+            results: Vec::new(),
+        }
+    }
+
+    fn passed(&self, result: &GoldenResult) -> bool {
+        match result {
+            GoldenResult::Ssim(ssim) => *ssim >= self.min_ssim,
+            GoldenResult::Created => true,
+            GoldenResult::Error(_) => false,
+        }
+    }
+}
+
+pub struct GoldenPlugin;
+impl Plugin for GoldenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, compare_golden);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn compare_golden(
+    mut commands: Commands,
+    golden: Option<ResMut<GoldenComparison>>,
+    scene_ready: Res<SceneReady>,
+    preset: Res<ScenePreset>,
+    mut camera: Query<(Entity, &mut Transform), With<Camera3d>>,
+    window: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let Some(mut golden) = golden else {
+        return;
+    };
+    if !scene_ready.ready {
+        return;
+    }
+    if let Some(pending) = &golden.pending {
+        let Some(result) = pending.lock().unwrap().take() else {
+            return;
+        };
+        let (name, _) = &preset.cameras[golden.step];
+        golden.results.push((name.clone(), result));
+        golden.pending = None;
+        golden.step += 1;
+        golden.frames_at_step = 0;
+    }
+    let Some((name, position)) = preset.cameras.get(golden.step) else {
+        print_report(&golden);
+        if golden
+            .results
+            .iter()
+            .all(|(_, result)| golden.passed(result))
+        {
+            app_exit.send(AppExit);
+        } else {
+            std::process::exit(1);
+        }
+        return;
+    };
+    let (Ok((entity, mut transform)), Ok(window)) = (camera.get_single_mut(), window.get_single())
+    else {
+        return;
+    };
+    if golden.frames_at_step == 0 {
+        cut_to(&mut commands, entity, &mut transform, *position, None);
+    }
+    golden.frames_at_step += 1;
+    if golden.frames_at_step < CAPTURE_DELAY_FRAMES {
+        return;
+    }
+
+    let file_name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let golden_path = golden.dir.join(format!("{file_name}.png"));
+    let actual_path = golden.dir.join(format!("{file_name}.actual.png"));
+    let pending = Arc::new(Mutex::new(None));
+    golden.pending = Some(pending.clone());
+    let result = screenshot_manager.take_screenshot(window, move |image| {
+        let result = match image.try_into_dynamic() {
+            Ok(image) => compare(image.to_rgb8(), &golden_path, &actual_path),
+            Err(e) => GoldenResult::Error(format!("Failed to convert screenshot: {e:?}")),
+        };
+        *pending.lock().unwrap() = Some(result);
+    });
+    if let Err(e) = result {
+        warn!("Screenshot not taken: {:?}", e);
+        golden.pending = None;
+        golden.frames_at_step = 0;
+    }
+}
+
+fn compare(actual: image::RgbImage, golden_path: &Path, actual_path: &Path) -> GoldenResult {
+    if !golden_path.exists() {
+        if let Some(dir) = golden_path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        return match actual.save(golden_path) {
+            Ok(_) => GoldenResult::Created,
+            Err(e) => GoldenResult::Error(format!("Failed to save new golden image: {e}")),
+        };
+    }
+    let expected = match image::open(golden_path) {
+        Ok(image) => image.to_luma8(),
+        Err(e) => return GoldenResult::Error(format!("Failed to read golden image: {e}")),
+    };
+    let actual_luma = imageops::grayscale(&actual);
+    if expected.dimensions() != actual_luma.dimensions() {
+        let _ = actual.save(actual_path);
+        return GoldenResult::Error(format!(
+            "Size {:?} doesn't match the golden image {:?}",
+            actual_luma.dimensions(),
+            expected.dimensions()
+        ));
+    }
+    let ssim = ssim(&expected, &actual_luma);
+    // Keep the capture around to look at when it differs
+    let _ = actual.save(actual_path);
+    GoldenResult::Ssim(ssim)
+}
+
+/// Mean structural similarity of two equally sized images, computed over non overlapping
+/// windows. 1.0 for identical images.
+fn ssim(a: &GrayImage, b: &GrayImage) -> f32 {
+    const C1: f32 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f32 = (0.03 * 255.0) * (0.03 * 255.0);
+    let (width, height) = a.dimensions();
+    let mut total = 0.0;
+    let mut windows = 0;
+    for y in (0..height.saturating_sub(SSIM_WINDOW - 1)).step_by(SSIM_WINDOW as usize) {
+        for x in (0..width.saturating_sub(SSIM_WINDOW - 1)).step_by(SSIM_WINDOW as usize) {
+            let pixels = (0..SSIM_WINDOW).flat_map(|dy| {
+                (0..SSIM_WINDOW).map(move |dx| {
+                    (
+                        a.get_pixel(x + dx, y + dy)[0] as f32,
+                        b.get_pixel(x + dx, y + dy)[0] as f32,
+                    )
+                })
+            });
+            let n = (SSIM_WINDOW * SSIM_WINDOW) as f32;
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+                (0.0, 0.0, 0.0, 0.0, 0.0);
+            for (pa, pb) in pixels {
+                sum_a += pa;
+                sum_b += pb;
+                sum_aa += pa * pa;
+                sum_bb += pb * pb;
+                sum_ab += pa * pb;
+            }
+            let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+            let var_a = sum_aa / n - mean_a * mean_a;
+            let var_b = sum_bb / n - mean_b * mean_b;
+            let covariance = sum_ab / n - mean_a * mean_b;
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    if windows == 0 {
+        return 1.0;
+    }
+    total / windows as f32
+}
+
+fn print_report(golden: &GoldenComparison) {
+    println!(
+        "Golden image comparison against {}, minimum SSIM {:.3}",
+        golden.dir.display(),
+        golden.min_ssim
+    );
+    for (name, result) in &golden.results {
+        let status = if golden.passed(result) { "ok" } else { "FAIL" };
+        match result {
+            GoldenResult::Ssim(ssim) => println!("{status:>4} {name}: SSIM {ssim:.4}"),
+            GoldenResult::Created => println!("{status:>4} {name}: saved as new golden image"),
+            GoldenResult::Error(e) => println!("{status:>4} {name}: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    fn gradient(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| Luma([((x * 7 + y * 3) % 256) as u8]))
+    }
+
+    #[test]
+    fn identical_images_score_one() {
+        let image = gradient(64, 64);
+        assert!((ssim(&image, &image) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn small_changes_score_high() {
+        let a = gradient(64, 64);
+        let mut b = a.clone();
+        b.put_pixel(10, 10, Luma([a.get_pixel(10, 10)[0].wrapping_add(20)]));
+        let score = ssim(&a, &b);
+        assert!(score < 1.0);
+        assert!(score > 0.95);
+    }
+
+    #[test]
+    fn inverted_images_score_low() {
+        let a = gradient(64, 64);
+        let mut b = a.clone();
+        imageops::invert(&mut b);
+        assert!(ssim(&a, &b) < 0.0);
+    }
+
+    #[test]
+    fn images_smaller_than_a_window_score_one() {
+        let a = gradient(4, 4);
+        let b = GrayImage::new(4, 4);
+        assert_eq!(ssim(&a, &b), 1.0);
+    }
+}
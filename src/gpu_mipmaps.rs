@@ -0,0 +1,250 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    asset::load_internal_asset,
+    core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    prelude::*,
+    render::{
+        graph::CameraDriverLabel,
+        render_asset::RenderAssets,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
+        render_resource::{
+            binding_types::{sampler, texture_2d},
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedRenderPipelineId,
+            ColorTargetState, ColorWrites, FilterMode, FragmentState, LoadOp, Operations,
+            PipelineCache, RenderPassColorAttachment, RenderPassDescriptor,
+            RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages,
+            SpecializedRenderPipeline, SpecializedRenderPipelines, StoreOp, TextureFormat,
+            TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
+        },
+        renderer::{RenderContext, RenderDevice},
+        Render, RenderApp, RenderSet,
+    },
+};
+
+const MIPMAP_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(30781295174624891046281937512);
+
+/// Images waiting to have their mip chain rendered, shared between the main and render world.
+#[derive(Resource, Clone, Default)]
+pub struct GpuMipmapQueue(Arc<Mutex<Vec<AssetId<Image>>>>);
+
+impl GpuMipmapQueue {
+    pub fn push(&self, image: AssetId<Image>) {
+        self.0.lock().unwrap().push(image);
+    }
+}
+
+/// Whether the GPU path can render mips for this format.
+pub fn gpu_mips_supported(format: TextureFormat) -> bool {
+    matches!(
+        format,
+        TextureFormat::R8Unorm
+            | TextureFormat::Rg8Unorm
+            | TextureFormat::Rgba8Unorm
+            | TextureFormat::Rgba8UnormSrgb
+    )
+}
+
+/// Grows `image` to hold a full mip chain, leaving the new levels zeroed for the GPU to fill in.
+/// Returns the new mip level count.
+pub fn allocate_gpu_mip_chain(image: &mut Image, minimum_mip_resolution: u32) -> u32 {
+    let descriptor = &mut image.texture_descriptor;
+    let pixel_size = descriptor.format.block_copy_size(None).unwrap_or(4) as usize;
+    let (mut width, mut height) = (descriptor.size.width, descriptor.size.height);
+    let mut mip_level_count = 1;
+    let mut len = image.data.len();
+    while width / 2 >= minimum_mip_resolution.max(1) && height / 2 >= minimum_mip_resolution.max(1)
+    {
+        width /= 2;
+        height /= 2;
+        len += width as usize * height as usize * pixel_size;
+        mip_level_count += 1;
+    }
+    image.data.resize(len, 0);
+    descriptor.mip_level_count = mip_level_count;
+    descriptor.usage |= TextureUsages::RENDER_ATTACHMENT;
+    mip_level_count
+}
+
+/// Renders mip chains with a chain of downsampling passes instead of filtering them on the CPU,
+/// see [`crate::mipmap_generator::MipmapGeneratorSettings::gpu`].
+pub struct GpuMipmapPlugin;
+impl Plugin for GpuMipmapPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            MIPMAP_SHADER_HANDLE,
+            "gpu_mipmaps.wgsl",
+            Shader::from_wgsl
+        );
+        app.init_resource::<GpuMipmapQueue>();
+    }
+
+    fn finish(&self, app: &mut App) {
+        let queue = app.world.resource::<GpuMipmapQueue>().clone();
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(queue)
+            .init_resource::<MipmapPipeline>()
+            .init_resource::<SpecializedRenderPipelines<MipmapPipeline>>()
+            .init_resource::<GpuMipmapJobs>()
+            .add_systems(Render, queue_gpu_mipmaps.in_set(RenderSet::Queue));
+
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        graph.add_node(GpuMipmapLabel, GpuMipmapNode);
+        graph.add_node_edge(GpuMipmapLabel, CameraDriverLabel);
+    }
+}
+
+#[derive(Resource)]
+struct MipmapPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+}
+
+impl FromWorld for MipmapPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "mipmap_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                ),
+            ),
+        );
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            label: Some("mipmap_sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..default()
+        });
+        Self { layout, sampler }
+    }
+}
+
+impl SpecializedRenderPipeline for MipmapPipeline {
+    type Key = TextureFormat;
+
+    fn specialize(&self, format: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("mipmap_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            push_constant_ranges: Vec::new(),
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: MIPMAP_SHADER_HANDLE,
+                shader_defs: Vec::new(),
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: default(),
+            depth_stencil: None,
+            multisample: default(),
+        }
+    }
+}
+
+/// Images that have been uploaded and are waiting for their pipeline to compile.
+#[derive(Resource, Default)]
+struct GpuMipmapJobs {
+    /// Queued images that haven't been uploaded yet.
+    waiting: Vec<AssetId<Image>>,
+    ready: Mutex<Vec<(AssetId<Image>, CachedRenderPipelineId)>>,
+}
+
+fn queue_gpu_mipmaps(
+    queue: Res<GpuMipmapQueue>,
+    mut jobs: ResMut<GpuMipmapJobs>,
+    images: Res<RenderAssets<Image>>,
+    pipeline: Res<MipmapPipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<MipmapPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+) {
+    let jobs = &mut *jobs;
+    jobs.waiting.append(&mut queue.0.lock().unwrap());
+    let ready = jobs.ready.get_mut().unwrap();
+    jobs.waiting.retain(|id| {
+        let Some(image) = images.get(*id) else {
+            return true;
+        };
+        let pipeline_id = pipelines.specialize(&pipeline_cache, &pipeline, image.texture_format);
+        ready.push((*id, pipeline_id));
+        false
+    });
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct GpuMipmapLabel;
+
+struct GpuMipmapNode;
+
+impl Node for GpuMipmapNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let jobs = world.resource::<GpuMipmapJobs>();
+        let images = world.resource::<RenderAssets<Image>>();
+        let pipeline = world.resource::<MipmapPipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        jobs.ready.lock().unwrap().retain(|(id, pipeline_id)| {
+            let Some(image) = images.get(*id) else {
+                // Removed before its mips were rendered
+                return false;
+            };
+            let Some(render_pipeline) = pipeline_cache.get_render_pipeline(*pipeline_id) else {
+                return true;
+            };
+            let level_view = |level| -> TextureView {
+                image
+                    .texture
+                    .create_view(&TextureViewDescriptor {
+                        label: Some("mipmap_level_view"),
+                        base_mip_level: level,
+                        mip_level_count: Some(1),
+                        ..default()
+                    })
+                    .into()
+            };
+            for level in 1..image.mip_level_count {
+                let source = level_view(level - 1);
+                let destination = level_view(level);
+                let bind_group = render_context.render_device().create_bind_group(
+                    "mipmap_bind_group",
+                    &pipeline.layout,
+                    &BindGroupEntries::sequential((&source, &pipeline.sampler)),
+                );
+                let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                    label: Some("mipmap_pass"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &destination,
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Load,
+                            store: StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                pass.set_render_pipeline(render_pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.draw(0..3, 0..1);
+            }
+            false
+        });
+        Ok(())
+    }
+}
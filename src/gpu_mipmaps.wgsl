@@ -0,0 +1,11 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // Each destination texel center lands on the corner shared by 2x2 source texels,
+    // so a single bilinear sample is a box filter.
+    return textureSample(source, source_sampler, in.uv);
+}
@@ -0,0 +1,197 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    render::{
+        graph::CameraDriverLabel,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, RenderLabel},
+        render_resource::{Buffer, BufferDescriptor, BufferUsages, MapMode},
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        Render, RenderApp, RenderSet,
+    },
+};
+use wgpu::{Features, Maintain, QuerySet, QuerySetDescriptor, QueryType};
+
+pub const GPU_FRAME_TIME: DiagnosticPath = DiagnosticPath::const_new("gpu_frame_time");
+
+/// Most recent GPU frame time in milliseconds, measured with timestamp queries written before and
+/// after all cameras are rendered. `None` if the adapter doesn't support timestamp queries.
+#[derive(Resource, Clone, Default)]
+pub struct GpuFrameTime(Arc<Mutex<Option<f32>>>);
+
+impl GpuFrameTime {
+    pub fn get(&self) -> Option<f32> {
+        *self.0.lock().unwrap()
+    }
+}
+
+pub struct GpuTimingPlugin;
+impl Plugin for GpuTimingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GpuFrameTime>()
+            .register_diagnostic(
+                Diagnostic::new(GPU_FRAME_TIME)
+                    .with_max_history_length(20)
+                    .with_suffix("ms"),
+            )
+            .add_systems(Update, gpu_frame_time_diagnostic);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let frame_time = app.world.resource::<GpuFrameTime>().clone();
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        let render_device = render_app.world.resource::<RenderDevice>();
+        if !render_device.features().contains(Features::TIMESTAMP_QUERY) {
+            warn!("Timestamp queries not supported by this adapter, GPU frame time is unavailable");
+            return;
+        }
+        let timer = GpuTimer::new(render_device, frame_time);
+        render_app
+            .insert_resource(timer)
+            .add_systems(Render, read_back_gpu_timer.in_set(RenderSet::Cleanup));
+
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        graph.add_node(GpuTimerStartLabel, GpuTimerNode::Start);
+        graph.add_node(GpuTimerEndLabel, GpuTimerNode::End);
+        graph.add_node_edge(GpuTimerStartLabel, CameraDriverLabel);
+        graph.add_node_edge(CameraDriverLabel, GpuTimerEndLabel);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ReadbackState {
+    Idle,
+    Written,
+    Mapping,
+    Mapped,
+}
+
+#[derive(Resource)]
+struct GpuTimer {
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    state: Arc<Mutex<ReadbackState>>,
+    frame_time: GpuFrameTime,
+}
+
+impl GpuTimer {
+    fn new(render_device: &RenderDevice, frame_time: GpuFrameTime) -> Self {
+        let query_set = render_device
+            .wgpu_device()
+            .create_query_set(&QuerySetDescriptor {
+                label: Some("gpu_timer_query_set"),
+                ty: QueryType::Timestamp,
+                count: 2,
+            });
+        let resolve_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("gpu_timer_resolve_buffer"),
+            size: 16,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("gpu_timer_readback_buffer"),
+            size: 16,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            state: Arc::new(Mutex::new(ReadbackState::Idle)),
+            frame_time,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct GpuTimerStartLabel;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct GpuTimerEndLabel;
+
+enum GpuTimerNode {
+    Start,
+    End,
+}
+
+impl Node for GpuTimerNode {
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let timer = world.resource::<GpuTimer>();
+        let mut state = timer.state.lock().unwrap();
+        // Skip frames while the previous measurement is still being read back
+        if *state != ReadbackState::Idle {
+            return Ok(());
+        }
+        let encoder = render_context.command_encoder();
+        match self {
+            GpuTimerNode::Start => encoder.write_timestamp(&timer.query_set, 0),
+            GpuTimerNode::End => {
+                encoder.write_timestamp(&timer.query_set, 1);
+                encoder.resolve_query_set(&timer.query_set, 0..2, &timer.resolve_buffer, 0);
+                encoder.copy_buffer_to_buffer(
+                    &timer.resolve_buffer,
+                    0,
+                    &timer.readback_buffer,
+                    0,
+                    16,
+                );
+                *state = ReadbackState::Written;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_back_gpu_timer(
+    timer: Res<GpuTimer>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let current = *timer.state.lock().unwrap();
+    match current {
+        ReadbackState::Written => {
+            *timer.state.lock().unwrap() = ReadbackState::Mapping;
+            let state = timer.state.clone();
+            timer
+                .readback_buffer
+                .slice(..)
+                .map_async(MapMode::Read, move |result| {
+                    *state.lock().unwrap() = match result {
+                        Ok(_) => ReadbackState::Mapped,
+                        Err(_) => ReadbackState::Idle,
+                    };
+                });
+        }
+        ReadbackState::Mapped => {
+            {
+                let data = timer.readback_buffer.slice(..).get_mapped_range();
+                let start = u64::from_le_bytes(data[0..8].try_into().unwrap());
+                let end = u64::from_le_bytes(data[8..16].try_into().unwrap());
+                let nanos =
+                    end.saturating_sub(start) as f64 * render_queue.get_timestamp_period() as f64;
+                *timer.frame_time.0.lock().unwrap() = Some((nanos / 1_000_000.0) as f32);
+            }
+            timer.readback_buffer.unmap();
+            *timer.state.lock().unwrap() = ReadbackState::Idle;
+        }
+        _ => (),
+    }
+    render_device.wgpu_device().poll(Maintain::Poll);
+}
+
+fn gpu_frame_time_diagnostic(mut diagnostics: Diagnostics, frame_time: Res<GpuFrameTime>) {
+    if let Some(ms) = frame_time.get() {
+        diagnostics.add_measurement(&GPU_FRAME_TIME, || ms as f64);
+    }
+}
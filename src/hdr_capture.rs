@@ -0,0 +1,458 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    app::AppExit,
+    asset::load_internal_asset,
+    core_pipeline::{
+        core_3d::graph::{Core3d, Node3d},
+        fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    },
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        render_resource::{
+            binding_types::texture_2d, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries,
+            Buffer, BufferDescriptor, BufferUsages, CachedRenderPipelineId, ColorTargetState,
+            ColorWrites, Extent3d, FragmentState, ImageCopyBuffer, ImageDataLayout, LoadOp,
+            MapMode, Operations, PipelineCache, RenderPassColorAttachment, RenderPassDescriptor,
+            RenderPipelineDescriptor, ShaderStages, StoreOp, TextureDescriptor, TextureDimension,
+            TextureFormat, TextureSampleType, TextureUsages,
+        },
+        renderer::{RenderContext, RenderDevice},
+        view::ViewTarget,
+        Render, RenderApp, RenderSet,
+    },
+    tasks::IoTaskPool,
+};
+use image::Rgb32FImage;
+use wgpu::Maintain;
+
+use crate::{
+    camera_controller::cut_to, loading::SceneReady, scene_preset::ScenePreset,
+    screenshot::CAPTURE_DELAY_FRAMES,
+};
+
+const HDR_CAPTURE_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(52716349018263547190382615473);
+
+/// Bytes per texel of the `Rgba32Float` copy that is read back.
+const TEXEL_SIZE: u32 = 16;
+
+/// A capture waiting to be rendered, `done` is set once the file has been written or failed.
+struct HdrCaptureRequest {
+    path: PathBuf,
+    done: Arc<AtomicBool>,
+}
+
+/// Captures waiting for the next frame, shared between the main and render world.
+#[derive(Resource, Clone, Default)]
+pub struct HdrCaptureQueue(Arc<Mutex<Vec<HdrCaptureRequest>>>);
+
+impl HdrCaptureQueue {
+    /// Saves the next frame's HDR render target to an OpenEXR file at `path`.
+    pub fn request(&self, path: PathBuf) -> Arc<AtomicBool> {
+        let done = Arc::new(AtomicBool::new(false));
+        self.0.lock().unwrap().push(HdrCaptureRequest {
+            path,
+            done: done.clone(),
+        });
+        done
+    }
+}
+
+/// Captures the HDR render target once the scene has finished loading, see `--hdr-capture`.
+/// With `all_positions`, `path` is a directory and each of the scene's camera positions is
+/// captured to a file named after it.
+#[derive(Resource)]
+pub struct AutoHdrCapture {
+    pub path: PathBuf,
+    pub all_positions: bool,
+    pub exit_after_capture: bool,
+    step: usize,
+    frames_at_step: u32,
+    pending: Option<Arc<AtomicBool>>,
+}
+
+impl AutoHdrCapture {
+    pub fn new(path: impl Into<PathBuf>, all_positions: bool, exit_after_capture: bool) -> Self {
+        Self {
+            path: path.into(),
+            all_positions,
+            exit_after_capture,
+            step: 0,
+            frames_at_step: 0,
+            pending: None,
+        }
+    }
+}
+
+/// Saves the render target before tonemapping to linear OpenEXR files, for comparing the raw
+/// radiance between engine versions. The capture is taken after TAA and before bloom.
+pub struct HdrCapturePlugin;
+impl Plugin for HdrCapturePlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            HDR_CAPTURE_SHADER_HANDLE,
+            "hdr_capture.wgsl",
+            Shader::from_wgsl
+        );
+        app.init_resource::<HdrCaptureQueue>()
+            .add_systems(Update, (hdr_capture_hotkey, auto_hdr_capture));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let queue = app.world.resource::<HdrCaptureQueue>().clone();
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(queue)
+            .init_resource::<HdrCapturePipeline>()
+            .init_resource::<HdrReadbacks>()
+            .add_systems(Render, read_back_hdr_captures.in_set(RenderSet::Cleanup))
+            .add_render_graph_node::<ViewNodeRunner<HdrCaptureNode>>(Core3d, HdrCaptureLabel)
+            .add_render_graph_edges(Core3d, (Node3d::Taa, HdrCaptureLabel, Node3d::Bloom));
+    }
+}
+
+fn hdr_capture_hotkey(input: Res<ButtonInput<KeyCode>>, queue: Res<HdrCaptureQueue>) {
+    if !input.just_pressed(KeyCode::F11) {
+        return;
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    queue.request(PathBuf::from(format!("hdr_{timestamp}.exr")));
+}
+
+fn auto_hdr_capture(
+    mut commands: Commands,
+    auto: Option<ResMut<AutoHdrCapture>>,
+    scene_ready: Res<SceneReady>,
+    preset: Res<ScenePreset>,
+    queue: Res<HdrCaptureQueue>,
+    mut camera: Query<(Entity, &mut Transform), With<Camera3d>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let Some(mut auto) = auto else {
+        return;
+    };
+    if !scene_ready.ready {
+        return;
+    }
+    if let Some(pending) = &auto.pending {
+        if !pending.load(Ordering::Acquire) {
+            return;
+        }
+        auto.pending = None;
+        auto.step += 1;
+        auto.frames_at_step = 0;
+    }
+    let captures = if auto.all_positions {
+        preset.cameras.len()
+    } else {
+        1
+    };
+    if auto.step >= captures {
+        if auto.exit_after_capture {
+            app_exit.send(AppExit);
+        }
+        return;
+    }
+    let Ok((entity, mut transform)) = camera.get_single_mut() else {
+        return;
+    };
+    if auto.all_positions && auto.frames_at_step == 0 {
+        let (_, position) = preset.cameras[auto.step];
+        cut_to(&mut commands, entity, &mut transform, position, None);
+    }
+    auto.frames_at_step += 1;
+    if auto.frames_at_step < CAPTURE_DELAY_FRAMES {
+        return;
+    }
+    let path = if auto.all_positions {
+        let (name, _) = &preset.cameras[auto.step];
+        let file_name: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        auto.path.join(format!("{file_name}.exr"))
+    } else {
+        auto.path.clone()
+    };
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            error!("Failed to create {}: {}", dir.display(), e);
+        }
+    }
+    auto.pending = Some(queue.request(path));
+}
+
+#[derive(Resource)]
+struct HdrCapturePipeline {
+    layout: BindGroupLayout,
+    pipeline_id: CachedRenderPipelineId,
+}
+
+impl FromWorld for HdrCapturePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "hdr_capture_bind_group_layout",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::FRAGMENT,
+                texture_2d(TextureSampleType::Float { filterable: false }),
+            ),
+        );
+        let pipeline_id =
+            world
+                .resource_mut::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("hdr_capture_pipeline".into()),
+                    layout: vec![layout.clone()],
+                    push_constant_ranges: Vec::new(),
+                    vertex: fullscreen_shader_vertex_state(),
+                    fragment: Some(FragmentState {
+                        shader: HDR_CAPTURE_SHADER_HANDLE,
+                        shader_defs: Vec::new(),
+                        entry_point: "fragment".into(),
+                        targets: vec![Some(ColorTargetState {
+                            format: TextureFormat::Rgba32Float,
+                            blend: None,
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: default(),
+                    depth_stencil: None,
+                    multisample: default(),
+                });
+        Self {
+            layout,
+            pipeline_id,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ReadbackState {
+    Written,
+    Mapping,
+    Mapped,
+    Failed,
+}
+
+/// A capture copied to `buffer` this frame, waiting to be mapped and saved.
+struct HdrReadback {
+    request: HdrCaptureRequest,
+    buffer: Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    state: Arc<Mutex<ReadbackState>>,
+}
+
+#[derive(Resource, Default)]
+struct HdrReadbacks(Mutex<Vec<HdrReadback>>);
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct HdrCaptureLabel;
+
+#[derive(Default)]
+struct HdrCaptureNode;
+
+impl ViewNode for HdrCaptureNode {
+    type ViewQuery = &'static ViewTarget;
+
+    fn run<'w>(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        view_target: QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let queue = world.resource::<HdrCaptureQueue>();
+        let capture_pipeline = world.resource::<HdrCapturePipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        // Leave the request for a later frame until the pipeline has compiled
+        let Some(pipeline) = pipeline_cache.get_render_pipeline(capture_pipeline.pipeline_id)
+        else {
+            return Ok(());
+        };
+        let request = {
+            let mut requests = queue.0.lock().unwrap();
+            if requests.is_empty() {
+                return Ok(());
+            }
+            requests.remove(0)
+        };
+        if !view_target.is_hdr() {
+            warn!(
+                "HDR capture needs an HDR camera, not saving {}",
+                request.path.display()
+            );
+            request.done.store(true, Ordering::Release);
+            return Ok(());
+        }
+
+        let source = view_target.main_texture();
+        let size = Extent3d {
+            width: source.width(),
+            height: source.height(),
+            depth_or_array_layers: 1,
+        };
+        let render_device = render_context.render_device().clone();
+        let texture = render_device.create_texture(&TextureDescriptor {
+            label: Some("hdr_capture_texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba32Float,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let destination = texture.create_view(&default());
+        let bind_group = render_device.create_bind_group(
+            "hdr_capture_bind_group",
+            &capture_pipeline.layout,
+            &BindGroupEntries::single(view_target.main_texture_view()),
+        );
+        {
+            let mut pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                label: Some("hdr_capture_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &destination,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(default()),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_render_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        let padded_bytes_per_row =
+            RenderDevice::align_copy_bytes_per_row((size.width * TEXEL_SIZE) as usize) as u32;
+        let buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("hdr_capture_buffer"),
+            size: padded_bytes_per_row as u64 * size.height as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        render_context.command_encoder().copy_texture_to_buffer(
+            texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        world
+            .resource::<HdrReadbacks>()
+            .0
+            .lock()
+            .unwrap()
+            .push(HdrReadback {
+                request,
+                buffer,
+                width: size.width,
+                height: size.height,
+                padded_bytes_per_row,
+                state: Arc::new(Mutex::new(ReadbackState::Written)),
+            });
+        Ok(())
+    }
+}
+
+fn read_back_hdr_captures(readbacks: Res<HdrReadbacks>, render_device: Res<RenderDevice>) {
+    readbacks.0.lock().unwrap().retain(|readback| {
+        let current = *readback.state.lock().unwrap();
+        match current {
+            ReadbackState::Written => {
+                *readback.state.lock().unwrap() = ReadbackState::Mapping;
+                let state = readback.state.clone();
+                readback
+                    .buffer
+                    .slice(..)
+                    .map_async(MapMode::Read, move |result| {
+                        *state.lock().unwrap() = match result {
+                            Ok(_) => ReadbackState::Mapped,
+                            Err(_) => ReadbackState::Failed,
+                        };
+                    });
+                true
+            }
+            ReadbackState::Mapping => true,
+            ReadbackState::Mapped => {
+                let image = {
+                    let data = readback.buffer.slice(..).get_mapped_range();
+                    decode_rows(
+                        &data,
+                        readback.width,
+                        readback.height,
+                        readback.padded_bytes_per_row,
+                    )
+                };
+                readback.buffer.unmap();
+                let path = readback.request.path.clone();
+                let done = readback.request.done.clone();
+                IoTaskPool::get()
+                    .spawn(async move {
+                        match image.save(&path) {
+                            Ok(_) => info!("Saved HDR capture to {}", path.display()),
+                            Err(e) => {
+                                error!("Failed to save HDR capture {}: {}", path.display(), e)
+                            }
+                        }
+                        done.store(true, Ordering::Release);
+                    })
+                    .detach();
+                false
+            }
+            ReadbackState::Failed => {
+                error!(
+                    "Failed to read back HDR capture {}",
+                    readback.request.path.display()
+                );
+                readback.request.done.store(true, Ordering::Release);
+                false
+            }
+        }
+    });
+    render_device.wgpu_device().poll(Maintain::Poll);
+}
+
+/// Strips the row padding and alpha from the read back `Rgba32Float` texels.
+fn decode_rows(data: &[u8], width: u32, height: u32, padded_bytes_per_row: u32) -> Rgb32FImage {
+    let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+    for row in data.chunks_exact(padded_bytes_per_row as usize) {
+        for texel in row[..(width * TEXEL_SIZE) as usize].chunks_exact(TEXEL_SIZE as usize) {
+            for channel in texel[..12].chunks_exact(4) {
+                pixels.push(f32::from_le_bytes(channel.try_into().unwrap()));
+            }
+        }
+    }
+    Rgb32FImage::from_raw(width, height, pixels).unwrap()
+}
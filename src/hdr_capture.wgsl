@@ -0,0 +1,9 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // Copied texel for texel, the main texture can't be copied to a buffer directly
+    return textureLoad(source, vec2<i32>(in.position.xy), 0);
+}
@@ -0,0 +1,125 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    bookmarks::{CameraBookmarks, BOOKMARKS_PATH},
+    light_editor::RigLight,
+    scene_preset::ScenePreset,
+    spawn_rig, Args,
+};
+
+/// How often to check the files for changes.
+const CHECK_INTERVAL_SECS: f32 = 0.5;
+
+/// A file that's reloaded when it changes on disk.
+struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl WatchedFile {
+    fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let modified = modified(&path);
+        Self { path, modified }
+    }
+
+    /// Whether the file was written since the last call.
+    fn changed(&mut self) -> bool {
+        let modified = modified(&self.path);
+        let changed = modified.is_some() && modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[derive(Resource)]
+struct WatchedFiles {
+    preset: Option<WatchedFile>,
+    bookmarks: WatchedFile,
+    since_check: f32,
+}
+
+/// Reapplies the `--preset` light rig and camera positions, and the camera bookmarks, when their
+/// files change, without reloading the scene. The scenes and rules of the preset are only read
+/// at startup.
+pub struct HotReloadPlugin;
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, watch_files)
+            .add_systems(Update, reload_changed_files);
+    }
+}
+
+fn watch_files(mut commands: Commands, args: Res<Args>) {
+    commands.insert_resource(WatchedFiles {
+        preset: args.preset.as_ref().map(WatchedFile::new),
+        bookmarks: WatchedFile::new(BOOKMARKS_PATH),
+        since_check: 0.0,
+    });
+}
+
+fn reload_changed_files(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    args: Res<Args>,
+    mut watched: ResMut<WatchedFiles>,
+    mut preset: ResMut<ScenePreset>,
+    bookmarks: Option<ResMut<CameraBookmarks>>,
+    rig: Query<Entity, With<RigLight>>,
+) {
+    watched.since_check += time.delta_seconds();
+    if watched.since_check < CHECK_INTERVAL_SECS {
+        return;
+    }
+    watched.since_check = 0.0;
+
+    if let Some(file) = watched
+        .preset
+        .as_mut()
+        .and_then(|file| file.changed().then_some(file))
+    {
+        match ScenePreset::load(&file.path) {
+            Ok(reloaded) => {
+                // Lights that were added, removed, or changed kind need spawning again
+                let same_lights = reloaded.lights.len() == preset.lights.len()
+                    && reloaded
+                        .lights
+                        .iter()
+                        .zip(&preset.lights)
+                        .all(|(a, b)| a.kind() == b.kind());
+                preset.lights = reloaded.lights;
+                preset.cameras = reloaded.cameras;
+                if !same_lights {
+                    for entity in &rig {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                    spawn_rig(&mut commands, &preset, &args);
+                }
+                info!(
+                    "Reloaded the light rig and cameras from {}",
+                    file.path.display()
+                );
+            }
+            Err(e) => warn!("Failed to reload {}: {}", file.path.display(), e),
+        }
+    }
+    if let Some(mut bookmarks) = bookmarks {
+        if watched.bookmarks.changed() {
+            *bookmarks = CameraBookmarks::load(&watched.bookmarks.path, &preset);
+            info!(
+                "Reloaded camera bookmarks from {}",
+                watched.bookmarks.path.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    diagnostic::{
+        DiagnosticPath, DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
+    },
+    prelude::*,
+};
+
+use crate::{
+    culling_stats::{CULLED_MESHES, DRAWN_MESHES},
+    gpu_timing::GPU_FRAME_TIME,
+    lod::VISIBLE_TRIANGLES,
+    memory_diagnostics::TEXTURE_GPU_MEMORY,
+    render_features::{FeatureToggle, RenderFeatures},
+};
+
+/// How often to refresh the text, so the numbers are readable. The graph updates every frame.
+const UPDATE_INTERVAL_SECS: f32 = 0.25;
+/// Frames shown in the frame time graph, one bar each.
+const GRAPH_FRAMES: usize = 120;
+const GRAPH_HEIGHT: f32 = 60.0;
+/// Frame time at the top of the graph, longer frames are clipped.
+const GRAPH_MAX_MS: f32 = 50.0;
+
+/// Whether the statistics HUD is shown, toggled with F1. Start with it shown using `--hud`.
+#[derive(Resource, Default)]
+pub struct Hud {
+    pub visible: bool,
+}
+
+#[derive(Component)]
+struct HudRoot;
+
+#[derive(Component)]
+struct HudText;
+
+/// A bar of the frame time graph, the index counts back from the latest frame.
+#[derive(Component)]
+struct HudBar(usize);
+
+/// Frame rate, a frame time graph, and scene statistics in the corner of the window, so they're
+/// visible when screensharing a benchmark session.
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hud>()
+            .add_systems(Startup, spawn_hud)
+            .add_systems(Update, (toggle_hud, update_hud_graph, update_hud_text));
+    }
+}
+
+fn spawn_hud(mut commands: Commands, hud: Res<Hud>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    padding: UiRect::all(Val::Px(6.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                visibility: if hud.visible {
+                    Visibility::Visible
+                } else {
+                    Visibility::Hidden
+                },
+                ..default()
+            },
+            HudRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                HudText,
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(GRAPH_FRAMES as f32 * 2.0),
+                        height: Val::Px(GRAPH_HEIGHT),
+                        align_items: AlignItems::FlexEnd,
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+                    ..default()
+                })
+                .with_children(|graph| {
+                    // Oldest frame on the left
+                    for i in (0..GRAPH_FRAMES).rev() {
+                        graph.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Px(2.0),
+                                    height: Val::Px(0.0),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            HudBar(i),
+                        ));
+                    }
+                });
+        });
+}
+
+fn toggle_hud(
+    input: Res<ButtonInput<KeyCode>>,
+    mut hud: ResMut<Hud>,
+    mut root: Query<&mut Visibility, With<HudRoot>>,
+) {
+    if !input.just_pressed(KeyCode::F1) {
+        return;
+    }
+    hud.visible = !hud.visible;
+    for mut visibility in &mut root {
+        *visibility = if hud.visible {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Green within 60 fps, yellow within 30 fps, red beyond.
+fn frame_time_color(ms: f32) -> Color {
+    if ms <= 1000.0 / 60.0 {
+        Color::rgb(0.3, 0.9, 0.3)
+    } else if ms <= 1000.0 / 30.0 {
+        Color::rgb(0.9, 0.8, 0.2)
+    } else {
+        Color::rgb(0.9, 0.3, 0.2)
+    }
+}
+
+fn update_hud_graph(
+    time: Res<Time>,
+    hud: Res<Hud>,
+    mut bars: Query<(&HudBar, &mut Style, &mut BackgroundColor)>,
+    mut frame_times: Local<VecDeque<f32>>,
+) {
+    frame_times.push_front(time.delta_seconds() * 1000.0);
+    frame_times.truncate(GRAPH_FRAMES);
+    if !hud.visible {
+        return;
+    }
+    for (bar, mut style, mut color) in &mut bars {
+        let ms = frame_times.get(bar.0).copied().unwrap_or(0.0);
+        style.height = Val::Px((ms / GRAPH_MAX_MS).min(1.0) * GRAPH_HEIGHT);
+        *color = frame_time_color(ms).into();
+    }
+}
+
+fn update_hud_text(
+    time: Res<Time>,
+    hud: Res<Hud>,
+    diagnostics: Res<DiagnosticsStore>,
+    features: Res<RenderFeatures>,
+    mut text: Query<&mut Text, With<HudText>>,
+    mut since_update: Local<f32>,
+) {
+    *since_update += time.delta_seconds();
+    if !hud.visible || *since_update < UPDATE_INTERVAL_SECS {
+        return;
+    }
+    *since_update = 0.0;
+
+    let value = |path: &DiagnosticPath| diagnostics.get(path).and_then(|d| d.smoothed());
+    let mut lines = Vec::new();
+    if let (Some(fps), Some(frame_time)) = (
+        value(&FrameTimeDiagnosticsPlugin::FPS),
+        value(&FrameTimeDiagnosticsPlugin::FRAME_TIME),
+    ) {
+        lines.push(format!("{fps:.0} fps, {frame_time:.2} ms"));
+    }
+    if let Some(gpu) = value(&GPU_FRAME_TIME) {
+        lines.push(format!("GPU: {gpu:.2} ms"));
+    }
+    if let Some(entities) = value(&EntityCountDiagnosticsPlugin::ENTITY_COUNT) {
+        lines.push(format!("Entities: {entities:.0}"));
+    }
+    if let (Some(drawn), Some(culled)) = (value(&DRAWN_MESHES), value(&CULLED_MESHES)) {
+        lines.push(format!("Meshes drawn: {drawn:.0}, culled: {culled:.0}"));
+    }
+    if let Some(triangles) = value(&VISIBLE_TRIANGLES) {
+        lines.push(format!("Triangles: {:.2}M", triangles / 1_000_000.0));
+    }
+    if let Some(memory) = value(&TEXTURE_GPU_MEMORY) {
+        lines.push(format!("Texture memory: {memory:.0} MB"));
+    }
+    let enabled: Vec<_> = FeatureToggle::ALL
+        .iter()
+        .filter(|toggle| toggle.value(&features))
+        .map(|toggle| toggle.name())
+        .collect();
+    lines.push(format!("Features: {}", enabled.join(", ")));
+    for mut text in &mut text {
+        text.sections[0].value = lines.join("\n");
+    }
+}
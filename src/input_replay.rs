@@ -0,0 +1,245 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use bevy::{
+    app::AppExit,
+    input::{
+        mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+        InputSystem,
+    },
+    prelude::*,
+    time::TimeUpdateStrategy,
+    window::PrimaryWindow,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{frame_pacing::FixedTimestep, loading::SceneReady};
+
+/// Keyboard and mouse input of a single frame.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub keys_pressed: Vec<KeyCode>,
+    pub keys_released: Vec<KeyCode>,
+    pub buttons_pressed: Vec<MouseButton>,
+    pub buttons_released: Vec<MouseButton>,
+    pub mouse_motion: Vec2,
+    /// Scrolled lines.
+    pub scroll: f32,
+}
+
+impl ReplayFrame {
+    fn is_empty(&self) -> bool {
+        self.keys_pressed.is_empty()
+            && self.keys_released.is_empty()
+            && self.buttons_pressed.is_empty()
+            && self.buttons_released.is_empty()
+            && self.mouse_motion == Vec2::ZERO
+            && self.scroll == 0.0
+    }
+}
+
+/// Input recorded with `--record-input`, one entry per frame since the scene finished loading.
+/// Played back with `--replay`, advancing time by as much as each frame did while recording, so
+/// every run moves the camera through exactly the same frames regardless of the frame rate.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputReplay {
+    /// Seconds each recorded frame advanced time by.
+    #[serde(default)]
+    pub frame_times: Vec<f32>,
+    /// Seconds to advance frames without a recorded time by, recordings from before they were
+    /// kept have none.
+    pub frame_time: f32,
+    /// Frame index and the input of that frame, frames without input are left out.
+    pub frames: Vec<(usize, ReplayFrame)>,
+    pub frame_count: usize,
+}
+
+impl InputReplay {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let pretty = ron::ser::PrettyConfig::default().struct_names(false);
+        fs::write(path, ron::ser::to_string_pretty(self, pretty)?)?;
+        Ok(())
+    }
+
+    fn time_update(&self, frame: usize) -> TimeUpdateStrategy {
+        let seconds = self
+            .frame_times
+            .get(frame)
+            .copied()
+            .unwrap_or(self.frame_time);
+        TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(seconds))
+    }
+}
+
+/// Records input to `path` from when the scene is ready until the app exits.
+#[derive(Resource)]
+pub struct InputRecorder {
+    path: PathBuf,
+    replay: InputReplay,
+}
+
+impl InputRecorder {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            replay: InputReplay {
+                frame_time: 1.0 / 60.0,
+                ..default()
+            },
+        }
+    }
+}
+
+/// Plays back an [`InputReplay`] once the scene is ready.
+#[derive(Resource)]
+pub struct InputPlayback {
+    replay: InputReplay,
+    frame: usize,
+    next: usize,
+    started: bool,
+}
+
+impl InputPlayback {
+    pub fn new(replay: InputReplay) -> Self {
+        Self {
+            replay,
+            frame: 0,
+            next: 0,
+            started: false,
+        }
+    }
+}
+
+pub struct InputReplayPlugin;
+impl Plugin for InputReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, play_input.after(InputSystem))
+            .add_systems(Update, record_input)
+            .add_systems(Last, save_recording);
+    }
+}
+
+fn record_input(
+    recorder: Option<ResMut<InputRecorder>>,
+    scene_ready: Res<SceneReady>,
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut scroll: EventReader<MouseWheel>,
+) {
+    let (Some(mut recorder), true) = (recorder, scene_ready.ready) else {
+        mouse_motion.clear();
+        scroll.clear();
+        return;
+    };
+    let frame = ReplayFrame {
+        keys_pressed: keys.get_just_pressed().copied().collect(),
+        keys_released: keys.get_just_released().copied().collect(),
+        buttons_pressed: buttons.get_just_pressed().copied().collect(),
+        buttons_released: buttons.get_just_released().copied().collect(),
+        mouse_motion: mouse_motion.read().map(|motion| motion.delta).sum(),
+        scroll: scroll
+            .read()
+            .filter(|ev| ev.unit == MouseScrollUnit::Line)
+            .map(|ev| ev.y)
+            .sum(),
+    };
+    let replay = &mut recorder.replay;
+    if !frame.is_empty() {
+        replay.frames.push((replay.frame_count, frame));
+    }
+    replay.frame_times.push(time.delta_seconds());
+    replay.frame_count += 1;
+}
+
+fn save_recording(recorder: Option<Res<InputRecorder>>, mut app_exit: EventReader<AppExit>) {
+    let Some(recorder) = recorder else {
+        return;
+    };
+    if app_exit.read().count() == 0 {
+        return;
+    }
+    let path = recorder.path.to_string_lossy();
+    match recorder.replay.save(&path) {
+        Ok(_) => println!(
+            "Saved {} frames of input to {path}",
+            recorder.replay.frame_count
+        ),
+        Err(e) => println!("Failed to save input to {path}: {e}"),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn play_input(
+    mut commands: Commands,
+    playback: Option<ResMut<InputPlayback>>,
+    fixed_timestep: Option<Res<FixedTimestep>>,
+    scene_ready: Res<SceneReady>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut buttons: ResMut<ButtonInput<MouseButton>>,
+    mut mouse_motion: EventWriter<MouseMotion>,
+    mut scroll: EventWriter<MouseWheel>,
+    window: Query<Entity, With<PrimaryWindow>>,
+) {
+    let (Some(mut playback), true) = (playback, scene_ready.ready) else {
+        return;
+    };
+    // The time of a frame is set the frame before, so the first recorded frame waits for it
+    if !playback.started {
+        playback.started = true;
+        commands.insert_resource(playback.replay.time_update(0));
+        println!(
+            "Playing back {} frames of input",
+            playback.replay.frame_count
+        );
+        return;
+    }
+    if playback.frame >= playback.replay.frame_count {
+        commands.insert_resource(FixedTimestep::strategy(fixed_timestep.as_deref()));
+        commands.remove_resource::<InputPlayback>();
+        println!("Input playback finished");
+        return;
+    }
+    let frame_index = playback.frame;
+    playback.frame += 1;
+    commands.insert_resource(playback.replay.time_update(frame_index + 1));
+    let Some((_, frame)) = playback
+        .replay
+        .frames
+        .get(playback.next)
+        .filter(|(index, _)| *index == frame_index)
+    else {
+        return;
+    };
+    let frame = frame.clone();
+    playback.next += 1;
+    for key in frame.keys_pressed {
+        keys.press(key);
+    }
+    for key in frame.keys_released {
+        keys.release(key);
+    }
+    for button in frame.buttons_pressed {
+        buttons.press(button);
+    }
+    for button in frame.buttons_released {
+        buttons.release(button);
+    }
+    if frame.mouse_motion != Vec2::ZERO {
+        mouse_motion.send(MouseMotion {
+            delta: frame.mouse_motion,
+        });
+    }
+    if let (true, Ok(window)) = (frame.scroll != 0.0, window.get_single()) {
+        scroll.send(MouseWheel {
+            unit: MouseScrollUnit::Line,
+            x: 0.0,
+            y: frame.scroll,
+            window,
+        });
+    }
+}
@@ -0,0 +1,492 @@
+use std::fmt::Write;
+
+use bevy::{
+    gltf::Gltf, prelude::*, render::primitives::Aabb, ui::RelativeCursorPosition,
+    window::PrimaryWindow,
+};
+
+use crate::{
+    material_variants::MaterialVariants,
+    picking::{pick_mesh, PickableMeshes},
+    render_scale::RenderScale,
+    split_screen::ComparisonCamera,
+};
+
+/// The mesh picked in the inspector, shown/hidden with U.
+#[derive(Resource, Default)]
+pub struct Inspector {
+    pub selected: Option<Entity>,
+    pub visible: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MaterialProperty {
+    Roughness,
+    Metallic,
+    Reflectance,
+}
+
+impl MaterialProperty {
+    const ALL: [MaterialProperty; 3] = [
+        MaterialProperty::Roughness,
+        MaterialProperty::Metallic,
+        MaterialProperty::Reflectance,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            MaterialProperty::Roughness => "Roughness",
+            MaterialProperty::Metallic => "Metallic",
+            MaterialProperty::Reflectance => "Reflectance",
+        }
+    }
+
+    /// All of them go from 0 to 1, which is what the sliders show.
+    fn value_mut<'a>(&self, material: &'a mut StandardMaterial) -> &'a mut f32 {
+        match self {
+            MaterialProperty::Roughness => &mut material.perceptual_roughness,
+            MaterialProperty::Metallic => &mut material.metallic,
+            MaterialProperty::Reflectance => &mut material.reflectance,
+        }
+    }
+
+    fn get(&self, material: &StandardMaterial) -> f32 {
+        match self {
+            MaterialProperty::Roughness => material.perceptual_roughness,
+            MaterialProperty::Metallic => material.metallic,
+            MaterialProperty::Reflectance => material.reflectance,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MaterialSwitch {
+    FlipNormalMapY,
+    Unlit,
+}
+
+impl MaterialSwitch {
+    const ALL: [MaterialSwitch; 2] = [MaterialSwitch::FlipNormalMapY, MaterialSwitch::Unlit];
+
+    fn name(&self) -> &'static str {
+        match self {
+            MaterialSwitch::FlipNormalMapY => "Flip normal map Y",
+            MaterialSwitch::Unlit => "Unlit",
+        }
+    }
+
+    fn get(&self, material: &StandardMaterial) -> bool {
+        match self {
+            MaterialSwitch::FlipNormalMapY => material.flip_normal_map_y,
+            MaterialSwitch::Unlit => material.unlit,
+        }
+    }
+
+    fn value_mut<'a>(&self, material: &'a mut StandardMaterial) -> &'a mut bool {
+        match self {
+            MaterialSwitch::FlipNormalMapY => &mut material.flip_normal_map_y,
+            MaterialSwitch::Unlit => &mut material.unlit,
+        }
+    }
+}
+
+/// Root node of the inspector.
+#[derive(Component)]
+pub struct InspectorPanel;
+
+/// Description of the picked mesh, its material, and textures.
+#[derive(Component)]
+pub struct InspectorText;
+
+/// Text of a slider or switch.
+#[derive(Component)]
+pub struct InspectorLabel(InspectorControl);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum InspectorControl {
+    Property(MaterialProperty),
+    Switch(MaterialSwitch),
+}
+
+/// Drag along this node to set the property.
+#[derive(Component)]
+pub struct InspectorSlider(MaterialProperty);
+
+/// The filled part of an [`InspectorSlider`].
+#[derive(Component)]
+pub struct InspectorSliderFill(MaterialProperty);
+
+#[derive(Component)]
+pub struct InspectorSwitch(MaterialSwitch);
+
+/// Pick a mesh with the right mouse button and show its material and textures. The sliders edit
+/// the material asset, so every mesh sharing it changes too, unlike the blanket rules of the
+/// scene preset.
+pub struct InspectorPlugin;
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inspector>()
+            .add_systems(Startup, spawn_inspector)
+            .add_systems(
+                Update,
+                (
+                    toggle_inspector,
+                    pick_inspected_mesh,
+                    drag_inspector_sliders,
+                    inspector_switches,
+                    update_inspector,
+                    highlight_inspected_mesh,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn text_style() -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color: Color::WHITE,
+        ..default()
+    }
+}
+
+fn spawn_inspector(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    left: Val::Percent(35.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            InspectorPanel,
+        ))
+        .with_children(|panel| {
+            panel.spawn((TextBundle::from_section("", text_style()), InspectorText));
+            for property in MaterialProperty::ALL {
+                panel
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn((
+                            TextBundle::from_section("", text_style()).with_style(Style {
+                                width: Val::Px(150.0),
+                                ..default()
+                            }),
+                            InspectorLabel(InspectorControl::Property(property)),
+                        ));
+                        row.spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(200.0),
+                                    height: Val::Px(14.0),
+                                    ..default()
+                                },
+                                background_color: Color::rgba(0.25, 0.25, 0.25, 0.9).into(),
+                                ..default()
+                            },
+                            RelativeCursorPosition::default(),
+                            InspectorSlider(property),
+                        ))
+                        .with_children(|slider| {
+                            slider.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        height: Val::Percent(100.0),
+                                        ..default()
+                                    },
+                                    background_color: Color::rgb(0.8, 0.6, 0.2).into(),
+                                    ..default()
+                                },
+                                InspectorSliderFill(property),
+                            ));
+                        });
+                    });
+            }
+            for switch in MaterialSwitch::ALL {
+                panel
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                                ..default()
+                            },
+                            background_color: Color::rgba(0.25, 0.25, 0.25, 0.9).into(),
+                            ..default()
+                        },
+                        InspectorSwitch(switch),
+                    ))
+                    .with_children(|button| {
+                        button.spawn((
+                            TextBundle::from_section("", text_style()),
+                            InspectorLabel(InspectorControl::Switch(switch)),
+                        ));
+                    });
+            }
+        });
+}
+
+fn toggle_inspector(
+    input: Res<ButtonInput<KeyCode>>,
+    mut inspector: ResMut<Inspector>,
+    mut panel: Query<&mut Visibility, With<InspectorPanel>>,
+) {
+    if !input.just_pressed(KeyCode::KeyU) {
+        return;
+    }
+    inspector.visible = !inspector.visible;
+    for mut visibility in &mut panel {
+        *visibility = if inspector.visible {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Picks the mesh under the cursor, or at the center of the screen while the cursor is grabbed.
+fn pick_inspected_mesh(
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut inspector: ResMut<Inspector>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), (With<Camera3d>, Without<ComparisonCamera>)>,
+    render_scale: Res<RenderScale>,
+    meshes: PickableMeshes,
+    mesh_assets: Res<Assets<Mesh>>,
+) {
+    if !inspector.visible || !mouse.just_pressed(MouseButton::Right) {
+        return;
+    }
+    let (Ok(window), Ok((camera, transform))) = (window.get_single(), camera.get_single()) else {
+        return;
+    };
+    // The camera renders to a smaller or larger image than the window with a render scale
+    let ray = window
+        .cursor_position()
+        .filter(|_| window.cursor.visible)
+        .and_then(|cursor| camera.viewport_to_world(transform, cursor * render_scale.scale));
+    let (origin, direction) = match ray {
+        Some(ray) => (ray.origin, *ray.direction),
+        None => (transform.translation(), transform.forward()),
+    };
+    inspector.selected = pick_mesh(origin, direction, &meshes, &mesh_assets).map(|hit| hit.entity);
+}
+
+fn drag_inspector_sliders(
+    sliders: Query<(&Interaction, &RelativeCursorPosition, &InspectorSlider)>,
+    inspector: Res<Inspector>,
+    handles: Query<&Handle<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(handle) = inspector
+        .selected
+        .and_then(|entity| handles.get(entity).ok())
+    else {
+        return;
+    };
+    for (interaction, cursor, slider) in &sliders {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(cursor) = cursor.normalized else {
+            continue;
+        };
+        let value = cursor.x.clamp(0.0, 1.0);
+        if materials
+            .get(handle)
+            .is_some_and(|material| slider.0.get(material) != value)
+        {
+            if let Some(material) = materials.get_mut(handle) {
+                *slider.0.value_mut(material) = value;
+            }
+        }
+    }
+}
+
+fn inspector_switches(
+    buttons: Query<(&Interaction, &InspectorSwitch), Changed<Interaction>>,
+    inspector: Res<Inspector>,
+    handles: Query<&Handle<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(handle) = inspector
+        .selected
+        .and_then(|entity| handles.get(entity).ok())
+    else {
+        return;
+    };
+    for (interaction, switch) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(material) = materials.get_mut(handle) {
+            let value = switch.0.value_mut(material);
+            *value = !*value;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_inspector(
+    inspector: Res<Inspector>,
+    selected: Query<(&Handle<Mesh>, Option<&Handle<StandardMaterial>>)>,
+    names: Query<(Option<&Name>, Option<&Parent>)>,
+    mesh_assets: Res<Assets<Mesh>>,
+    materials: Res<Assets<StandardMaterial>>,
+    images: Res<Assets<Image>>,
+    mut text: Query<&mut Text, (With<InspectorText>, Without<InspectorLabel>)>,
+    mut labels: Query<(&mut Text, &InspectorLabel)>,
+    mut fills: Query<(&mut Style, &InspectorSliderFill)>,
+    variants: Option<Res<MaterialVariants>>,
+    gltfs: Res<Assets<Gltf>>,
+) {
+    if !inspector.visible {
+        return;
+    }
+    let selected = inspector
+        .selected
+        .and_then(|entity| Some((entity, selected.get(entity).ok()?)));
+    let material = selected
+        .and_then(|(_, (_, material))| material)
+        .and_then(|handle| Some((handle, materials.get(handle)?)));
+
+    let mut description = String::new();
+    match selected {
+        None => description.push_str("Right click a mesh to inspect it"),
+        Some((entity, (mesh, _))) => {
+            let _ = writeln!(description, "{}", entity_name(entity, &names));
+            if let Some(mesh) = mesh_assets.get(mesh) {
+                let _ = writeln!(
+                    description,
+                    "{} vertices, {} triangles",
+                    mesh.count_vertices(),
+                    mesh.indices()
+                        .map_or(mesh.count_vertices(), |indices| indices.len())
+                        / 3
+                );
+            }
+            match material {
+                None => description.push_str("No standard material"),
+                Some((handle, material)) => {
+                    let _ = write!(description, "Material: {}", asset_name(handle));
+                    // The name material variants match against
+                    if let Some(name) = variants
+                        .as_ref()
+                        .and_then(|variants| variants.material_name(&gltfs, handle))
+                    {
+                        let _ = write!(description, " ({name})");
+                    }
+                    description.push('\n');
+                    let _ = writeln!(
+                        description,
+                        "Base color: {:.2?}, alpha mode {:?}, double sided {}",
+                        material.base_color.as_rgba_f32(),
+                        material.alpha_mode,
+                        material.double_sided
+                    );
+                    let textures = [
+                        ("Base color", &material.base_color_texture),
+                        ("Normal map", &material.normal_map_texture),
+                        ("Metallic roughness", &material.metallic_roughness_texture),
+                        ("Occlusion", &material.occlusion_texture),
+                        ("Emissive", &material.emissive_texture),
+                    ];
+                    for (name, texture) in textures {
+                        let Some(texture) = texture else {
+                            continue;
+                        };
+                        let _ = write!(description, "{name}: {}", asset_name(texture));
+                        if let Some(image) = images.get(texture) {
+                            let size = image.texture_descriptor.size;
+                            let _ = write!(
+                                description,
+                                ", {}x{} {:?}, {} mips",
+                                size.width,
+                                size.height,
+                                image.texture_descriptor.format,
+                                image.texture_descriptor.mip_level_count
+                            );
+                        }
+                        description.push('\n');
+                    }
+                }
+            }
+        }
+    }
+    for mut text in &mut text {
+        if text.sections[0].value != description {
+            text.sections[0].value = description.clone();
+        }
+    }
+    let material = material.map(|(_, material)| material);
+    for (mut text, label) in &mut labels {
+        let value = match (label.0, material) {
+            (InspectorControl::Property(property), Some(material)) => {
+                format!("{}: {:.2}", property.name(), property.get(material))
+            }
+            (InspectorControl::Switch(switch), Some(material)) => format!(
+                "{}: {}",
+                switch.name(),
+                if switch.get(material) { "on" } else { "off" }
+            ),
+            (InspectorControl::Property(property), None) => property.name().to_string(),
+            (InspectorControl::Switch(switch), None) => switch.name().to_string(),
+        };
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+    for (mut style, fill) in &mut fills {
+        let width = Val::Percent(material.map_or(0.0, |material| fill.0.get(material)) * 100.0);
+        if style.width != width {
+            style.width = width;
+        }
+    }
+}
+
+/// Name of the entity or its closest named ancestor, glTF primitives are named after their mesh.
+fn entity_name(entity: Entity, names: &Query<(Option<&Name>, Option<&Parent>)>) -> String {
+    let mut current = Some(entity);
+    while let Some((name, parent)) = current.and_then(|entity| names.get(entity).ok()) {
+        if let Some(name) = name {
+            return name.to_string();
+        }
+        current = parent.map(Parent::get);
+    }
+    format!("{entity:?}")
+}
+
+fn asset_name<A: Asset>(handle: &Handle<A>) -> String {
+    handle
+        .path()
+        .map_or_else(|| String::from("generated"), ToString::to_string)
+}
+
+fn highlight_inspected_mesh(
+    inspector: Res<Inspector>,
+    meshes: Query<(&Aabb, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    if !inspector.visible {
+        return;
+    }
+    let Some(Ok((aabb, transform))) = inspector.selected.map(|entity| meshes.get(entity)) else {
+        return;
+    };
+    let local = Transform::from_translation(aabb.center.into())
+        .with_scale(Vec3::from(aabb.half_extents) * 2.0);
+    gizmos.cuboid(*transform * local, Color::YELLOW);
+}
@@ -0,0 +1,122 @@
+use std::{fs, path::Path};
+
+use bevy::{
+    pbr::{irradiance_volume::IrradianceVolume, LightProbe},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{skybox::f32_to_f16, GrifLight};
+
+/// Ambient light probes on a grid, baked with `--bake-gi` and loaded with `--irradiance-volume`.
+/// Replaces the spot and point lights of the rig, which fake the bounce light it bakes.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct IrradianceVolumeData {
+    /// The volume covers the unit cube centered on `transform`, like the reflection probes.
+    pub transform: Transform,
+    pub resolution: UVec3,
+    /// An ambient cube per voxel, X varying fastest, then Y, then Z. Each holds the cosine
+    /// weighted average radiance arriving from +X, -X, +Y, -Y, +Z, and -Z, in cd/m².
+    pub voxels: Vec<[Vec3; 6]>,
+}
+
+impl IrradianceVolumeData {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        fs::write(path, ron::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// World space position of a voxel's probe.
+    pub fn probe_position(transform: &Transform, resolution: UVec3, voxel: UVec3) -> Vec3 {
+        let uvw = (voxel.as_vec3() + 0.5) / resolution.as_vec3();
+        transform.transform_point(uvw - 0.5)
+    }
+
+    /// Lays the ambient cubes out the way Bevy samples them: the X, Y, and Z faces in the
+    /// first, second, and last third of the depth, with the negative faces in the bottom half.
+    pub fn to_image(&self) -> Image {
+        let r = self.resolution;
+        let (width, height, depth) = (r.x, r.y * 2, r.z * 3);
+        let mut data = vec![0; (width * height * depth * 8) as usize];
+        for z in 0..r.z {
+            for y in 0..r.y {
+                for x in 0..r.x {
+                    let cube = self.voxels[(x + r.x * (y + r.y * z)) as usize];
+                    for (face, radiance) in cube.iter().enumerate() {
+                        let ty = y + if face % 2 == 1 { r.y } else { 0 };
+                        let tz = z + (face as u32 / 2) * r.z;
+                        let offset = (((tz * height + ty) * width + x) * 8) as usize;
+                        for (i, channel) in [radiance.x, radiance.y, radiance.z, 1.0]
+                            .into_iter()
+                            .enumerate()
+                        {
+                            data[offset + i * 2..offset + i * 2 + 2]
+                                .copy_from_slice(&f32_to_f16(channel).to_le_bytes());
+                        }
+                    }
+                }
+            }
+        }
+        Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: depth,
+            },
+            TextureDimension::D3,
+            data,
+            TextureFormat::Rgba16Float,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+    }
+}
+
+/// Marks the spawned irradiance volume, so a new bake can replace it.
+#[derive(Component)]
+pub struct BakedIrradianceVolume;
+
+pub struct IrradianceVolumePlugin;
+impl Plugin for IrradianceVolumePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, spawn_irradiance_volume);
+    }
+}
+
+fn spawn_irradiance_volume(
+    mut commands: Commands,
+    volume: Option<Res<IrradianceVolumeData>>,
+    mut images: ResMut<Assets<Image>>,
+    spawned: Query<Entity, With<BakedIrradianceVolume>>,
+    fake_bounce_lights: Query<Entity, (With<GrifLight>, Or<(With<SpotLight>, With<PointLight>)>)>,
+) {
+    let Some(volume) = volume else {
+        return;
+    };
+    if !volume.is_changed() {
+        return;
+    }
+    for entity in spawned.iter().chain(fake_bounce_lights.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.spawn((
+        SpatialBundle::from_transform(volume.transform),
+        LightProbe,
+        IrradianceVolume {
+            voxels: images.add(volume.to_image()),
+            intensity: 1.0,
+        },
+        BakedIrradianceVolume,
+    ));
+    info!(
+        "Spawned a {}x{}x{} irradiance volume",
+        volume.resolution.x, volume.resolution.y, volume.resolution.z
+    );
+}
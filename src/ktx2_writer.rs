@@ -0,0 +1,146 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+
+use crate::compression::CompressionFormat;
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+/// Identifier, header, and index, after which the level index starts.
+const LEVEL_INDEX_OFFSET: usize = 80;
+const SUPERCOMPRESSION_ZSTD: u32 = 2;
+
+/// Vulkan format and data format descriptor color model of each compressed format.
+fn vk_format(format: CompressionFormat, srgb: bool) -> (u32, u32) {
+    let (unorm, color_model) = match format {
+        CompressionFormat::Bc1 => (133, 128),
+        CompressionFormat::Bc7 => (145, 134),
+        CompressionFormat::Bc5 => (141, 131),
+        CompressionFormat::Astc4x4 => (157, 162),
+        CompressionFormat::Astc8x8 => (171, 162),
+    };
+    // The sRGB variant always follows the UNORM one
+    (unorm + srgb as u32, color_model)
+}
+
+/// Basic data format descriptor for a block compressed format, with a sample per independently
+/// encoded channel.
+fn data_format_descriptor(format: CompressionFormat, srgb: bool, block_bytes: u32) -> Vec<u8> {
+    let (_, color_model) = vk_format(format, srgb);
+    let (block_width, block_height) = format.block_size();
+    // Bit offset, bit length, and channel of each sample. BC1 with alpha names its only channel
+    // alpha, BC5 has red and green halves, the other formats a single color channel
+    let samples: &[(u32, u32, u32)] = match format {
+        CompressionFormat::Bc1 => &[(0, 64, 15)],
+        CompressionFormat::Bc5 => &[(0, 64, 0), (64, 64, 1)],
+        _ => &[(0, 128, 0)],
+    };
+    let transfer = if srgb { 2 } else { 1 };
+    let mut words = vec![
+        0,
+        2 | (24 + 16 * samples.len() as u32) << 16,
+        color_model | 1 << 8 | transfer << 16,
+        (block_width - 1) | (block_height - 1) << 8,
+        block_bytes,
+        0,
+    ];
+    for (bit_offset, bit_length, channel) in samples {
+        words.extend([
+            bit_offset | (bit_length - 1) << 16 | channel << 24,
+            0,
+            0,
+            u32::MAX,
+        ]);
+    }
+    let mut dfd = (4 + words.len() as u32 * 4).to_le_bytes().to_vec();
+    dfd.extend(words.iter().flat_map(|word| word.to_le_bytes()));
+    dfd
+}
+
+/// Writes a compressed mip chain, as left by [`compress_mips`](crate::compression::compress_mips),
+/// to a KTX2 file, with each level zstd supercompressed at `zstd_level` if given, 0 being
+/// zstd's default level.
+pub fn write_ktx2(
+    path: &Path,
+    image: &Image,
+    format: CompressionFormat,
+    zstd_level: Option<u32>,
+) -> anyhow::Result<()> {
+    let descriptor = &image.texture_descriptor;
+    let srgb = descriptor.format.is_srgb();
+    let size = descriptor.size;
+    let level_count = descriptor.mip_level_count;
+    let (block_width, block_height) = format.block_size();
+    let block_bytes = descriptor.format.block_copy_size(None).unwrap_or(16);
+
+    let mut levels = Vec::new();
+    let mut offset = 0;
+    for level in 0..level_count {
+        let width = (size.width >> level).max(1);
+        let height = (size.height >> level).max(1);
+        let len =
+            (width.div_ceil(block_width) * height.div_ceil(block_height) * block_bytes) as usize;
+        let data = &image.data[offset..offset + len];
+        let stored = match zstd_level {
+            Some(level) => zstd::bulk::compress(data, level as i32)?,
+            None => data.to_vec(),
+        };
+        levels.push((stored, len));
+        offset += len;
+    }
+
+    let dfd = data_format_descriptor(format, srgb, block_bytes);
+    let dfd_offset = LEVEL_INDEX_OFFSET + level_count as usize * 24;
+    let (vk_format, _) = vk_format(format, srgb);
+    let mut bytes = IDENTIFIER.to_vec();
+    for value in [
+        vk_format,
+        1,
+        size.width,
+        size.height,
+        0,
+        0,
+        1,
+        level_count,
+        if zstd_level.is_some() {
+            SUPERCOMPRESSION_ZSTD
+        } else {
+            0
+        },
+        dfd_offset as u32,
+        dfd.len() as u32,
+        0,
+        0,
+    ] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    // No supercompression global data
+    bytes.extend_from_slice(&[0; 16]);
+
+    // Levels are stored smallest first, each aligned to the block size unless supercompressed
+    let alignment = if zstd_level.is_some() {
+        1
+    } else {
+        block_bytes as usize
+    };
+    let mut data_offset = dfd_offset + dfd.len();
+    let mut level_offsets = vec![0; levels.len()];
+    for (level, (data, _)) in levels.iter().enumerate().rev() {
+        data_offset = data_offset.next_multiple_of(alignment);
+        level_offsets[level] = data_offset;
+        data_offset += data.len();
+    }
+    for ((data, uncompressed_len), offset) in levels.iter().zip(&level_offsets) {
+        for value in [*offset, data.len(), *uncompressed_len] {
+            bytes.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+    }
+    bytes.extend_from_slice(&dfd);
+    for (level, (data, _)) in levels.iter().enumerate().rev() {
+        bytes.resize(level_offsets[level], 0);
+        bytes.extend_from_slice(data);
+    }
+    fs::write(path, bytes)?;
+    Ok(())
+}
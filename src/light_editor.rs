@@ -0,0 +1,584 @@
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+
+use crate::{
+    render_features::RenderFeatures,
+    scene_preset::{PresetLight, ScenePreset},
+    Args,
+};
+
+/// Where the rig is saved when the scene wasn't loaded with `--preset`.
+const DEFAULT_PRESET_PATH: &str = "scene_preset.ron";
+
+/// Index of the light in [`ScenePreset::lights`] that this entity was spawned from.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct RigLight(pub usize);
+
+/// Which light the editor is working on, shown/hidden with F4.
+#[derive(Resource, Default)]
+pub struct LightEditor {
+    pub selected: usize,
+    pub visible: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LightProperty {
+    X,
+    Y,
+    Z,
+    Yaw,
+    Pitch,
+    Red,
+    Green,
+    Blue,
+    Intensity,
+    Range,
+    InnerAngle,
+    OuterAngle,
+}
+
+impl LightProperty {
+    const ALL: [LightProperty; 12] = [
+        LightProperty::X,
+        LightProperty::Y,
+        LightProperty::Z,
+        LightProperty::Yaw,
+        LightProperty::Pitch,
+        LightProperty::Red,
+        LightProperty::Green,
+        LightProperty::Blue,
+        LightProperty::Intensity,
+        LightProperty::Range,
+        LightProperty::InnerAngle,
+        LightProperty::OuterAngle,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            LightProperty::X => "X",
+            LightProperty::Y => "Y",
+            LightProperty::Z => "Z",
+            LightProperty::Yaw => "Yaw",
+            LightProperty::Pitch => "Pitch",
+            LightProperty::Red => "Red",
+            LightProperty::Green => "Green",
+            LightProperty::Blue => "Blue",
+            LightProperty::Intensity => "Intensity",
+            LightProperty::Range => "Range",
+            LightProperty::InnerAngle => "Inner Angle",
+            LightProperty::OuterAngle => "Outer Angle",
+        }
+    }
+
+    /// The value shown on the slider, angles are in degrees. `None` if the light doesn't have
+    /// this property.
+    fn get(&self, light: &PresetLight) -> Option<f32> {
+        let mut light = light.clone();
+        let transform = *light.transform_mut();
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let color = *light.color_mut();
+        Some(match self {
+            LightProperty::X => transform.translation.x,
+            LightProperty::Y => transform.translation.y,
+            LightProperty::Z => transform.translation.z,
+            LightProperty::Yaw => yaw.to_degrees(),
+            LightProperty::Pitch => pitch.to_degrees(),
+            LightProperty::Red => color.r(),
+            LightProperty::Green => color.g(),
+            LightProperty::Blue => color.b(),
+            LightProperty::Intensity => *light.intensity_mut(),
+            LightProperty::Range => *light.range_mut()?,
+            LightProperty::InnerAngle => light.angles_mut()?.0.to_degrees(),
+            LightProperty::OuterAngle => light.angles_mut()?.1.to_degrees(),
+        })
+    }
+
+    fn set(&self, light: &mut PresetLight, value: f32) {
+        let transform = light.transform_mut();
+        let (yaw, pitch, roll) = transform.rotation.to_euler(EulerRot::YXZ);
+        match self {
+            LightProperty::X => transform.translation.x = value,
+            LightProperty::Y => transform.translation.y = value,
+            LightProperty::Z => transform.translation.z = value,
+            LightProperty::Yaw => {
+                transform.rotation =
+                    Quat::from_euler(EulerRot::YXZ, value.to_radians(), pitch, roll)
+            }
+            LightProperty::Pitch => {
+                transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw, value.to_radians(), roll)
+            }
+            LightProperty::Red => {
+                light.color_mut().set_r(value);
+            }
+            LightProperty::Green => {
+                light.color_mut().set_g(value);
+            }
+            LightProperty::Blue => {
+                light.color_mut().set_b(value);
+            }
+            LightProperty::Intensity => *light.intensity_mut() = value,
+            LightProperty::Range => {
+                if let Some(range) = light.range_mut() {
+                    *range = value;
+                }
+            }
+            // Keep the inner angle inside the outer one
+            LightProperty::InnerAngle => {
+                if let Some((inner, outer)) = light.angles_mut() {
+                    *inner = value.to_radians().min(*outer);
+                }
+            }
+            LightProperty::OuterAngle => {
+                if let Some((inner, outer)) = light.angles_mut() {
+                    *outer = value.to_radians().max(*inner);
+                }
+            }
+        }
+    }
+
+    /// Slider range. Intensity is on a log scale since the rig spans several orders of magnitude.
+    fn range(&self) -> (f32, f32) {
+        match self {
+            LightProperty::X | LightProperty::Y | LightProperty::Z => (-50.0, 50.0),
+            LightProperty::Yaw => (-180.0, 180.0),
+            LightProperty::Pitch => (-90.0, 90.0),
+            LightProperty::Red | LightProperty::Green | LightProperty::Blue => (0.0, 1.0),
+            LightProperty::Intensity => (2.0, 8.0),
+            LightProperty::Range => (0.0, 100.0),
+            LightProperty::InnerAngle | LightProperty::OuterAngle => (0.0, 90.0),
+        }
+    }
+
+    fn slider_position(&self, value: f32) -> f32 {
+        let value = match self {
+            LightProperty::Intensity => value.max(1.0).log10(),
+            _ => value,
+        };
+        let (min, max) = self.range();
+        ((value - min) / (max - min)).clamp(0.0, 1.0)
+    }
+
+    fn slider_value(&self, t: f32) -> f32 {
+        let (min, max) = self.range();
+        let value = min + t.clamp(0.0, 1.0) * (max - min);
+        match self {
+            LightProperty::Intensity => 10.0f32.powf(value),
+            _ => value,
+        }
+    }
+
+    fn label(&self, value: f32) -> String {
+        match self {
+            LightProperty::Yaw
+            | LightProperty::Pitch
+            | LightProperty::InnerAngle
+            | LightProperty::OuterAngle => format!("{}: {:.0}°", self.name(), value),
+            LightProperty::Red | LightProperty::Green | LightProperty::Blue => {
+                format!("{}: {:.2}", self.name(), value)
+            }
+            LightProperty::Intensity => format!("{}: {:.0}", self.name(), value),
+            _ => format!("{}: {:.2}m", self.name(), value),
+        }
+    }
+}
+
+/// Root node of the light editor.
+#[derive(Component)]
+pub struct LightEditorPanel;
+
+/// Text of the editor, `None` is the header naming the selected light.
+#[derive(Component)]
+pub struct LightEditorLabel(Option<LightProperty>);
+
+/// A row of the editor, hidden when the selected light doesn't have the property.
+#[derive(Component)]
+pub struct LightPropertyRow(LightProperty);
+
+/// Drag along this node to set the property.
+#[derive(Component)]
+pub struct LightSlider(LightProperty);
+
+/// The filled part of a [`LightSlider`].
+#[derive(Component)]
+pub struct LightSliderFill(LightProperty);
+
+/// Steps the selection through the rig.
+#[derive(Component)]
+pub struct SelectLightButton(isize);
+
+/// Turns the selected light's shadows on or off.
+#[derive(Component)]
+pub struct LightShadowsButton;
+
+#[derive(Component)]
+pub struct SaveRigButton;
+
+/// Adjust the [`crate::GrifLight`] rig with sliders and save it back to the preset file.
+pub struct LightEditorPlugin;
+impl Plugin for LightEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightEditor>()
+            .add_systems(Startup, spawn_light_editor)
+            .add_systems(
+                Update,
+                (
+                    toggle_light_editor,
+                    select_light_buttons,
+                    drag_light_sliders,
+                    toggle_light_shadows,
+                    save_rig_button,
+                    apply_rig,
+                    update_light_editor,
+                    highlight_selected_light,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn text_style() -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color: Color::WHITE,
+        ..default()
+    }
+}
+
+fn spawn_button(parent: &mut ChildBuilder, text: &str, marker: impl Component) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.25, 0.25, 0.25, 0.9).into(),
+                ..default()
+            },
+            marker,
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(text, text_style()));
+        });
+}
+
+fn spawn_light_editor(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            LightEditorPanel,
+        ))
+        .with_children(|panel| {
+            panel
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.0),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    spawn_button(row, "<", SelectLightButton(-1));
+                    row.spawn((
+                        TextBundle::from_section("", text_style()),
+                        LightEditorLabel(None),
+                    ));
+                    spawn_button(row, ">", SelectLightButton(1));
+                });
+            for property in LightProperty::ALL {
+                panel
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        LightPropertyRow(property),
+                    ))
+                    .with_children(|row| {
+                        row.spawn((
+                            TextBundle::from_section("", text_style()).with_style(Style {
+                                width: Val::Px(150.0),
+                                ..default()
+                            }),
+                            LightEditorLabel(Some(property)),
+                        ));
+                        row.spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(200.0),
+                                    height: Val::Px(14.0),
+                                    ..default()
+                                },
+                                background_color: Color::rgba(0.25, 0.25, 0.25, 0.9).into(),
+                                ..default()
+                            },
+                            RelativeCursorPosition::default(),
+                            LightSlider(property),
+                        ))
+                        .with_children(|slider| {
+                            slider.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        height: Val::Percent(100.0),
+                                        ..default()
+                                    },
+                                    background_color: Color::rgb(0.8, 0.6, 0.2).into(),
+                                    ..default()
+                                },
+                                LightSliderFill(property),
+                            ));
+                        });
+                    });
+            }
+            spawn_button(panel, "Toggle shadows", LightShadowsButton);
+            spawn_button(panel, "Save rig", SaveRigButton);
+        });
+}
+
+fn toggle_light_editor(
+    input: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<LightEditor>,
+    mut panel: Query<&mut Visibility, With<LightEditorPanel>>,
+) {
+    if !input.just_pressed(KeyCode::F4) {
+        return;
+    }
+    editor.visible = !editor.visible;
+    for mut visibility in &mut panel {
+        *visibility = if editor.visible {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn select_light_buttons(
+    buttons: Query<(&Interaction, &SelectLightButton), Changed<Interaction>>,
+    preset: Res<ScenePreset>,
+    mut editor: ResMut<LightEditor>,
+) {
+    let count = preset.lights.len() as isize;
+    for (interaction, button) in &buttons {
+        if *interaction == Interaction::Pressed && count > 0 {
+            editor.selected = (editor.selected as isize + button.0).rem_euclid(count) as usize;
+        }
+    }
+}
+
+fn drag_light_sliders(
+    sliders: Query<(&Interaction, &RelativeCursorPosition, &LightSlider)>,
+    editor: Res<LightEditor>,
+    mut preset: ResMut<ScenePreset>,
+) {
+    let selected = editor.selected;
+    for (interaction, cursor, slider) in &sliders {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let (Some(cursor), Some(light)) = (cursor.normalized, preset.lights.get(selected)) else {
+            continue;
+        };
+        let value = slider.0.slider_value(cursor.x);
+        if slider.0.get(light).is_some_and(|current| current != value) {
+            slider.0.set(&mut preset.lights[selected], value);
+        }
+    }
+}
+
+fn toggle_light_shadows(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<LightShadowsButton>)>,
+    editor: Res<LightEditor>,
+    mut preset: ResMut<ScenePreset>,
+) {
+    if !buttons.iter().any(|i| *i == Interaction::Pressed) {
+        return;
+    }
+    if let Some(light) = preset.lights.get_mut(editor.selected) {
+        let shadows = light.shadows_enabled_mut();
+        *shadows = !*shadows;
+    }
+}
+
+fn save_rig_button(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<SaveRigButton>)>,
+    preset: Res<ScenePreset>,
+    args: Res<Args>,
+) {
+    if !buttons.iter().any(|i| *i == Interaction::Pressed) {
+        return;
+    }
+    let path = args.preset.as_deref().unwrap_or(DEFAULT_PRESET_PATH);
+    match preset.save(path) {
+        Ok(()) => info!("Saved light rig to {path}"),
+        Err(e) => error!("Failed to save light rig to {path}: {e}"),
+    }
+}
+
+/// Copies the edited preset onto the spawned lights. Lights only cast shadows while shadows are
+/// also on in the feature panel.
+fn apply_rig(
+    preset: Res<ScenePreset>,
+    features: Res<RenderFeatures>,
+    mut lights: Query<(
+        &RigLight,
+        &mut Transform,
+        Option<&mut DirectionalLight>,
+        Option<&mut SpotLight>,
+        Option<&mut PointLight>,
+    )>,
+) {
+    if !preset.is_changed() && !features.is_changed() {
+        return;
+    }
+    for (index, mut transform, directional, spot, point) in &mut lights {
+        let Some(light) = preset.lights.get(index.0) else {
+            continue;
+        };
+        match (light.clone(), directional, spot, point) {
+            (
+                PresetLight::Directional {
+                    transform: t,
+                    color,
+                    illuminance,
+                    shadows_enabled,
+                    ..
+                },
+                Some(mut l),
+                _,
+                _,
+            ) => {
+                *transform = t;
+                l.color = color;
+                l.illuminance = illuminance;
+                l.shadows_enabled = shadows_enabled && features.shadows;
+            }
+            (
+                PresetLight::Spot {
+                    transform: t,
+                    color,
+                    intensity,
+                    range,
+                    inner_angle,
+                    outer_angle,
+                    shadows_enabled,
+                    ..
+                },
+                _,
+                Some(mut l),
+                _,
+            ) => {
+                *transform = t;
+                l.color = color;
+                l.intensity = intensity;
+                l.range = range;
+                l.inner_angle = inner_angle;
+                l.outer_angle = outer_angle;
+                l.shadows_enabled = shadows_enabled && features.shadows;
+            }
+            (
+                PresetLight::Point {
+                    transform: t,
+                    color,
+                    intensity,
+                    range,
+                    shadows_enabled,
+                    ..
+                },
+                _,
+                _,
+                Some(mut l),
+            ) => {
+                *transform = t;
+                l.color = color;
+                l.intensity = intensity;
+                l.range = range;
+                l.shadows_enabled = shadows_enabled && features.shadows;
+            }
+            _ => (),
+        }
+    }
+}
+
+fn update_light_editor(
+    preset: Res<ScenePreset>,
+    editor: Res<LightEditor>,
+    mut labels: Query<(&mut Text, &LightEditorLabel)>,
+    mut rows: Query<(&mut Style, &LightPropertyRow)>,
+    mut fills: Query<(&mut Style, &LightSliderFill), Without<LightPropertyRow>>,
+) {
+    if !preset.is_changed() && !editor.is_changed() {
+        return;
+    }
+    let light = preset.lights.get(editor.selected);
+    for (mut text, label) in &mut labels {
+        text.sections[0].value = match (label.0, light) {
+            (None, Some(light)) => format!(
+                "{} {}/{}, shadows {}",
+                light.kind(),
+                editor.selected + 1,
+                preset.lights.len(),
+                if *light.clone().shadows_enabled_mut() {
+                    "on"
+                } else {
+                    "off"
+                }
+            ),
+            (None, None) => String::from("No lights"),
+            (Some(property), Some(light)) => property
+                .get(light)
+                .map(|value| property.label(value))
+                .unwrap_or_default(),
+            (Some(_), None) => String::new(),
+        };
+    }
+    for (mut style, row) in &mut rows {
+        style.display = match light.and_then(|light| row.0.get(light)) {
+            Some(_) => Display::Flex,
+            None => Display::None,
+        };
+    }
+    for (mut style, fill) in &mut fills {
+        if let Some(value) = light.and_then(|light| fill.0.get(light)) {
+            style.width = Val::Percent(fill.0.slider_position(value) * 100.0);
+        }
+    }
+}
+
+/// Marks the selected light so it can be found in the scene.
+fn highlight_selected_light(
+    editor: Res<LightEditor>,
+    lights: Query<(&RigLight, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    if !editor.visible {
+        return;
+    }
+    for (index, transform) in &lights {
+        if index.0 == editor.selected {
+            let position = transform.translation();
+            gizmos.sphere(position, Quat::IDENTITY, 0.25, Color::YELLOW);
+            gizmos.arrow(
+                position,
+                position + transform.forward() * 1.5,
+                Color::YELLOW,
+            );
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::{fs, path::Path};
+
+use bevy::{
+    pbr::{environment_map::ReflectionProbeBundle, LightProbe},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::environment_map::{EnvironmentMapEntry, EnvironmentMaps};
+
+/// A reflection probe volume. The probe affects the unit cube centered on `transform`, so the
+/// scale is the size of the volume.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReflectionProbeLayout {
+    pub name: String,
+    pub transform: Transform,
+    /// Environment map name from the manifest, or a path prefix, see [`EnvironmentMaps::load`].
+    pub environment_map: String,
+}
+
+/// Reflection probes to place in the scene, loaded from a RON file with `--light-probes`.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LightProbeLayout {
+    pub probes: Vec<ReflectionProbeLayout>,
+}
+
+impl LightProbeLayout {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// Toggles drawing the bounds of every reflection probe, with P.
+#[derive(Resource, Default)]
+pub struct ShowProbeBounds(pub bool);
+
+pub struct LightProbesPlugin;
+impl Plugin for LightProbesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowProbeBounds>()
+            .add_systems(Startup, spawn_reflection_probes)
+            .add_systems(Update, draw_probe_bounds);
+    }
+}
+
+fn spawn_reflection_probes(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    layout: Option<Res<LightProbeLayout>>,
+    env_maps: Res<EnvironmentMaps>,
+) {
+    let Some(layout) = layout else {
+        return;
+    };
+    for probe in &layout.probes {
+        let map = env_maps
+            .maps
+            .iter()
+            .find(|map| map.name == probe.environment_map)
+            .cloned()
+            .unwrap_or_else(|| EnvironmentMapEntry::from_path(&probe.environment_map));
+        commands.spawn((
+            ReflectionProbeBundle {
+                spatial: SpatialBundle::from_transform(probe.transform),
+                light_probe: LightProbe,
+                environment_map: map.light(&asset_server),
+            },
+            Name::new(probe.name.clone()),
+        ));
+    }
+    info!("Spawned {} reflection probes", layout.probes.len());
+}
+
+fn draw_probe_bounds(
+    input: Res<ButtonInput<KeyCode>>,
+    mut show: ResMut<ShowProbeBounds>,
+    probes: Query<&GlobalTransform, With<LightProbe>>,
+    mut gizmos: Gizmos,
+) {
+    if input.just_pressed(KeyCode::KeyP) {
+        show.0 = !show.0;
+    }
+    if !show.0 {
+        return;
+    }
+    for transform in &probes {
+        gizmos.cuboid(transform.compute_transform(), Color::CYAN);
+    }
+}
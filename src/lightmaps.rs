@@ -0,0 +1,174 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{pbr::Lightmap, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{loading::SceneReady, GrifLight};
+
+/// Name of the manifest inside the `--lightmaps` directory.
+pub const LIGHTMAPS_MANIFEST: &str = "lightmaps.ron";
+
+/// Where a mesh's lightmap is in an atlas.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LightmapEntry {
+    /// Name of the mesh entity, the glTF mesh name with `.<primitive index>` appended when it has
+    /// more than one primitive.
+    pub mesh: String,
+    /// Atlas image, relative to the manifest.
+    pub image: String,
+    /// Region of the atlas the mesh's second UV set maps to, in 0..1.
+    pub uv_rect: Rect,
+}
+
+/// Lightmaps from an external baker, loaded from `<dir>/lightmaps.ron` with `--lightmaps dir/`.
+/// The meshes need a second UV set, which the baker usually writes into the glTF it unwrapped.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LightmapManifest {
+    /// Directory of the manifest, relative to `assets`.
+    #[serde(skip)]
+    pub dir: PathBuf,
+    pub lightmaps: Vec<LightmapEntry>,
+}
+
+impl LightmapManifest {
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let path = Path::new("assets").join(dir).join(LIGHTMAPS_MANIFEST);
+        let mut manifest: Self = ron::from_str(&fs::read_to_string(path)?)?;
+        manifest.dir = dir.to_path_buf();
+        Ok(manifest)
+    }
+}
+
+/// Whether the baked lightmaps are shown, toggled with K to compare them with the rig's realtime
+/// spot and point lights, which fake the bounce light and are hidden while the lightmaps are on.
+#[derive(Resource)]
+pub struct BakedLighting {
+    pub enabled: bool,
+    /// Lightmap of each mesh it was applied to, to put back after toggling.
+    lightmaps: HashMap<Entity, Lightmap>,
+}
+
+impl Default for BakedLighting {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            lightmaps: HashMap::new(),
+        }
+    }
+}
+
+pub struct LightmapsPlugin;
+impl Plugin for LightmapsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BakedLighting>()
+            .add_systems(Update, (apply_lightmaps, toggle_lightmaps).chain());
+    }
+}
+
+fn apply_lightmaps(
+    mut commands: Commands,
+    manifest: Option<Res<LightmapManifest>>,
+    scene_ready: Res<SceneReady>,
+    asset_server: Res<AssetServer>,
+    mut baked: ResMut<BakedLighting>,
+    named_meshes: Query<(Entity, &Name, &Handle<Mesh>)>,
+    meshes: Res<Assets<Mesh>>,
+    mut fake_bounce_lights: Query<
+        &mut Visibility,
+        (With<GrifLight>, Or<(With<SpotLight>, With<PointLight>)>),
+    >,
+    mut applied: Local<bool>,
+) {
+    let Some(manifest) = manifest else {
+        return;
+    };
+    if *applied || !scene_ready.ready {
+        return;
+    }
+    *applied = true;
+
+    let entries: HashMap<_, _> = manifest
+        .lightmaps
+        .iter()
+        .map(|entry| (entry.mesh.as_str(), entry))
+        .collect();
+    let mut images = HashMap::new();
+    let mut missing_uvs = 0;
+    for (entity, name, handle) in &named_meshes {
+        let Some(entry) = entries.get(name.as_str()) else {
+            continue;
+        };
+        let has_uvs = meshes
+            .get(handle)
+            .is_some_and(|mesh| mesh.contains_attribute(Mesh::ATTRIBUTE_UV_1));
+        if !has_uvs {
+            missing_uvs += 1;
+            continue;
+        }
+        let image = images
+            .entry(entry.image.as_str())
+            .or_insert_with(|| asset_server.load(manifest.dir.join(&entry.image)))
+            .clone();
+        let lightmap = Lightmap {
+            image,
+            uv_rect: entry.uv_rect,
+        };
+        if baked.enabled {
+            commands.entity(entity).insert(lightmap.clone());
+        }
+        baked.lightmaps.insert(entity, lightmap);
+    }
+    if missing_uvs > 0 {
+        warn!("Skipped lightmaps for {missing_uvs} meshes without a second UV set");
+    }
+    if baked.enabled {
+        for mut visibility in &mut fake_bounce_lights {
+            *visibility = Visibility::Hidden;
+        }
+    }
+    info!(
+        "Applied lightmaps to {} of {} meshes in the manifest",
+        baked.lightmaps.len(),
+        manifest.lightmaps.len()
+    );
+}
+
+fn toggle_lightmaps(
+    mut commands: Commands,
+    input: Res<ButtonInput<KeyCode>>,
+    mut baked: ResMut<BakedLighting>,
+    mut fake_bounce_lights: Query<
+        &mut Visibility,
+        (With<GrifLight>, Or<(With<SpotLight>, With<PointLight>)>),
+    >,
+) {
+    if !input.just_pressed(KeyCode::KeyK) || baked.lightmaps.is_empty() {
+        return;
+    }
+    baked.enabled = !baked.enabled;
+    for (entity, lightmap) in &baked.lightmaps {
+        let Some(mut entity) = commands.get_entity(*entity) else {
+            continue;
+        };
+        if baked.enabled {
+            entity.insert(lightmap.clone());
+        } else {
+            entity.remove::<Lightmap>();
+        }
+    }
+    for mut visibility in &mut fake_bounce_lights {
+        *visibility = if baked.enabled {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+    info!(
+        "{} lighting",
+        if baked.enabled { "Baked" } else { "Realtime" }
+    );
+}
@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+
+use crate::{mipmap_generator::MipmapTasks, PostProcScene};
+
+/// How many consecutive frames everything needs to look loaded before the scene is considered ready.
+/// Material asset events can land a frame or two after the scene itself finishes loading.
+const SETTLE_FRAMES: u32 = 10;
+
+/// Scenes that need to finish loading before [`SceneReady`] is set.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct LoadingScenes(pub Vec<Handle<Scene>>);
+
+/// Set once all scenes are loaded, post-processed, and have their mipmaps generated.
+#[derive(Resource, Default)]
+pub struct SceneReady {
+    pub ready: bool,
+    settled_frames: u32,
+}
+
+pub struct SceneLoadingPlugin;
+impl Plugin for SceneLoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadingScenes>()
+            .init_resource::<SceneReady>()
+            .add_systems(Startup, spawn_loading_screen)
+            .add_systems(Update, (check_scene_ready, update_loading_screen).chain());
+    }
+}
+
+/// Root node of the loading screen, despawned once the scene is ready.
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingBar;
+
+#[derive(Component)]
+struct LoadingText;
+
+fn spawn_loading_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(12.0),
+                    ..default()
+                },
+                background_color: Color::rgb(0.05, 0.05, 0.05).into(),
+                z_index: ZIndex::Global(100),
+                ..default()
+            },
+            LoadingScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "Loading scenes",
+                    TextStyle {
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                LoadingText,
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(400.0),
+                        height: Val::Px(16.0),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.2, 0.2, 0.2).into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::rgb(0.9, 0.9, 0.9).into(),
+                            ..default()
+                        },
+                        LoadingBar,
+                    ));
+                });
+        });
+}
+
+/// Scene loading fills the first half of the bar, mipmap generation the second half. Placeholder
+/// textures stand in while the mipmaps are generated, so the screen moves out of the way then.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn update_loading_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    scenes: Res<LoadingScenes>,
+    scene_ready: Res<SceneReady>,
+    images: Res<Assets<Image>>,
+    mipmap_tasks: Option<Res<MipmapTasks<StandardMaterial>>>,
+    mut screen: Query<
+        (Entity, &mut Style, &mut BackgroundColor),
+        (With<LoadingScreen>, Without<LoadingBar>),
+    >,
+    mut bar: Query<&mut Style, (With<LoadingBar>, Without<LoadingScreen>)>,
+    mut text: Query<&mut Text, With<LoadingText>>,
+) {
+    if scene_ready.ready {
+        for (entity, _, _) in &screen {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    let scenes_loaded = scenes
+        .iter()
+        .filter(|scene| asset_server.is_loaded_with_dependencies(*scene))
+        .count();
+    let (progress, status) = if scenes_loaded < scenes.len() {
+        (
+            scenes_loaded as f32 / scenes.len() as f32 * 0.5,
+            format!(
+                "Loading scenes {scenes_loaded}/{}, {} textures decoded",
+                scenes.len(),
+                images.len()
+            ),
+        )
+    } else {
+        let (processed, remaining) = mipmap_tasks
+            .map(|tasks| (tasks.processed(), tasks.remaining()))
+            .unwrap_or((0, 0));
+        let total = processed + remaining;
+        for (_, mut style, mut background) in &mut screen {
+            if style.justify_content != JustifyContent::FlexEnd {
+                style.justify_content = JustifyContent::FlexEnd;
+                style.padding.bottom = Val::Px(40.0);
+                *background = Color::NONE.into();
+            }
+        }
+        (
+            0.5 + processed as f32 / total.max(1) as f32 * 0.5,
+            format!("Generating mipmaps {processed}/{total}"),
+        )
+    };
+    for mut style in &mut bar {
+        style.width = Val::Percent(progress * 100.0);
+    }
+    for mut text in &mut text {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
+
+fn check_scene_ready(
+    asset_server: Res<AssetServer>,
+    scenes: Res<LoadingScenes>,
+    mut scene_ready: ResMut<SceneReady>,
+    post_proc: Query<(), With<PostProcScene>>,
+    mipmap_tasks: Option<Res<MipmapTasks<StandardMaterial>>>,
+) {
+    if scene_ready.ready {
+        return;
+    }
+    let loaded = scenes
+        .iter()
+        .all(|scene| asset_server.is_loaded_with_dependencies(scene))
+        && post_proc.is_empty()
+        && mipmap_tasks.map(|tasks| tasks.remaining()).unwrap_or(0) == 0;
+    if loaded {
+        scene_ready.settled_frames += 1;
+        if scene_ready.settled_frames >= SETTLE_FRAMES {
+            info!("Scene ready");
+            scene_ready.ready = true;
+        }
+    } else {
+        scene_ready.settled_frames = 0;
+    }
+}
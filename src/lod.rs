@@ -0,0 +1,190 @@
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    render::{mesh::Indices, primitives::Aabb},
+    utils::{HashMap, Instant},
+};
+use meshopt::{SimplifyOptions, VertexDataAdapter};
+
+use crate::loading::SceneReady;
+
+pub const VISIBLE_TRIANGLES: DiagnosticPath = DiagnosticPath::const_new("visible_triangles");
+
+/// Fraction of the full detail triangles kept by each LOD after the first.
+const LOD_RATIOS: [f32; 3] = [0.5, 0.25, 0.1];
+/// Simplification error allowed per LOD, relative to the size of the mesh.
+const LOD_ERRORS: [f32; 3] = [0.005, 0.02, 0.05];
+/// Switch to the next LOD at this many bounding radii from the camera, doubling for each LOD.
+const LOD_DISTANCE: f32 = 8.0;
+/// Meshes with fewer triangles aren't worth simplifying.
+const MIN_TRIANGLES: usize = 256;
+
+/// Generates simplified LODs for the scene's meshes once it loads, and swaps them by distance to
+/// the camera. Only active when the resource is present, see `--lods`.
+#[derive(Resource, Default)]
+pub struct MeshLods;
+
+/// Levels of detail of a mesh entity, the first is the mesh it was loaded with.
+#[derive(Component)]
+pub struct Lods {
+    levels: Vec<Handle<Mesh>>,
+    /// Bounding sphere radius, in world space.
+    radius: f32,
+    current: usize,
+}
+
+pub struct LodPlugin;
+impl Plugin for LodPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(VISIBLE_TRIANGLES).with_max_history_length(20))
+            .add_systems(
+                Update,
+                (generate_lods, switch_lods, count_visible_triangles).chain(),
+            );
+    }
+}
+
+/// Simplifies a triangle list mesh, keeping `ratio` of its triangles. Keeps every vertex and only
+/// replaces the indices, so all attributes carry over. Returns `None` when it couldn't get the
+/// triangle count down by much, since such a LOD would only cost memory.
+fn simplify(mesh: &Mesh, ratio: f32, error: f32) -> Option<Mesh> {
+    let indices: Vec<u32> = mesh.indices()?.iter().map(|i| i as u32).collect();
+    let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+    let bytes: Vec<u8> = positions
+        .iter()
+        .flatten()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect();
+    let vertices = VertexDataAdapter::new(&bytes, 12, 0).ok()?;
+    let target = ((indices.len() / 3) as f32 * ratio) as usize * 3;
+    // Keep the borders in place, so meshes that were split by material don't open up seams
+    let simplified = meshopt::simplify(
+        &indices,
+        &vertices,
+        target,
+        error,
+        SimplifyOptions::LockBorder,
+        None,
+    );
+    if simplified.is_empty() || simplified.len() as f32 > indices.len() as f32 * 0.9 {
+        return None;
+    }
+    let mut lod = mesh.clone();
+    lod.insert_indices(Indices::U32(simplified));
+    Some(lod)
+}
+
+fn triangle_count(mesh: &Mesh) -> usize {
+    match mesh.indices() {
+        Some(indices) => indices.len() / 3,
+        None => mesh.count_vertices() / 3,
+    }
+}
+
+fn generate_lods(
+    mut commands: Commands,
+    lods: Option<Res<MeshLods>>,
+    scene_ready: Res<SceneReady>,
+    entities: Query<(Entity, &Handle<Mesh>, &Aabb, &GlobalTransform), Without<Lods>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut generated: Local<bool>,
+) {
+    if lods.is_none() || *generated || !scene_ready.ready {
+        return;
+    }
+    *generated = true;
+    let _span = info_span!("generate_lods").entered();
+
+    let start = Instant::now();
+    // Meshes are often shared between entities, only simplify each once
+    let mut levels_by_mesh: HashMap<AssetId<Mesh>, Vec<Handle<Mesh>>> = HashMap::new();
+    let (mut before, mut after) = (0, 0);
+    for (entity, handle, aabb, transform) in &entities {
+        let levels = match levels_by_mesh.get(&handle.id()) {
+            Some(levels) => levels.clone(),
+            None => {
+                let mut levels = vec![handle.clone()];
+                if let Some(mesh) = meshes.get(handle) {
+                    let triangles = triangle_count(mesh);
+                    if triangles >= MIN_TRIANGLES {
+                        let lods: Vec<_> = LOD_RATIOS
+                            .iter()
+                            .zip(LOD_ERRORS)
+                            .map_while(|(ratio, error)| simplify(mesh, *ratio, error))
+                            .collect();
+                        before += triangles;
+                        after += lods.last().map_or(triangles, triangle_count);
+                        levels.extend(lods.into_iter().map(|lod| meshes.add(lod)));
+                    }
+                }
+                levels_by_mesh.insert(handle.id(), levels.clone());
+                levels
+            }
+        };
+        if levels.len() < 2 {
+            continue;
+        }
+        let scale = transform.compute_transform().scale.abs().max_element();
+        commands.entity(entity).insert(Lods {
+            levels,
+            radius: Vec3::from(aabb.half_extents).length() * scale,
+            current: 0,
+        });
+    }
+    info!(
+        "Generated LODs for {} meshes in {:.2}s, the lowest detail has {after} of {before} triangles",
+        levels_by_mesh
+            .values()
+            .filter(|levels| levels.len() > 1)
+            .count(),
+        start.elapsed().as_secs_f32()
+    );
+}
+
+fn switch_lods(
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    mut entities: Query<(&mut Lods, &mut Handle<Mesh>, &GlobalTransform, &Aabb)>,
+) {
+    let Some(camera) = cameras.iter().next() else {
+        return;
+    };
+    let camera = camera.translation();
+    for (mut lods, mut handle, transform, aabb) in &mut entities {
+        let center = transform.transform_point(aabb.center.into());
+        let distance = camera.distance(center) / lods.radius.max(0.01);
+        let mut level = 0;
+        while level + 1 < lods.levels.len() && distance > LOD_DISTANCE * 2f32.powi(level as i32) {
+            level += 1;
+        }
+        if level != lods.current {
+            lods.current = level;
+            *handle = lods.levels[level].clone();
+        }
+    }
+}
+
+/// Counts the triangles of meshes that passed frustum culling, shown with the diagnostics (F3).
+fn count_visible_triangles(
+    mut diagnostics: Diagnostics,
+    entities: Query<(&Handle<Mesh>, &ViewVisibility)>,
+    meshes: Res<Assets<Mesh>>,
+    mut counts: Local<HashMap<AssetId<Mesh>, usize>>,
+) {
+    diagnostics.add_measurement(&VISIBLE_TRIANGLES, || {
+        entities
+            .iter()
+            .filter(|(_, visibility)| visibility.get())
+            .map(|(handle, _)| match counts.get(&handle.id()) {
+                Some(count) => *count,
+                None => {
+                    let Some(mesh) = meshes.get(handle) else {
+                        return 0;
+                    };
+                    let count = triangle_count(mesh);
+                    counts.insert(handle.id(), count);
+                    count
+                }
+            })
+            .sum::<usize>() as f64
+    });
+}
@@ -1,65 +1,892 @@
-use std::{f32::consts::PI, time::Instant};
-
 mod auto_instance;
+mod benchmark;
+mod bookmarks;
 mod camera_controller;
+mod clay;
+mod compression;
+#[cfg(not(target_arch = "wasm32"))]
 mod convert;
+mod culling_stats;
+mod debug_text;
+mod debug_view;
+#[cfg(feature = "download")]
+mod download;
+mod emissive;
+mod environment_map;
+mod frame_pacing;
+#[cfg(feature = "physics")]
+mod gi_bake;
+#[cfg(not(target_arch = "wasm32"))]
+mod gltf_file;
+mod gltf_lights;
+mod golden;
+mod gpu_mipmaps;
+mod gpu_timing;
+#[cfg(not(target_arch = "wasm32"))]
+mod hdr_capture;
+#[cfg(not(target_arch = "wasm32"))]
+mod hot_reload;
+mod hud;
+mod input_replay;
+mod inspector;
+mod irradiance_volume;
+#[cfg(not(target_arch = "wasm32"))]
+mod ktx2_writer;
+mod light_editor;
+mod light_probes;
+mod lightmaps;
+mod loading;
+mod lod;
+mod many_lights;
+mod material_variants;
+mod memory_diagnostics;
+#[cfg(not(target_arch = "wasm32"))]
+mod mip_cache;
+mod mip_filter;
 mod mipmap_generator;
+mod path_editor;
+mod picking;
+mod props;
+mod quality_tier;
+mod render_features;
+mod render_scale;
+mod rig_gizmos;
+mod scene_preset;
+mod scene_rules;
+mod screenshot;
+mod settings;
+mod shadows;
+mod sky;
+mod skybox;
+mod split_screen;
+mod system_info;
+mod texture_streaming;
+mod time_of_day;
+mod tonemapping;
+#[cfg(not(target_arch = "wasm32"))]
+mod validate;
+#[cfg(not(target_arch = "wasm32"))]
+mod video;
+#[cfg(feature = "physics")]
+mod walk;
+mod wind;
+mod wireframe;
+
+use std::path::{Path, PathBuf};
 
 use argh::FromArgs;
 use auto_instance::{AutoInstanceMaterialPlugin, AutoInstancePlugin};
+use benchmark::{
+    AutoBenchmark, Benchmark, BenchmarkBaseline, BenchmarkMatrix, BenchmarkPlugin, BenchmarkResults,
+};
 use bevy::{
-    core_pipeline::{
-        bloom::BloomSettings,
-        experimental::taa::{TemporalAntiAliasBundle, TemporalAntiAliasPlugin},
-    },
-    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
-    pbr::ScreenSpaceAmbientOcclusionBundle,
+    core_pipeline::{experimental::taa::TemporalAntiAliasPlugin, tonemapping::Tonemapping},
+    diagnostic::{EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
+    pbr::{DefaultOpaqueRendererMethod, ShadowFilteringMethod},
     prelude::*,
-    render::view::NoFrustumCulling,
-    window::{PresentMode, WindowResolution},
+    render::camera::Exposure,
+    utils::HashSet,
+    window::{PresentMode, WindowMode, WindowResolution},
     winit::{UpdateMode, WinitSettings},
 };
-use camera_controller::{CameraController, CameraControllerPlugin};
+use bookmarks::CameraBookmarksPlugin;
+use camera_controller::{
+    CameraController, CameraControllerPlugin, CameraPath, CameraPathPlayback, SmoothCuts,
+};
+use clay::{ClayMode, ClayPlugin};
+use compression::CompressionFormat;
+use culling_stats::CullingStatsPlugin;
+use debug_text::DebugTextPlugin;
+use debug_view::DebugViewPlugin;
+use emissive::EmissivePlugin;
+use environment_map::{EnvironmentMapPlugin, EnvironmentMaps};
+#[cfg(not(target_arch = "wasm32"))]
+use frame_pacing::FrameLimiter;
+use frame_pacing::{FixedTimestep, FramePacingPlugin};
+use gltf_lights::{GltfLight, GltfLights, GltfLightsPlugin};
+use golden::{GoldenComparison, GoldenPlugin};
+use gpu_timing::GpuTimingPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+use hdr_capture::{AutoHdrCapture, HdrCapturePlugin};
+#[cfg(not(target_arch = "wasm32"))]
+use hot_reload::HotReloadPlugin;
+use hud::{Hud, HudPlugin};
+use input_replay::{InputPlayback, InputRecorder, InputReplay, InputReplayPlugin};
+use inspector::InspectorPlugin;
+use irradiance_volume::{IrradianceVolumeData, IrradianceVolumePlugin};
+use light_editor::{LightEditorPlugin, RigLight};
+use light_probes::{LightProbeLayout, LightProbesPlugin};
+use lightmaps::{LightmapManifest, LightmapsPlugin};
+use loading::{LoadingScenes, SceneLoadingPlugin};
+use lod::{LodPlugin, MeshLods};
+use many_lights::{ManyLights, ManyLightsPlugin};
+use material_variants::{MaterialVariants, MaterialVariantsPlugin, DEFAULT_VARIANTS_PATH};
+use memory_diagnostics::MemoryDiagnosticsPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+use mip_cache::{print_cache_info, MipCache, PrintCacheInfo};
+use mip_filter::MipFilter;
 use mipmap_generator::{generate_mipmaps, MipmapGeneratorPlugin, MipmapGeneratorSettings};
+use path_editor::{PathEditor, PathEditorPlugin};
+use props::{Props, PropsPlugin};
+use quality_tier::{AutoQuality, QualityTier, QualityTierPlugin};
+use render_features::{RenderFeatures, RenderFeaturesPlugin};
+use render_scale::{RenderScale, RenderScalePlugin};
+use rig_gizmos::RigGizmosPlugin;
+use scene_preset::{PresetLight, ScenePreset};
+use scene_rules::SceneRule;
+use screenshot::{AutoScreenshot, ScreenshotPlugin};
+use settings::{UserSettings, UserSettingsPlugin};
+use shadows::{parse_shadow_filter, ShadowSettings, ShadowSettingsPlugin};
+use sky::{ProceduralSky, ProceduralSkyPlugin};
+use skybox::{SkyboxPlugin, Skyboxes};
+use split_screen::{SplitScreen, SplitScreenPlugin};
+use system_info::SystemInfoPlugin;
+use texture_streaming::{TextureStreaming, TextureStreamingPlugin};
+use time_of_day::{TimeOfDay, TimeOfDayPlugin};
+use tonemapping::{parse_tonemapping, TonemappingPlugin};
+#[cfg(not(target_arch = "wasm32"))]
+use validate::validate_assets;
+#[cfg(not(target_arch = "wasm32"))]
+use video::{FrameFormat, VideoRender, VideoRenderPlugin};
+use wind::{Wind, WindPlugin};
+use wireframe::MeshDebugPlugin;
 
-use crate::{
-    auto_instance::{AutoInstanceMaterialRecursive, AutoInstanceMeshRecursive},
-    convert::{change_gltf_to_use_ktx2, convert_images_to_ktx2},
+use crate::auto_instance::{AutoInstanceMaterialRecursive, AutoInstanceMeshRecursive};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::convert::{
+    parse_zstd_level, prepare_assets, revert_gltf, sponza_gltfs, ConvertQuality, ConvertSettings,
+    ConvertTarget, DEFAULT_ZSTD_LEVEL,
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+const MIP_CACHE_DIR: &str = "mipmap_cache";
+#[cfg(not(target_arch = "wasm32"))]
+const MIP_CACHE_MAX_BYTES: u64 = 8 * 1024 * 1024 * 1024;
+
 #[derive(FromArgs, Resource, Clone)]
 /// Config
 pub struct Args {
-    /// convert gltf to use ktx
+    /// convert gltf to use ktx, same as running the prepare subcommand before starting
+    #[cfg(not(target_arch = "wasm32"))]
     #[argh(switch)]
     convert: bool,
 
+    /// number of images to convert at once with --convert, defaults to the number of cores
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(option)]
+    convert_threads: Option<usize>,
+
+    /// quality preset for --convert: fast (BC1), default, or high (slow BC7 with Kaiser filtered mips), also checked by --validate
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(option)]
+    convert_quality: Option<ConvertQuality>,
+
+    /// zstd supercompression level (1-22) for KTX2 files written by --convert, 0 for the encoder's default
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(option, default = "DEFAULT_ZSTD_LEVEL", from_str_fn(parse_zstd_level))]
+    ktx2_zstd: u32,
+
+    /// fetch the scene and environment maps listed in assets/downloads.ron, needs the download feature
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(switch)]
+    download_assets: bool,
+
+    /// restore the original glTF files that reference the PNG textures, undoing --convert
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(switch)]
+    revert: bool,
+
+    /// check the KTX2 textures referenced by the glTF files and exit, nonzero if any are broken
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(switch)]
+    validate: bool,
+
     /// enable auto instancing for meshes/materials
     #[argh(switch)]
     instance: bool,
 
-    /// disable bloom, AO, AA, shadows
+    /// disable bloom, AO, AA, shadows, and the camera controller
     #[argh(switch)]
     minimal: bool,
 
+    /// disable bloom
+    #[argh(switch)]
+    no_bloom: bool,
+
+    /// replace all materials with grey clay to judge the lighting on its own, toggle with F9
+    #[argh(switch)]
+    clay: bool,
+
+    /// keep normal maps on the clay materials
+    #[argh(switch)]
+    clay_normal_maps: bool,
+
+    /// disable SSAO
+    #[argh(switch)]
+    no_ssao: bool,
+
+    /// disable TAA
+    #[argh(switch)]
+    no_taa: bool,
+
+    /// disable shadows
+    #[argh(switch)]
+    no_shadows: bool,
+
     /// whether to disable frustum culling.
     #[argh(switch)]
     no_frustum_culling: bool,
+
+    /// shade opaque materials in a deferred pass instead of the forward pass. Rules out MSAA, and
+    /// isn't available on the web
+    #[argh(switch)]
+    deferred: bool,
+
+    /// window width, defaults to the last run's or 1920
+    #[argh(option)]
+    width: Option<f32>,
+
+    /// window height, defaults to the last run's or 1080
+    #[argh(option)]
+    height: Option<f32>,
+
+    /// use a borderless fullscreen window
+    #[argh(switch)]
+    fullscreen: bool,
+
+    /// show the scene without TAA, SSAO, or bloom in the right half of the window, next to the
+    /// selected features in the left half
+    #[argh(switch)]
+    split_screen: bool,
+
+    /// cap the frame rate, so laptops don't throttle during long benchmarks. Without --vsync this
+    /// also presents without tearing where supported
+    #[argh(option)]
+    max_fps: Option<f32>,
+
+    /// enable vsync
+    #[argh(switch)]
+    vsync: bool,
+
+    /// play back a camera path recorded with the R key
+    #[argh(option)]
+    camera_path: Option<String>,
+
+    /// open the camera path editor on this file, F10 shows and hides it
+    #[argh(option)]
+    edit_camera_path: Option<String>,
+
+    /// render the --camera-path frame by frame to numbered images in this directory, or to a video through ffmpeg if it ends in .mp4, .mkv, .mov, or .webm, then exit
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(option)]
+    render_video: Option<String>,
+
+    /// frames per second of --render-video
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(option, default = "60.0")]
+    fps: f32,
+
+    /// seconds of --render-video, defaults to the length of the camera path
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(option)]
+    duration: Option<f32>,
+
+    /// image format of the --render-video frames: png, or exr for linear float images
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(option, default = "FrameFormat::Png")]
+    frame_format: FrameFormat,
+
+    /// record keyboard and mouse input from when the scene is ready to this file, saved on exit
+    #[argh(option)]
+    record_input: Option<String>,
+
+    /// play back input recorded with --record-input, advancing time by as much as each recorded frame did
+    #[argh(option)]
+    replay: Option<String>,
+
+    /// render each camera position, compare it against the PNGs in this directory, and exit with
+    /// an error if any differ. Missing images are saved as the new golden images
+    #[argh(option)]
+    compare_golden: Option<String>,
+
+    /// lowest SSIM (0-1) that passes --compare-golden
+    #[argh(option, default = "0.98")]
+    golden_min_ssim: f32,
+
+    /// start at one of the scene's camera positions, starting from 1
+    #[argh(option)]
+    camera_position: Option<usize>,
+
+    /// save a screenshot to this path once the scene has loaded
+    #[argh(option)]
+    screenshot: Option<String>,
+
+    /// save the render target before tonemapping to this OpenEXR file once the scene has loaded, F11 saves one at any time
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(option)]
+    hdr_capture: Option<String>,
+
+    /// capture each of the scene's camera positions to an OpenEXR file in the --hdr-capture directory
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(switch)]
+    hdr_capture_positions: bool,
+
+    /// exit after the --screenshot or --hdr-capture has been saved
+    #[argh(switch)]
+    exit_after_capture: bool,
+
+    /// forget the window size, features, camera, and render scale saved by the last run
+    #[argh(switch)]
+    reset_settings: bool,
+
+    /// scene to load: sponza, bistro, san-miguel, or a glTF path in the assets folder
+    #[argh(option, default = "String::from(\"sponza\")")]
+    scene: String,
+
+    /// load the scene, light rig, and camera positions from a RON preset file instead of --scene
+    #[argh(option)]
+    preset: Option<String>,
+
+    /// write the current scene preset to a RON file
+    #[argh(option)]
+    save_preset: Option<String>,
+
+    /// compress textures after generating mipmaps: bc1, bc7, astc, or astc8x8
+    #[argh(option)]
+    format: Option<CompressionFormat>,
+
+    /// downscale textures so neither side is larger than this, to save memory
+    #[argh(option)]
+    max_texture_size: Option<u32>,
+
+    /// low, medium, or high. Sets the texture size, compression, shadow map size, SSAO, and TAA, picked from the GPU and memory by default. High is the usual settings
+    #[argh(option)]
+    quality_tier: Option<QualityTier>,
+
+    /// run the benchmark once the scene is loaded, then exit
+    #[argh(switch)]
+    bench: bool,
+
+    /// seconds to render at each benchmark position before recording frame times
+    #[argh(option, default = "1.0")]
+    bench_warmup_secs: f32,
+
+    /// seconds to record frame times at each benchmark position
+    #[argh(option, default = "2.0")]
+    bench_step_secs: f32,
+
+    /// sweep through the benchmark positions this many times, discarding the first run, and report the spread of the others
+    #[argh(option, default = "1")]
+    bench_runs: usize,
+
+    /// write the results of --bench or --bench-matrix to this JSON file
+    #[argh(option)]
+    bench_output: Option<String>,
+
+    /// compare the results of --bench or --bench-matrix with this JSON file from --bench-output, and exit with an error if any got slower by more than --bench-threshold
+    #[argh(option)]
+    bench_baseline: Option<String>,
+
+    /// percentage a frame time can get slower by compared to --bench-baseline before it counts as a regression
+    #[argh(option, default = "5.0")]
+    bench_threshold: f32,
+
+    /// write every benchmark frame's cpu and gpu time to this CSV file
+    #[argh(option)]
+    bench_trace: Option<String>,
+
+    /// benchmark every on/off combination of these comma separated features and exit, like
+    /// ssao,taa,shadows. Features: ssao, taa, bloom, shadows, msaa, frustum_culling
+    #[argh(option)]
+    bench_matrix: Option<String>,
+
+    /// comma separated render scales to run each --bench-matrix combination at, like 0.5,1.0
+    #[argh(option)]
+    bench_matrix_scales: Option<String>,
+
+    /// render the scene at this fraction of the window resolution (0.5-2.0), adjust with Page
+    /// Up and Page Down. Defaults to the last run's or 1.0
+    #[argh(option)]
+    render_scale: Option<f32>,
+
+    /// vertical field of view of the camera in degrees, adjust with Z and X
+    #[argh(option, default = "60.0")]
+    fov_degrees: f32,
+
+    /// distance to the camera's near clipping plane
+    #[argh(option, default = "0.1")]
+    near: f32,
+
+    /// distance to the camera's far clipping plane
+    #[argh(option, default = "1000.0")]
+    far: f32,
+
+    /// flying speed of the camera in meters per second, 5 by default. Adjust with the scroll wheel
+    #[argh(option)]
+    move_speed: Option<f32>,
+
+    /// speed multiplier while Shift is held, 3 by default
+    #[argh(option)]
+    run_multiplier: Option<f32>,
+
+    /// speed multiplier while Alt is held, 0.2 by default
+    #[argh(option)]
+    slow_multiplier: Option<f32>,
+
+    /// look speed of the mouse and gamepad, 0.25 by default
+    #[argh(option)]
+    mouse_sensitivity: Option<f32>,
+
+    /// invert the mouse's vertical look
+    #[argh(switch)]
+    invert_y: bool,
+
+    /// seconds to smooth the mouse movement over, like 0.05
+    #[argh(option)]
+    mouse_smoothing: Option<f32>,
+
+    /// extra mouse sensitivity per 1000 pixels per second of movement, like 0.5
+    #[argh(option)]
+    mouse_acceleration: Option<f32>,
+
+    /// capture the cursor when clicking in the window so the mouse looks around without holding the button, Escape releases it
+    #[argh(switch)]
+    grab_cursor: bool,
+
+    /// start the camera in orbit mode, turning at this many radians per second
+    #[argh(option)]
+    auto_rotate: Option<f32>,
+
+    /// advance time by 1/N seconds every frame instead of by the clock, so camera paths, benchmark transitions, and the time of day render the same frames on every machine
+    #[argh(option)]
+    fixed_timestep: Option<f32>,
+
+    /// ease the camera between bookmarks and benchmark positions over this many seconds
+    #[argh(option)]
+    smooth_cuts: Option<f32>,
+
+    /// environment map to start with, a name from assets/environment_maps/environment_maps.ron
+    /// or a path prefix like environment_maps/pisa
+    #[argh(option)]
+    env_map: Option<String>,
+
+    /// spawn reflection probes from a RON layout, like assets/light_probes/sponza.ron
+    #[argh(option)]
+    light_probes: Option<String>,
+
+    /// filter used to generate mipmaps: box, triangle, kaiser, or lanczos3
+    #[argh(option, default = "MipFilter::Kaiser")]
+    mip_filter: MipFilter,
+
+    /// generate mipmaps on the GPU instead of the CPU
+    #[argh(switch)]
+    gpu_mipmaps: bool,
+
+    /// raise roughness in lower mips where the normal map is bumpy, to reduce specular aliasing
+    #[argh(switch)]
+    toksvig: bool,
+
+    /// delete the mipmap cache before starting
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(switch)]
+    clear_cache: bool,
+
+    /// directory of the mipmap cache, can be shared between checkouts, e.g. ~/.cache/bevy_sponza_scene
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(option, default = "MIP_CACHE_DIR.to_string()")]
+    cache_dir: String,
+
+    /// bloom intensity, adjust with , and .
+    #[argh(option, default = "0.05")]
+    bloom_intensity: f32,
+
+    /// brightness above which pixels bloom, adjust with shift + , and .
+    #[argh(option, default = "0.0")]
+    bloom_threshold: f32,
+
+    /// number of shadow cascades for the sun, 1-4. Cycle with F6
+    #[argh(option)]
+    shadow_cascades: Option<usize>,
+
+    /// far bound of the first, most detailed, shadow cascade
+    #[argh(option)]
+    shadow_first_cascade_bound: Option<f32>,
+
+    /// distance past which there are no sun shadows
+    #[argh(option)]
+    shadow_distance: Option<f32>,
+
+    /// shadow map resolution, like 2048 or 4096. Cycle with F5
+    #[argh(option)]
+    shadow_map_size: Option<usize>,
+
+    /// shadow filtering: hardware2x2, castano13, or jimenez14. Cycle with F7
+    #[argh(option, from_str_fn(parse_shadow_filter))]
+    shadow_filter: Option<ShadowFilteringMethod>,
+
+    /// tonemapper: tony_mc_mapface, agx, reinhard, or none. Cycle with T
+    #[argh(
+        option,
+        default = "Tonemapping::TonyMcMapface",
+        from_str_fn(parse_tonemapping)
+    )]
+    tonemapping: Tonemapping,
+
+    /// camera exposure in EV100, higher is darker. Adjust with - and =
+    #[argh(option)]
+    ev100: Option<f32>,
+
+    /// animate the sun from this hour of the day (0-24), adjust with [ and ]
+    #[argh(option)]
+    time_of_day: Option<f32>,
+
+    /// scatter this many dynamic spheres and cubes through the atrium
+    #[argh(option)]
+    props: Option<usize>,
+
+    /// let the --props fall and collide with the scene. Needs the physics feature
+    #[argh(switch)]
+    props_fall: bool,
+
+    /// scatter this many small shadowless point lights through the atrium and corridors, to
+    /// stress the clustered lighting
+    #[argh(option)]
+    many_lights: Option<usize>,
+
+    /// move the --many-lights in circles
+    #[argh(switch)]
+    many_lights_animate: bool,
+
+    /// RON file with named material variants, cycled with J. Defaults to material_variants.ron if
+    /// it exists, otherwise a wet floor and metallic trim for Sponza
+    #[argh(option)]
+    material_variants: Option<String>,
+
+    /// start with the material variant of this name
+    #[argh(option)]
+    material_variant: Option<String>,
+
+    /// show the flat clear color instead of the environment map behind the scene
+    #[argh(switch)]
+    no_skybox: bool,
+
+    /// replace the environment map with a generated sky that follows the sun, see also --time-of-day
+    #[argh(switch)]
+    procedural_sky: bool,
+
+    /// sway the curtains in the wind, to have something moving for judging TAA
+    #[argh(switch)]
+    wind: bool,
+
+    /// how far the curtains sway with --wind
+    #[argh(option, default = "1.0")]
+    wind_strength: f32,
+
+    /// start in walk mode, colliding with the scene at human height. Needs the physics feature
+    #[argh(switch)]
+    walk: bool,
+
+    /// keep the flying camera from passing through walls and floors, toggle with C. Needs the physics feature
+    #[argh(switch)]
+    camera_collision: bool,
+
+    /// keep the lights that came with the glTF instead of the preset's spot and point lights
+    #[argh(switch)]
+    use_gltf_lights: bool,
+
+    /// multiplier for glTF light intensities with --use-gltf-lights
+    #[argh(option, default = "1.0")]
+    gltf_light_scale: f32,
+
+    /// how many of the brightest glTF lights cast shadows with --use-gltf-lights
+    #[argh(option, default = "4")]
+    gltf_light_shadows: usize,
+
+    /// load a baked irradiance volume, replacing the preset's spot and point lights
+    #[argh(option)]
+    irradiance_volume: Option<String>,
+
+    /// write a chrome://tracing profile of the run to this file. Needs the trace_chrome feature
+    #[argh(option)]
+    trace_chrome: Option<String>,
+
+    /// stream texture mips in by distance to the camera, keeping the GPU textures within this many
+    /// megabytes
+    #[argh(option)]
+    texture_budget: Option<u64>,
+
+    /// print the size of the mipmap cache and how many textures it served once the scene loads
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(switch)]
+    cache_info: bool,
+
+    /// keep the full size textures without mips on screen while mipmaps are generated, instead of
+    /// small placeholders
+    #[argh(switch)]
+    no_mip_placeholders: bool,
+
+    /// start with the statistics HUD shown, toggle it with F1
+    #[argh(switch)]
+    hud: bool,
+
+    /// generate simplified LODs for the scene's meshes and switch them by camera distance
+    #[argh(switch)]
+    lods: bool,
+
+    /// apply the lightmaps listed in lightmaps.ron in this directory under assets, toggle with K
+    #[argh(option)]
+    lightmaps: Option<String>,
+
+    /// bake an irradiance volume for the scene and write it to this file. Needs the physics feature
+    #[argh(option)]
+    bake_gi: Option<String>,
+
+    /// distance between probes in meters with --bake-gi
+    #[argh(option, default = "1.0")]
+    bake_gi_spacing: f32,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[argh(subcommand)]
+    command: Option<Command>,
 }
 
-pub fn main() {
-    let args: Args = argh::from_env();
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(FromArgs, Clone)]
+#[argh(subcommand)]
+enum Command {
+    Prepare(PrepareArgs),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(FromArgs, Clone)]
+/// Convert the scene textures to KTX2 and update the glTF files, without starting the app
+#[argh(subcommand, name = "prepare")]
+struct PrepareArgs {
+    /// glTF or glb files to convert, relative to --input, defaults to the Sponza scenes
+    #[argh(positional)]
+    gltf: Vec<PathBuf>,
+
+    /// directory the glTF files are in
+    #[argh(option, default = "PathBuf::from(\"assets\")")]
+    input: PathBuf,
+
+    /// directory to write the converted scene to, defaults to converting in place
+    #[argh(option)]
+    output: Option<PathBuf>,
+
+    /// texture format: bc1, bc7, astc, astc8x8, or uastc (Basis Universal, transcoded when loaded)
+    #[argh(option, default = "ConvertTarget::Gpu(CompressionFormat::Bc7)")]
+    format: ConvertTarget,
+
+    /// quality preset: fast (BC1), default, or high (slow encoding with Kaiser filtered mips)
+    #[argh(option, default = "ConvertQuality::Default")]
+    quality: ConvertQuality,
+
+    /// zstd supercompression level (1-22), 0 for the encoder's default
+    #[argh(option, default = "DEFAULT_ZSTD_LEVEL", from_str_fn(parse_zstd_level))]
+    zstd: u32,
+
+    /// number of images to convert at once, defaults to the number of cores
+    #[argh(option)]
+    threads: Option<usize>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PrepareArgs {
+    fn settings(&self) -> ConvertSettings {
+        let mut settings = ConvertSettings {
+            input_dir: self.input.clone(),
+            output_dir: self.output.clone().unwrap_or_else(|| self.input.clone()),
+            gltfs: if self.gltf.is_empty() {
+                sponza_gltfs()
+            } else {
+                self.gltf.clone()
+            },
+            format: self.format,
+            quality: self.quality,
+            zstd_level: self.zstd,
+            ..default()
+        };
+        if let Some(threads) = self.threads {
+            settings.threads = threads.max(1);
+        }
+        settings
+    }
+}
+
+/// Runs the commands that convert, check, or fetch the assets, and sets up the mipmap cache.
+/// Returns `None` if a command ran that doesn't start the app afterwards.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_asset_commands(args: &Args) -> Option<MipCache> {
+    if let Some(Command::Prepare(prepare)) = &args.command {
+        if let Err(e) = prepare_assets(&prepare.settings()) {
+            println!("Failed to prepare assets: {e:#}");
+            std::process::exit(1);
+        }
+        return None;
+    }
+
+    if args.validate {
+        let valid = validate_assets(
+            "assets".as_ref(),
+            &sponza_gltfs(),
+            args.format,
+            args.convert_quality,
+        );
+        std::process::exit(if valid { 0 } else { 1 });
+    }
+
+    if args.download_assets {
+        #[cfg(feature = "download")]
+        if let Err(e) = download::download_assets(download::DOWNLOADS_MANIFEST.as_ref()) {
+            println!("Failed to download assets: {e:#}");
+            std::process::exit(1);
+        }
+        #[cfg(not(feature = "download"))]
+        {
+            println!("--download-assets needs the download feature: cargo run --features download -- --download-assets");
+            std::process::exit(1);
+        }
+    }
+
+    if args.revert {
+        if let Err(e) = revert_gltf("assets".as_ref(), &sponza_gltfs()) {
+            println!("Failed to revert assets: {e:#}");
+            std::process::exit(1);
+        }
+    }
 
     if args.convert {
         println!("This will take a few minutes");
-        convert_images_to_ktx2();
-        change_gltf_to_use_ktx2();
+        let mut settings = ConvertSettings::default();
+        if let Some(threads) = args.convert_threads {
+            settings.threads = threads.max(1);
+        }
+        settings.quality = args.convert_quality.unwrap_or_default();
+        settings.zstd_level = args.ktx2_zstd;
+        if let Err(e) = prepare_assets(&settings) {
+            println!("Failed to convert assets: {e:#}");
+            std::process::exit(1);
+        }
+    }
+
+    // The shell leaves a quoted ~ alone
+    let cache_dir = match (args.cache_dir.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(&args.cache_dir),
+    };
+    if args.clear_cache {
+        MipCache::clear(&cache_dir);
+    }
+    let mip_cache = MipCache::new(cache_dir, MIP_CACHE_MAX_BYTES);
+    mip_cache.prune();
+    Some(mip_cache)
+}
+
+/// Web builds have no command line, the arguments are read from the page's query string instead,
+/// `?bench&render-scale=0.5` runs with `--bench --render-scale 0.5`.
+#[cfg(target_arch = "wasm32")]
+fn args_from_url() -> Args {
+    let query = web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .unwrap_or_default();
+    let mut strings = Vec::new();
+    for pair in query.trim_start_matches('?').split('&') {
+        match pair.split_once('=') {
+            Some((name, value)) => strings.extend([format!("--{name}"), value.to_string()]),
+            None if !pair.is_empty() => strings.push(format!("--{pair}")),
+            None => (),
+        }
+    }
+    let strings: Vec<&str> = strings.iter().map(String::as_str).collect();
+    Args::from_args(&["sponza"], &strings)
+        .unwrap_or_else(|early_exit| panic!("{}", early_exit.output))
+}
+
+pub fn main() {
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut args: Args = argh::from_env();
+    #[cfg(target_arch = "wasm32")]
+    let args = args_from_url();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let Some(mip_cache) = run_asset_commands(&args) else {
+        return;
+    };
+
+    let preset = match &args.preset {
+        Some(path) => ScenePreset::load(path)
+            .unwrap_or_else(|e| panic!("Failed to load scene preset {path}: {e}")),
+        None => ScenePreset::from_arg(&args.scene),
+    };
+    if let Some(path) = &args.save_preset {
+        match preset.save(path) {
+            Ok(_) => println!("Saved scene preset to {path}"),
+            Err(e) => println!("Failed to save scene preset to {path}: {e}"),
+        }
+    }
+
+    let mut benchmark = Benchmark::new(args.bench_warmup_secs, args.bench_step_secs);
+    benchmark.trace_path = args.bench_trace.as_ref().map(PathBuf::from);
+    benchmark.runs = args.bench_runs.max(1);
+    benchmark.output_path = args.bench_output.as_ref().map(PathBuf::from);
+
+    let mut shadows = ShadowSettings::default();
+    shadows.cascades = args.shadow_cascades.unwrap_or(shadows.cascades);
+    shadows.first_cascade_far_bound = args
+        .shadow_first_cascade_bound
+        .unwrap_or(shadows.first_cascade_far_bound);
+    shadows.maximum_distance = args.shadow_distance.unwrap_or(shadows.maximum_distance);
+    shadows.map_size = args.shadow_map_size.unwrap_or(shadows.map_size);
+    shadows.filtering = args.shadow_filter.unwrap_or(shadows.filtering);
+
+    if let Some(path) = &args.trace_chrome {
+        // Read by Bevy's LogPlugin when it sets up the chrome tracing layer
+        #[cfg(feature = "trace_chrome")]
+        std::env::set_var("TRACE_CHROME", path);
+        #[cfg(not(feature = "trace_chrome"))]
+        println!("--trace-chrome needs the trace_chrome feature: cargo run --features trace_chrome -- --trace-chrome {path}");
     }
 
+    // Browsers have nowhere to keep the settings file
+    #[cfg(not(target_arch = "wasm32"))]
+    let settings = UserSettings::for_args(&args);
+    #[cfg(target_arch = "wasm32")]
+    let settings: Option<UserSettings> = None;
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(settings) = &settings {
+        settings.restore_args(&mut args);
+    }
+    let mut features = RenderFeatures {
+        ssao: !(args.minimal || args.no_ssao),
+        taa: !(args.minimal || args.no_taa),
+        bloom: !(args.minimal || args.no_bloom),
+        shadows: !(args.minimal || args.no_shadows),
+        msaa: false,
+        frustum_culling: !args.no_frustum_culling,
+        bloom_intensity: args.bloom_intensity,
+        bloom_threshold: args.bloom_threshold,
+        deferred: args.deferred,
+        ..default()
+    };
+    if let Some(settings) = &settings {
+        settings.restore_features(&mut features);
+    }
+
+    let window_resolution =
+        WindowResolution::new(args.width.unwrap_or(1920.0), args.height.unwrap_or(1080.0))
+            .with_scale_factor_override(1.0);
+
     let mut app = App::new();
 
+    if let Some(settings) = settings {
+        app.insert_resource(settings);
+    }
     app.insert_resource(args.clone())
+        .insert_resource(preset)
+        .insert_resource(EnvironmentMaps::load(args.env_map.as_deref()))
         .insert_resource(Msaa::Off)
+        .insert_resource(features)
         .insert_resource(ClearColor(Color::rgb(1.75, 1.9, 1.99)))
         .insert_resource(AmbientLight {
             color: Color::rgb(1.0, 1.0, 1.0),
@@ -71,36 +898,346 @@ pub fn main() {
         })
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
-                present_mode: PresentMode::Immediate,
-                resolution: WindowResolution::new(1920.0, 1080.0).with_scale_factor_override(1.0),
+                // Browsers always present in step with the display
+                present_mode: if args.vsync || cfg!(target_arch = "wasm32") {
+                    PresentMode::AutoVsync
+                } else if args.max_fps.is_some() {
+                    PresentMode::AutoNoVsync
+                } else {
+                    PresentMode::Immediate
+                },
+                mode: if args.fullscreen {
+                    WindowMode::BorderlessFullscreen
+                } else {
+                    WindowMode::Windowed
+                },
+                resolution: window_resolution,
+                // Fill the page's canvas, see index.html
+                #[cfg(target_arch = "wasm32")]
+                canvas: Some("#bevy".to_string()),
+                #[cfg(target_arch = "wasm32")]
+                fit_canvas_to_parent: true,
                 ..default()
             }),
             ..default()
         }))
-        .add_plugins((LogDiagnosticsPlugin::default(), FrameTimeDiagnosticsPlugin))
+        .add_plugins((
+            FrameTimeDiagnosticsPlugin,
+            EntityCountDiagnosticsPlugin,
+            MemoryDiagnosticsPlugin,
+            DebugTextPlugin,
+            HudPlugin,
+            FramePacingPlugin,
+        ))
+        .insert_resource(benchmark)
+        .insert_resource(Hud { visible: args.hud })
+        .insert_resource(shadows)
+        .insert_resource(AutoQuality {
+            tier: args.quality_tier,
+            keep_texture_size: args.max_texture_size.is_some(),
+            keep_compression: args.format.is_some(),
+            keep_shadow_map_size: args.shadow_map_size.is_some(),
+        })
+        .insert_resource(RenderScale::new(args.render_scale.unwrap_or(1.0)))
+        .insert_resource(ClayMode {
+            enabled: args.clay,
+            keep_normal_maps: args.clay_normal_maps,
+            ..default()
+        })
         // Generating mipmaps takes a minute
         .insert_resource(MipmapGeneratorSettings {
             anisotropic_filtering: 16,
+            filter_type: args.mip_filter,
+            #[cfg(not(target_arch = "wasm32"))]
+            cache: Some(mip_cache),
+            compression: args.format,
+            max_texture_size: args.max_texture_size,
+            gpu: args.gpu_mipmaps,
+            toksvig: args.toksvig,
+            placeholder_size: if args.no_mip_placeholders {
+                None
+            } else {
+                Some(64)
+            },
             ..default()
         })
         .add_plugins((
             MipmapGeneratorPlugin,
             CameraControllerPlugin,
             TemporalAntiAliasPlugin,
+            GpuTimingPlugin,
+            BenchmarkPlugin,
+            SceneLoadingPlugin,
+            ScreenshotPlugin,
+            RenderFeaturesPlugin,
+            TimeOfDayPlugin,
+            CameraBookmarksPlugin,
+            EnvironmentMapPlugin,
+            LightProbesPlugin,
+            TonemappingPlugin,
+            ShadowSettingsPlugin,
+            RenderScalePlugin,
+        ))
+        .add_plugins((
+            InputReplayPlugin,
+            GoldenPlugin,
+            DebugViewPlugin,
+            ClayPlugin,
+            RigGizmosPlugin,
+            LightEditorPlugin,
+            GltfLightsPlugin,
+            EmissivePlugin,
+            PropsPlugin,
+            WindPlugin,
+            SkyboxPlugin,
+            ProceduralSkyPlugin,
+            IrradianceVolumePlugin,
+            LightmapsPlugin,
+            LodPlugin,
+        ))
+        .add_plugins((
+            CullingStatsPlugin,
+            TextureStreamingPlugin,
+            QualityTierPlugin,
+            SystemInfoPlugin,
+            PathEditorPlugin,
+            UserSettingsPlugin,
+            SplitScreenPlugin,
+            ManyLightsPlugin,
+            MeshDebugPlugin,
+            InspectorPlugin,
+            MaterialVariantsPlugin,
         ))
         // Mipmap generation be skipped if ktx2 is used
         .add_systems(
             Update,
-            (
-                generate_mipmaps::<StandardMaterial>,
-                proc_scene,
-                input,
-                benchmark,
-            ),
+            (generate_mipmaps::<StandardMaterial>, proc_scene, input),
         )
         .add_systems(Startup, setup);
-    if args.no_frustum_culling {
-        app.add_systems(Update, add_no_frustum_culling);
+    if let Some(path) = &args.camera_path {
+        let camera_path = CameraPath::load(path)
+            .unwrap_or_else(|e| panic!("Failed to load camera path {path}: {e}"));
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(output) = &args.render_video {
+            let video = VideoRender::new(
+                output,
+                camera_path,
+                args.fps,
+                args.duration,
+                args.frame_format,
+            )
+            .unwrap_or_else(|e| panic!("Failed to start rendering to {output}: {e:#}"));
+            app.insert_resource(video.fixed_timestep())
+                .insert_resource(video);
+        } else {
+            app.insert_resource(CameraPathPlayback::new(camera_path));
+        }
+        #[cfg(target_arch = "wasm32")]
+        app.insert_resource(CameraPathPlayback::new(camera_path));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if args.render_video.is_some() && args.camera_path.is_none() {
+        println!("--render-video needs a --camera-path to play, record one with the R key");
+        return;
+    }
+    if let Some(path) = &args.edit_camera_path {
+        app.insert_resource(PathEditor::load(path));
+    }
+    if args.split_screen {
+        app.insert_resource(SplitScreen);
+    }
+    // Materials left on the default method follow this, blended ones are always forward
+    if args.deferred {
+        app.insert_resource(DefaultOpaqueRendererMethod::deferred());
+    }
+    if let Some(path) = &args.bench_baseline {
+        let results = BenchmarkResults::load(path.as_ref())
+            .unwrap_or_else(|e| panic!("Failed to load benchmark baseline {path}: {e}"));
+        app.insert_resource(BenchmarkBaseline {
+            results,
+            threshold_percent: args.bench_threshold,
+        });
+    }
+    if let Some(dir) = &args.compare_golden {
+        app.insert_resource(GoldenComparison::new(dir, args.golden_min_ssim));
+    }
+    if let Some(path) = &args.record_input {
+        app.insert_resource(InputRecorder::new(path));
+    }
+    if let Some(path) = &args.replay {
+        let replay = InputReplay::load(path)
+            .unwrap_or_else(|e| panic!("Failed to load input replay {path}: {e}"));
+        app.insert_resource(InputPlayback::new(replay));
+    }
+    if let Some(path) = &args.screenshot {
+        app.insert_resource(AutoScreenshot {
+            path: path.into(),
+            exit_after_capture: args.exit_after_capture,
+        });
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = &args.hdr_capture {
+        app.insert_resource(AutoHdrCapture::new(
+            path,
+            args.hdr_capture_positions,
+            args.exit_after_capture,
+        ));
+    }
+    if let Some(path) = &args.light_probes {
+        match LightProbeLayout::load(path) {
+            Ok(layout) => {
+                app.insert_resource(layout);
+            }
+            Err(e) => println!("Failed to load light probe layout {path}: {e}"),
+        }
+    }
+    if let Some(duration) = args.smooth_cuts {
+        app.insert_resource(SmoothCuts { duration });
+    }
+    if let Some(features) = &args.bench_matrix {
+        let matrix = BenchmarkMatrix::from_arg(features).and_then(|matrix| {
+            match &args.bench_matrix_scales {
+                Some(scales) => matrix.with_scales(scales),
+                None => Ok(matrix),
+            }
+        });
+        match matrix {
+            Ok(matrix) => {
+                app.insert_resource(matrix);
+            }
+            // It's for scripts, which shouldn't get an interactive run instead
+            Err(e) => {
+                println!("{e}");
+                std::process::exit(1);
+            }
+        }
+    } else if args.bench {
+        app.init_resource::<AutoBenchmark>();
+    }
+    // The limiter sleeps, which the browser's main thread can't
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(max_fps) = args.max_fps {
+        app.insert_resource(FrameLimiter::new(max_fps));
+    }
+    if let Some(fps) = args.fixed_timestep {
+        app.insert_resource(FixedTimestep::new(fps));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    app.add_plugins((VideoRenderPlugin, HdrCapturePlugin, HotReloadPlugin));
+    if let Some(hour) = args.time_of_day {
+        app.insert_resource(TimeOfDay::new(hour));
+    }
+    if args.walk {
+        #[cfg(feature = "physics")]
+        app.insert_resource(walk::WalkMode::enabled());
+        #[cfg(not(feature = "physics"))]
+        println!("--walk needs the physics feature: cargo run --features physics -- --walk");
+    }
+    if args.camera_collision {
+        #[cfg(feature = "physics")]
+        app.insert_resource(walk::CameraCollision::enabled());
+        #[cfg(not(feature = "physics"))]
+        println!(
+            "--camera-collision needs the physics feature: cargo run --features physics -- --camera-collision"
+        );
+    }
+    #[cfg(feature = "physics")]
+    app.add_plugins((walk::WalkPlugin, gi_bake::GiBakePlugin));
+    if let Some(path) = &args.irradiance_volume {
+        match IrradianceVolumeData::load(path) {
+            Ok(volume) => {
+                app.insert_resource(volume);
+            }
+            Err(e) => println!("Failed to load irradiance volume {path}: {e}"),
+        }
+    }
+    if let Some(megabytes) = args.texture_budget {
+        app.insert_resource(TextureStreaming::new(megabytes * 1_000_000));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        app.add_systems(Update, print_cache_info);
+        if args.cache_info {
+            app.insert_resource(PrintCacheInfo);
+        }
+    }
+    if args.lods {
+        app.init_resource::<MeshLods>();
+    }
+    if let Some(dir) = &args.lightmaps {
+        match LightmapManifest::load(dir) {
+            Ok(manifest) => {
+                app.insert_resource(manifest);
+            }
+            Err(e) => println!("Failed to load lightmaps from {dir}: {e}"),
+        }
+    }
+    if let Some(path) = &args.bake_gi {
+        if args.bake_gi_spacing <= 0.0 {
+            println!("--bake-gi-spacing must be positive");
+        } else {
+            #[cfg(feature = "physics")]
+            app.insert_resource(gi_bake::GiBake::new(path.into(), args.bake_gi_spacing));
+            #[cfg(not(feature = "physics"))]
+            println!(
+                "--bake-gi needs the physics feature: cargo run --features physics -- --bake-gi {path}"
+            );
+        }
+    }
+    // The time of day drives the clear color, which the skybox would hide
+    if args.no_skybox || args.time_of_day.is_some() || args.procedural_sky {
+        app.insert_resource(Skyboxes::disabled());
+    }
+    if args.procedural_sky {
+        app.insert_resource(ProceduralSky::default());
+    }
+    if args.wind {
+        app.insert_resource(Wind {
+            strength: args.wind_strength,
+        });
+    }
+    if let Some(count) = args.props {
+        if args.props_fall && cfg!(not(feature = "physics")) {
+            println!("--props-fall needs the physics feature, the props will bob in place instead");
+        }
+        app.insert_resource(Props {
+            count,
+            fall: args.props_fall && cfg!(feature = "physics"),
+        });
+    }
+    let material_variants = match &args.material_variants {
+        Some(path) => MaterialVariants::load(path)
+            .unwrap_or_else(|e| panic!("Failed to load material variants {path}: {e}")),
+        None if Path::new(DEFAULT_VARIANTS_PATH).exists() => {
+            MaterialVariants::load(DEFAULT_VARIANTS_PATH).unwrap_or_else(|e| {
+                println!("Failed to load {DEFAULT_VARIANTS_PATH}: {e}");
+                MaterialVariants::sponza()
+            })
+        }
+        None => MaterialVariants::sponza(),
+    };
+    let mut material_variants = MaterialVariants::new(material_variants);
+    if let Some(name) = &args.material_variant {
+        material_variants = material_variants
+            .with_active(name)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
+    app.insert_resource(material_variants);
+    if let Some(count) = args.many_lights {
+        app.insert_resource(ManyLights {
+            count,
+            animate: args.many_lights_animate,
+        });
+    }
+    if args.use_gltf_lights {
+        app.insert_resource(GltfLights {
+            scale: args.gltf_light_scale,
+            shadow_count: if args.minimal || args.no_shadows {
+                0
+            } else {
+                args.gltf_light_shadows
+            },
+        });
     }
     if args.instance {
         app.add_plugins((
@@ -118,182 +1255,97 @@ pub struct PostProcScene;
 #[derive(Component)]
 pub struct GrifLight;
 
-pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>, args: Res<Args>) {
-    println!("Loading models, generating mipmaps");
-
-    // sponza
-    commands.spawn((
-        SceneBundle {
-            scene: asset_server.load("main_sponza/NewSponza_Main_glTF_002.gltf#Scene0"),
-            ..default()
-        },
-        PostProcScene,
-        AutoInstanceMaterialRecursive,
-        AutoInstanceMeshRecursive,
-    ));
-
-    // curtains
-    commands.spawn((
-        SceneBundle {
-            scene: asset_server.load("PKG_A_Curtains/NewSponza_Curtains_glTF.gltf#Scene0"),
-            ..default()
-        },
-        PostProcScene,
-        AutoInstanceMaterialRecursive,
-        AutoInstanceMeshRecursive,
-    ));
-
-    // Sun
-    commands.spawn((
-        DirectionalLightBundle {
-            transform: Transform::from_rotation(Quat::from_euler(
-                EulerRot::XYZ,
-                PI * -0.43,
-                PI * -0.08,
-                0.0,
-            )),
-            directional_light: DirectionalLight {
-                color: Color::rgb(1.0, 1.0, 0.99),
-                illuminance: 300000.0 * 0.2,
-                shadows_enabled: !args.minimal,
-                shadow_depth_bias: 0.3,
-                shadow_normal_bias: 0.7,
-            },
-            ..default()
-        },
-        GrifLight,
-    ));
-
-    let point_spot_mult = 1000.0;
-
-    // Sun Refl
-    commands.spawn((
-        SpotLightBundle {
-            transform: Transform::from_xyz(2.0, -0.0, -2.0)
-                .looking_at(Vec3::new(0.0, 999.0, 0.0), Vec3::X),
-            spot_light: SpotLight {
-                range: 15.0,
-                intensity: 700.0 * point_spot_mult,
-                color: Color::rgb(1.0, 0.97, 0.85),
-                shadows_enabled: false,
-                inner_angle: PI * 0.4,
-                outer_angle: PI * 0.5,
-                ..default()
-            },
-            ..default()
-        },
-        GrifLight,
-    ));
-
-    // Sun refl 2nd bounce / misc bounces
-    commands.spawn((
-        SpotLightBundle {
-            transform: Transform::from_xyz(2.0, 5.5, -2.0)
-                .looking_at(Vec3::new(0.0, -999.0, 0.0), Vec3::X),
-            spot_light: SpotLight {
-                range: 13.0,
-                intensity: 500.0 * point_spot_mult,
-                color: Color::rgb(1.0, 0.97, 0.85),
-                shadows_enabled: false,
-                inner_angle: PI * 0.3,
-                outer_angle: PI * 0.4,
-                ..default()
-            },
-            ..default()
-        },
-        GrifLight,
-    ));
-
-    // sky
-    // seems to be making blocky artifacts. Even if it's the only light.
-    commands.spawn((
-        PointLightBundle {
-            point_light: PointLight {
-                color: Color::rgb(0.8, 0.9, 0.97),
-                intensity: 10000.0 * point_spot_mult,
-                shadows_enabled: false,
-                range: 24.0,
-                radius: 3.0,
-                ..default()
-            },
-            transform: Transform::from_xyz(0.0, 30.0, 0.0),
-            ..default()
-        },
-        GrifLight,
-    ));
-
-    // sky refl
-    commands.spawn((
-        SpotLightBundle {
-            transform: Transform::from_xyz(0.0, -2.0, 0.0)
-                .looking_at(Vec3::new(0.0, 999.0, 0.0), Vec3::X),
-            spot_light: SpotLight {
-                range: 11.0,
-                intensity: 40.0 * point_spot_mult,
-                color: Color::rgb(0.8, 0.9, 0.97),
-                shadows_enabled: false,
-                inner_angle: PI * 0.46,
-                outer_angle: PI * 0.49,
-                ..default()
-            },
-            ..default()
-        },
-        GrifLight,
-    ));
+pub fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    args: Res<Args>,
+    preset: Res<ScenePreset>,
+    env_maps: Res<EnvironmentMaps>,
+    mut loading_scenes: ResMut<LoadingScenes>,
+    settings: Option<Res<UserSettings>>,
+) {
+    for path in &preset.scenes {
+        let scene = asset_server.load(path.clone());
+        loading_scenes.push(scene.clone());
+        commands.spawn((
+            SceneBundle { scene, ..default() },
+            PostProcScene,
+            AutoInstanceMaterialRecursive,
+            AutoInstanceMeshRecursive,
+        ));
+    }
 
-    // sky low
-    commands.spawn((
-        SpotLightBundle {
-            transform: Transform::from_xyz(3.0, 2.0, 0.0)
-                .looking_at(Vec3::new(0.0, -999.0, 0.0), Vec3::X),
-            spot_light: SpotLight {
-                range: 12.0,
-                radius: 0.0,
-                intensity: 600.0 * point_spot_mult,
-                color: Color::rgb(0.8, 0.9, 0.95),
-                shadows_enabled: false,
-                inner_angle: PI * 0.34,
-                outer_angle: PI * 0.5,
-                ..default()
-            },
-            ..default()
-        },
-        GrifLight,
-    ));
+    spawn_rig(&mut commands, &preset, &args);
 
     // Camera
+    let scene_name = args.preset.as_ref().unwrap_or(&args.scene);
+    let camera_transform = args
+        .camera_position
+        .and_then(|n| preset.cameras.get(n.wrapping_sub(1)))
+        .map(|(_, transform)| *transform)
+        .or_else(|| settings.and_then(|settings| settings.camera(scene_name)))
+        .or(preset.cameras.first().map(|(_, transform)| *transform))
+        .unwrap_or_default();
     let mut cam = commands.spawn((
         Camera3dBundle {
             camera: Camera {
                 hdr: true,
                 ..default()
             },
-            transform: Transform::from_xyz(-10.5, 1.7, -1.0)
-                .looking_at(Vec3::new(0.0, 3.5, 0.0), Vec3::Y),
+            transform: camera_transform,
             projection: Projection::Perspective(PerspectiveProjection {
-                fov: std::f32::consts::PI / 3.0,
-                near: 0.1,
-                far: 1000.0,
+                fov: args.fov_degrees.to_radians(),
+                near: args.near,
+                far: args.far,
                 aspect_ratio: 1.0,
             }),
+            tonemapping: args.tonemapping,
+            exposure: args.ev100.map_or_else(default, |ev100| Exposure { ev100 }),
             ..default()
         },
-        EnvironmentMapLight {
-            diffuse_map: asset_server.load("environment_maps/pisa_diffuse_rgb9e5_zstd.ktx2"),
-            specular_map: asset_server.load("environment_maps/pisa_specular_rgb9e5_zstd.ktx2"),
-            intensity: 250.0,
-        },
+        env_maps.current().light(&asset_server),
     ));
+    // Bloom, TAA, and SSAO are added by apply_render_features
     if !args.minimal {
-        cam.insert((
-            BloomSettings {
-                intensity: 0.05,
-                ..default()
-            },
-            CameraController::default().print_controls(),
-            TemporalAntiAliasBundle::default(),
-        ))
-        .insert(ScreenSpaceAmbientOcclusionBundle::default());
+        let mut controller = CameraController {
+            invert_y: args.invert_y,
+            grab_cursor: args.grab_cursor,
+            ..default()
+        };
+        if let Some(sensitivity) = args.mouse_sensitivity {
+            controller.sensitivity = sensitivity;
+        }
+        if let Some(smoothing) = args.mouse_smoothing {
+            controller.mouse_smoothing = smoothing;
+        }
+        if let Some(acceleration) = args.mouse_acceleration {
+            controller.mouse_acceleration = acceleration;
+        }
+        if let Some(speed) = args.move_speed {
+            controller.walk_speed = speed;
+        }
+        if let Some(multiplier) = args.run_multiplier {
+            controller.run_multiplier = multiplier;
+        }
+        if let Some(multiplier) = args.slow_multiplier {
+            controller.slow_multiplier = multiplier;
+        }
+        if let Some(speed) = args.auto_rotate {
+            controller = controller.auto_rotate(speed);
+        }
+        cam.insert(controller.print_controls());
+    }
+}
+
+/// Spawns the preset's light rig, each light marked with its index in the preset.
+pub fn spawn_rig(commands: &mut Commands, preset: &ScenePreset, args: &Args) {
+    for (index, light) in preset.lights.iter().enumerate() {
+        // The glTF lights stand in for the fill lights, but usually don't include a sun
+        if args.use_gltf_lights && !matches!(light, PresetLight::Directional { .. }) {
+            continue;
+        }
+        let entity = light.spawn(commands, !(args.minimal || args.no_shadows));
+        commands.entity(entity).insert(RigLight(index));
     }
 }
 
@@ -310,7 +1362,7 @@ pub fn all_children<F: FnMut(Entity)>(
     }
 }
 
-#[allow(clippy::type_complexity)]
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub fn proc_scene(
     mut commands: Commands,
     flip_normals_query: Query<Entity, With<PostProcScene>>,
@@ -325,24 +1377,43 @@ pub fn proc_scene(
         ),
     >,
     cameras: Query<Entity, With<Camera>>,
+    preset: Res<ScenePreset>,
+    gltf_lights: Option<Res<GltfLights>>,
 ) {
+    let despawn_lights = preset.rules.contains(&SceneRule::DespawnLights);
+    let despawn_cameras = preset.rules.contains(&SceneRule::DespawnCameras);
     for entity in flip_normals_query.iter() {
+        let _span = info_span!("proc_scene").entered();
         if let Ok(children) = children_query.get(entity) {
+            // Materials are shared between meshes, only apply the rules once
+            let mut processed = HashSet::new();
             all_children(children, &children_query, &mut |entity| {
-                // Sponza needs flipped normals
                 if let Ok(mat_h) = has_std_mat.get(entity) {
-                    if let Some(mat) = materials.get_mut(mat_h) {
-                        mat.flip_normal_map_y = true;
+                    if processed.insert(mat_h.id()) {
+                        if let Some(mat) = materials.get_mut(mat_h) {
+                            for rule in &preset.rules {
+                                rule.apply_to_material(mat);
+                            }
+                        }
+                    }
+                    if let Some(mat) = materials.get(mat_h) {
+                        for rule in &preset.rules {
+                            if let Some(emissive) = rule.emissive_mesh(mat) {
+                                commands.entity(entity).insert(emissive);
+                            }
+                        }
                     }
                 }
 
-                // Sponza has a bunch of lights by default
                 if lights.get(entity).is_ok() {
-                    commands.entity(entity).despawn_recursive();
+                    if gltf_lights.is_some() {
+                        commands.entity(entity).insert(GltfLight);
+                    } else if despawn_lights {
+                        commands.entity(entity).despawn_recursive();
+                    }
                 }
 
-                // Sponza has a bunch of cameras by default
-                if cameras.get(entity).is_ok() {
+                if despawn_cameras && cameras.get(entity).is_ok() {
                     commands.entity(entity).despawn_recursive();
                 }
             });
@@ -351,90 +1422,11 @@ pub fn proc_scene(
     }
 }
 
-const CAM_POS_1: Transform = Transform {
-    translation: Vec3::new(-10.5, 1.7, -1.0),
-    rotation: Quat::from_array([-0.05678932, 0.7372272, -0.062454797, -0.670351]),
-    scale: Vec3::ONE,
-};
-
-const CAM_POS_2: Transform = Transform {
-    translation: Vec3::new(11.901049, 6.9060106, -4.561092),
-    rotation: Quat::from_array([-0.0066631963, -0.86618143, 0.011553433, -0.49955168]),
-    scale: Vec3::ONE,
-};
-
-const CAM_POS_3: Transform = Transform {
-    translation: Vec3::new(19.087378, 1.4913027, -2.7349238),
-    rotation: Quat::from_array([0.017711632, 0.7889913, -0.022769613, 0.61372685]),
-    scale: Vec3::ONE,
-};
-
-fn input(input: Res<ButtonInput<KeyCode>>, mut camera: Query<&mut Transform, With<Camera>>) {
-    let Ok(mut transform) = camera.get_single_mut() else {
+fn input(input: Res<ButtonInput<KeyCode>>, camera: Query<&Transform, With<Camera3d>>) {
+    let Ok(transform) = camera.get_single() else {
         return;
     };
     if input.just_pressed(KeyCode::KeyI) {
         info!("{:?}", transform);
     }
-    if input.just_pressed(KeyCode::Digit1) {
-        *transform = CAM_POS_1
-    }
-    if input.just_pressed(KeyCode::Digit2) {
-        *transform = CAM_POS_2
-    }
-    if input.just_pressed(KeyCode::Digit3) {
-        *transform = CAM_POS_3
-    }
-}
-
-fn benchmark(
-    input: Res<ButtonInput<KeyCode>>,
-    mut camera: Query<&mut Transform, With<Camera>>,
-    mut bench_started: Local<Option<Instant>>,
-    mut bench_frame: Local<u32>,
-    mut count_per_step: Local<u32>,
-    time: Res<Time>,
-) {
-    if input.just_pressed(KeyCode::KeyB) && bench_started.is_none() {
-        *bench_started = Some(Instant::now());
-        *bench_frame = 0;
-        // Try to render for around 2s or at least 30 frames per step
-        *count_per_step = ((2.0 / time.delta_seconds()) as u32).max(30);
-        println!(
-            "Starting Benchmark with {} frames per step",
-            *count_per_step
-        );
-    }
-    if bench_started.is_none() {
-        return;
-    }
-    let Ok(mut transform) = camera.get_single_mut() else {
-        return;
-    };
-    if *bench_frame == 0 {
-        *transform = CAM_POS_1
-    } else if *bench_frame == *count_per_step {
-        *transform = CAM_POS_2
-    } else if *bench_frame == *count_per_step * 2 {
-        *transform = CAM_POS_3
-    } else if *bench_frame == *count_per_step * 3 {
-        let elapsed = bench_started.unwrap().elapsed().as_secs_f32();
-        println!(
-            "Benchmark avg cpu frame time: {:.2}ms",
-            (elapsed / *bench_frame as f32) * 1000.0
-        );
-        *bench_started = None;
-        *bench_frame = 0;
-        *transform = CAM_POS_1;
-    }
-    *bench_frame += 1;
-}
-
-pub fn add_no_frustum_culling(
-    mut commands: Commands,
-    convert_query: Query<Entity, (Without<NoFrustumCulling>, With<Handle<StandardMaterial>>)>,
-) {
-    for entity in convert_query.iter() {
-        commands.entity(entity).insert(NoFrustumCulling);
-    }
 }
@@ -0,0 +1,100 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::{loading::SceneReady, props::next_random};
+
+/// Region of Sponza the lights are scattered through, the atrium and the corridors around it on
+/// both floors.
+const LIGHTS_MIN: Vec3 = Vec3::new(-12.0, 0.3, -5.5);
+const LIGHTS_MAX: Vec3 = Vec3::new(12.0, 9.0, 5.5);
+const LIGHT_RANGE: f32 = 2.0;
+const LIGHT_INTENSITY: f32 = 100_000.0;
+/// Radius of the circles the lights move along with `--many-lights-animate`.
+const PATH_RADIUS: f32 = 1.0;
+
+/// Scatters small shadowless point lights through the scene, see `--many-lights`. Each only
+/// reaches a couple of meters, so this stresses the clustering of the forward renderer rather
+/// than the shading of every pixel.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ManyLights {
+    pub count: usize,
+    /// Move the lights in circles, so they're assigned to different clusters every frame.
+    pub animate: bool,
+}
+
+/// One of the scattered lights and the circle it moves along.
+#[derive(Component)]
+pub struct ScatteredLight {
+    center: Vec3,
+    phase: f32,
+    speed: f32,
+}
+
+pub struct ManyLightsPlugin;
+impl Plugin for ManyLightsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (spawn_lights, animate_lights));
+    }
+}
+
+fn spawn_lights(
+    mut commands: Commands,
+    many_lights: Option<Res<ManyLights>>,
+    scene_ready: Res<SceneReady>,
+    mut spawned: Local<bool>,
+) {
+    let Some(many_lights) = many_lights else {
+        return;
+    };
+    if *spawned || !scene_ready.ready {
+        return;
+    }
+    *spawned = true;
+
+    let mut state = 0x9e37_79b9;
+    for _ in 0..many_lights.count {
+        let center = LIGHTS_MIN
+            + Vec3::new(
+                next_random(&mut state),
+                next_random(&mut state),
+                next_random(&mut state),
+            ) * (LIGHTS_MAX - LIGHTS_MIN);
+        commands.spawn((
+            PointLightBundle {
+                point_light: PointLight {
+                    color: Color::hsl(next_random(&mut state) * 360.0, 0.8, 0.6),
+                    intensity: LIGHT_INTENSITY,
+                    range: LIGHT_RANGE,
+                    radius: 0.05,
+                    shadows_enabled: false,
+                    ..default()
+                },
+                transform: Transform::from_translation(center),
+                ..default()
+            },
+            ScatteredLight {
+                center,
+                phase: next_random(&mut state) * TAU,
+                speed: 0.5 + next_random(&mut state),
+            },
+        ));
+    }
+    info!("Spawned {} point lights", many_lights.count);
+}
+
+fn animate_lights(
+    time: Res<Time>,
+    many_lights: Option<Res<ManyLights>>,
+    mut query: Query<(&mut Transform, &ScatteredLight)>,
+) {
+    if !many_lights.is_some_and(|many_lights| many_lights.animate) {
+        return;
+    }
+    let t = time.elapsed_seconds();
+    for (mut transform, light) in &mut query {
+        let angle = light.phase + t * light.speed;
+        transform.translation =
+            light.center + Vec3::new(angle.cos(), 0.0, angle.sin()) * PATH_RADIUS;
+    }
+}
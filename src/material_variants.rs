@@ -0,0 +1,258 @@
+use std::{fs, path::Path};
+
+use bevy::{gltf::Gltf, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{loading::SceneReady, scene_preset::ScenePreset};
+
+/// Read at startup when `--material-variants` isn't given, if it exists.
+pub const DEFAULT_VARIANTS_PATH: &str = "material_variants.ron";
+
+/// PBR parameters to set on the materials whose glTF name contains one of `materials`, ignoring
+/// case. Parameters left out keep the value the material was loaded with.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialOverride {
+    pub materials: Vec<String>,
+    pub base_color: Option<Color>,
+    pub perceptual_roughness: Option<f32>,
+    pub metallic: Option<f32>,
+    pub reflectance: Option<f32>,
+}
+
+impl MaterialOverride {
+    fn matches(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.materials
+            .iter()
+            .any(|pattern| name.contains(&pattern.to_lowercase()))
+    }
+
+    fn apply(&self, material: &mut StandardMaterial) {
+        if let Some(base_color) = self.base_color {
+            material.base_color = base_color;
+        }
+        if let Some(roughness) = self.perceptual_roughness {
+            material.perceptual_roughness = roughness;
+        }
+        if let Some(metallic) = self.metallic {
+            material.metallic = metallic;
+        }
+        if let Some(reflectance) = self.reflectance {
+            material.reflectance = reflectance;
+        }
+    }
+}
+
+/// A named set of overrides, like a wet floor.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MaterialVariant {
+    pub name: String,
+    pub overrides: Vec<MaterialOverride>,
+}
+
+/// The parameters an override can change, kept to undo it.
+#[derive(Clone, Copy, Debug)]
+struct OriginalMaterial {
+    base_color: Color,
+    perceptual_roughness: f32,
+    metallic: f32,
+    reflectance: f32,
+}
+
+impl OriginalMaterial {
+    fn new(material: &StandardMaterial) -> Self {
+        Self {
+            base_color: material.base_color,
+            perceptual_roughness: material.perceptual_roughness,
+            metallic: material.metallic,
+            reflectance: material.reflectance,
+        }
+    }
+
+    fn restore(&self, material: &mut StandardMaterial) {
+        material.base_color = self.base_color;
+        material.perceptual_roughness = self.perceptual_roughness;
+        material.metallic = self.metallic;
+        material.reflectance = self.reflectance;
+    }
+}
+
+/// Material variants from a RON file, cycled with J. Materials are matched by the names they
+/// have in the glTF files of the scene preset.
+#[derive(Resource, Default)]
+pub struct MaterialVariants {
+    pub variants: Vec<MaterialVariant>,
+    /// `None` shows the materials as they were loaded.
+    pub active: Option<usize>,
+    gltfs: Vec<Handle<Gltf>>,
+    originals: HashMap<AssetId<StandardMaterial>, OriginalMaterial>,
+}
+
+impl MaterialVariants {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Vec<MaterialVariant>> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// A glossy floor to show off reflections and metallic trim, for Sponza.
+    pub fn sponza() -> Vec<MaterialVariant> {
+        vec![
+            MaterialVariant {
+                name: String::from("Wet floor"),
+                overrides: vec![MaterialOverride {
+                    materials: vec![String::from("floor")],
+                    perceptual_roughness: Some(0.08),
+                    reflectance: Some(0.6),
+                    ..default()
+                }],
+            },
+            MaterialVariant {
+                name: String::from("Metallic trim"),
+                overrides: vec![MaterialOverride {
+                    materials: vec![String::from("trim"), String::from("metal")],
+                    perceptual_roughness: Some(0.3),
+                    metallic: Some(1.0),
+                    ..default()
+                }],
+            },
+        ]
+    }
+
+    pub fn new(variants: Vec<MaterialVariant>) -> Self {
+        Self {
+            variants,
+            ..default()
+        }
+    }
+
+    /// Name of the material in the glTF it came from.
+    pub fn material_name<'a>(
+        &self,
+        gltfs: &'a Assets<Gltf>,
+        material: &Handle<StandardMaterial>,
+    ) -> Option<&'a str> {
+        self.gltfs
+            .iter()
+            .filter_map(|handle| gltfs.get(handle))
+            .flat_map(|gltf| &gltf.named_materials)
+            .find(|(_, handle)| *handle == material)
+            .map(|(name, _)| &**name)
+    }
+
+    /// Starts with the variant of this name active, ignoring case.
+    pub fn with_active(mut self, name: &str) -> anyhow::Result<Self> {
+        let index = self
+            .variants
+            .iter()
+            .position(|variant| variant.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow::anyhow!("No material variant named {name}"))?;
+        self.active = Some(index);
+        Ok(self)
+    }
+}
+
+pub struct MaterialVariantsPlugin;
+impl Plugin for MaterialVariantsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_scene_gltfs).add_systems(
+            Update,
+            (cycle_material_variant, apply_material_variant).chain(),
+        );
+    }
+}
+
+/// The scenes only hold material handles, the names are in the glTF assets.
+fn load_scene_gltfs(
+    variants: Option<ResMut<MaterialVariants>>,
+    preset: Res<ScenePreset>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(mut variants) = variants else {
+        return;
+    };
+    variants.gltfs = preset
+        .scenes
+        .iter()
+        .map(|scene| asset_server.load(scene.split('#').next().unwrap_or(scene).to_string()))
+        .collect();
+}
+
+fn cycle_material_variant(
+    input: Res<ButtonInput<KeyCode>>,
+    variants: Option<ResMut<MaterialVariants>>,
+) {
+    let Some(mut variants) = variants else {
+        return;
+    };
+    if !input.just_pressed(KeyCode::KeyJ) || variants.variants.is_empty() {
+        return;
+    }
+    variants.active = match variants.active {
+        None => Some(0),
+        Some(i) if i + 1 < variants.variants.len() => Some(i + 1),
+        Some(_) => None,
+    };
+    let name = variants
+        .active
+        .map_or("none", |i| variants.variants[i].name.as_str());
+    info!("Material variant: {name}");
+}
+
+/// Puts back the materials changed by the last variant, then applies the active one. Waits for
+/// the scene rules to run first, so undoing a variant doesn't undo them too.
+fn apply_material_variant(
+    variants: Option<ResMut<MaterialVariants>>,
+    scene_ready: Res<SceneReady>,
+    gltfs: Res<Assets<Gltf>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut pending: Local<bool>,
+) {
+    let Some(mut variants) = variants else {
+        return;
+    };
+    *pending |= variants.is_changed();
+    if !*pending || !scene_ready.ready {
+        return;
+    }
+    *pending = false;
+    let variants = variants.bypass_change_detection();
+
+    for (id, original) in &variants.originals {
+        if let Some(material) = materials.get_mut(*id) {
+            original.restore(material);
+        }
+    }
+    let Some(variant) = variants.active.map(|i| &variants.variants[i]) else {
+        return;
+    };
+    let mut matched = 0;
+    for gltf in variants.gltfs.iter().filter_map(|handle| gltfs.get(handle)) {
+        for (name, handle) in &gltf.named_materials {
+            let overrides: Vec<_> = variant
+                .overrides
+                .iter()
+                .filter(|o| o.matches(name))
+                .collect();
+            if overrides.is_empty() {
+                continue;
+            }
+            let Some(material) = materials.get_mut(handle) else {
+                continue;
+            };
+            variants
+                .originals
+                .entry(handle.id())
+                .or_insert_with(|| OriginalMaterial::new(material));
+            for o in overrides {
+                o.apply(material);
+            }
+            matched += 1;
+        }
+    }
+    if matched == 0 {
+        warn!(
+            "Material variant {} didn't match any materials of the scene",
+            variant.name
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+    render::{render_asset::RenderAssets, Render, RenderApp, RenderSet},
+};
+use sysinfo::{Pid, System};
+
+pub const PROCESS_MEMORY: DiagnosticPath = DiagnosticPath::const_new("process_memory");
+pub const IMAGE_ASSET_MEMORY: DiagnosticPath = DiagnosticPath::const_new("image_asset_memory");
+pub const TEXTURE_GPU_MEMORY: DiagnosticPath = DiagnosticPath::const_new("texture_gpu_memory");
+
+/// How often to sample process memory, querying the OS every frame isn't free.
+const SAMPLE_INTERVAL_SECS: f32 = 0.5;
+
+const MB: f64 = 1_000_000.0;
+
+/// Estimated size of all textures uploaded to the GPU in bytes, summed in the render world.
+/// wgpu doesn't report actual VRAM usage, so this doesn't include buffers or driver overhead.
+#[derive(Resource, Clone, Default)]
+pub struct GpuTextureMemory(Arc<Mutex<Option<u64>>>);
+
+/// Reports process resident memory, CPU side image asset memory, and GPU texture memory.
+pub struct MemoryDiagnosticsPlugin;
+impl Plugin for MemoryDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GpuTextureMemory>()
+            .register_diagnostic(
+                Diagnostic::new(PROCESS_MEMORY)
+                    .with_max_history_length(1)
+                    .with_suffix("MB"),
+            )
+            .register_diagnostic(
+                Diagnostic::new(IMAGE_ASSET_MEMORY)
+                    .with_max_history_length(1)
+                    .with_suffix("MB"),
+            )
+            .register_diagnostic(
+                Diagnostic::new(TEXTURE_GPU_MEMORY)
+                    .with_max_history_length(1)
+                    .with_suffix("MB"),
+            )
+            .add_systems(Update, memory_diagnostics);
+    }
+
+    fn finish(&self, app: &mut App) {
+        let memory = app.world.resource::<GpuTextureMemory>().clone();
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.insert_resource(memory).add_systems(
+            Render,
+            measure_gpu_texture_memory.in_set(RenderSet::Cleanup),
+        );
+    }
+}
+
+fn measure_gpu_texture_memory(images: Res<RenderAssets<Image>>, memory: Res<GpuTextureMemory>) {
+    let mut total = 0;
+    for (_, image) in images.iter() {
+        let texture = &image.texture;
+        let size = texture.size();
+        let format = texture.format();
+        let (block_width, block_height) = format.block_dimensions();
+        let block_size = format.block_copy_size(None).unwrap_or(4) as u64;
+        for level in 0..texture.mip_level_count() {
+            let width = (size.width >> level).max(1);
+            let height = (size.height >> level).max(1);
+            let blocks = width.div_ceil(block_width) as u64 * height.div_ceil(block_height) as u64;
+            total += blocks * block_size * size.depth_or_array_layers as u64;
+        }
+    }
+    *memory.0.lock().unwrap() = Some(total);
+}
+
+fn memory_diagnostics(
+    mut diagnostics: Diagnostics,
+    time: Res<Time>,
+    images: Res<Assets<Image>>,
+    gpu_memory: Res<GpuTextureMemory>,
+    mut system: Local<Option<(System, Pid)>>,
+    mut since_sample: Local<f32>,
+) {
+    *since_sample += time.delta_seconds();
+    if *since_sample < SAMPLE_INTERVAL_SECS {
+        return;
+    }
+    *since_sample = 0.0;
+
+    if system.is_none() {
+        *system = sysinfo::get_current_pid()
+            .ok()
+            .map(|pid| (System::new(), pid));
+    }
+    if let Some((system, pid)) = system.as_mut() {
+        system.refresh_process(*pid);
+        if let Some(process) = system.process(*pid) {
+            diagnostics.add_measurement(&PROCESS_MEMORY, || process.memory() as f64 / MB);
+        }
+    }
+    diagnostics.add_measurement(&IMAGE_ASSET_MEMORY, || {
+        images
+            .iter()
+            .map(|(_, image)| image.data.len() as f64)
+            .sum::<f64>()
+            / MB
+    });
+    if let Some(bytes) = *gpu_memory.0.lock().unwrap() {
+        diagnostics.add_measurement(&TEXTURE_GPU_MEMORY, || bytes as f64 / MB);
+    }
+}
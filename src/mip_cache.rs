@@ -0,0 +1,322 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    loading::SceneReady,
+    mipmap_generator::{MipRole, MipmapGeneratorSettings},
+};
+
+/// A mip chain as stored in the cache.
+pub struct CachedMips {
+    pub mip_level_count: u32,
+    /// Whether `data` has been encoded with the settings' compression format.
+    pub compressed: bool,
+    pub data: Vec<u8>,
+}
+
+/// Bumped whenever the generated output or the entry layout changes for the same settings, so
+/// stale entries miss and are pruned.
+const CACHE_VERSION: u32 = 5;
+
+const MAGIC: &[u8; 4] = b"MIPS";
+
+/// Size of the header in front of the mip data in each cache entry: the magic, version, settings
+/// hash, mip level count, and whether the data is compressed.
+const HEADER_LEN: usize = 21;
+
+/// Entries that haven't been used for this long are pruned on startup, they're most likely for
+/// settings or textures that aren't used anymore.
+const MAX_UNUSED_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Temporary files older than this were left behind by a crashed write, younger ones may still be
+/// written by another process sharing the cache.
+const MAX_TMP_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Locked for reading entries, and exclusively for replacing or removing them, so several
+/// checkouts or simultaneous runs can share a cache directory.
+const LOCK_FILE: &str = "cache.lock";
+
+/// Identifies a cache entry. The content hash covers the source image and what its mips are
+/// filtered with, the settings hash the settings that affect the output. The settings hash is
+/// also stored in the entry's header and checked on read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheKey {
+    pub content: u64,
+    pub settings: u64,
+}
+
+/// Hits and misses since startup, shared by every clone of the cache.
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    pub hits: AtomicU64,
+    pub misses: AtomicU64,
+    /// Entries found with an old version or mismatched header, which count as misses too.
+    pub stale: AtomicU64,
+    pub writes: AtomicU64,
+}
+
+/// On disk cache of generated mip chains, keyed by a hash of the source image and the settings
+/// that affect the output. Least recently used entries are evicted once `max_bytes` is exceeded.
+/// The directory can be shared between processes, see [`LOCK_FILE`].
+#[derive(Clone, Debug)]
+pub struct MipCache {
+    pub dir: PathBuf,
+    pub max_bytes: u64,
+    pub stats: Arc<CacheStats>,
+}
+
+impl MipCache {
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes,
+            stats: default(),
+        }
+    }
+
+    pub fn settings_hash(settings: &MipmapGeneratorSettings) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        CACHE_VERSION.hash(&mut hasher);
+        settings.minimum_mip_resolution.hash(&mut hasher);
+        settings.filter_type.hash(&mut hasher);
+        settings.compression.hash(&mut hasher);
+        settings.max_texture_size.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn key(image: &Image, settings: &MipmapGeneratorSettings, role: &MipRole) -> CacheKey {
+        let mut hasher = DefaultHasher::new();
+        image.data.hash(&mut hasher);
+        image.texture_descriptor.size.hash(&mut hasher);
+        image.texture_descriptor.format.hash(&mut hasher);
+        match role {
+            MipRole::Other => 0u8.hash(&mut hasher),
+            MipRole::NormalMap => 1u8.hash(&mut hasher),
+            MipRole::MetallicRoughness(normal_map) => {
+                2u8.hash(&mut hasher);
+                normal_map.data.hash(&mut hasher);
+                (normal_map.width, normal_map.height).hash(&mut hasher);
+            }
+        }
+        CacheKey {
+            content: hasher.finish(),
+            settings: Self::settings_hash(settings),
+        }
+    }
+
+    /// Locks the cache directory until the returned file is dropped. Locking can fail on some
+    /// network file systems, the cache is then used unlocked.
+    fn lock(&self, exclusive: bool) -> Option<fs::File> {
+        fs::create_dir_all(&self.dir).ok()?;
+        let file = fs::File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.dir.join(LOCK_FILE))
+            .ok()?;
+        let locked = if exclusive {
+            file.lock()
+        } else {
+            file.lock_shared()
+        };
+        locked.ok().map(|_| file)
+    }
+
+    fn entry_path(&self, key: CacheKey) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}_{:016x}.mips", key.content, key.settings))
+    }
+
+    /// Checks the magic and version of an entry's header, returning its settings hash.
+    fn parse_header(header: &[u8]) -> Option<u64> {
+        if header.len() < HEADER_LEN || &header[0..4] != MAGIC {
+            return None;
+        }
+        let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        (version == CACHE_VERSION).then(|| u64::from_le_bytes(header[8..16].try_into().unwrap()))
+    }
+
+    pub fn get(&self, key: CacheKey) -> Option<CachedMips> {
+        let path = self.entry_path(key);
+        let lock = self.lock(false);
+        let Ok(mut bytes) = fs::read(&path) else {
+            self.stats.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        if Self::parse_header(&bytes) != Some(key.settings) {
+            warn!("Removing stale mipmap cache entry {}", path.display());
+            drop(lock);
+            let _lock = self.lock(true);
+            let _ = fs::remove_file(&path);
+            self.stats.stale.fetch_add(1, Ordering::Relaxed);
+            self.stats.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        let mip_level_count = u32::from_le_bytes(bytes[16..20].try_into().unwrap());
+        let compressed = bytes[20] != 0;
+        bytes.drain(0..HEADER_LEN);
+        // Mark as recently used
+        if let Ok(file) = fs::File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        self.stats.hits.fetch_add(1, Ordering::Relaxed);
+        Some(CachedMips {
+            mip_level_count,
+            compressed,
+            data: bytes,
+        })
+    }
+
+    pub fn put(&self, key: CacheKey, mips: &CachedMips) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.entry_path(key);
+        // Unique per process, so runs sharing the cache don't write into each other's file
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        let mut bytes = Vec::with_capacity(mips.data.len() + HEADER_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&key.settings.to_le_bytes());
+        bytes.extend_from_slice(&mips.mip_level_count.to_le_bytes());
+        bytes.push(mips.compressed as u8);
+        bytes.extend_from_slice(&mips.data);
+        fs::write(&tmp_path, bytes)?;
+        let _lock = self.lock(true);
+        fs::rename(&tmp_path, &path)?;
+        self.stats.writes.fetch_add(1, Ordering::Relaxed);
+        self.evict();
+        Ok(())
+    }
+
+    /// Removes entries from older versions of the cache, ones unused for a month, and temporary
+    /// files left behind by interrupted writes. Returns how many were removed.
+    pub fn prune(&self) -> usize {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return 0;
+        };
+        let _lock = self.lock(true);
+        let now = SystemTime::now();
+        let mut removed = 0;
+        for entry in dir.flatten() {
+            let path = entry.path();
+            let age = entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok());
+            let stale = match path.extension().and_then(|e| e.to_str()) {
+                Some("tmp") => age.is_some_and(|age| age > MAX_TMP_AGE),
+                Some("mips") => {
+                    let mut header = [0; HEADER_LEN];
+                    let valid = fs::File::open(&path)
+                        .and_then(|mut file| file.read_exact(&mut header))
+                        .is_ok()
+                        && Self::parse_header(&header).is_some();
+                    !valid || age.is_some_and(|age| age > MAX_UNUSED_AGE)
+                }
+                _ => false,
+            };
+            if stale && fs::remove_file(&path).is_ok() {
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            println!(
+                "Pruned {removed} stale entries from the mipmap cache {}",
+                self.dir.display()
+            );
+        }
+        removed
+    }
+
+    /// Number of entries and their total size in bytes.
+    pub fn size(&self) -> (usize, u64) {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return (0, 0);
+        };
+        dir.flatten()
+            .filter(|e| e.path().extension().unwrap_or_default() == "mips")
+            .filter_map(|e| e.metadata().ok())
+            .fold((0, 0), |(count, bytes), meta| {
+                (count + 1, bytes + meta.len())
+            })
+    }
+
+    /// Removes the least recently used entries until the cache fits in `max_bytes`. Expects the
+    /// cache to be locked exclusively.
+    fn evict(&self) {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = dir
+            .flatten()
+            .filter(|e| e.path().extension().unwrap_or_default() == "mips")
+            .filter_map(|e| {
+                let meta = e.metadata().ok()?;
+                Some((meta.modified().ok()?, meta.len(), e.path()))
+            })
+            .collect();
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort_by_key(|(modified, _, _)| *modified);
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total -= len;
+            }
+        }
+    }
+
+    pub fn clear(dir: &Path) {
+        match fs::remove_dir_all(dir) {
+            Ok(_) => println!("Cleared mipmap cache {}", dir.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => println!("Failed to clear mipmap cache {}: {}", dir.display(), e),
+        }
+    }
+}
+
+/// Prints the cache's size and how many mip chains it served once the scene is ready. Only
+/// active when the resource is present, see `--cache-info`.
+#[derive(Resource)]
+pub struct PrintCacheInfo;
+
+pub fn print_cache_info(
+    print: Option<Res<PrintCacheInfo>>,
+    scene_ready: Res<SceneReady>,
+    settings: Res<MipmapGeneratorSettings>,
+    mut printed: Local<bool>,
+) {
+    if print.is_none() || *printed || !scene_ready.ready {
+        return;
+    }
+    *printed = true;
+    let Some(cache) = &settings.cache else {
+        println!("The mipmap cache is disabled");
+        return;
+    };
+    let (entries, bytes) = cache.size();
+    let stats = &cache.stats;
+    println!(
+        "Mipmap cache {}: {entries} entries, {:.1}MB of {:.1}MB. {} hits, {} misses ({} stale), {} written",
+        cache.dir.display(),
+        bytes as f64 / 1_000_000.0,
+        cache.max_bytes as f64 / 1_000_000.0,
+        stats.hits.load(Ordering::Relaxed),
+        stats.misses.load(Ordering::Relaxed),
+        stats.stale.load(Ordering::Relaxed),
+        stats.writes.load(Ordering::Relaxed),
+    );
+}
@@ -0,0 +1,313 @@
+use std::{f32::consts::PI, str::FromStr, sync::OnceLock};
+
+use anyhow::anyhow;
+use bevy::math::Vec3;
+
+/// Kernel used to downsample each mip level from the previous one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MipFilter {
+    /// Averages each 2x2 block. Cheap, but aliases on high frequency detail.
+    Box,
+    Triangle,
+    /// Kaiser windowed sinc, the default in most offline texture tools. Keeps more detail than
+    /// triangle without the ringing of Lanczos.
+    Kaiser,
+    Lanczos3,
+}
+
+impl FromStr for MipFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "box" => Ok(MipFilter::Box),
+            "triangle" => Ok(MipFilter::Triangle),
+            "kaiser" => Ok(MipFilter::Kaiser),
+            "lanczos3" => Ok(MipFilter::Lanczos3),
+            _ => Err(anyhow!(
+                "Unknown mip filter {s}, expected box, triangle, kaiser, or lanczos3"
+            )),
+        }
+    }
+}
+
+impl MipFilter {
+    /// Kernel radius in destination texels.
+    fn support(&self) -> f32 {
+        match self {
+            MipFilter::Box => 0.5,
+            MipFilter::Triangle => 1.0,
+            MipFilter::Kaiser | MipFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            MipFilter::Box => {
+                if x <= 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            MipFilter::Triangle => (1.0 - x).max(0.0),
+            MipFilter::Kaiser => {
+                const ALPHA: f32 = 4.0;
+                let width = self.support();
+                if x >= width {
+                    return 0.0;
+                }
+                let t = x / width;
+                sinc(x) * bessel_i0(ALPHA * (1.0 - t * t).sqrt()) / bessel_i0(ALPHA)
+            }
+            MipFilter::Lanczos3 => {
+                if x >= 3.0 {
+                    0.0
+                } else {
+                    sinc(x) * sinc(x / 3.0)
+                }
+            }
+        }
+    }
+
+    /// Normalized weights for each destination texel along one axis, halving `src_len` texels.
+    fn weights(&self, src_len: u32, dst_len: u32) -> Vec<(usize, Vec<f32>)> {
+        let support = self.support();
+        (0..dst_len)
+            .map(|dst| {
+                // Destination texel center in source texel coordinates
+                let center = (dst as f32 + 0.5) * 2.0;
+                let first = (center - support * 2.0).floor() as i64;
+                let last = (center + support * 2.0).ceil() as i64;
+                let mut weights: Vec<f32> = (first..last)
+                    .map(|src| self.weight((src as f32 + 0.5 - center) / 2.0))
+                    .collect();
+                let sum: f32 = weights.iter().sum();
+                if sum != 0.0 {
+                    weights.iter_mut().for_each(|w| *w /= sum);
+                }
+                // Offset by the source length so clamping to the edge below never goes negative
+                let first = (first + src_len as i64) as usize;
+                (first, weights)
+            })
+            .collect()
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Zeroth order modified Bessel function of the first kind, used by the Kaiser window.
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x = x * 0.5;
+    for k in 1..20 {
+        term *= half_x / k as f32;
+        sum += term * term;
+    }
+    sum
+}
+
+fn srgb_to_linear_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        std::array::from_fn(|i| {
+            let c = i as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        })
+    })
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Layout of the 8 bit texels being filtered.
+#[derive(Clone, Copy, Debug)]
+pub struct TexelLayout {
+    pub channels: usize,
+    /// The first three channels are sRGB encoded and are filtered in linear space. Alpha is
+    /// always linear.
+    pub srgb: bool,
+    /// The first three channels hold a unit vector mapped to 0..1, which is renormalized after
+    /// filtering so lower mips of normal maps don't flatten out.
+    pub normal_map: bool,
+}
+
+impl TexelLayout {
+    fn is_srgb_channel(&self, channel: usize) -> bool {
+        self.srgb && channel < 3
+    }
+}
+
+/// Downsamples a `width` x `height` level of 8 bit texels to half size, returning the new level.
+/// Filtering is separable, with the horizontal pass kept in f32 so precision isn't lost between
+/// passes. Texels past the edge are clamped.
+pub fn downsample_half(
+    src: &[u8],
+    width: u32,
+    height: u32,
+    layout: TexelLayout,
+    filter: MipFilter,
+) -> Vec<u8> {
+    let channels = layout.channels;
+    let dst_width = (width / 2).max(1);
+    let dst_height = (height / 2).max(1);
+    let to_linear = srgb_to_linear_table();
+    let decode = |value: u8, channel: usize| {
+        if layout.is_srgb_channel(channel) {
+            to_linear[value as usize]
+        } else {
+            value as f32 / 255.0
+        }
+    };
+
+    let clamp = |i: usize, len: u32| i.saturating_sub(len as usize).min(len as usize - 1);
+
+    // Horizontal pass
+    let x_weights = filter.weights(width, dst_width);
+    let mut horizontal = vec![0.0f32; dst_width as usize * height as usize * channels];
+    for y in 0..height as usize {
+        let row = &src[y * width as usize * channels..(y + 1) * width as usize * channels];
+        let out_row = &mut horizontal
+            [y * dst_width as usize * channels..(y + 1) * dst_width as usize * channels];
+        for (x, (first, weights)) in x_weights.iter().enumerate() {
+            for (i, weight) in weights.iter().enumerate() {
+                let src_x = clamp(first + i, width);
+                for c in 0..channels {
+                    out_row[x * channels + c] += decode(row[src_x * channels + c], c) * weight;
+                }
+            }
+        }
+    }
+
+    // Vertical pass
+    let y_weights = filter.weights(height, dst_height);
+    let mut dst = vec![0u8; dst_width as usize * dst_height as usize * channels];
+    let row_len = dst_width as usize * channels;
+    let mut sums = vec![0.0f32; row_len];
+    for (y, (first, weights)) in y_weights.iter().enumerate() {
+        sums.iter_mut().for_each(|s| *s = 0.0);
+        for (i, weight) in weights.iter().enumerate() {
+            let src_y = clamp(first + i, height);
+            let row = &horizontal[src_y * row_len..(src_y + 1) * row_len];
+            for (sum, value) in sums.iter_mut().zip(row) {
+                *sum += value * weight;
+            }
+        }
+        if layout.normal_map && channels >= 3 {
+            for texel in sums.chunks_exact_mut(channels) {
+                let normal = Vec3::new(texel[0], texel[1], texel[2]) * 2.0 - 1.0;
+                let normal = normal.try_normalize().unwrap_or(Vec3::Z) * 0.5 + 0.5;
+                texel[..3].copy_from_slice(&normal.to_array());
+            }
+        }
+        let out_row = &mut dst[y * row_len..(y + 1) * row_len];
+        for (i, (out, sum)) in out_row.iter_mut().zip(&sums).enumerate() {
+            // Sinc based kernels have negative lobes that can overshoot
+            let value = sum.clamp(0.0, 1.0);
+            let value = if layout.is_srgb_channel(i % channels) {
+                linear_to_srgb(value)
+            } else {
+                value
+            };
+            *out = (value * 255.0 + 0.5) as u8;
+        }
+    }
+    dst
+}
+
+/// The first level of a material's normal map, used to adjust the roughness mips of the same
+/// material in [`apply_toksvig`].
+#[derive(Clone)]
+pub struct NormalMapLevel {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub channels: usize,
+}
+
+/// Widens the roughness (green channel) of each generated level of a metallic-roughness mip
+/// chain using Toksvig's approximation: the shorter the average of the normals a texel covers,
+/// the more they disagree, and the rougher the surface looks from a distance. Keeps bumpy
+/// surfaces from turning into mirrors in the lower mips. Levels without a normal map level of the
+/// same size are left as they are.
+pub fn apply_toksvig(
+    data: &mut [u8],
+    mut width: u32,
+    mut height: u32,
+    layout: TexelLayout,
+    mip_level_count: u32,
+    normal_map: &NormalMapLevel,
+    filter: MipFilter,
+) {
+    if layout.channels < 2 || normal_map.channels < 3 {
+        return;
+    }
+    // Average the normals without renormalizing them, the lost length is what's measured
+    let normal_layout = TexelLayout {
+        channels: normal_map.channels,
+        srgb: false,
+        normal_map: false,
+    };
+    let mut normal_levels = Vec::new();
+    let (mut normal_width, mut normal_height) = (normal_map.width, normal_map.height);
+    let mut normal_level = normal_map.data.clone();
+    while normal_width > 1 || normal_height > 1 {
+        normal_level = downsample_half(
+            &normal_level,
+            normal_width,
+            normal_height,
+            normal_layout,
+            filter,
+        );
+        normal_width = (normal_width / 2).max(1);
+        normal_height = (normal_height / 2).max(1);
+        normal_levels.push((normal_width, normal_height, normal_level.clone()));
+    }
+
+    let mut level_offset = 0;
+    for _ in 0..mip_level_count {
+        let level_len = width as usize * height as usize * layout.channels;
+        let Some(level) = data.get_mut(level_offset..level_offset + level_len) else {
+            return;
+        };
+        let normals = normal_levels
+            .iter()
+            .find(|(w, h, _)| (*w, *h) == (width, height));
+        if let (Some((_, _, normals)), true) = (normals, level_offset > 0) {
+            for (texel, normal) in level
+                .chunks_exact_mut(layout.channels)
+                .zip(normals.chunks_exact(normal_map.channels))
+            {
+                let normal =
+                    Vec3::new(normal[0] as f32, normal[1] as f32, normal[2] as f32) / 255.0 * 2.0
+                        - 1.0;
+                let length = normal.length().clamp(1e-4, 1.0);
+                let variance = (1.0 - length) / length;
+                let roughness = texel[1] as f32 / 255.0;
+                let alpha = roughness * roughness;
+                let alpha = (alpha * alpha + variance).min(1.0).sqrt();
+                texel[1] = (alpha.sqrt() * 255.0 + 0.5) as u8;
+            }
+        }
+        level_offset += level_len;
+        width = (width / 2).max(1);
+        height = (height / 2).max(1);
+    }
+}
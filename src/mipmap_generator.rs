@@ -3,17 +3,34 @@
 use anyhow::anyhow;
 
 use bevy::render::render_asset::RenderAssetUsages;
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::Task;
 use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
     prelude::*,
     render::{
         render_resource::{Extent3d, TextureDimension, TextureFormat},
+        renderer::RenderDevice,
         texture::{ImageSampler, ImageSamplerDescriptor},
     },
-    tasks::{AsyncComputeTaskPool, Task},
-    utils::HashMap,
+    tasks::AsyncComputeTaskPool,
+    utils::{HashMap, Instant},
 };
 use futures_lite::future;
-use image::{imageops::FilterType, DynamicImage, ImageBuffer};
+use image::{DynamicImage, ImageBuffer};
+use std::{collections::VecDeque, time::Duration};
+use sysinfo::System;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    compression::compress_mips,
+    mip_cache::{CachedMips, MipCache},
+};
+use crate::{
+    compression::CompressionFormat,
+    gpu_mipmaps::{allocate_gpu_mip_chain, gpu_mips_supported, GpuMipmapPlugin, GpuMipmapQueue},
+    mip_filter::{apply_toksvig, downsample_half, MipFilter, NormalMapLevel, TexelLayout},
+};
 
 #[derive(Resource, Deref)]
 pub struct DefaultSampler(ImageSamplerDescriptor);
@@ -22,8 +39,35 @@ pub struct DefaultSampler(ImageSamplerDescriptor);
 pub struct MipmapGeneratorSettings {
     /// Valid values: 1, 2, 4, 8, and 16.
     pub anisotropic_filtering: u16,
-    pub filter_type: FilterType,
+    pub filter_type: MipFilter,
     pub minimum_mip_resolution: u32,
+    /// Upper bound on the memory used by in-flight mipmap generation tasks. Images are queued
+    /// and only handed to the task pool while their estimated footprint fits in this budget.
+    /// A single image is always allowed to run, even if it exceeds the budget on its own.
+    pub max_memory_bytes: u64,
+    /// Cache generated mip chains on disk so later runs can skip generating them.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub cache: Option<MipCache>,
+    /// Encode RGBA8 mip chains to a GPU compressed format after generating them.
+    /// Reset to `None` if the GPU doesn't support the format, and on the web.
+    pub compression: Option<CompressionFormat>,
+    /// Halve textures until their largest side fits in this size before generating mips.
+    /// Lowers memory use for machines that can't fit the full size textures.
+    pub max_texture_size: Option<u32>,
+    /// Render mip chains on the GPU with a chain of downsampling passes. Much faster, but the
+    /// box filter ignores `filter_type`, and compression, the cache, and normal map handling
+    /// aren't used.
+    pub gpu: bool,
+    /// Widen the roughness in the lower mips of metallic-roughness textures by how much the
+    /// material's normal map varies, see [`apply_toksvig`].
+    pub toksvig: bool,
+    /// Show a copy of each texture shrunk to fit in this size while its full mip chain is
+    /// generated, so the scene can be looked around right away without aliasing. Normal maps
+    /// stay at full size with `toksvig`, which reads them.
+    pub placeholder_size: Option<u32>,
+    /// Finished mip chains are swapped in until this many bytes were replaced in a frame, so a
+    /// burst of them doesn't stall a frame on uploading. At least one is swapped in each frame.
+    pub max_upload_bytes_per_frame: u64,
 }
 
 ///Mipmaps will not be generated for materials found on entities that also have the `NoMipmapGeneration` component.
@@ -35,27 +79,290 @@ impl Default for MipmapGeneratorSettings {
         Self {
             // Default to 8x anisotropic filtering
             anisotropic_filtering: 8,
-            filter_type: FilterType::Triangle,
+            filter_type: MipFilter::Triangle,
             minimum_mip_resolution: 1,
+            max_memory_bytes: adaptive_memory_budget(),
+            #[cfg(not(target_arch = "wasm32"))]
+            cache: None,
+            compression: None,
+            max_texture_size: None,
+            gpu: false,
+            toksvig: false,
+            placeholder_size: Some(64),
+            max_upload_bytes_per_frame: 64 * 1024 * 1024,
         }
     }
 }
 
+/// Half of the currently available system memory.
+pub fn adaptive_memory_budget() -> u64 {
+    let mut sys = System::new();
+    sys.refresh_memory();
+    sys.available_memory() / 2
+}
+
+/// Rough peak memory needed to generate mips for an image of `image_bytes` bytes: the copy
+/// handed to the task (sized for the full mip chain), and the filter's f32 scratch buffer,
+/// which is twice the size of the level being filtered.
+pub fn estimate_task_memory(image_bytes: usize) -> u64 {
+    image_bytes as u64 * 4
+}
+
+/// Upper bound on the size of a full mip chain whose first level is `image_bytes` bytes.
+pub fn mip_chain_capacity(image_bytes: usize) -> usize {
+    image_bytes + image_bytes / 3 + 1
+}
+
+/// A copy of `image` shrunk to fit in `max_size`, with its own mips. `None` if it's already small
+/// enough that the full size image can stand in for itself.
+pub fn placeholder_texture(
+    image: &Image,
+    max_size: u32,
+    normal_map: bool,
+    settings: &MipmapGeneratorSettings,
+) -> anyhow::Result<Option<Image>> {
+    let size = image.texture_descriptor.size;
+    if size.width <= max_size && size.height <= max_size {
+        return Ok(None);
+    }
+    let layout = TexelLayout {
+        normal_map,
+        ..texel_layout(image.texture_descriptor.format)?
+    };
+    let mut placeholder = Image {
+        data: image.data.clone(),
+        texture_descriptor: image.texture_descriptor.clone(),
+        sampler: image.sampler.clone(),
+        texture_view_descriptor: image.texture_view_descriptor.clone(),
+        asset_usage: image.asset_usage,
+    };
+    downscale_texture(&mut placeholder, max_size, layout, settings.filter_type);
+    let size = placeholder.texture_descriptor.size;
+    placeholder.texture_descriptor.mip_level_count = generate_mips(
+        &mut placeholder.data,
+        size.width,
+        size.height,
+        layout,
+        settings.minimum_mip_resolution,
+        u32::MAX,
+        settings.filter_type,
+    )?;
+    Ok(Some(placeholder))
+}
+
+/// Copy an image, reserving room in the data buffer so mips can be appended without reallocating.
+pub fn clone_with_mip_capacity(image: &Image) -> Image {
+    let mut data = Vec::with_capacity(mip_chain_capacity(image.data.len()));
+    data.extend_from_slice(&image.data);
+    Image {
+        data,
+        texture_descriptor: image.texture_descriptor.clone(),
+        sampler: image.sampler.clone(),
+        texture_view_descriptor: image.texture_view_descriptor.clone(),
+        asset_usage: image.asset_usage,
+    }
+}
+
+pub const MIPMAP_FILTER_TIME: DiagnosticPath = DiagnosticPath::const_new("mipmap_filter_time");
+pub const MIPMAP_COMPRESS_TIME: DiagnosticPath = DiagnosticPath::const_new("mipmap_compress_time");
+
+/// Time spent in each stage of generating the mip chain for one image, summed over all images
+/// for the totals in [`MipmapTasks`]. Per image timings are logged at debug level.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MipTimings {
+    /// Reading a previously generated mip chain from the [`MipCache`].
+    pub cache_read: Duration,
+    pub downscale: Duration,
+    pub filter: Duration,
+    pub compress: Duration,
+    pub cache_write: Duration,
+    /// Size of the first level before any processing.
+    pub input_bytes: u64,
+    /// Size of the finished mip chain, after compression.
+    pub output_bytes: u64,
+}
+
+impl MipTimings {
+    pub fn total(&self) -> Duration {
+        self.cache_read + self.downscale + self.filter + self.compress + self.cache_write
+    }
+
+    fn add(&mut self, other: &MipTimings) {
+        self.cache_read += other.cache_read;
+        self.downscale += other.downscale;
+        self.filter += other.filter;
+        self.compress += other.compress;
+        self.cache_write += other.cache_write;
+        self.input_bytes += other.input_bytes;
+        self.output_bytes += other.output_bytes;
+    }
+}
+
+impl std::fmt::Display for MipTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cache read {:.1}ms, downscale {:.1}ms, filter {:.1}ms, compress {:.1}ms, \
+            cache write {:.1}ms, {:.1}MB -> {:.1}MB",
+            self.cache_read.as_secs_f64() * 1000.0,
+            self.downscale.as_secs_f64() * 1000.0,
+            self.filter.as_secs_f64() * 1000.0,
+            self.compress.as_secs_f64() * 1000.0,
+            self.cache_write.as_secs_f64() * 1000.0,
+            self.input_bytes as f64 / 1_000_000.0,
+            self.output_bytes as f64 / 1_000_000.0,
+        )
+    }
+}
+
 pub struct MipmapGeneratorPlugin;
 impl Plugin for MipmapGeneratorPlugin {
     fn build(&self, app: &mut App) {
         if let Some(image_plugin) = app.get_added_plugins::<ImagePlugin>().first() {
             let default_sampler = image_plugin.default_sampler.clone();
-            app.insert_resource(DefaultSampler(default_sampler))
-                .init_resource::<MipmapGeneratorSettings>();
+            app.add_plugins(GpuMipmapPlugin)
+                .insert_resource(DefaultSampler(default_sampler))
+                .init_resource::<MipmapGeneratorSettings>()
+                .register_diagnostic(
+                    Diagnostic::new(MIPMAP_FILTER_TIME)
+                        .with_max_history_length(20)
+                        .with_suffix("ms"),
+                )
+                .register_diagnostic(
+                    Diagnostic::new(MIPMAP_COMPRESS_TIME)
+                        .with_max_history_length(20)
+                        .with_suffix("ms"),
+                );
         } else {
             warn!("No ImagePlugin found. Try adding MipmapGeneratorPlugin after DefaultPlugins");
         }
     }
+
+    fn finish(&self, app: &mut App) {
+        #[cfg(target_arch = "wasm32")]
+        if let Some(mut settings) = app.world.get_resource_mut::<MipmapGeneratorSettings>() {
+            if let Some(format) = settings.compression.take() {
+                warn!("Compressing to {format:?} isn't available on the web, compression disabled");
+            }
+        }
+        let Some(features) = app
+            .world
+            .get_resource::<RenderDevice>()
+            .map(|device| device.features())
+        else {
+            return;
+        };
+        if let Some(mut settings) = app.world.get_resource_mut::<MipmapGeneratorSettings>() {
+            if let Some(format) = settings.compression {
+                if !features.contains(format.required_features()) {
+                    warn!(
+                        "{:?} textures are not supported by this GPU, compression disabled",
+                        format
+                    );
+                    settings.compression = None;
+                }
+            }
+        }
+    }
+}
+
+/// An image waiting for room in the memory budget.
+struct PendingImage<M: Material> {
+    image_h: Handle<Image>,
+    material_h: Handle<M>,
+    /// The full size image while a placeholder stands in for it, otherwise it's still in
+    /// `Assets<Image>`.
+    original: Option<Image>,
 }
 
-#[derive(Resource, Default, Deref, DerefMut)]
-pub struct MipmapTasks<M: Material + GetImages>(HashMap<Handle<Image>, (Task<Image>, Handle<M>)>);
+/// A generated mip chain waiting for room in the per frame upload budget.
+struct FinishedImage<M: Material> {
+    image_h: Handle<Image>,
+    material_h: Handle<M>,
+    image: Image,
+    timings: MipTimings,
+    task_bytes: u64,
+}
+
+/// A mip chain being generated on the task pool.
+#[cfg(not(target_arch = "wasm32"))]
+type MipTask = Task<(Image, MipTimings)>;
+/// On the web the task pool runs on the main thread and can't hand back results, so mip chains
+/// are generated right away instead. The task pool has a single thread there, so that's one
+/// image per frame.
+#[cfg(target_arch = "wasm32")]
+type MipTask = future::Ready<(Image, MipTimings)>;
+
+fn spawn_mip_task(
+    thread_pool: &AsyncComputeTaskPool,
+    generate: impl FnOnce() -> (Image, MipTimings) + Send + 'static,
+) -> MipTask {
+    #[cfg(not(target_arch = "wasm32"))]
+    return thread_pool.spawn(async move { generate() });
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = thread_pool;
+        future::ready(generate())
+    }
+}
+
+#[derive(Resource)]
+pub struct MipmapTasks<M: Material + GetImages> {
+    /// Running tasks along with their material and estimated memory footprint.
+    tasks: HashMap<Handle<Image>, (MipTask, Handle<M>, u64)>,
+    /// Images waiting for room in the memory budget.
+    pending: VecDeque<PendingImage<M>>,
+    finished: VecDeque<FinishedImage<M>>,
+    in_flight_bytes: u64,
+    /// Number of images that have finished, used for progress reporting.
+    processed: usize,
+    /// Stage timings summed over every finished image.
+    totals: MipTimings,
+    /// When the current batch of images started, for the summary logged once they all finish.
+    batch_start: Option<Instant>,
+}
+
+impl<M: Material + GetImages> Default for MipmapTasks<M> {
+    fn default() -> Self {
+        Self {
+            tasks: HashMap::new(),
+            pending: VecDeque::new(),
+            finished: VecDeque::new(),
+            in_flight_bytes: 0,
+            processed: 0,
+            totals: MipTimings::default(),
+            batch_start: None,
+        }
+    }
+}
+
+impl<M: Material + GetImages> MipmapTasks<M> {
+    /// Number of images that are queued or currently having mipmaps generated.
+    pub fn remaining(&self) -> usize {
+        self.tasks.len() + self.pending.len() + self.finished.len()
+    }
+
+    /// Number of images that have had mipmaps generated so far.
+    pub fn processed(&self) -> usize {
+        self.processed
+    }
+
+    pub fn totals(&self) -> &MipTimings {
+        &self.totals
+    }
+
+    fn contains(&self, image_h: &Handle<Image>) -> bool {
+        self.tasks.contains_key(image_h)
+            || self
+                .pending
+                .iter()
+                .any(|pending| &pending.image_h == image_h)
+            || self
+                .finished
+                .iter()
+                .any(|finished| &finished.image_h == image_h)
+    }
+}
 
 #[allow(clippy::too_many_arguments)]
 pub fn generate_mipmaps<M: Material + GetImages>(
@@ -67,10 +374,12 @@ pub fn generate_mipmaps<M: Material + GetImages>(
     default_sampler: Res<DefaultSampler>,
     settings: Res<MipmapGeneratorSettings>,
     mut tasks_res: Option<ResMut<MipmapTasks<M>>>,
+    mut diagnostics: Diagnostics,
+    gpu_queue: Option<Res<GpuMipmapQueue>>,
 ) {
-    let mut new_tasks = MipmapTasks(HashMap::new());
+    let mut new_tasks = MipmapTasks::default();
 
-    let tasks = if let Some(ref mut tasks) = tasks_res {
+    let tasks: &mut MipmapTasks<M> = if let Some(ref mut tasks) = tasks_res {
         tasks
     } else {
         &mut new_tasks
@@ -92,7 +401,7 @@ pub fn generate_mipmaps<M: Material + GetImages>(
         // and even if mipmaps aren't made, we still get the filtering
         if let Some(material) = materials.get_mut(*material_h) {
             for image_h in material.get_images().into_iter() {
-                if tasks.contains_key(image_h) {
+                if tasks.contains(image_h) {
                     continue; //There is already a task for this image
                 }
                 if let Some(image) = images.get_mut(image_h) {
@@ -102,41 +411,166 @@ pub fn generate_mipmaps<M: Material + GetImages>(
                     };
                     descriptor.anisotropy_clamp = settings.anisotropic_filtering;
                     image.sampler = ImageSampler::Descriptor(descriptor);
+                    if let Some(srgb) = material.is_color(image_h) {
+                        set_color_space(image, srgb);
+                    }
                     if image.texture_descriptor.mip_level_count == 1
                         && check_image_compatible(image).is_ok()
                     {
-                        let mut image = image.clone();
-                        let settings = settings.clone();
-                        let task = thread_pool.spawn(async move {
-                            match generate_mips_texture(&mut image, &settings.clone()) {
-                                Ok(_) => (),
-                                Err(e) => warn!("{}", e),
+                        let normal_map = material.normal_map() == Some(image_h);
+                        let on_gpu = settings.gpu
+                            && gpu_queue.is_some()
+                            && gpu_mips_supported(image.texture_descriptor.format);
+                        let placeholder = match settings.placeholder_size {
+                            Some(size) if !on_gpu && !(normal_map && settings.toksvig) => {
+                                placeholder_texture(image, size, normal_map, &settings)
+                                    .unwrap_or_else(|e| {
+                                        warn!("Failed to make a placeholder texture: {e}");
+                                        None
+                                    })
                             }
-                            image
+                            _ => None,
+                        };
+                        tasks.pending.push_back(PendingImage {
+                            image_h: image_h.clone(),
+                            material_h: Handle::Weak(*material_h),
+                            original: placeholder
+                                .map(|placeholder| std::mem::replace(image, placeholder)),
                         });
-                        tasks.insert(image_h.clone(), (task, Handle::Weak(*material_h)));
                     }
                 }
             }
         }
     }
 
-    let mut completed = Vec::new();
+    // Start queued images while they fit in the memory budget and there are threads to run them
+    while let Some(pending) = tasks.pending.front() {
+        let image = match &pending.original {
+            Some(original) => original,
+            None => match images.get(&pending.image_h) {
+                Some(image) => image,
+                None => {
+                    tasks.pending.pop_front();
+                    continue;
+                }
+            },
+        };
+        if let (true, Some(gpu_queue)) = (settings.gpu, &gpu_queue) {
+            if gpu_mips_supported(image.texture_descriptor.format) {
+                let pending = tasks.pending.pop_front().unwrap();
+                if let Some(image) = images.get_mut(&pending.image_h) {
+                    allocate_gpu_mip_chain(image, settings.minimum_mip_resolution);
+                    gpu_queue.push(pending.image_h.id());
+                }
+                let _ = materials.get_mut(&pending.material_h);
+                tasks.processed += 1;
+                continue;
+            }
+        }
+        let task_bytes = estimate_task_memory(image.data.len());
+        if !tasks.tasks.is_empty()
+            && (tasks.in_flight_bytes + task_bytes > settings.max_memory_bytes
+                || tasks.tasks.len() >= thread_pool.thread_num())
+        {
+            break;
+        }
+        let pending = tasks.pending.pop_front().unwrap();
+        let mut image = match pending.original {
+            Some(mut original) => {
+                let capacity = mip_chain_capacity(original.data.len());
+                original.data.reserve_exact(capacity - original.data.len());
+                original
+            }
+            None => match images.get(&pending.image_h) {
+                Some(image) => clone_with_mip_capacity(image),
+                None => continue,
+            },
+        };
+        let role = materials
+            .get(&pending.material_h)
+            .map(|material| mip_role(material, &pending.image_h, &images, settings.toksvig))
+            .unwrap_or_default();
+        let settings = settings.clone();
+        let task = spawn_mip_task(thread_pool, move || {
+            let timings = match generate_mips_texture(&mut image, &settings, &role) {
+                Ok(timings) => timings,
+                Err(e) => {
+                    warn!("{}", e);
+                    MipTimings::default()
+                }
+            };
+            (image, timings)
+        });
+        tasks.batch_start.get_or_insert_with(Instant::now);
+        tasks.in_flight_bytes += task_bytes;
+        tasks
+            .tasks
+            .insert(pending.image_h, (task, pending.material_h, task_bytes));
+    }
 
-    for (image_h, inner) in tasks.iter_mut() {
+    let mut completed = Vec::new();
+    for (image_h, inner) in tasks.tasks.iter_mut() {
         // TODO couldn't get &mut in destructure to work correctly for (task, material_h)
-        if let Some(new_image) = future::block_on(future::poll_once(&mut inner.0)) {
-            if let Some(image) = images.get_mut(image_h) {
-                *image = new_image;
-            }
-            // Touch material to trigger change detection
-            let _ = materials.get_mut(&inner.1);
-            completed.push(image_h.clone());
+        if let Some((image, timings)) = future::block_on(future::poll_once(&mut inner.0)) {
+            completed.push((image_h.clone(), image, timings));
+        }
+    }
+    for (image_h, image, timings) in completed {
+        if let Some((_, material_h, task_bytes)) = tasks.tasks.remove(&image_h) {
+            tasks.finished.push_back(FinishedImage {
+                image_h,
+                material_h,
+                image,
+                timings,
+                task_bytes,
+            });
         }
     }
 
-    for image_h in completed {
-        tasks.remove(&image_h);
+    // Swap finished mip chains in, spreading the uploads over several frames
+    let mut uploaded_bytes = 0;
+    while let Some(finished) = tasks.finished.front() {
+        if uploaded_bytes > 0
+            && uploaded_bytes + finished.image.data.len() as u64
+                > settings.max_upload_bytes_per_frame
+        {
+            break;
+        }
+        let finished = tasks.finished.pop_front().unwrap();
+        uploaded_bytes += finished.image.data.len() as u64;
+        if let Some(image) = images.get_mut(&finished.image_h) {
+            *image = finished.image;
+        }
+        // Touch material to trigger change detection
+        let _ = materials.get_mut(&finished.material_h);
+        let timings = finished.timings;
+        debug!(
+            "Generated mipmaps for {:?}: {}",
+            finished.image_h.path(),
+            timings
+        );
+        tasks.in_flight_bytes -= finished.task_bytes;
+        tasks.processed += 1;
+        tasks.totals.add(&timings);
+        diagnostics.add_measurement(&MIPMAP_FILTER_TIME, || {
+            timings.filter.as_secs_f64() * 1000.0
+        });
+        if settings.compression.is_some() {
+            diagnostics.add_measurement(&MIPMAP_COMPRESS_TIME, || {
+                timings.compress.as_secs_f64() * 1000.0
+            });
+        }
+    }
+
+    if tasks.remaining() == 0 {
+        if let Some(start) = tasks.batch_start.take() {
+            info!(
+                "Generated mipmaps for {} images in {:.1}s. Time summed over all threads: {}",
+                tasks.processed,
+                start.elapsed().as_secs_f32(),
+                tasks.totals
+            );
+        }
     }
 
     if tasks_res.is_none() {
@@ -144,53 +578,245 @@ pub fn generate_mipmaps<M: Material + GetImages>(
     }
 }
 
+/// What a texture is used for by its material, which changes how its mips are filtered.
+#[derive(Clone, Default)]
+pub enum MipRole {
+    #[default]
+    Other,
+    /// Texels are renormalized after each downsample.
+    NormalMap,
+    /// Roughness is widened by the variance of the material's normal map, see [`apply_toksvig`].
+    MetallicRoughness(NormalMapLevel),
+}
+
+impl MipRole {
+    /// Format to compress the texture to when the others are compressed to `format`. Bevy
+    /// rebuilds the Z of two channel normal maps when the material is prepared.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn compression(&self, format: CompressionFormat) -> CompressionFormat {
+        match self {
+            MipRole::NormalMap => format.for_normal_map(),
+            _ => format,
+        }
+    }
+}
+
+fn mip_role<M: GetImages>(
+    material: &M,
+    image_h: &Handle<Image>,
+    images: &Assets<Image>,
+    toksvig: bool,
+) -> MipRole {
+    if material.normal_map() == Some(image_h) {
+        return MipRole::NormalMap;
+    }
+    if toksvig && material.metallic_roughness() == Some(image_h) {
+        // Normal maps that were already compressed can't be read back
+        let normal_map = material
+            .normal_map()
+            .and_then(|normal_h| images.get(normal_h))
+            .and_then(|normal_map| {
+                let layout = texel_layout(normal_map.texture_descriptor.format).ok()?;
+                let size = normal_map.texture_descriptor.size;
+                let len = size.width as usize * size.height as usize * layout.channels;
+                Some(NormalMapLevel {
+                    data: normal_map.data.get(..len)?.to_vec(),
+                    width: size.width,
+                    height: size.height,
+                    channels: layout.channels,
+                })
+            });
+        if let Some(normal_map) = normal_map {
+            return MipRole::MetallicRoughness(normal_map);
+        }
+    }
+    MipRole::Other
+}
+
 pub fn generate_mips_texture(
     image: &mut Image,
     settings: &MipmapGeneratorSettings,
-) -> anyhow::Result<()> {
+    role: &MipRole,
+) -> anyhow::Result<MipTimings> {
+    let _span = info_span!("generate_mips_texture").entered();
     check_image_compatible(image)?;
-    match try_into_dynamic(image.clone()) {
-        Ok(mut dyn_image) => {
-            let (mip_level_count, image_data) = generate_mips(
-                &mut dyn_image,
-                settings.minimum_mip_resolution,
-                u32::MAX,
-                settings.filter_type,
-            );
-            image.texture_descriptor.mip_level_count = mip_level_count;
-            image.data = image_data;
-            Ok(())
+    let mut timings = MipTimings {
+        input_bytes: image.data.len() as u64,
+        ..default()
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    let cache_key = settings
+        .cache
+        .as_ref()
+        .map(|_| MipCache::key(image, settings, role));
+    #[cfg(not(target_arch = "wasm32"))]
+    if let (Some(cache), Some(key)) = (&settings.cache, cache_key) {
+        let start = Instant::now();
+        let cached = cache.get(key);
+        timings.cache_read = start.elapsed();
+        if let Some(cached) = cached {
+            let size = &mut image.texture_descriptor.size;
+            (size.width, size.height) =
+                fit_texture_size(size.width, size.height, settings.max_texture_size);
+            image.texture_descriptor.mip_level_count = cached.mip_level_count;
+            if let (true, Some(format)) = (cached.compressed, settings.compression) {
+                let srgb = image.texture_descriptor.format == TextureFormat::Rgba8UnormSrgb;
+                image.texture_descriptor.format = role.compression(format).texture_format(srgb);
+            }
+            image.data = cached.data;
+            timings.output_bytes = image.data.len() as u64;
+            return Ok(timings);
         }
-        Err(e) => Err(e),
     }
+    let layout = TexelLayout {
+        normal_map: matches!(role, MipRole::NormalMap),
+        ..texel_layout(image.texture_descriptor.format)?
+    };
+    if let Some(max_size) = settings.max_texture_size {
+        let start = Instant::now();
+        downscale_texture(image, max_size, layout, settings.filter_type);
+        timings.downscale = start.elapsed();
+    }
+    let start = Instant::now();
+    let filter_span = info_span!("filter_mips").entered();
+    let size = image.texture_descriptor.size;
+    let mip_level_count = generate_mips(
+        &mut image.data,
+        size.width,
+        size.height,
+        layout,
+        settings.minimum_mip_resolution,
+        u32::MAX,
+        settings.filter_type,
+    )?;
+    if let MipRole::MetallicRoughness(normal_map) = role {
+        apply_toksvig(
+            &mut image.data,
+            size.width,
+            size.height,
+            layout,
+            mip_level_count,
+            normal_map,
+            settings.filter_type,
+        );
+    }
+    image.texture_descriptor.mip_level_count = mip_level_count;
+    timings.filter = start.elapsed();
+    drop(filter_span);
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut compressed = false;
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(format) = settings.compression {
+        let _span = info_span!("compress_mips").entered();
+        let start = Instant::now();
+        match compress_mips(image, role.compression(format), false) {
+            Ok(_) => compressed = true,
+            Err(e) => warn!("{}", e),
+        }
+        timings.compress = start.elapsed();
+    }
+    timings.output_bytes = image.data.len() as u64;
+    #[cfg(not(target_arch = "wasm32"))]
+    if let (Some(cache), Some(key)) = (&settings.cache, cache_key) {
+        let start = Instant::now();
+        let cached = CachedMips {
+            mip_level_count,
+            compressed,
+            data: std::mem::take(&mut image.data),
+        };
+        if let Err(e) = cache.put(key, &cached) {
+            warn!("Failed to write mipmap cache: {}", e);
+        }
+        image.data = cached.data;
+        timings.cache_write = start.elapsed();
+    }
+    Ok(timings)
 }
 
-/// Returns the number of mip levels, and a vec of bytes containing the image data.
+/// Size of a `width` x `height` texture after halving it until it fits in `max_size`.
+pub fn fit_texture_size(mut width: u32, mut height: u32, max_size: Option<u32>) -> (u32, u32) {
+    if let Some(max_size) = max_size {
+        while width.max(height) > max_size.max(1) {
+            width = (width / 2).max(1);
+            height = (height / 2).max(1);
+        }
+    }
+    (width, height)
+}
+
+/// Shrinks the first level of `image` to fit in `max_size`. Only valid before mips are generated.
+pub fn downscale_texture(
+    image: &mut Image,
+    max_size: u32,
+    layout: TexelLayout,
+    filter_type: MipFilter,
+) {
+    let size = &mut image.texture_descriptor.size;
+    let (width, height) = fit_texture_size(size.width, size.height, Some(max_size));
+    while (size.width, size.height) != (width, height) {
+        image.data = downsample_half(&image.data, size.width, size.height, layout, filter_type);
+        size.width = (size.width / 2).max(1);
+        size.height = (size.height / 2).max(1);
+    }
+}
+
+/// How the texels of a format are filtered, color formats are filtered in linear space.
+pub fn texel_layout(format: TextureFormat) -> anyhow::Result<TexelLayout> {
+    let (channels, srgb) = match format {
+        TextureFormat::R8Unorm => (1, false),
+        TextureFormat::Rg8Unorm => (2, false),
+        TextureFormat::Rgba8Unorm => (4, false),
+        TextureFormat::Rgba8UnormSrgb => (4, true),
+        texture_format => {
+            return Err(anyhow!(
+                "Mipmap generation not supported for {:?}.",
+                texture_format
+            ))
+        }
+    };
+    Ok(TexelLayout {
+        channels,
+        srgb,
+        normal_map: false,
+    })
+}
+
+/// Appends a mip chain to `data`, which must start out holding a single `width` x `height` level
+/// of texels. Each level is filtered straight from the previous level in `data`, so the only
+/// extra allocations alive at a time are the filter's scratch buffer and the level being produced.
+/// Returns the number of mip levels.
 /// The `max_mip_count` includes the first input mip level. So setting this to 2 will
 /// result in a single additional mip level being generated, for a total of 2 levels.
 pub fn generate_mips(
-    dyn_image: &mut DynamicImage,
+    data: &mut Vec<u8>,
+    mut width: u32,
+    mut height: u32,
+    layout: TexelLayout,
     minimum_mip_resolution: u32,
     max_mip_count: u32,
-    filter_type: FilterType,
-) -> (u32, Vec<u8>) {
-    let mut image_data = dyn_image.as_bytes().to_vec();
+    filter_type: MipFilter,
+) -> anyhow::Result<u32> {
+    data.reserve(mip_chain_capacity(data.len()) - data.len());
     let mut mip_level_count = 1;
-    let mut width = dyn_image.width();
-    let mut height = dyn_image.height();
+    let mut level_offset = 0;
 
     while width / 2 >= minimum_mip_resolution.max(1)
         && height / 2 >= minimum_mip_resolution.max(1)
         && mip_level_count < max_mip_count
     {
+        let level_len = width as usize * height as usize * layout.channels;
+        let level = data
+            .get(level_offset..level_offset + level_len)
+            .ok_or_else(|| anyhow!("Mip level {mip_level_count} has an unexpected size."))?;
+        let next_level = downsample_half(level, width, height, layout, filter_type);
+        data.extend_from_slice(&next_level);
+        level_offset += level_len;
         width /= 2;
         height /= 2;
-        *dyn_image = dyn_image.resize_exact(width, height, filter_type);
-        image_data.append(&mut dyn_image.as_bytes().to_vec());
         mip_level_count += 1;
     }
 
-    (mip_level_count, image_data)
+    Ok(mip_level_count)
 }
 
 /// Extract a specific individual mip level as a new image.
@@ -268,6 +894,22 @@ pub fn check_image_compatible(image: &Image) -> anyhow::Result<()> {
 // Implement the GetImages trait for any materials that need conversion
 pub trait GetImages {
     fn get_images(&self) -> Vec<&Handle<Image>>;
+
+    /// Tangent space normal map, its mips are renormalized.
+    fn normal_map(&self) -> Option<&Handle<Image>> {
+        None
+    }
+
+    /// glTF style metallic-roughness texture, with roughness in the green channel.
+    fn metallic_roughness(&self) -> Option<&Handle<Image>> {
+        None
+    }
+
+    /// Whether the material uses a texture as a color, which is stored as sRGB, rather than
+    /// data, which is linear. `None` leaves the texture's format alone.
+    fn is_color(&self, _image_h: &Handle<Image>) -> Option<bool> {
+        None
+    }
 }
 
 impl GetImages for StandardMaterial {
@@ -283,8 +925,40 @@ impl GetImages for StandardMaterial {
         .flatten()
         .collect()
     }
+
+    fn normal_map(&self) -> Option<&Handle<Image>> {
+        self.normal_map_texture.as_ref()
+    }
+
+    fn metallic_roughness(&self) -> Option<&Handle<Image>> {
+        self.metallic_roughness_texture.as_ref()
+    }
+
+    fn is_color(&self, image_h: &Handle<Image>) -> Option<bool> {
+        Some(
+            self.base_color_texture.as_ref() == Some(image_h)
+                || self.emissive_texture.as_ref() == Some(image_h),
+        )
+    }
 }
 
+/// Tags a texture as sRGB or linear, whatever its file or the loader said. Only the format
+/// changes, the texels were authored in the space the material reads them in. Textures
+/// converted before their role was checked can have sRGB roughness, which washes it out.
+fn set_color_space(image: &mut Image, srgb: bool) {
+    let format = image.texture_descriptor.format;
+    let tagged = if srgb {
+        format.add_srgb_suffix()
+    } else {
+        format.remove_srgb_suffix()
+    };
+    if tagged != format {
+        debug!("Changing texture format from {:?} to {:?}", format, tagged);
+        image.texture_descriptor.format = tagged;
+    }
+}
+
+#[allow(dead_code)]
 pub fn try_into_dynamic(image: Image) -> anyhow::Result<DynamicImage> {
     match image.texture_descriptor.format {
         TextureFormat::R8Unorm => ImageBuffer::from_raw(
@@ -0,0 +1,443 @@
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+
+use crate::camera_controller::{
+    cut_to, CameraPath, CameraPathPlayback, PathControlPoint, PathEase, SmoothCuts,
+};
+
+/// Seconds between the keyframes baked from the control points.
+const BAKE_INTERVAL: f32 = 1.0 / 30.0;
+/// Range of the speed slider in meters per second.
+const MIN_SPEED: f32 = 0.1;
+const MAX_SPEED: f32 = 10.0;
+
+/// Control points of the camera path being edited, shown/hidden with F10. Saved to `output` as
+/// a [`CameraPath`] that `--camera-path` plays back.
+#[derive(Resource)]
+pub struct PathEditor {
+    pub points: Vec<PathControlPoint>,
+    pub selected: usize,
+    pub visible: bool,
+    pub output: String,
+}
+
+impl PathEditor {
+    /// Opens `output` for editing, picking up the control points if the editor saved it.
+    pub fn load(output: &str) -> Self {
+        let points = match CameraPath::load(output) {
+            Ok(path) if path.control_points.is_empty() && !path.keyframes.is_empty() => {
+                println!("{output} wasn't made in the path editor, starting a new path");
+                Vec::new()
+            }
+            Ok(path) => path.control_points,
+            Err(_) => Vec::new(),
+        };
+        Self {
+            points,
+            selected: 0,
+            visible: true,
+            output: output.to_string(),
+        }
+    }
+
+    fn path(&self) -> CameraPath {
+        CameraPath::from_control_points(&self.points, BAKE_INTERVAL)
+    }
+}
+
+impl Default for PathEditor {
+    fn default() -> Self {
+        Self {
+            points: Vec::new(),
+            selected: 0,
+            visible: false,
+            output: String::from("camera_path.ron"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PathEditorAction {
+    Select(isize),
+    /// Inserts a point at the camera after the selected one.
+    Add,
+    /// Moves the selected point to the camera.
+    Move,
+    Delete,
+    Reorder(isize),
+    /// Moves the camera to the selected point.
+    GoTo,
+    CycleEase,
+    Preview,
+    Save,
+}
+
+/// Root node of the path editor.
+#[derive(Component)]
+pub struct PathEditorPanel;
+
+#[derive(Component)]
+pub struct PathEditorButton(PathEditorAction);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PathEditorText {
+    Header,
+    Speed,
+    Ease,
+    Preview,
+}
+
+#[derive(Component)]
+pub struct PathEditorLabel(PathEditorText);
+
+/// Drag along this node to set the selected point's speed.
+#[derive(Component)]
+pub struct PathSpeedSlider;
+
+#[derive(Component)]
+pub struct PathSpeedSliderFill;
+
+/// Place, reorder, and preview the control points of a camera path, drawn with gizmos.
+pub struct PathEditorPlugin;
+impl Plugin for PathEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PathEditor>()
+            .add_systems(Startup, spawn_path_editor)
+            .add_systems(
+                Update,
+                (
+                    toggle_path_editor,
+                    path_editor_buttons,
+                    drag_speed_slider,
+                    update_path_editor,
+                    draw_path_gizmos,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn text_style() -> TextStyle {
+    TextStyle {
+        font_size: 16.0,
+        color: Color::WHITE,
+        ..default()
+    }
+}
+
+fn spawn_button(parent: &mut ChildBuilder, text: &str, action: PathEditorAction) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.25, 0.25, 0.25, 0.9).into(),
+                ..default()
+            },
+            PathEditorButton(action),
+        ))
+        .with_children(|button| {
+            let text = TextBundle::from_section(text, text_style());
+            // Buttons with changing text get it from update_path_editor
+            match action {
+                PathEditorAction::CycleEase => {
+                    button.spawn((text, PathEditorLabel(PathEditorText::Ease)));
+                }
+                PathEditorAction::Preview => {
+                    button.spawn((text, PathEditorLabel(PathEditorText::Preview)));
+                }
+                _ => {
+                    button.spawn(text);
+                }
+            }
+        });
+}
+
+fn spawn_row(parent: &mut ChildBuilder, children: impl FnOnce(&mut ChildBuilder)) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                column_gap: Val::Px(8.0),
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(children);
+}
+
+fn spawn_path_editor(mut commands: Commands, editor: Res<PathEditor>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                visibility: if editor.visible {
+                    Visibility::Visible
+                } else {
+                    Visibility::Hidden
+                },
+                ..default()
+            },
+            PathEditorPanel,
+        ))
+        .with_children(|panel| {
+            spawn_row(panel, |row| {
+                spawn_button(row, "<", PathEditorAction::Select(-1));
+                row.spawn((
+                    TextBundle::from_section("", text_style()),
+                    PathEditorLabel(PathEditorText::Header),
+                ));
+                spawn_button(row, ">", PathEditorAction::Select(1));
+            });
+            spawn_row(panel, |row| {
+                spawn_button(row, "Add", PathEditorAction::Add);
+                spawn_button(row, "Move here", PathEditorAction::Move);
+                spawn_button(row, "Delete", PathEditorAction::Delete);
+            });
+            spawn_row(panel, |row| {
+                spawn_button(row, "Earlier", PathEditorAction::Reorder(-1));
+                spawn_button(row, "Later", PathEditorAction::Reorder(1));
+                spawn_button(row, "Go to", PathEditorAction::GoTo);
+            });
+            spawn_row(panel, |row| {
+                row.spawn((
+                    TextBundle::from_section("", text_style()).with_style(Style {
+                        width: Val::Px(110.0),
+                        ..default()
+                    }),
+                    PathEditorLabel(PathEditorText::Speed),
+                ));
+                row.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(150.0),
+                            height: Val::Px(14.0),
+                            ..default()
+                        },
+                        background_color: Color::rgba(0.25, 0.25, 0.25, 0.9).into(),
+                        ..default()
+                    },
+                    RelativeCursorPosition::default(),
+                    PathSpeedSlider,
+                ))
+                .with_children(|slider| {
+                    slider.spawn((
+                        NodeBundle {
+                            style: Style {
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::rgb(0.8, 0.6, 0.2).into(),
+                            ..default()
+                        },
+                        PathSpeedSliderFill,
+                    ));
+                });
+            });
+            spawn_row(panel, |row| {
+                spawn_button(row, "", PathEditorAction::CycleEase);
+                spawn_button(row, "", PathEditorAction::Preview);
+                spawn_button(row, "Save", PathEditorAction::Save);
+            });
+        });
+}
+
+fn toggle_path_editor(
+    input: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<PathEditor>,
+    mut panel: Query<&mut Visibility, With<PathEditorPanel>>,
+) {
+    if !input.just_pressed(KeyCode::F10) {
+        return;
+    }
+    editor.visible = !editor.visible;
+    for mut visibility in &mut panel {
+        *visibility = if editor.visible {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn path_editor_buttons(
+    mut commands: Commands,
+    buttons: Query<(&Interaction, &PathEditorButton), Changed<Interaction>>,
+    mut editor: ResMut<PathEditor>,
+    mut camera: Query<(Entity, &mut Transform), With<Camera3d>>,
+    playback: Option<Res<CameraPathPlayback>>,
+    smooth_cuts: Option<Res<SmoothCuts>>,
+) {
+    let Ok((entity, mut transform)) = camera.get_single_mut() else {
+        return;
+    };
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let count = editor.points.len();
+        let selected = editor.selected;
+        match button.0 {
+            PathEditorAction::Select(step) => {
+                if count > 0 {
+                    editor.selected =
+                        (selected as isize + step).rem_euclid(count as isize) as usize;
+                }
+            }
+            PathEditorAction::Add => {
+                // New points keep the speed and easing of the one before them
+                let mut point = PathControlPoint::new(&transform);
+                if let Some(previous) = editor.points.get(selected) {
+                    point.speed = previous.speed;
+                    point.ease = previous.ease;
+                }
+                let index = if count == 0 { 0 } else { selected + 1 };
+                editor.points.insert(index, point);
+                editor.selected = index;
+            }
+            PathEditorAction::Move => {
+                if let Some(point) = editor.points.get_mut(selected) {
+                    point.translation = transform.translation.to_array();
+                    point.rotation = transform.rotation.to_array();
+                }
+            }
+            PathEditorAction::Delete => {
+                if selected < count {
+                    editor.points.remove(selected);
+                    editor.selected = selected.min(count.saturating_sub(2));
+                }
+            }
+            PathEditorAction::Reorder(step) => {
+                let target = selected as isize + step;
+                if selected < count && (0..count as isize).contains(&target) {
+                    editor.points.swap(selected, target as usize);
+                    editor.selected = target as usize;
+                }
+            }
+            PathEditorAction::GoTo => {
+                if let Some(point) = editor.points.get(selected) {
+                    cut_to(
+                        &mut commands,
+                        entity,
+                        &mut transform,
+                        point.transform(),
+                        smooth_cuts.as_deref(),
+                    );
+                }
+            }
+            PathEditorAction::CycleEase => {
+                if let Some(point) = editor.points.get_mut(selected) {
+                    let current = PathEase::ALL.iter().position(|e| *e == point.ease);
+                    point.ease = PathEase::ALL[current.map_or(0, |i| i + 1) % PathEase::ALL.len()];
+                }
+            }
+            PathEditorAction::Preview => {
+                if playback.is_some() {
+                    commands.remove_resource::<CameraPathPlayback>();
+                } else if count > 1 {
+                    let mut preview = CameraPathPlayback::new(editor.path());
+                    preview.looping = false;
+                    commands.insert_resource(preview);
+                }
+            }
+            PathEditorAction::Save => {
+                let path = editor.path();
+                match path.save(&editor.output) {
+                    Ok(_) => info!(
+                        "Saved camera path with {} points, {:.1}s long, to {}",
+                        editor.points.len(),
+                        path.duration(),
+                        editor.output
+                    ),
+                    Err(e) => error!("Failed to save camera path to {}: {}", editor.output, e),
+                }
+            }
+        }
+    }
+}
+
+fn drag_speed_slider(
+    sliders: Query<(&Interaction, &RelativeCursorPosition), With<PathSpeedSlider>>,
+    mut editor: ResMut<PathEditor>,
+) {
+    let selected = editor.selected;
+    for (interaction, cursor) in &sliders {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(cursor) = cursor.normalized else {
+            continue;
+        };
+        let speed = MIN_SPEED + cursor.x.clamp(0.0, 1.0) * (MAX_SPEED - MIN_SPEED);
+        if let Some(point) = editor.points.get_mut(selected) {
+            point.speed = speed;
+        }
+    }
+}
+
+fn update_path_editor(
+    editor: Res<PathEditor>,
+    playback: Option<Res<CameraPathPlayback>>,
+    mut labels: Query<(&mut Text, &PathEditorLabel)>,
+    mut fills: Query<&mut Style, With<PathSpeedSliderFill>>,
+) {
+    if !editor.visible {
+        return;
+    }
+    let point = editor.points.get(editor.selected);
+    for (mut text, label) in &mut labels {
+        text.sections[0].value = match (label.0, point) {
+            (PathEditorText::Header, Some(_)) => {
+                format!("Point {}/{}", editor.selected + 1, editor.points.len())
+            }
+            (PathEditorText::Header, None) => String::from("No points, add one"),
+            (PathEditorText::Speed, Some(point)) => format!("Speed: {:.1}m/s", point.speed),
+            (PathEditorText::Ease, Some(point)) => format!("Ease: {:?}", point.ease),
+            (PathEditorText::Preview, _) if playback.is_some() => String::from("Stop"),
+            (PathEditorText::Preview, _) => String::from("Preview"),
+            (_, None) => String::new(),
+        };
+    }
+    for mut style in &mut fills {
+        let speed = point.map_or(MIN_SPEED, |point| point.speed);
+        style.width = Val::Percent((speed - MIN_SPEED) / (MAX_SPEED - MIN_SPEED) * 100.0);
+    }
+}
+
+/// Draws the baked path, its control points, and which way each one looks.
+fn draw_path_gizmos(editor: Res<PathEditor>, mut gizmos: Gizmos) {
+    if !editor.visible || editor.points.is_empty() {
+        return;
+    }
+    let path = editor.path();
+    gizmos.linestrip(
+        path.keyframes
+            .iter()
+            .map(|k| Vec3::from_array(k.translation)),
+        Color::CYAN,
+    );
+    for (index, point) in editor.points.iter().enumerate() {
+        let transform = point.transform();
+        let color = if index == editor.selected {
+            Color::YELLOW
+        } else {
+            Color::WHITE
+        };
+        gizmos.sphere(transform.translation, Quat::IDENTITY, 0.1, color);
+        gizmos.arrow(
+            transform.translation,
+            transform.translation + transform.forward() * 0.5,
+            color,
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+        primitives::Aabb,
+    },
+};
+
+/// Meshes that can be hit by [`pick_mesh`].
+pub type PickableMeshes<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Handle<Mesh>,
+        &'static Aabb,
+        &'static GlobalTransform,
+        &'static ViewVisibility,
+    ),
+>;
+
+/// A mesh hit by a ray.
+#[derive(Clone, Copy, Debug)]
+pub struct MeshHit {
+    pub entity: Entity,
+    /// Distance along the ray, in units of its direction.
+    pub distance: f32,
+    /// Vertex indices of the triangle that was hit.
+    pub triangle: [usize; 3],
+}
+
+/// Closest visible mesh hit by the ray, tested triangle by triangle on the CPU. Fine for picking
+/// once a frame, the bounding boxes skip most meshes.
+pub fn pick_mesh(
+    origin: Vec3,
+    direction: Vec3,
+    meshes: &PickableMeshes,
+    mesh_assets: &Assets<Mesh>,
+) -> Option<MeshHit> {
+    let mut closest: Option<MeshHit> = None;
+    for (entity, handle, aabb, transform, visibility) in meshes {
+        if !visibility.get() {
+            continue;
+        }
+        let Some(mesh) = mesh_assets.get(handle) else {
+            continue;
+        };
+        // Intersect in the mesh's space, distances along the ray stay comparable since the
+        // direction isn't normalized again
+        let inverse = transform.affine().inverse();
+        let local_origin = inverse.transform_point3(origin);
+        let local_direction = inverse.transform_vector3(direction);
+        let Some(entry) = ray_aabb(local_origin, local_direction, aabb) else {
+            continue;
+        };
+        if closest.is_some_and(|hit| hit.distance < entry) {
+            continue;
+        }
+        if let Some((distance, triangle)) = ray_mesh(local_origin, local_direction, mesh) {
+            if closest.map_or(true, |hit| distance < hit.distance) {
+                closest = Some(MeshHit {
+                    entity,
+                    distance,
+                    triangle,
+                });
+            }
+        }
+    }
+    closest
+}
+
+/// Distance along the ray to where it enters the box, `None` if it misses.
+fn ray_aabb(origin: Vec3, direction: Vec3, aabb: &Aabb) -> Option<f32> {
+    let min = Vec3::from(aabb.min());
+    let max = Vec3::from(aabb.max());
+    let inverse = direction.recip();
+    let t0 = (min - origin) * inverse;
+    let t1 = (max - origin) * inverse;
+    let near = t0.min(t1).max_element();
+    let far = t0.max(t1).min_element();
+    (far >= near.max(0.0)).then_some(near.max(0.0))
+}
+
+/// Closest triangle hit by the ray and the distance along it.
+fn ray_mesh(origin: Vec3, direction: Vec3, mesh: &Mesh) -> Option<(f32, [usize; 3])> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    let positions = mesh
+        .attribute(Mesh::ATTRIBUTE_POSITION)
+        .and_then(VertexAttributeValues::as_float3)?;
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|i| *i as usize).collect(),
+        Some(Indices::U32(indices)) => indices.iter().map(|i| *i as usize).collect(),
+        None => (0..positions.len()).collect(),
+    };
+    let mut closest: Option<(f32, [usize; 3])> = None;
+    for triangle in indices.chunks_exact(3) {
+        let triangle = [triangle[0], triangle[1], triangle[2]];
+        let [a, b, c] = triangle.map(|i| Vec3::from(positions[i]));
+        if let Some(distance) = ray_triangle(origin, direction, a, b, c) {
+            if closest.map_or(true, |(closest, _)| distance < closest) {
+                closest = Some((distance, triangle));
+            }
+        }
+    }
+    closest
+}
+
+/// Möller–Trumbore, both sides of the triangle count.
+fn ray_triangle(origin: Vec3, direction: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+    let ab = b - a;
+    let ac = c - a;
+    let p = direction.cross(ac);
+    let det = ab.dot(p);
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let inverse_det = 1.0 / det;
+    let to_origin = origin - a;
+    let u = to_origin.dot(p) * inverse_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = to_origin.cross(ab);
+    let v = direction.dot(q) * inverse_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = ac.dot(q) * inverse_det;
+    (distance > 0.0).then_some(distance)
+}
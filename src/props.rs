@@ -0,0 +1,148 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+#[cfg(feature = "physics")]
+use bevy_rapier3d::prelude::{Collider, RigidBody};
+
+use crate::loading::SceneReady;
+#[cfg(feature = "physics")]
+use crate::walk::SceneColliders;
+
+/// Region of the Sponza atrium the props are scattered through.
+const PROPS_MIN: Vec3 = Vec3::new(-9.0, 0.5, -3.0);
+const PROPS_MAX: Vec3 = Vec3::new(9.0, 8.0, 3.0);
+const PROP_SIZE: f32 = 0.4;
+const MATERIAL_COUNT: usize = 8;
+
+/// Scatters dynamic spheres and cubes through the scene, see `--props`. The static scene never
+/// moves, so these add per-frame transform updates and more shadow casters on top of it.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Props {
+    pub count: usize,
+    /// Let the props fall and collide with the scene instead of bobbing in place, needs the
+    /// `physics` feature.
+    pub fall: bool,
+}
+
+/// A prop and where it bobs around.
+#[derive(Component)]
+pub struct Prop {
+    origin: Vec3,
+    phase: f32,
+}
+
+pub struct PropsPlugin;
+impl Plugin for PropsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (spawn_props, animate_props));
+    }
+}
+
+/// Small deterministic generator so every run scatters the props the same way, also used by
+/// `--many-lights`.
+pub fn next_random(state: &mut u32) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    (*state as f32) / (u32::MAX as f32)
+}
+
+fn spawn_props(
+    mut commands: Commands,
+    props: Option<Res<Props>>,
+    scene_ready: Res<SceneReady>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    #[cfg(feature = "physics")] mut colliders: ResMut<SceneColliders>,
+    mut spawned: Local<bool>,
+) {
+    let Some(props) = props else {
+        return;
+    };
+    if *spawned || !scene_ready.ready {
+        return;
+    }
+    // Wait for the floor before dropping anything on it
+    #[cfg(feature = "physics")]
+    if props.fall {
+        colliders.wanted = true;
+        if !colliders.built {
+            return;
+        }
+    }
+    *spawned = true;
+
+    let sphere = meshes.add(Sphere::new(PROP_SIZE * 0.5).mesh().uv(32, 18));
+    let cube = meshes.add(Cuboid::new(PROP_SIZE, PROP_SIZE, PROP_SIZE));
+    let mut state = 0x2545_f491;
+    let prop_materials: Vec<_> = (0..MATERIAL_COUNT)
+        .map(|i| {
+            materials.add(StandardMaterial {
+                base_color: Color::hsl(i as f32 / MATERIAL_COUNT as f32 * 360.0, 0.6, 0.5),
+                metallic: if i % 2 == 0 { 1.0 } else { 0.0 },
+                perceptual_roughness: 0.2 + next_random(&mut state) * 0.6,
+                ..default()
+            })
+        })
+        .collect();
+
+    for i in 0..props.count {
+        let origin = PROPS_MIN
+            + Vec3::new(
+                next_random(&mut state),
+                next_random(&mut state),
+                next_random(&mut state),
+            ) * (PROPS_MAX - PROPS_MIN);
+        let is_sphere = i % 2 == 0;
+        #[cfg_attr(not(feature = "physics"), allow(unused_variables))]
+        let prop = commands
+            .spawn((
+                PbrBundle {
+                    mesh: if is_sphere {
+                        sphere.clone()
+                    } else {
+                        cube.clone()
+                    },
+                    material: prop_materials[i % MATERIAL_COUNT].clone(),
+                    transform: Transform::from_translation(origin),
+                    ..default()
+                },
+                Prop {
+                    origin,
+                    phase: next_random(&mut state) * TAU,
+                },
+            ))
+            .id();
+        #[cfg(feature = "physics")]
+        if props.fall {
+            commands.entity(prop).insert((
+                RigidBody::Dynamic,
+                if is_sphere {
+                    Collider::ball(PROP_SIZE * 0.5)
+                } else {
+                    Collider::cuboid(PROP_SIZE * 0.5, PROP_SIZE * 0.5, PROP_SIZE * 0.5)
+                },
+            ));
+        }
+    }
+    info!("Spawned {} props", props.count);
+}
+
+/// Without physics the props spin and bob, so their transforms still change every frame.
+fn animate_props(
+    time: Res<Time>,
+    props: Option<Res<Props>>,
+    mut query: Query<(&mut Transform, &Prop)>,
+) {
+    let Some(props) = props else {
+        return;
+    };
+    if props.fall {
+        return;
+    }
+    let t = time.elapsed_seconds();
+    for (mut transform, prop) in &mut query {
+        transform.translation = prop.origin + Vec3::Y * (t + prop.phase).sin() * 0.3;
+        transform.rotation = Quat::from_rotation_y(t + prop.phase);
+    }
+}
@@ -0,0 +1,187 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+use bevy::{
+    prelude::*,
+    render::renderer::{RenderAdapterInfo, RenderDevice},
+};
+use sysinfo::System;
+use wgpu::{DeviceType, Features};
+
+use crate::{
+    compression::CompressionFormat, mipmap_generator::MipmapGeneratorSettings,
+    render_features::RenderFeatures, shadows::ShadowSettings,
+};
+
+const GIB: u64 = 1024 * 1024 * 1024;
+/// Discrete GPUs get the high tier with at least this much system memory, integrated ones the
+/// medium tier. Less memory than this runs out while the full size textures load.
+const HIGH_TIER_MEMORY: u64 = 16 * GIB;
+/// Integrated GPUs share system memory, below this they get the low tier.
+const MEDIUM_TIER_MEMORY: u64 = 12 * GIB;
+
+/// Presets for the settings that decide whether the scene fits in memory and runs smoothly.
+/// High is the scene's usual settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QualityTier {
+    Low,
+    Medium,
+    High,
+}
+
+impl FromStr for QualityTier {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(QualityTier::Low),
+            "medium" => Ok(QualityTier::Medium),
+            "high" => Ok(QualityTier::High),
+            _ => Err(anyhow!(
+                "Unknown quality tier {s}, expected low, medium, or high"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for QualityTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            QualityTier::Low => "low",
+            QualityTier::Medium => "medium",
+            QualityTier::High => "high",
+        })
+    }
+}
+
+impl QualityTier {
+    /// wgpu doesn't report how much memory the GPU has, so the kind of GPU stands in for it.
+    /// Integrated GPUs share the system memory with the textures waiting for their mipmaps.
+    fn detect(device_type: DeviceType, max_texture_size: u32, memory: u64) -> Self {
+        if max_texture_size < 4096 {
+            return QualityTier::Low;
+        }
+        match device_type {
+            DeviceType::DiscreteGpu if memory >= HIGH_TIER_MEMORY => QualityTier::High,
+            DeviceType::DiscreteGpu => QualityTier::Medium,
+            DeviceType::IntegratedGpu if memory >= MEDIUM_TIER_MEMORY => QualityTier::Medium,
+            _ => QualityTier::Low,
+        }
+    }
+
+    fn max_texture_size(&self) -> Option<u32> {
+        match self {
+            QualityTier::Low => Some(1024),
+            QualityTier::Medium => Some(2048),
+            QualityTier::High => None,
+        }
+    }
+
+    /// Picks a format the GPU supports, and none on the web where textures can't be encoded.
+    fn compression(&self, features: Features) -> Option<CompressionFormat> {
+        let (bc, astc) = match self {
+            QualityTier::Low => (CompressionFormat::Bc1, CompressionFormat::Astc8x8),
+            QualityTier::Medium => (CompressionFormat::Bc7, CompressionFormat::Astc4x4),
+            QualityTier::High => return None,
+        };
+        [bc, astc].into_iter().find(|format| {
+            !cfg!(target_arch = "wasm32") && features.contains(format.required_features())
+        })
+    }
+
+    fn shadow_map_size(&self) -> usize {
+        match self {
+            QualityTier::Low => 1024,
+            QualityTier::Medium | QualityTier::High => 2048,
+        }
+    }
+
+    fn ssao(&self) -> bool {
+        *self == QualityTier::High
+    }
+
+    fn taa(&self) -> bool {
+        *self != QualityTier::Low
+    }
+}
+
+/// Picks a [`QualityTier`] from the GPU and system memory once the renderer is up, and applies
+/// it to the texture, shadow, and post processing settings. The tier only turns SSAO and TAA
+/// off, and settings given on the command line are kept. See `--quality-tier`.
+#[derive(Resource, Default)]
+pub struct AutoQuality {
+    /// Tier to use instead of detecting one.
+    pub tier: Option<QualityTier>,
+    pub keep_texture_size: bool,
+    pub keep_compression: bool,
+    pub keep_shadow_map_size: bool,
+}
+
+pub struct QualityTierPlugin;
+impl Plugin for QualityTierPlugin {
+    fn build(&self, _app: &mut App) {}
+
+    fn finish(&self, app: &mut App) {
+        let world = &mut app.world;
+        let (Some(auto), Some(adapter), Some(device)) = (
+            world.get_resource::<AutoQuality>(),
+            world.get_resource::<RenderAdapterInfo>(),
+            world.get_resource::<RenderDevice>(),
+        ) else {
+            return;
+        };
+        let limits = device.limits();
+        let features = device.features();
+        let mut sys = System::new();
+        sys.refresh_memory();
+        let memory = sys.total_memory();
+        let tier = auto.tier.unwrap_or_else(|| {
+            QualityTier::detect(adapter.device_type, limits.max_texture_dimension_2d, memory)
+        });
+        let source = match auto.tier {
+            Some(_) => String::from("from --quality-tier"),
+            None => format!(
+                "for {} ({:?}, {:.0}GB memory)",
+                adapter.name,
+                adapter.device_type,
+                memory as f64 / GIB as f64
+            ),
+        };
+        let keep_texture_size = auto.keep_texture_size;
+        let keep_compression = auto.keep_compression;
+        let keep_shadow_map_size = auto.keep_shadow_map_size;
+
+        let mut mipmaps = world.resource_mut::<MipmapGeneratorSettings>();
+        if !keep_texture_size {
+            // Larger textures than the GPU allows would fail to upload whatever the tier
+            let limit = limits.max_texture_dimension_2d;
+            mipmaps.max_texture_size = match tier.max_texture_size() {
+                Some(size) => Some(size.min(limit)),
+                None => (limit < 8192).then_some(limit),
+            };
+        }
+        if !keep_compression {
+            mipmaps.compression = tier.compression(features);
+        }
+        let (max_texture_size, compression) = (mipmaps.max_texture_size, mipmaps.compression);
+
+        let mut shadows = world.resource_mut::<ShadowSettings>();
+        if !keep_shadow_map_size {
+            shadows.map_size = tier.shadow_map_size();
+        }
+        let map_size = shadows.map_size;
+
+        let mut render_features = world.resource_mut::<RenderFeatures>();
+        render_features.ssao &= tier.ssao();
+        render_features.taa &= tier.taa();
+        let on_off = |enabled| if enabled { "on" } else { "off" };
+        info!(
+            "Using the {tier} quality tier {source}: textures up to {}, {} compression, \
+            {map_size} shadow maps, SSAO {}, TAA {}",
+            max_texture_size.map_or(String::from("full size"), |size| size.to_string()),
+            compression.map_or(String::from("no"), |format| format!("{format:?}")),
+            on_off(render_features.ssao),
+            on_off(render_features.taa),
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use bevy::{
+    core_pipeline::{
+        bloom::{BloomCompositeMode, BloomPrefilterSettings, BloomSettings},
+        experimental::taa::{TemporalAntiAliasBundle, TemporalAntiAliasSettings},
+        prepass::{DeferredPrepass, DepthPrepass, MotionVectorPrepass, NormalPrepass},
+    },
+    pbr::ScreenSpaceAmbientOcclusionSettings,
+    prelude::*,
+    render::{camera::TemporalJitter, view::NoFrustumCulling},
+};
+
+use crate::{light_editor::RigLight, split_screen::ComparisonCamera, GrifLight};
+
+/// Rendering features that can be switched at runtime. Changing this resource adds or removes
+/// the corresponding components on the camera.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct RenderFeatures {
+    pub ssao: bool,
+    pub taa: bool,
+    pub bloom: bool,
+    pub shadows: bool,
+    pub msaa: bool,
+    pub frustum_culling: bool,
+    pub bloom_intensity: f32,
+    /// Only pixels brighter than this bloom, 0.0 blooms everything.
+    pub bloom_threshold: f32,
+    /// Add the bloom on top instead of blending it in, which keeps more of the scene's energy.
+    pub bloom_additive: bool,
+    /// Shade opaque materials in a deferred pass. Only set at launch with `--deferred`, since it
+    /// changes how the materials are rendered. Rules out MSAA.
+    pub deferred: bool,
+}
+
+impl Default for RenderFeatures {
+    fn default() -> Self {
+        Self {
+            ssao: true,
+            taa: true,
+            bloom: true,
+            shadows: true,
+            msaa: false,
+            frustum_culling: true,
+            bloom_intensity: 0.05,
+            bloom_threshold: 0.0,
+            bloom_additive: false,
+            deferred: false,
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub enum FeatureToggle {
+    Ssao,
+    Taa,
+    Bloom,
+    BloomAdditive,
+    Shadows,
+    Msaa,
+    FrustumCulling,
+}
+
+impl FeatureToggle {
+    pub const ALL: [FeatureToggle; 7] = [
+        FeatureToggle::Ssao,
+        FeatureToggle::Taa,
+        FeatureToggle::Bloom,
+        FeatureToggle::BloomAdditive,
+        FeatureToggle::Shadows,
+        FeatureToggle::Msaa,
+        FeatureToggle::FrustumCulling,
+    ];
+
+    /// Parses the names used by `--bench-matrix`: ssao, taa, bloom, shadows, msaa, or
+    /// frustum_culling.
+    pub fn from_arg(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "ssao" => Some(FeatureToggle::Ssao),
+            "taa" => Some(FeatureToggle::Taa),
+            "bloom" => Some(FeatureToggle::Bloom),
+            "shadows" => Some(FeatureToggle::Shadows),
+            "msaa" => Some(FeatureToggle::Msaa),
+            "frustum_culling" => Some(FeatureToggle::FrustumCulling),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FeatureToggle::Ssao => "SSAO",
+            FeatureToggle::Taa => "TAA",
+            FeatureToggle::Bloom => "Bloom",
+            FeatureToggle::BloomAdditive => "Additive Bloom",
+            FeatureToggle::Shadows => "Shadows",
+            FeatureToggle::Msaa => "MSAA",
+            FeatureToggle::FrustumCulling => "Frustum Culling",
+        }
+    }
+
+    pub fn value(&self, features: &RenderFeatures) -> bool {
+        match self {
+            FeatureToggle::Ssao => features.ssao,
+            FeatureToggle::Taa => features.taa,
+            FeatureToggle::Bloom => features.bloom,
+            FeatureToggle::BloomAdditive => features.bloom_additive,
+            FeatureToggle::Shadows => features.shadows,
+            FeatureToggle::Msaa => features.msaa,
+            FeatureToggle::FrustumCulling => features.frustum_culling,
+        }
+    }
+
+    fn toggle(&self, features: &mut RenderFeatures) {
+        match self {
+            FeatureToggle::Ssao => features.ssao = !features.ssao,
+            FeatureToggle::Taa => {
+                features.taa = !features.taa;
+                // TAA doesn't support MSAA
+                features.msaa &= !features.taa;
+            }
+            FeatureToggle::Bloom => features.bloom = !features.bloom,
+            FeatureToggle::BloomAdditive => features.bloom_additive = !features.bloom_additive,
+            FeatureToggle::Shadows => features.shadows = !features.shadows,
+            FeatureToggle::Msaa => {
+                features.msaa = !features.msaa && !features.deferred;
+                features.taa &= !features.msaa;
+            }
+            FeatureToggle::FrustumCulling => features.frustum_culling = !features.frustum_culling,
+        }
+    }
+
+    /// Turns the feature on or off, along with anything that conflicts with it.
+    pub fn set(&self, features: &mut RenderFeatures, value: bool) {
+        if self.value(features) != value {
+            self.toggle(features);
+        }
+    }
+
+    fn label(&self, features: &RenderFeatures) -> String {
+        let state = if self.value(features) { "on" } else { "off" };
+        match self {
+            FeatureToggle::Bloom => format!(
+                "{}: {}, intensity {:.2}, threshold {:.1}",
+                self.name(),
+                state,
+                features.bloom_intensity,
+                features.bloom_threshold
+            ),
+            _ => format!("{}: {}", self.name(), state),
+        }
+    }
+}
+
+/// Marks the text of a [`FeatureToggle`] button.
+#[derive(Component)]
+pub struct FeatureToggleLabel(FeatureToggle);
+
+/// Root node of the feature toggle panel, shown/hidden with F2.
+#[derive(Component)]
+pub struct FeaturePanel;
+
+pub struct RenderFeaturesPlugin;
+impl Plugin for RenderFeaturesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RenderFeatures>()
+            .add_systems(Startup, spawn_feature_panel)
+            .add_systems(
+                Update,
+                (
+                    toggle_panel,
+                    feature_buttons,
+                    tune_bloom,
+                    apply_render_features,
+                    apply_frustum_culling,
+                    update_feature_labels,
+                ),
+            );
+    }
+}
+
+fn spawn_feature_panel(mut commands: Commands, features: Res<RenderFeatures>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            FeaturePanel,
+        ))
+        .with_children(|parent| {
+            for toggle in FeatureToggle::ALL {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                                ..default()
+                            },
+                            background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                            ..default()
+                        },
+                        toggle,
+                    ))
+                    .with_children(|button| {
+                        button.spawn((
+                            TextBundle::from_section(
+                                toggle.label(&features),
+                                TextStyle {
+                                    font_size: 18.0,
+                                    color: Color::WHITE,
+                                    ..default()
+                                },
+                            ),
+                            FeatureToggleLabel(toggle),
+                        ));
+                    });
+            }
+        });
+}
+
+fn toggle_panel(
+    input: Res<ButtonInput<KeyCode>>,
+    mut panel: Query<&mut Visibility, With<FeaturePanel>>,
+) {
+    if !input.just_pressed(KeyCode::F2) {
+        return;
+    }
+    for mut visibility in &mut panel {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Visible,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn feature_buttons(
+    buttons: Query<(&Interaction, &FeatureToggle), Changed<Interaction>>,
+    mut features: ResMut<RenderFeatures>,
+) {
+    for (interaction, toggle) in &buttons {
+        if *interaction == Interaction::Pressed {
+            toggle.toggle(&mut features);
+        }
+    }
+}
+
+/// `,` and `.` lower and raise the bloom intensity, with shift they change the threshold.
+fn tune_bloom(input: Res<ButtonInput<KeyCode>>, mut features: ResMut<RenderFeatures>) {
+    let step = if input.just_pressed(KeyCode::Period) {
+        1.0
+    } else if input.just_pressed(KeyCode::Comma) {
+        -1.0
+    } else {
+        return;
+    };
+    if input.pressed(KeyCode::ShiftLeft) || input.pressed(KeyCode::ShiftRight) {
+        features.bloom_threshold = (features.bloom_threshold + step * 0.5).max(0.0);
+    } else {
+        features.bloom_intensity = (features.bloom_intensity + step * 0.01).clamp(0.0, 1.0);
+    }
+}
+
+fn update_feature_labels(
+    features: Res<RenderFeatures>,
+    mut labels: Query<(&mut Text, &FeatureToggleLabel)>,
+) {
+    if !features.is_changed() {
+        return;
+    }
+    for (mut text, label) in &mut labels {
+        text.sections[0].value = label.0.label(&features);
+    }
+}
+
+pub fn apply_render_features(
+    mut commands: Commands,
+    features: Res<RenderFeatures>,
+    cameras: Query<Entity, (With<Camera3d>, Without<ComparisonCamera>)>,
+    mut lights: Query<&mut DirectionalLight, (With<GrifLight>, Without<RigLight>)>,
+    mut msaa: ResMut<Msaa>,
+) {
+    if !features.is_changed() {
+        return;
+    }
+    for entity in &cameras {
+        let mut cam = commands.entity(entity);
+        if features.bloom {
+            cam.insert(BloomSettings {
+                intensity: features.bloom_intensity,
+                prefilter_settings: BloomPrefilterSettings {
+                    threshold: features.bloom_threshold,
+                    threshold_softness: 0.0,
+                },
+                composite_mode: if features.bloom_additive {
+                    BloomCompositeMode::Additive
+                } else {
+                    BloomCompositeMode::EnergyConserving
+                },
+                ..default()
+            });
+        } else {
+            cam.remove::<BloomSettings>();
+        }
+        if features.taa {
+            cam.insert(TemporalAntiAliasBundle::default());
+        } else {
+            cam.remove::<(
+                TemporalAntiAliasSettings,
+                TemporalJitter,
+                MotionVectorPrepass,
+            )>();
+        }
+        if features.ssao {
+            cam.insert((
+                ScreenSpaceAmbientOcclusionSettings::default(),
+                DepthPrepass,
+                NormalPrepass,
+            ));
+        } else {
+            cam.remove::<(ScreenSpaceAmbientOcclusionSettings, NormalPrepass)>();
+        }
+        if features.deferred {
+            cam.insert((DepthPrepass, DeferredPrepass));
+        }
+        // Only keep the prepasses that are still needed
+        if !features.taa && !features.ssao && !features.deferred {
+            cam.remove::<DepthPrepass>();
+        }
+    }
+    // The rig's lights also keep their own shadow setting, see the light editor
+    for mut light in &mut lights {
+        light.shadows_enabled = features.shadows;
+    }
+    *msaa = if features.msaa {
+        Msaa::Sample4
+    } else {
+        Msaa::Off
+    };
+}
+
+pub fn apply_frustum_culling(
+    mut commands: Commands,
+    features: Res<RenderFeatures>,
+    culled: Query<Entity, (Without<NoFrustumCulling>, With<Handle<StandardMaterial>>)>,
+    not_culled: Query<Entity, With<NoFrustumCulling>>,
+) {
+    if features.frustum_culling {
+        if features.is_changed() {
+            for entity in &not_culled {
+                commands.entity(entity).remove::<NoFrustumCulling>();
+            }
+        }
+    } else {
+        for entity in &culled {
+            commands.entity(entity).insert(NoFrustumCulling);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use bevy::{
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+    },
+    ui::IsDefaultUiCamera,
+    window::{PrimaryWindow, WindowRef, WindowResized},
+};
+
+pub const MIN_RENDER_SCALE: f32 = 0.5;
+pub const MAX_RENDER_SCALE: f32 = 2.0;
+const RENDER_SCALE_STEP: f32 = 0.25;
+
+/// Resolution of the 3D camera relative to the window. At any scale other than 1.0 the scene is
+/// rendered to an offscreen image that is stretched over the window. Adjusted with Page Up and
+/// Page Down.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct RenderScale {
+    pub scale: f32,
+}
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        Self { scale: 1.0 }
+    }
+}
+
+impl RenderScale {
+    pub fn new(scale: f32) -> Self {
+        Self {
+            scale: scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE),
+        }
+    }
+
+    /// Physical size the 3D camera renders at for a window of `width` x `height` pixels.
+    pub fn render_size(&self, width: u32, height: u32) -> (u32, u32) {
+        (
+            ((width as f32 * self.scale) as u32).max(1),
+            ((height as f32 * self.scale) as u32).max(1),
+        )
+    }
+}
+
+/// The offscreen image the 3D camera renders to when scaled.
+#[derive(Resource)]
+struct ScaledTarget(Handle<Image>);
+
+/// Full window UI node showing the [`ScaledTarget`].
+#[derive(Component)]
+struct ScaledTargetView;
+
+pub struct RenderScalePlugin;
+impl Plugin for RenderScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RenderScale>()
+            .add_systems(Startup, setup_scaled_target)
+            .add_systems(Update, (adjust_render_scale, apply_render_scale).chain());
+    }
+}
+
+fn setup_scaled_target(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let size = Extent3d {
+        width: 1,
+        height: 1,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("scaled_render_target"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    let handle = images.add(image);
+
+    // Draws the UI, and the scaled image when the 3D camera isn't rendering to the window
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: 1,
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            ..default()
+        },
+        IsDefaultUiCamera,
+    ));
+    commands.spawn((
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            image: UiImage::new(handle.clone()),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(-1),
+            ..default()
+        },
+        ScaledTargetView,
+    ));
+    commands.insert_resource(ScaledTarget(handle));
+}
+
+fn adjust_render_scale(input: Res<ButtonInput<KeyCode>>, mut render_scale: ResMut<RenderScale>) {
+    let step = if input.just_pressed(KeyCode::PageUp) {
+        RENDER_SCALE_STEP
+    } else if input.just_pressed(KeyCode::PageDown) {
+        -RENDER_SCALE_STEP
+    } else {
+        return;
+    };
+    *render_scale = RenderScale::new(render_scale.scale + step);
+    println!("Render scale: {:.2}", render_scale.scale);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_render_scale(
+    render_scale: Res<RenderScale>,
+    target: Res<ScaledTarget>,
+    mut resized: EventReader<WindowResized>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut images: ResMut<Assets<Image>>,
+    mut cameras: Query<&mut Camera, With<Camera3d>>,
+    added_cameras: Query<(), Added<Camera3d>>,
+    mut view: Query<&mut Visibility, With<ScaledTargetView>>,
+) {
+    let resized = resized.read().count() > 0;
+    if !render_scale.is_changed() && !resized && added_cameras.is_empty() {
+        return;
+    }
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let scaled = render_scale.scale != 1.0;
+    if scaled {
+        let (width, height) =
+            render_scale.render_size(window.physical_width(), window.physical_height());
+        if let Some(image) = images.get_mut(&target.0) {
+            image.resize(Extent3d {
+                width,
+                height,
+                ..default()
+            });
+        }
+    }
+    for mut camera in &mut cameras {
+        camera.target = if scaled {
+            RenderTarget::Image(target.0.clone())
+        } else {
+            RenderTarget::Window(WindowRef::Primary)
+        };
+    }
+    for mut visibility in &mut view {
+        *visibility = if scaled {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
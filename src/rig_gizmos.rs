@@ -0,0 +1,103 @@
+use bevy::{
+    math::primitives::Direction3d,
+    prelude::*,
+    render::primitives::{Aabb, CascadesFrusta, Frustum},
+};
+
+use crate::GrifLight;
+
+const CASCADE_COLORS: [Color; 4] = [Color::RED, Color::GREEN, Color::BLUE, Color::YELLOW];
+
+/// Whether to draw mesh bounding boxes and the extents of the [`GrifLight`] rig: shadow cascades
+/// of directional lights, spot light cones, and point light ranges. Toggled with G.
+#[derive(Resource, Default)]
+pub struct ShowRigGizmos(pub bool);
+
+pub struct RigGizmosPlugin;
+impl Plugin for RigGizmosPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowRigGizmos>()
+            .add_systems(Update, draw_rig_gizmos);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn draw_rig_gizmos(
+    input: Res<ButtonInput<KeyCode>>,
+    mut show: ResMut<ShowRigGizmos>,
+    meshes: Query<(&Aabb, &GlobalTransform), With<Handle<Mesh>>>,
+    directional: Query<&CascadesFrusta, With<GrifLight>>,
+    spots: Query<(&SpotLight, &GlobalTransform), With<GrifLight>>,
+    points: Query<(&PointLight, &GlobalTransform), With<GrifLight>>,
+    mut gizmos: Gizmos,
+) {
+    if input.just_pressed(KeyCode::KeyG) {
+        show.0 = !show.0;
+    }
+    if !show.0 {
+        return;
+    }
+    for (aabb, transform) in &meshes {
+        let local = Transform::from_translation(aabb.center.into())
+            .with_scale(Vec3::from(aabb.half_extents) * 2.0);
+        gizmos.cuboid(*transform * local, Color::GRAY);
+    }
+    for cascades in &directional {
+        for view_frusta in cascades.frusta.values() {
+            for (frustum, color) in view_frusta.iter().zip(CASCADE_COLORS) {
+                draw_frustum(&mut gizmos, frustum, color);
+            }
+        }
+    }
+    for (light, transform) in &spots {
+        let position = transform.translation();
+        let forward = transform
+            .affine()
+            .transform_vector3(Vec3::NEG_Z)
+            .normalize();
+        let radius = light.range * light.outer_angle.tan();
+        let end = position + forward * light.range;
+        let normal = Direction3d::new(forward).unwrap_or(Direction3d::NEG_Z);
+        gizmos.circle(end, normal, radius, Color::YELLOW);
+        // Four lines from the light to the edge of the cone
+        let side = forward.any_orthonormal_vector();
+        for i in 0..4 {
+            let offset =
+                Quat::from_axis_angle(forward, i as f32 * std::f32::consts::FRAC_PI_2) * side;
+            gizmos.line(position, end + offset * radius, Color::YELLOW);
+        }
+    }
+    for (light, transform) in &points {
+        gizmos.sphere(
+            transform.translation(),
+            Quat::IDENTITY,
+            light.range,
+            Color::ORANGE,
+        );
+    }
+}
+
+/// Draws the edges of a frustum, from the corners where its planes meet.
+fn draw_frustum(gizmos: &mut Gizmos, frustum: &Frustum, color: Color) {
+    // Half spaces are ordered left, right, bottom, top, near, far
+    let planes = frustum.half_spaces.map(|half_space| half_space.normal_d());
+    let corner = |x: usize, y: usize, z: usize| {
+        let (a, b, c) = (planes[x], planes[2 + y], planes[4 + z]);
+        let (na, nb, nc) = (a.truncate(), b.truncate(), c.truncate());
+        -(a.w * nb.cross(nc) + b.w * nc.cross(na) + c.w * na.cross(nb)) / na.dot(nb.cross(nc))
+    };
+    for z in 0..2 {
+        let ring = [
+            corner(0, 0, z),
+            corner(1, 0, z),
+            corner(1, 1, z),
+            corner(0, 1, z),
+        ];
+        for (start, end) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+            gizmos.line(*start, *end, color);
+        }
+    }
+    for (x, y) in [(0, 0), (1, 0), (1, 1), (0, 1)] {
+        gizmos.line(corner(x, y, 0), corner(x, y, 1), color);
+    }
+}
@@ -0,0 +1,401 @@
+use std::{f32::consts::PI, fs, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{scene_rules::SceneRule, GrifLight};
+
+/// A hand placed light that is part of a scene's light rig.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PresetLight {
+    Directional {
+        transform: Transform,
+        color: Color,
+        illuminance: f32,
+        shadows_enabled: bool,
+        shadow_depth_bias: f32,
+        shadow_normal_bias: f32,
+    },
+    Spot {
+        transform: Transform,
+        color: Color,
+        intensity: f32,
+        range: f32,
+        radius: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+        shadows_enabled: bool,
+    },
+    Point {
+        transform: Transform,
+        color: Color,
+        intensity: f32,
+        range: f32,
+        radius: f32,
+        shadows_enabled: bool,
+    },
+}
+
+impl PresetLight {
+    /// Spawns the light tagged with [`GrifLight`]. Shadows are only enabled if both the light
+    /// and `allow_shadows` want them.
+    pub fn spawn(&self, commands: &mut Commands, allow_shadows: bool) -> Entity {
+        match self.clone() {
+            PresetLight::Directional {
+                transform,
+                color,
+                illuminance,
+                shadows_enabled,
+                shadow_depth_bias,
+                shadow_normal_bias,
+            } => commands.spawn((
+                DirectionalLightBundle {
+                    transform,
+                    directional_light: DirectionalLight {
+                        color,
+                        illuminance,
+                        shadows_enabled: shadows_enabled && allow_shadows,
+                        shadow_depth_bias,
+                        shadow_normal_bias,
+                    },
+                    ..default()
+                },
+                GrifLight,
+            )),
+            PresetLight::Spot {
+                transform,
+                color,
+                intensity,
+                range,
+                radius,
+                inner_angle,
+                outer_angle,
+                shadows_enabled,
+            } => commands.spawn((
+                SpotLightBundle {
+                    transform,
+                    spot_light: SpotLight {
+                        color,
+                        intensity,
+                        range,
+                        radius,
+                        inner_angle,
+                        outer_angle,
+                        shadows_enabled: shadows_enabled && allow_shadows,
+                        ..default()
+                    },
+                    ..default()
+                },
+                GrifLight,
+            )),
+            PresetLight::Point {
+                transform,
+                color,
+                intensity,
+                range,
+                radius,
+                shadows_enabled,
+            } => commands.spawn((
+                PointLightBundle {
+                    transform,
+                    point_light: PointLight {
+                        color,
+                        intensity,
+                        range,
+                        radius,
+                        shadows_enabled: shadows_enabled && allow_shadows,
+                        ..default()
+                    },
+                    ..default()
+                },
+                GrifLight,
+            )),
+        }
+        .id()
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PresetLight::Directional { .. } => "Directional",
+            PresetLight::Spot { .. } => "Spot",
+            PresetLight::Point { .. } => "Point",
+        }
+    }
+
+    pub fn transform_mut(&mut self) -> &mut Transform {
+        match self {
+            PresetLight::Directional { transform, .. }
+            | PresetLight::Spot { transform, .. }
+            | PresetLight::Point { transform, .. } => transform,
+        }
+    }
+
+    pub fn color_mut(&mut self) -> &mut Color {
+        match self {
+            PresetLight::Directional { color, .. }
+            | PresetLight::Spot { color, .. }
+            | PresetLight::Point { color, .. } => color,
+        }
+    }
+
+    /// Illuminance in lux for directional lights, luminous power in lumens otherwise.
+    pub fn intensity_mut(&mut self) -> &mut f32 {
+        match self {
+            PresetLight::Directional { illuminance, .. } => illuminance,
+            PresetLight::Spot { intensity, .. } | PresetLight::Point { intensity, .. } => intensity,
+        }
+    }
+
+    pub fn range_mut(&mut self) -> Option<&mut f32> {
+        match self {
+            PresetLight::Directional { .. } => None,
+            PresetLight::Spot { range, .. } | PresetLight::Point { range, .. } => Some(range),
+        }
+    }
+
+    pub fn shadows_enabled_mut(&mut self) -> &mut bool {
+        match self {
+            PresetLight::Directional {
+                shadows_enabled, ..
+            }
+            | PresetLight::Spot {
+                shadows_enabled, ..
+            }
+            | PresetLight::Point {
+                shadows_enabled, ..
+            } => shadows_enabled,
+        }
+    }
+
+    /// Inner and outer cone angles of spot lights, in radians.
+    pub fn angles_mut(&mut self) -> Option<(&mut f32, &mut f32)> {
+        match self {
+            PresetLight::Spot {
+                inner_angle,
+                outer_angle,
+                ..
+            } => Some((inner_angle, outer_angle)),
+            _ => None,
+        }
+    }
+}
+
+/// Everything that is specific to a particular test scene: what to load, how to clean it up,
+/// the light rig, and the camera viewpoints used by the number keys and the benchmark.
+/// Can be loaded from a RON file with `--preset`, use `--save-preset` to get a starting point.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct ScenePreset {
+    pub name: String,
+    /// glTF scene asset paths, relative to the assets folder.
+    pub scenes: Vec<String>,
+    pub lights: Vec<PresetLight>,
+    /// Named viewpoints. The first one is where the camera starts.
+    pub cameras: Vec<(String, Transform)>,
+    /// Fixups applied to the scene once it has spawned.
+    #[serde(default)]
+    pub rules: Vec<SceneRule>,
+}
+
+impl ScenePreset {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        fs::write(
+            path,
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?,
+        )?;
+        Ok(())
+    }
+
+    /// Looks up a named preset, otherwise treats `scene` as a path to a glTF file in the assets folder.
+    pub fn from_arg(scene: &str) -> Self {
+        match scene {
+            "sponza" => Self::sponza(),
+            "bistro" => Self::bistro(),
+            "san-miguel" => Self::san_miguel(),
+            path => Self::gltf(path),
+        }
+    }
+
+    /// Any glTF, lit by a single sun and keeping its own lights.
+    pub fn gltf(path: &str) -> Self {
+        let scene = if path.contains('#') {
+            path.to_string()
+        } else {
+            format!("{path}#Scene0")
+        };
+        Self {
+            name: path.to_string(),
+            scenes: vec![scene],
+            lights: vec![default_sun()],
+            cameras: vec![(
+                String::from("default"),
+                Transform::from_xyz(-10.0, 2.0, 0.0).looking_at(Vec3::new(0.0, 2.0, 0.0), Vec3::Y),
+            )],
+            rules: vec![SceneRule::DespawnCameras],
+        }
+    }
+
+    /// Amazon Lumberyard Bistro exterior, expected in `./assets/bistro/`.
+    pub fn bistro() -> Self {
+        Self {
+            name: String::from("bistro"),
+            ..Self::gltf("bistro/BistroExterior.gltf")
+        }
+    }
+
+    /// Morgan McGuire's San Miguel, expected in `./assets/san_miguel/`.
+    pub fn san_miguel() -> Self {
+        Self {
+            name: String::from("san-miguel"),
+            ..Self::gltf("san_miguel/san-miguel.gltf")
+        }
+    }
+
+    pub fn sponza() -> Self {
+        let point_spot_mult = 1000.0;
+        Self {
+            name: String::from("sponza"),
+            scenes: vec![
+                String::from("main_sponza/NewSponza_Main_glTF_002.gltf#Scene0"),
+                String::from("PKG_A_Curtains/NewSponza_Curtains_glTF.gltf#Scene0"),
+            ],
+            lights: vec![
+                // Sun
+                default_sun(),
+                // Sun Refl
+                PresetLight::Spot {
+                    transform: Transform::from_xyz(2.0, -0.0, -2.0)
+                        .looking_at(Vec3::new(0.0, 999.0, 0.0), Vec3::X),
+                    range: 15.0,
+                    radius: 0.0,
+                    intensity: 700.0 * point_spot_mult,
+                    color: Color::rgb(1.0, 0.97, 0.85),
+                    shadows_enabled: false,
+                    inner_angle: PI * 0.4,
+                    outer_angle: PI * 0.5,
+                },
+                // Sun refl 2nd bounce / misc bounces
+                PresetLight::Spot {
+                    transform: Transform::from_xyz(2.0, 5.5, -2.0)
+                        .looking_at(Vec3::new(0.0, -999.0, 0.0), Vec3::X),
+                    range: 13.0,
+                    radius: 0.0,
+                    intensity: 500.0 * point_spot_mult,
+                    color: Color::rgb(1.0, 0.97, 0.85),
+                    shadows_enabled: false,
+                    inner_angle: PI * 0.3,
+                    outer_angle: PI * 0.4,
+                },
+                // sky
+                // seems to be making blocky artifacts. Even if it's the only light.
+                PresetLight::Point {
+                    transform: Transform::from_xyz(0.0, 30.0, 0.0),
+                    color: Color::rgb(0.8, 0.9, 0.97),
+                    intensity: 10000.0 * point_spot_mult,
+                    shadows_enabled: false,
+                    range: 24.0,
+                    radius: 3.0,
+                },
+                // sky refl
+                PresetLight::Spot {
+                    transform: Transform::from_xyz(0.0, -2.0, 0.0)
+                        .looking_at(Vec3::new(0.0, 999.0, 0.0), Vec3::X),
+                    range: 11.0,
+                    radius: 0.0,
+                    intensity: 40.0 * point_spot_mult,
+                    color: Color::rgb(0.8, 0.9, 0.97),
+                    shadows_enabled: false,
+                    inner_angle: PI * 0.46,
+                    outer_angle: PI * 0.49,
+                },
+                // sky low
+                PresetLight::Spot {
+                    transform: Transform::from_xyz(3.0, 2.0, 0.0)
+                        .looking_at(Vec3::new(0.0, -999.0, 0.0), Vec3::X),
+                    range: 12.0,
+                    radius: 0.0,
+                    intensity: 600.0 * point_spot_mult,
+                    color: Color::rgb(0.8, 0.9, 0.95),
+                    shadows_enabled: false,
+                    inner_angle: PI * 0.34,
+                    outer_angle: PI * 0.5,
+                },
+            ],
+            cameras: vec![
+                (
+                    // Curtains
+                    String::from("CAM_POS_1"),
+                    Transform {
+                        translation: Vec3::new(-10.5, 1.7, -1.0),
+                        rotation: Quat::from_array([
+                            -0.05678932,
+                            0.7372272,
+                            -0.062454797,
+                            -0.670351,
+                        ]),
+                        scale: Vec3::ONE,
+                    },
+                ),
+                (
+                    // Long hallway
+                    String::from("CAM_POS_2"),
+                    Transform {
+                        translation: Vec3::new(11.901049, 6.9060106, -4.561092),
+                        rotation: Quat::from_array([
+                            -0.0066631963,
+                            -0.86618143,
+                            0.011553433,
+                            -0.49955168,
+                        ]),
+                        scale: Vec3::ONE,
+                    },
+                ),
+                (
+                    // Atrium
+                    String::from("CAM_POS_3"),
+                    Transform {
+                        translation: Vec3::new(19.087378, 1.4913027, -2.7349238),
+                        rotation: Quat::from_array([
+                            0.017711632,
+                            0.7889913,
+                            -0.022769613,
+                            0.61372685,
+                        ]),
+                        scale: Vec3::ONE,
+                    },
+                ),
+            ],
+            rules: vec![
+                // Sponza needs flipped normals
+                SceneRule::FlipNormalMapY,
+                // Sponza has a bunch of lights and cameras by default
+                SceneRule::DespawnLights,
+                SceneRule::DespawnCameras,
+                // The lamps are authored at an emissive strength of 1
+                SceneRule::BoostEmissive {
+                    strength: 1000.0,
+                    light_intensity: None,
+                },
+            ],
+        }
+    }
+}
+
+fn default_sun() -> PresetLight {
+    PresetLight::Directional {
+        transform: Transform::from_rotation(Quat::from_euler(
+            EulerRot::XYZ,
+            PI * -0.43,
+            PI * -0.08,
+            0.0,
+        )),
+        color: Color::rgb(1.0, 1.0, 0.99),
+        illuminance: 300000.0 * 0.2,
+        shadows_enabled: true,
+        shadow_depth_bias: 0.3,
+        shadow_normal_bias: 0.7,
+    }
+}
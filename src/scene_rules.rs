@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::emissive::EmissiveMesh;
+
+/// A fixup applied to everything under [`crate::PostProcScene`] once the glTF has spawned.
+/// Listed in the `rules` of a scene preset, since every scene needs something different.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SceneRule {
+    /// Flip the Y channel of normal maps on every material.
+    FlipNormalMapY,
+    /// Despawn cameras that came with the glTF.
+    DespawnCameras,
+    /// Despawn lights that came with the glTF, leaving only the preset light rig.
+    DespawnLights,
+    /// Raise `perceptual_roughness` to at least this, to tame specular aliasing on surfaces
+    /// authored as perfectly smooth.
+    MinRoughness(f32),
+    /// Multiply the emissive color of materials with an emissive texture, since glTF emissive
+    /// strength isn't scaled and lamps render dead next to the light rig. Toggled with L. With
+    /// `light_intensity`, a point light is also spawned at the center of each emissive mesh.
+    BoostEmissive {
+        strength: f32,
+        light_intensity: Option<f32>,
+    },
+}
+
+impl SceneRule {
+    /// Applies the rule to a material, each material is only passed in once.
+    pub fn apply_to_material(&self, material: &mut StandardMaterial) {
+        match self {
+            SceneRule::FlipNormalMapY => material.flip_normal_map_y = true,
+            SceneRule::MinRoughness(min) => {
+                material.perceptual_roughness = material.perceptual_roughness.max(*min)
+            }
+            SceneRule::DespawnCameras
+            | SceneRule::DespawnLights
+            | SceneRule::BoostEmissive { .. } => (),
+        }
+    }
+
+    /// Whether a mesh with this material should be boosted by [`SceneRule::BoostEmissive`].
+    pub fn emissive_mesh(&self, material: &StandardMaterial) -> Option<EmissiveMesh> {
+        match self {
+            SceneRule::BoostEmissive {
+                strength,
+                light_intensity,
+            } if material.emissive_texture.is_some() => Some(EmissiveMesh {
+                strength: *strength,
+                light_intensity: *light_intensity,
+            }),
+            _ => None,
+        }
+    }
+}
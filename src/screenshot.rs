@@ -0,0 +1,125 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    app::AppExit, prelude::*, render::view::screenshot::ScreenshotManager, window::PrimaryWindow,
+};
+
+use crate::loading::SceneReady;
+
+/// Frames to render after the scene is ready before capturing, so TAA and auto exposure settle.
+pub const CAPTURE_DELAY_FRAMES: u32 = 60;
+
+/// Captures a single screenshot once the scene has finished loading.
+#[derive(Resource, Clone)]
+pub struct AutoScreenshot {
+    pub path: PathBuf,
+    pub exit_after_capture: bool,
+}
+
+pub struct ScreenshotPlugin;
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (screenshot_hotkey, auto_screenshot));
+    }
+}
+
+/// Whether a screenshot was written, filled in by the screenshot callback once it has run.
+pub type ScreenshotOutcome = Arc<Mutex<Option<Result<(), String>>>>;
+
+/// Saves the primary window to `path`, storing in `outcome` whether the file was written.
+/// Returns false if the screenshot couldn't be requested, in which case `outcome` is never set.
+pub fn save_screenshot(
+    screenshot_manager: &mut ScreenshotManager,
+    window: Entity,
+    path: PathBuf,
+    outcome: Option<ScreenshotOutcome>,
+) -> bool {
+    let result = screenshot_manager.take_screenshot(window, move |image| {
+        let result = match image.try_into_dynamic() {
+            Ok(dyn_image) => dyn_image
+                .to_rgb8()
+                .save(&path)
+                .map_err(|e| format!("Failed to save screenshot {}: {}", path.display(), e)),
+            Err(e) => Err(format!("Failed to convert screenshot: {:?}", e)),
+        };
+        match &result {
+            Ok(()) => info!("Saved screenshot to {}", path.display()),
+            Err(e) => error!("{e}"),
+        }
+        if let Some(outcome) = outcome {
+            *outcome.lock().unwrap() = Some(result);
+        }
+    });
+    if let Err(e) = &result {
+        warn!("Screenshot not taken: {:?}", e);
+    }
+    result.is_ok()
+}
+
+fn screenshot_hotkey(
+    input: Res<ButtonInput<KeyCode>>,
+    window: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    if !input.just_pressed(KeyCode::F12) {
+        return;
+    }
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = PathBuf::from(format!("screenshot_{timestamp}.png"));
+    save_screenshot(&mut screenshot_manager, window, path, None);
+}
+
+fn auto_screenshot(
+    auto: Option<Res<AutoScreenshot>>,
+    scene_ready: Res<SceneReady>,
+    window: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+    mut app_exit: EventWriter<AppExit>,
+    mut frames_since_ready: Local<u32>,
+    mut pending: Local<Option<ScreenshotOutcome>>,
+) {
+    let Some(auto) = auto else {
+        return;
+    };
+    if !scene_ready.ready {
+        return;
+    }
+    if let Some(pending) = &*pending {
+        match pending.lock().unwrap().as_ref() {
+            Some(Ok(())) if auto.exit_after_capture => {
+                app_exit.send(AppExit);
+            }
+            // The error has already been logged by the callback
+            Some(Err(_)) if auto.exit_after_capture => std::process::exit(1),
+            _ => {}
+        }
+        return;
+    }
+    *frames_since_ready += 1;
+    if *frames_since_ready < CAPTURE_DELAY_FRAMES {
+        return;
+    }
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let outcome = Arc::new(Mutex::new(None));
+    // Try again next frame if the screenshot couldn't be requested
+    if save_screenshot(
+        &mut screenshot_manager,
+        window,
+        auto.path.clone(),
+        Some(outcome.clone()),
+    ) {
+        *pending = Some(outcome);
+    }
+}
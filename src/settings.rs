@@ -0,0 +1,163 @@
+use std::{fs, path::Path};
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    window::{PrimaryWindow, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{render_features::RenderFeatures, render_scale::RenderScale, Args};
+
+pub const SETTINGS_PATH: &str = "settings.toml";
+
+/// The window size, feature toggles, camera, and render scale of the last run, restored at
+/// startup and saved on exit. Options given on the command line win over the saved ones. Left
+/// out of benchmarks and other automated runs so they always start the same way.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct UserSettings {
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    pub render_scale: Option<f32>,
+    pub ssao: Option<bool>,
+    pub taa: Option<bool>,
+    pub bloom: Option<bool>,
+    pub shadows: Option<bool>,
+    pub msaa: Option<bool>,
+    pub frustum_culling: Option<bool>,
+    /// Preset the camera was in, it's only restored in the same one.
+    pub scene: Option<String>,
+    pub camera_translation: Option<[f32; 3]>,
+    pub camera_rotation: Option<[f32; 4]>,
+}
+
+impl UserSettings {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Settings to start with, `None` for runs that shouldn't depend on the last one.
+    pub fn for_args(args: &Args) -> Option<Self> {
+        let automated = args.bench
+            || args.bench_matrix.is_some()
+            || args.compare_golden.is_some()
+            || args.replay.is_some()
+            || args.exit_after_capture;
+        #[cfg(not(target_arch = "wasm32"))]
+        let automated = automated || args.render_video.is_some();
+        if automated {
+            return None;
+        }
+        if args.reset_settings {
+            if Path::new(SETTINGS_PATH).exists() {
+                if let Err(e) = fs::remove_file(SETTINGS_PATH) {
+                    println!("Failed to remove {SETTINGS_PATH}: {e}");
+                }
+            }
+            return Some(Self::default());
+        }
+        match Self::load(SETTINGS_PATH) {
+            Ok(settings) => Some(settings),
+            Err(e) => {
+                if Path::new(SETTINGS_PATH).exists() {
+                    println!("Ignoring invalid {SETTINGS_PATH}: {e}");
+                }
+                Some(Self::default())
+            }
+        }
+    }
+
+    /// Fills in the options that weren't given on the command line.
+    pub fn restore_args(&self, args: &mut Args) {
+        args.width = args.width.or(self.width);
+        args.height = args.height.or(self.height);
+        args.render_scale = args.render_scale.or(self.render_scale);
+    }
+
+    /// Features turned off on the command line stay off.
+    pub fn restore_features(&self, features: &mut RenderFeatures) {
+        features.ssao &= self.ssao.unwrap_or(true);
+        features.taa &= self.taa.unwrap_or(true);
+        features.bloom &= self.bloom.unwrap_or(true);
+        features.shadows &= self.shadows.unwrap_or(true);
+        features.frustum_culling &= self.frustum_culling.unwrap_or(true);
+        features.msaa |= self.msaa.unwrap_or(false) && !features.deferred;
+        features.taa &= !features.msaa;
+    }
+
+    /// Where the camera was left, if it was in the same scene.
+    pub fn camera(&self, scene: &str) -> Option<Transform> {
+        if self.scene.as_deref() != Some(scene) {
+            return None;
+        }
+        Some(
+            Transform::from_translation(Vec3::from_array(self.camera_translation?))
+                .with_rotation(Quat::from_array(self.camera_rotation?)),
+        )
+    }
+}
+
+pub struct UserSettingsPlugin;
+impl Plugin for UserSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, track_window_size)
+            .add_systems(Last, save_settings_on_exit);
+    }
+}
+
+/// The window is already closed by the time the app exits, so its size is kept as it changes.
+fn track_window_size(
+    settings: Option<ResMut<UserSettings>>,
+    window: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+) {
+    let (Some(mut settings), Ok(window)) = (settings, window.get_single()) else {
+        return;
+    };
+    // The size of a fullscreen window is the monitor's
+    if window.mode != WindowMode::Windowed {
+        return;
+    }
+    let (width, height) = (window.resolution.width(), window.resolution.height());
+    if width > 0.0 && height > 0.0 {
+        settings.width = Some(width);
+        settings.height = Some(height);
+    }
+}
+
+fn save_settings_on_exit(
+    mut exit: EventReader<AppExit>,
+    settings: Option<ResMut<UserSettings>>,
+    features: Res<RenderFeatures>,
+    render_scale: Res<RenderScale>,
+    args: Res<Args>,
+    camera: Query<&Transform, With<Camera3d>>,
+) {
+    if exit.read().next().is_none() {
+        return;
+    }
+    let Some(mut settings) = settings else {
+        return;
+    };
+    settings.render_scale = Some(render_scale.scale);
+    settings.ssao = Some(features.ssao);
+    settings.taa = Some(features.taa);
+    settings.bloom = Some(features.bloom);
+    settings.shadows = Some(features.shadows);
+    settings.msaa = Some(features.msaa);
+    settings.frustum_culling = Some(features.frustum_culling);
+    if let Ok(transform) = camera.get_single() {
+        settings.scene = Some(args.preset.clone().unwrap_or_else(|| args.scene.clone()));
+        settings.camera_translation = Some(transform.translation.to_array());
+        settings.camera_rotation = Some(transform.rotation.to_array());
+    }
+    match settings.save(SETTINGS_PATH) {
+        Ok(_) => info!("Saved settings to {SETTINGS_PATH}"),
+        Err(e) => error!("Failed to save settings to {SETTINGS_PATH}: {e}"),
+    }
+}
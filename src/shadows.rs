@@ -0,0 +1,127 @@
+use bevy::{
+    pbr::{
+        CascadeShadowConfig, CascadeShadowConfigBuilder, DirectionalLightShadowMap,
+        ShadowFilteringMethod,
+    },
+    prelude::*,
+};
+
+use crate::GrifLight;
+
+const MAP_SIZES: [usize; 4] = [1024, 2048, 4096, 8192];
+const FILTERS: [(&str, ShadowFilteringMethod); 3] = [
+    ("hardware2x2", ShadowFilteringMethod::Hardware2x2),
+    ("castano13", ShadowFilteringMethod::Castano13),
+    ("jimenez14", ShadowFilteringMethod::Jimenez14),
+];
+
+/// Shadow quality for the directional lights of the light rig. Changing this resource updates
+/// the cascades on the lights, the shadow map size, and the camera's filtering method.
+/// F5 cycles the map size, F6 the cascade count, and F7 the filtering method.
+#[derive(Resource, Clone, Copy)]
+pub struct ShadowSettings {
+    pub cascades: usize,
+    /// Far bound of the first cascade, the one with the most detail.
+    pub first_cascade_far_bound: f32,
+    /// Distance from the camera past which there are no shadows.
+    pub maximum_distance: f32,
+    pub map_size: usize,
+    pub filtering: ShadowFilteringMethod,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        let cascades = CascadeShadowConfigBuilder::default();
+        Self {
+            cascades: cascades.num_cascades,
+            first_cascade_far_bound: cascades.first_cascade_far_bound,
+            maximum_distance: cascades.maximum_distance,
+            map_size: DirectionalLightShadowMap::default().size,
+            filtering: ShadowFilteringMethod::default(),
+        }
+    }
+}
+
+impl ShadowSettings {
+    fn cascade_config(&self) -> CascadeShadowConfig {
+        CascadeShadowConfigBuilder {
+            num_cascades: self.cascades.max(1),
+            first_cascade_far_bound: self.first_cascade_far_bound,
+            maximum_distance: self.maximum_distance,
+            ..default()
+        }
+        .into()
+    }
+}
+
+pub fn parse_shadow_filter(value: &str) -> Result<ShadowFilteringMethod, String> {
+    FILTERS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, filter)| *filter)
+        .ok_or_else(|| {
+            format!("Unknown shadow filter {value}, expected hardware2x2, castano13, or jimenez14")
+        })
+}
+
+fn filter_name(filter: ShadowFilteringMethod) -> &'static str {
+    FILTERS
+        .iter()
+        .find(|(_, f)| *f == filter)
+        .map_or("other", |(name, _)| name)
+}
+
+pub struct ShadowSettingsPlugin;
+impl Plugin for ShadowSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShadowSettings>().add_systems(
+            Update,
+            (cycle_shadow_settings, apply_shadow_settings).chain(),
+        );
+    }
+}
+
+fn cycle_shadow_settings(input: Res<ButtonInput<KeyCode>>, mut settings: ResMut<ShadowSettings>) {
+    if input.just_pressed(KeyCode::F5) {
+        let current = MAP_SIZES.iter().position(|size| *size == settings.map_size);
+        settings.map_size = MAP_SIZES[current.map_or(0, |i| (i + 1) % MAP_SIZES.len())];
+    } else if input.just_pressed(KeyCode::F6) {
+        settings.cascades = settings.cascades % 4 + 1;
+    } else if input.just_pressed(KeyCode::F7) {
+        let current = FILTERS.iter().position(|(_, f)| *f == settings.filtering);
+        settings.filtering = FILTERS[current.map_or(0, |i| (i + 1) % FILTERS.len())].1;
+    } else {
+        return;
+    }
+    println!(
+        "Shadows: {} map, {} cascades, {} filtering",
+        settings.map_size,
+        settings.cascades,
+        filter_name(settings.filtering)
+    );
+}
+
+fn apply_shadow_settings(
+    mut commands: Commands,
+    settings: Res<ShadowSettings>,
+    mut shadow_map: ResMut<DirectionalLightShadowMap>,
+    lights: Query<Entity, (With<DirectionalLight>, With<GrifLight>)>,
+    new_lights: Query<Entity, (With<DirectionalLight>, Added<GrifLight>)>,
+    cameras: Query<Entity, With<Camera3d>>,
+    new_cameras: Query<Entity, Added<Camera3d>>,
+) {
+    let (lights, cameras): (Vec<_>, Vec<_>) = if settings.is_changed() {
+        (lights.iter().collect(), cameras.iter().collect())
+    } else {
+        (new_lights.iter().collect(), new_cameras.iter().collect())
+    };
+    if settings.is_changed() {
+        shadow_map.size = settings.map_size;
+    }
+    for entity in lights {
+        commands.entity(entity).insert(settings.cascade_config());
+    }
+    for entity in cameras {
+        commands.entity(entity).insert(settings.filtering);
+    }
+}
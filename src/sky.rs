@@ -0,0 +1,301 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    core_pipeline::Skybox,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension,
+        },
+    },
+};
+
+use crate::{
+    environment_map::EnvironmentMaps,
+    skybox::{cube_direction, f32_to_f16},
+    GrifLight,
+};
+
+/// Face size of the sky cubemap, the diffuse cubemap is much smaller since it's so smooth.
+const SKY_SIZE: u32 = 64;
+const DIFFUSE_SIZE: u32 = 16;
+/// Brightness of the skybox and environment light, so the sky ends up around 1.0 at noon.
+pub const SKY_BRIGHTNESS: f32 = 1000.0;
+/// Radiance of sunlight before it's scattered, scales the sky to the range above.
+const SUN_RADIANCE: f32 = 50.0;
+/// Only shown in the skybox, the directional light already lights the scene.
+const SUN_DISC_RADIANCE: f32 = 200.0;
+/// Cosine of the sun disc's angular radius, drawn a few times larger than the real sun.
+const SUN_DISC_COS: f32 = 0.99995;
+/// Relative Rayleigh scattering per color channel, blue scatters the most.
+const RAYLEIGH: Vec3 = Vec3::new(0.058, 0.135, 0.331);
+const MIE: f32 = 0.02;
+const MIE_G: f32 = 0.76;
+const NIGHT_SKY: Vec3 = Vec3::new(0.0005, 0.0007, 0.0015);
+const GROUND_ALBEDO: f32 = 0.3;
+/// Regenerate the cubemaps when the sun has moved this far, in radians.
+const REGENERATE_ANGLE: f32 = 0.01;
+
+/// An analytic sky whose sun follows the [`GrifLight`] sun, so sweeping the time of day also
+/// changes the sky and the environment lighting. Replaces the environment map when the resource
+/// is present, see `--procedural-sky`.
+#[derive(Resource, Default)]
+pub struct ProceduralSky {
+    /// Sun direction the cubemaps were last generated for.
+    sun: Option<Vec3>,
+    skybox: Option<Handle<Image>>,
+    specular: Option<Handle<Image>>,
+    diffuse: Option<Handle<Image>>,
+}
+
+pub struct ProceduralSkyPlugin;
+impl Plugin for ProceduralSkyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_procedural_sky);
+    }
+}
+
+/// Air mass along a view direction with a vertical component of `y`, Kasten and Young's
+/// approximation so it stays finite at the horizon.
+fn air_mass(y: f32) -> f32 {
+    let y = y.max(0.0);
+    let elevation = y.min(1.0).asin().to_degrees();
+    1.0 / (y + 0.50572 * (6.07995 + elevation).powf(-1.6364))
+}
+
+/// Single scattering sky radiance looking along `dir`, with the sun towards `sun`.
+pub fn sky_radiance(dir: Vec3, sun: Vec3) -> Vec3 {
+    if dir.y < 0.0 {
+        // Light bounced off the ground, lit by the sky near the horizon
+        let horizon = Vec3::new(dir.x, 0.0, dir.z).normalize_or_zero();
+        return sky_radiance(horizon, sun) * GROUND_ALBEDO * sun.y.max(0.05);
+    }
+    let mu = dir.dot(sun);
+    let rayleigh_phase = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+    let mie_phase = 1.0 / (4.0 * PI) * (1.0 - MIE_G * MIE_G)
+        / (1.0 + MIE_G * MIE_G - 2.0 * MIE_G * mu).powf(1.5);
+    let extinction = RAYLEIGH + Vec3::splat(MIE);
+    let sun_transmittance = (-extinction * air_mass(sun.y)).exp();
+    let view_extinction = extinction * air_mass(dir.y);
+    let inscatter = sun_transmittance * (RAYLEIGH * rayleigh_phase + MIE * mie_phase) / extinction
+        * (Vec3::ONE - (-view_extinction).exp());
+    // Fade out once the sun is below the horizon
+    let daylight = ((sun.y + 0.1) / 0.15).clamp(0.0, 1.0);
+    NIGHT_SKY + inscatter * SUN_RADIANCE * daylight
+}
+
+fn sun_disc(dir: Vec3, sun: Vec3) -> Vec3 {
+    if dir.y < 0.0 || dir.dot(sun) < SUN_DISC_COS {
+        return Vec3::ZERO;
+    }
+    let extinction = RAYLEIGH + Vec3::splat(MIE);
+    (-extinction * air_mass(sun.y)).exp() * SUN_DISC_RADIANCE
+}
+
+/// Texels of the six faces of a cubemap, in the order of [`cube_direction`].
+type Faces = [Vec<Vec3>; 6];
+
+fn render_faces(size: u32, mut radiance: impl FnMut(Vec3) -> Vec3) -> Faces {
+    std::array::from_fn(|face| {
+        let mut texels = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let u = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                texels.push(radiance(cube_direction(face, u, v)));
+            }
+        }
+        texels
+    })
+}
+
+/// A box filtered mip chain. Not the GGX prefiltering Bevy's baked maps use, but the sky is
+/// smooth enough that rough reflections look right.
+fn mip_chain(faces: Faces, size: u32) -> Vec<Faces> {
+    let mut levels = vec![faces];
+    let mut size = size;
+    while size > 1 {
+        let half = size / 2;
+        let previous = levels.last().unwrap();
+        let next = std::array::from_fn(|face| {
+            let texels = &previous[face];
+            let mut out = Vec::with_capacity((half * half) as usize);
+            for y in 0..half {
+                for x in 0..half {
+                    let i = (y * 2 * size + x * 2) as usize;
+                    let s = size as usize;
+                    out.push(
+                        (texels[i] + texels[i + 1] + texels[i + s] + texels[i + s + 1]) * 0.25,
+                    );
+                }
+            }
+            out
+        });
+        levels.push(next);
+        size = half;
+    }
+    levels
+}
+
+/// Projects the sky onto the first 9 spherical harmonics.
+fn project_sh(faces: &Faces, size: u32) -> [Vec3; 9] {
+    let mut sh = [Vec3::ZERO; 9];
+    for (face, texels) in faces.iter().enumerate() {
+        for y in 0..size {
+            for x in 0..size {
+                let u = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let solid_angle = 4.0 / ((size * size) as f32 * (1.0 + u * u + v * v).powf(1.5));
+                let dir = cube_direction(face, u, v);
+                let radiance = texels[(y * size + x) as usize];
+                for (coefficient, basis) in sh.iter_mut().zip(sh_basis(dir)) {
+                    *coefficient += radiance * basis * solid_angle;
+                }
+            }
+        }
+    }
+    sh
+}
+
+fn sh_basis(n: Vec3) -> [f32; 9] {
+    [
+        0.282095,
+        0.488603 * n.y,
+        0.488603 * n.z,
+        0.488603 * n.x,
+        1.092548 * n.x * n.y,
+        1.092548 * n.y * n.z,
+        0.315392 * (3.0 * n.z * n.z - 1.0),
+        1.092548 * n.x * n.z,
+        0.546274 * (n.x * n.x - n.y * n.y),
+    ]
+}
+
+/// Cosine convolved radiance for a surface facing `n`, divided by PI as Bevy's diffuse maps are.
+fn diffuse_radiance(sh: &[Vec3; 9], n: Vec3) -> Vec3 {
+    // Lambert convolution weights per band, already divided by PI
+    const BAND: [f32; 9] = [
+        1.0,
+        2.0 / 3.0,
+        2.0 / 3.0,
+        2.0 / 3.0,
+        0.25,
+        0.25,
+        0.25,
+        0.25,
+        0.25,
+    ];
+    sh.iter()
+        .zip(sh_basis(n))
+        .zip(BAND)
+        .map(|((coefficient, basis), band)| *coefficient * basis * band)
+        .sum::<Vec3>()
+        .max(Vec3::ZERO)
+}
+
+/// Packs a cubemap with its mips into a half float image, all mips of a face before the next
+/// face as wgpu expects.
+fn cubemap_image(levels: &[Faces], size: u32) -> Image {
+    let mut data = Vec::new();
+    for face in 0..6 {
+        for level in levels {
+            for texel in &level[face] {
+                for channel in [texel.x, texel.y, texel.z, 1.0] {
+                    data.extend_from_slice(&f32_to_f16(channel).to_le_bytes());
+                }
+            }
+        }
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba16Float,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.texture_descriptor.mip_level_count = levels.len() as u32;
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        ..default()
+    });
+    image
+}
+
+/// Replaces the image behind `handle`, or adds it the first time.
+fn set_image(images: &mut Assets<Image>, handle: &mut Option<Handle<Image>>, image: Image) {
+    match handle.as_ref().and_then(|handle| images.get_mut(handle)) {
+        Some(existing) => *existing = image,
+        None => *handle = Some(images.add(image)),
+    }
+}
+
+fn update_procedural_sky(
+    mut commands: Commands,
+    sky: Option<ResMut<ProceduralSky>>,
+    env_maps: Res<EnvironmentMaps>,
+    suns: Query<&GlobalTransform, (With<DirectionalLight>, With<GrifLight>)>,
+    cameras: Query<Entity, With<Camera3d>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(mut sky) = sky else {
+        return;
+    };
+    let Some(sun) = suns.iter().next() else {
+        return;
+    };
+    let sun = -sun.forward();
+    // Switching environment maps would replace the sky's lighting, so put it back
+    let moved = match sky.sun {
+        Some(last) => last.angle_between(sun) > REGENERATE_ANGLE,
+        None => true,
+    };
+    if !moved && !env_maps.is_changed() {
+        return;
+    }
+    sky.sun = Some(sun);
+
+    let faces = render_faces(SKY_SIZE, |dir| sky_radiance(dir, sun));
+    let skybox = render_faces(SKY_SIZE, |dir| sky_radiance(dir, sun) + sun_disc(dir, sun));
+    let sh = project_sh(&faces, SKY_SIZE);
+    let diffuse = render_faces(DIFFUSE_SIZE, |dir| diffuse_radiance(&sh, dir));
+    let specular = mip_chain(faces, SKY_SIZE);
+
+    let sky = &mut *sky;
+    set_image(
+        &mut images,
+        &mut sky.skybox,
+        cubemap_image(&[skybox], SKY_SIZE),
+    );
+    set_image(
+        &mut images,
+        &mut sky.specular,
+        cubemap_image(&specular, SKY_SIZE),
+    );
+    set_image(
+        &mut images,
+        &mut sky.diffuse,
+        cubemap_image(&[diffuse], DIFFUSE_SIZE),
+    );
+    let (Some(skybox), Some(specular), Some(diffuse)) = (&sky.skybox, &sky.specular, &sky.diffuse)
+    else {
+        return;
+    };
+    for entity in &cameras {
+        commands.entity(entity).insert((
+            Skybox {
+                image: skybox.clone(),
+                brightness: SKY_BRIGHTNESS,
+            },
+            EnvironmentMapLight {
+                diffuse_map: diffuse.clone(),
+                specular_map: specular.clone(),
+                intensity: SKY_BRIGHTNESS,
+            },
+        ));
+    }
+}
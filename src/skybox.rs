@@ -0,0 +1,199 @@
+use std::f32::consts::PI;
+
+use anyhow::anyhow;
+use bevy::{
+    core_pipeline::Skybox,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension,
+        },
+    },
+    utils::HashMap,
+};
+use image::Rgba32FImage;
+
+use crate::environment_map::EnvironmentMaps;
+
+/// Shows the current environment map behind the scene instead of the flat `ClearColor`, which
+/// makes screenshots look unfinished and biases bloom. Off with `--no-skybox`, and while
+/// `--time-of-day` drives the sky color.
+#[derive(Resource)]
+pub struct Skyboxes {
+    pub enabled: bool,
+    /// Equirectangular HDRs from the manifest that are loading, by path.
+    equirect: HashMap<String, Handle<Image>>,
+    /// Cubemaps converted from them.
+    cubemaps: HashMap<String, Handle<Image>>,
+    waiting: bool,
+}
+
+impl Default for Skyboxes {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            equirect: HashMap::new(),
+            cubemaps: HashMap::new(),
+            waiting: false,
+        }
+    }
+}
+
+impl Skyboxes {
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..default()
+        }
+    }
+}
+
+pub struct SkyboxPlugin;
+impl Plugin for SkyboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Skyboxes>()
+            .add_systems(Update, apply_skybox);
+    }
+}
+
+fn apply_skybox(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    env_maps: Res<EnvironmentMaps>,
+    mut skyboxes: ResMut<Skyboxes>,
+    mut images: ResMut<Assets<Image>>,
+    cameras: Query<Entity, With<Camera3d>>,
+) {
+    if !skyboxes.enabled || !(env_maps.is_changed() || skyboxes.waiting) {
+        return;
+    }
+    let entry = env_maps.current();
+    skyboxes.waiting = false;
+    let image = match &entry.skybox {
+        None => asset_server.load(&entry.specular),
+        Some(path) => match skyboxes.cubemaps.get(path) {
+            Some(cubemap) => cubemap.clone(),
+            None => {
+                let equirect = skyboxes
+                    .equirect
+                    .entry(path.clone())
+                    .or_insert_with(|| asset_server.load(path))
+                    .clone();
+                let Some(source) = images.get(&equirect) else {
+                    skyboxes.waiting = true;
+                    return;
+                };
+                match equirect_to_cubemap(source) {
+                    Ok(cubemap) => {
+                        let cubemap = images.add(cubemap);
+                        skyboxes.cubemaps.insert(path.clone(), cubemap.clone());
+                        cubemap
+                    }
+                    Err(e) => {
+                        warn!("Showing the specular map, failed to convert skybox {path}: {e}");
+                        asset_server.load(&entry.specular)
+                    }
+                }
+            }
+        },
+    };
+    for entity in &cameras {
+        commands.entity(entity).insert(Skybox {
+            image: image.clone(),
+            brightness: entry.intensity,
+        });
+    }
+}
+
+/// Direction through a point on a cubemap face, `u` and `v` in -1..1, following the wgpu face
+/// order +X, -X, +Y, -Y, +Z, -Z.
+pub fn cube_direction(face: usize, u: f32, v: f32) -> Vec3 {
+    match face {
+        0 => Vec3::new(1.0, -v, -u),
+        1 => Vec3::new(-1.0, -v, u),
+        2 => Vec3::new(u, 1.0, v),
+        3 => Vec3::new(u, -1.0, -v),
+        4 => Vec3::new(u, -v, 1.0),
+        _ => Vec3::new(-u, -v, -1.0),
+    }
+    .normalize()
+}
+
+fn sample_bilinear(image: &Rgba32FImage, x: f32, y: f32) -> [f32; 4] {
+    let (width, height) = image.dimensions();
+    let x = (x - 0.5).rem_euclid(width as f32);
+    let y = (y - 0.5).clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1) % width, (y0 + 1).min(height - 1));
+    let (fx, fy) = (x.fract(), y.fract());
+    let mut out = [0.0; 4];
+    for (c, out) in out.iter_mut().enumerate() {
+        let top = image.get_pixel(x0, y0)[c] * (1.0 - fx) + image.get_pixel(x1, y0)[c] * fx;
+        let bottom = image.get_pixel(x0, y1)[c] * (1.0 - fx) + image.get_pixel(x1, y1)[c] * fx;
+        *out = top * (1.0 - fy) + bottom * fy;
+    }
+    out
+}
+
+/// Converts to a half float, flushing values too small for it to zero. 32 bit float textures
+/// can't be filtered on every GPU, so cubemaps are stored as 16 bit.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent <= 0 {
+        sign
+    } else if exponent >= 31 {
+        sign | 0x7c00
+    } else {
+        sign | ((exponent as u16) << 10) | ((mantissa >> 13) as u16)
+    }
+}
+
+/// Resamples an equirectangular panorama into a cubemap with faces a quarter of its width.
+fn equirect_to_cubemap(image: &Image) -> anyhow::Result<Image> {
+    let source = image
+        .clone()
+        .try_into_dynamic()
+        .map_err(|e| anyhow!("{e:?}"))?
+        .to_rgba32f();
+    let size = (source.width() / 4).max(1);
+    let mut data = Vec::with_capacity((size * size * 6 * 8) as usize);
+    for face in 0..6 {
+        for y in 0..size {
+            for x in 0..size {
+                let u = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let dir = cube_direction(face, u, v);
+                let longitude = dir.z.atan2(dir.x);
+                let latitude = dir.y.clamp(-1.0, 1.0).asin();
+                let pixel = sample_bilinear(
+                    &source,
+                    (0.5 + longitude / (2.0 * PI)) * source.width() as f32,
+                    (0.5 - latitude / PI) * source.height() as f32,
+                );
+                for channel in pixel {
+                    data.extend_from_slice(&f32_to_f16(channel).to_le_bytes());
+                }
+            }
+        }
+    }
+    let mut cubemap = Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba16Float,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    cubemap.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        ..default()
+    });
+    Ok(cubemap)
+}
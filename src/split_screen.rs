@@ -0,0 +1,194 @@
+use bevy::{
+    core_pipeline::{
+        prepass::{DeferredPrepass, DepthPrepass},
+        tonemapping::Tonemapping,
+    },
+    pbr::environment_map::EnvironmentMapLight,
+    prelude::*,
+    render::{
+        camera::{ClearColorConfig, Exposure, Viewport},
+        view::VisibilitySystems,
+    },
+    transform::TransformSystem,
+    window::PrimaryWindow,
+};
+
+use crate::render_features::RenderFeatures;
+
+/// Renders the right half of the window from a second camera without TAA, SSAO, or bloom, so
+/// the features toggled on the main camera in the left half can be compared side by side.
+#[derive(Resource, Default)]
+pub struct SplitScreen;
+
+/// The camera rendering the right half. It has no `Transform` of its own and follows the main
+/// camera, so the systems that move the camera leave it alone.
+#[derive(Component)]
+pub struct ComparisonCamera;
+
+pub struct SplitScreenPlugin;
+impl Plugin for SplitScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (spawn_comparison_camera, set_viewports).run_if(resource_exists::<SplitScreen>),
+        )
+        .add_systems(
+            PostUpdate,
+            follow_main_camera
+                .run_if(resource_exists::<SplitScreen>)
+                .after(TransformSystem::TransformPropagate)
+                .before(VisibilitySystems::UpdatePerspectiveFrusta),
+        );
+    }
+}
+
+fn spawn_comparison_camera(
+    mut commands: Commands,
+    main: Query<
+        (&Camera, Option<&EnvironmentMapLight>),
+        (With<Camera3d>, Without<ComparisonCamera>),
+    >,
+    comparison: Query<(), With<ComparisonCamera>>,
+    features: Res<RenderFeatures>,
+) {
+    if !comparison.is_empty() {
+        return;
+    }
+    let Ok((camera, env_map)) = main.get_single() else {
+        return;
+    };
+    let mut cam = commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                hdr: camera.hdr,
+                order: camera.order + 1,
+                target: camera.target.clone(),
+                // Both cameras draw into the same texture, clearing would erase the left half
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            ..default()
+        },
+        ComparisonCamera,
+    ));
+    cam.remove::<Transform>();
+    if let Some(env_map) = env_map {
+        cam.insert(env_map.clone());
+    }
+    // Deferred materials aren't drawn by the forward pass
+    if features.deferred {
+        cam.insert((DepthPrepass, DeferredPrepass));
+    }
+
+    let label = |text: &str, left: Val| {
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font_size: 18.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            left,
+            ..default()
+        })
+    };
+    commands.spawn(label("Selected features", Val::Percent(1.0)));
+    commands.spawn(label("No TAA, SSAO, or bloom", Val::Percent(51.0)));
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(50.0),
+            width: Val::Px(2.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        background_color: Color::BLACK.into(),
+        ..default()
+    });
+}
+
+/// Splits the render target between the two cameras, following window resizes and the render
+/// scale.
+fn set_viewports(
+    mut main: Query<&mut Camera, (With<Camera3d>, Without<ComparisonCamera>)>,
+    mut comparison: Query<&mut Camera, With<ComparisonCamera>>,
+) {
+    let (Ok(mut main), Ok(mut comparison)) = (main.get_single_mut(), comparison.get_single_mut())
+    else {
+        return;
+    };
+    let Some(size) = main.physical_target_size() else {
+        return;
+    };
+    let half = UVec2::new((size.x / 2).max(1), size.y.max(1));
+    set_viewport(&mut main, UVec2::ZERO, half);
+    set_viewport(&mut comparison, UVec2::new(size.x - half.x, 0), half);
+}
+
+fn set_viewport(camera: &mut Camera, position: UVec2, size: UVec2) {
+    let current = camera
+        .viewport
+        .as_ref()
+        .map(|viewport| (viewport.physical_position, viewport.physical_size));
+    if current != Some((position, size)) {
+        camera.viewport = Some(Viewport {
+            physical_position: position,
+            physical_size: size,
+            ..default()
+        });
+    }
+}
+
+fn follow_main_camera(
+    main: Query<
+        (
+            &GlobalTransform,
+            &Camera,
+            &Projection,
+            &Tonemapping,
+            &Exposure,
+        ),
+        (With<Camera3d>, Without<ComparisonCamera>),
+    >,
+    mut comparison: Query<
+        (
+            &mut GlobalTransform,
+            &mut Camera,
+            &mut Projection,
+            &mut Tonemapping,
+            &mut Exposure,
+        ),
+        With<ComparisonCamera>,
+    >,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+) {
+    let (Ok(main), Ok(mut comparison)) = (main.get_single(), comparison.get_single_mut()) else {
+        return;
+    };
+    *comparison.0 = *main.0;
+    // Render targets aren't comparable until the window they refer to is resolved
+    let primary_window = primary_window.get_single().ok();
+    if comparison.1.target.normalize(primary_window) != main.1.target.normalize(primary_window) {
+        comparison.1.target = main.1.target.clone();
+    }
+    // The aspect ratio comes from each camera's own viewport
+    if let (Projection::Perspective(from), Projection::Perspective(to)) = (main.2, &*comparison.2) {
+        if to.fov != from.fov || to.near != from.near || to.far != from.far {
+            let projection = PerspectiveProjection {
+                aspect_ratio: to.aspect_ratio,
+                ..from.clone()
+            };
+            *comparison.2 = Projection::Perspective(projection);
+        }
+    }
+    if *comparison.3 != *main.3 {
+        *comparison.3 = *main.3;
+    }
+    if comparison.4.ev100 != main.4.ev100 {
+        *comparison.4 = *main.4;
+    }
+}
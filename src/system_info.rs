@@ -0,0 +1,99 @@
+use std::fmt;
+
+use bevy::{prelude::*, render::renderer::RenderAdapterInfo};
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
+
+/// Hardware and build the app runs on, stamped into the benchmark results so numbers shared
+/// elsewhere keep their context.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct SystemInfo {
+    pub gpu: String,
+    /// Driver name and version as reported by the backend, empty on some platforms.
+    pub driver: String,
+    pub backend: String,
+    pub os: String,
+    pub cpu: String,
+    pub memory_gb: f32,
+    /// Commit the app was run from, with `-dirty` if there were uncommitted changes.
+    pub git_hash: Option<String>,
+}
+
+impl SystemInfo {
+    fn collect(adapter: &RenderAdapterInfo) -> Self {
+        let mut sys = System::new();
+        sys.refresh_memory();
+        sys.refresh_cpu();
+        let driver = [adapter.driver.as_str(), adapter.driver_info.as_str()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self {
+            gpu: adapter.name.clone(),
+            driver,
+            backend: format!("{:?}", adapter.backend),
+            os: System::long_os_version().unwrap_or_else(|| std::env::consts::OS.to_string()),
+            cpu: sys
+                .cpus()
+                .first()
+                .map(|cpu| cpu.brand().trim().to_string())
+                .unwrap_or_default(),
+            memory_gb: sys.total_memory() as f32 / (1024.0 * 1024.0 * 1024.0),
+            git_hash: git_hash(),
+        }
+    }
+}
+
+/// Asks git rather than stamping the hash at build time, which would need a build script.
+#[cfg(not(target_arch = "wasm32"))]
+fn git_hash() -> Option<String> {
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let hash = git(&["rev-parse", "--short", "HEAD"])?;
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+    Some(if dirty { format!("{hash}-dirty") } else { hash })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn git_hash() -> Option<String> {
+    None
+}
+
+impl fmt::Display for SystemInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}", self.gpu, self.backend)?;
+        if !self.driver.is_empty() {
+            write!(f, ", {}", self.driver)?;
+        }
+        write!(
+            f,
+            "), {}, {:.0}GB, {}, git {}",
+            self.cpu,
+            self.memory_gb,
+            self.os,
+            self.git_hash.as_deref().unwrap_or("unknown")
+        )
+    }
+}
+
+pub struct SystemInfoPlugin;
+impl Plugin for SystemInfoPlugin {
+    fn build(&self, _app: &mut App) {}
+
+    fn finish(&self, app: &mut App) {
+        let Some(adapter) = app.world.get_resource::<RenderAdapterInfo>() else {
+            return;
+        };
+        let info = SystemInfo::collect(adapter);
+        info!("System: {info}");
+        app.insert_resource(info);
+    }
+}
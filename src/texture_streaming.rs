@@ -0,0 +1,296 @@
+use bevy::{
+    prelude::*,
+    render::{
+        primitives::Aabb,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureUsages},
+    },
+    utils::HashMap,
+};
+
+use crate::{loading::SceneReady, mipmap_generator::GetImages, wind::WindMaterial};
+
+/// Textures within this distance of the camera keep their full resolution, each doubling of the
+/// distance drops another mip.
+const FULL_RESOLUTION_DISTANCE: f32 = 4.0;
+/// Never drop mips below this size, so distant textures keep their color.
+const MIN_RESIDENT_SIZE: u32 = 32;
+/// How often to recompute the resident mips, the camera rarely moves far in a quarter second.
+const UPDATE_INTERVAL_SECS: f32 = 0.25;
+/// Textures swapped per update at most, so streaming in a room doesn't hitch.
+const MAX_SWAPS_PER_UPDATE: usize = 16;
+
+/// Keeps only the lower mips of distant materials' textures on the GPU, streaming the higher ones
+/// back in as the camera gets close, and dropping more mips from the farthest textures while the
+/// total is over `budget_bytes`. The full mip chains stay in system memory, Bevy 0.13 can only
+/// load a KTX2 file whole. They're moved out of `Assets<Image>` so there's only the one copy,
+/// the textures swapped in are only kept in the render world. Only active when the resource is
+/// present, see `--texture-budget`.
+#[derive(Resource)]
+pub struct TextureStreaming {
+    pub budget_bytes: u64,
+    textures: HashMap<AssetId<Image>, StreamedTexture>,
+    since_update: f32,
+}
+
+impl TextureStreaming {
+    pub fn new(budget_bytes: u64) -> Self {
+        Self {
+            budget_bytes,
+            textures: HashMap::new(),
+            since_update: 0.0,
+        }
+    }
+}
+
+struct StreamedTexture {
+    full: Image,
+    /// Mips currently left out of the GPU texture, counted from the full resolution one.
+    dropped: u32,
+}
+
+impl StreamedTexture {
+    fn max_dropped(&self) -> u32 {
+        let size = self.full.texture_descriptor.size;
+        let mut dropped = 0;
+        while dropped + 1 < self.full.texture_descriptor.mip_level_count
+            && (size.width.min(size.height) >> (dropped + 1)) >= MIN_RESIDENT_SIZE
+        {
+            dropped += 1;
+        }
+        dropped
+    }
+
+    fn resident_bytes(&self, dropped: u32) -> u64 {
+        (dropped..self.full.texture_descriptor.mip_level_count)
+            .map(|level| mip_level_bytes(&self.full, level) as u64)
+            .sum()
+    }
+}
+
+pub struct TextureStreamingPlugin;
+impl Plugin for TextureStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (collect_streamed_textures, stream_textures).chain());
+    }
+}
+
+fn mip_level_bytes(image: &Image, level: u32) -> usize {
+    let size = image.texture_descriptor.size;
+    let format = image.texture_descriptor.format;
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format.block_copy_size(None).unwrap_or(4) as usize;
+    let width = (size.width >> level).max(1);
+    let height = (size.height >> level).max(1);
+    width.div_ceil(block_width) as usize * height.div_ceil(block_height) as usize * block_size
+}
+
+/// A copy of `image` without its first `dropped` mips.
+fn without_top_mips(image: &Image, dropped: u32) -> Image {
+    let offset: usize = (0..dropped)
+        .map(|level| mip_level_bytes(image, level))
+        .sum();
+    let mut descriptor = image.texture_descriptor.clone();
+    descriptor.size = Extent3d {
+        width: (descriptor.size.width >> dropped).max(1),
+        height: (descriptor.size.height >> dropped).max(1),
+        depth_or_array_layers: 1,
+    };
+    descriptor.mip_level_count -= dropped;
+    Image {
+        data: image.data[offset..].to_vec(),
+        texture_descriptor: descriptor,
+        sampler: image.sampler.clone(),
+        texture_view_descriptor: image.texture_view_descriptor.clone(),
+        asset_usage: image.asset_usage,
+    }
+}
+
+/// Takes over the material textures once their mip chains are done. Textures with mips rendered
+/// on the GPU have no mips on the CPU to stream, so they're left alone. Removing the images
+/// leaves their GPU textures in place until the first swap.
+fn collect_streamed_textures(
+    streaming: Option<ResMut<TextureStreaming>>,
+    scene_ready: Res<SceneReady>,
+    materials: Res<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut collected: Local<bool>,
+) {
+    let Some(mut streaming) = streaming else {
+        return;
+    };
+    if *collected || !scene_ready.ready {
+        return;
+    }
+    *collected = true;
+
+    for (_, material) in materials.iter() {
+        for image_h in material.get_images() {
+            if streaming.textures.contains_key(&image_h.id()) {
+                continue;
+            }
+            let Some(image) = images.get(image_h) else {
+                continue;
+            };
+            let descriptor = &image.texture_descriptor;
+            if descriptor.mip_level_count < 2
+                || descriptor.dimension != TextureDimension::D2
+                || descriptor.size.depth_or_array_layers != 1
+                || descriptor.usage.contains(TextureUsages::RENDER_ATTACHMENT)
+            {
+                continue;
+            }
+            let Some(mut full) = images.remove(image_h) else {
+                continue;
+            };
+            full.asset_usage = RenderAssetUsages::RENDER_WORLD;
+            streaming
+                .textures
+                .insert(image_h.id(), StreamedTexture { full, dropped: 0 });
+        }
+    }
+    let full_bytes: u64 = streaming
+        .textures
+        .values()
+        .map(|texture| texture.resident_bytes(0))
+        .sum();
+    info!(
+        "Streaming {} textures, {:.0}MB at full resolution with a {:.0}MB budget",
+        streaming.textures.len(),
+        full_bytes as f64 / 1_000_000.0,
+        streaming.budget_bytes as f64 / 1_000_000.0
+    );
+}
+
+/// Distance from `point` to the nearest point of a mesh's bounds, zero inside them.
+fn distance_to_aabb(point: Vec3, aabb: &Aabb, transform: &GlobalTransform) -> f32 {
+    let local = transform.affine().inverse().transform_point3(point);
+    let center = Vec3::from(aabb.center);
+    let half_extents = Vec3::from(aabb.half_extents);
+    let nearest = center + (local - center).clamp(-half_extents, half_extents);
+    if nearest == local {
+        return 0.0;
+    }
+    transform.transform_point(nearest).distance(point)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn stream_textures(
+    time: Res<Time>,
+    streaming: Option<ResMut<TextureStreaming>>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    standard_meshes: Query<(&Handle<StandardMaterial>, &Aabb, &GlobalTransform)>,
+    wind_meshes: Query<(&Handle<WindMaterial>, &Aabb, &GlobalTransform)>,
+    mut wind_materials: ResMut<Assets<WindMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(mut streaming) = streaming else {
+        return;
+    };
+    streaming.since_update += time.delta_seconds();
+    if streaming.textures.is_empty() || streaming.since_update < UPDATE_INTERVAL_SECS {
+        return;
+    }
+    streaming.since_update = 0.0;
+    let Some(camera) = cameras.iter().next() else {
+        return;
+    };
+    let camera = camera.translation();
+
+    // Closest any mesh using each texture gets to the camera
+    let mut image_distance: HashMap<AssetId<Image>, f32> = HashMap::new();
+    let mut nearest = |images: Vec<&Handle<Image>>, aabb: &Aabb, transform: &GlobalTransform| {
+        let distance = distance_to_aabb(camera, aabb, transform);
+        for image_h in images {
+            let entry = image_distance.entry(image_h.id()).or_insert(f32::MAX);
+            *entry = entry.min(distance);
+        }
+    };
+    for (handle, aabb, transform) in &standard_meshes {
+        if let Some(material) = materials.get(handle) {
+            nearest(material.get_images(), aabb, transform);
+        }
+    }
+    // The curtains keep their textures in the base of the wind material
+    for (handle, aabb, transform) in &wind_meshes {
+        if let Some(material) = wind_materials.get(handle) {
+            nearest(material.base.get_images(), aabb, transform);
+        }
+    }
+
+    let mut wanted: Vec<(AssetId<Image>, f32, u32)> = streaming
+        .textures
+        .iter()
+        .map(|(id, texture)| {
+            let distance = image_distance.get(id).copied().unwrap_or(f32::MAX);
+            let dropped = (distance / FULL_RESOLUTION_DISTANCE)
+                .max(1.0)
+                .log2()
+                .floor() as u32;
+            (*id, distance, dropped.min(texture.max_dropped()))
+        })
+        .collect();
+
+    // Over the budget, drop another mip from the farthest textures until it fits
+    wanted.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut total: u64 = wanted
+        .iter()
+        .map(|(id, _, dropped)| streaming.textures[id].resident_bytes(*dropped))
+        .sum();
+    while total > streaming.budget_bytes {
+        let mut evicted = false;
+        for (id, _, dropped) in wanted.iter_mut() {
+            let texture = &streaming.textures[id];
+            if *dropped < texture.max_dropped() {
+                total -= texture.resident_bytes(*dropped) - texture.resident_bytes(*dropped + 1);
+                *dropped += 1;
+                evicted = true;
+                if total <= streaming.budget_bytes {
+                    break;
+                }
+            }
+        }
+        if !evicted {
+            break;
+        }
+    }
+
+    // Evictions first so they make room, then the closest textures streamed in first
+    wanted.sort_by_key(|(id, distance, dropped)| {
+        let evicting = *dropped > streaming.textures[id].dropped;
+        (!evicting, (*distance * 100.0) as u32)
+    });
+    let mut swaps = 0;
+    for (id, _, dropped) in wanted {
+        if swaps == MAX_SWAPS_PER_UPDATE {
+            break;
+        }
+        let texture = streaming.textures.get_mut(&id).unwrap();
+        if texture.dropped == dropped {
+            continue;
+        }
+        texture.dropped = dropped;
+        // Extraction moves the image to the render world, see `collect_streamed_textures`
+        images.insert(id, without_top_mips(&texture.full, dropped));
+        // Touch the materials so their bind groups pick up the new texture
+        let uses_image = |images: Vec<&Handle<Image>>| images.iter().any(|h| h.id() == id);
+        let standard: Vec<_> = materials
+            .iter()
+            .filter(|(_, material)| uses_image(material.get_images()))
+            .map(|(material_id, _)| material_id)
+            .collect();
+        for material_id in standard {
+            let _ = materials.get_mut(material_id);
+        }
+        let wind: Vec<_> = wind_materials
+            .iter()
+            .filter(|(_, material)| uses_image(material.base.get_images()))
+            .map(|(material_id, _)| material_id)
+            .collect();
+        for material_id in wind {
+            let _ = wind_materials.get_mut(material_id);
+        }
+        swaps += 1;
+    }
+}
@@ -0,0 +1,146 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::GrifLight;
+
+/// Yaw of the sun's path across the sky, matching the hand placed Sponza sun.
+const SUN_AZIMUTH: f32 = PI * -0.08;
+const NOON_ILLUMINANCE: f32 = 300000.0 * 0.2;
+const NIGHT_ILLUMINANCE: f32 = 0.5;
+const DAY_SKY: Vec3 = Vec3::new(1.75, 1.9, 1.99);
+const SUNSET_SKY: Vec3 = Vec3::new(1.6, 0.9, 0.5);
+const NIGHT_SKY: Vec3 = Vec3::new(0.005, 0.007, 0.015);
+const DAY_AMBIENT: f32 = 0.02;
+const NIGHT_AMBIENT: f32 = 0.001;
+
+/// Drives the sun, sky clear color, and ambient light from a time of day in hours.
+/// Only active when the resource is present, see `--time-of-day`.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct TimeOfDay {
+    /// Hour of the day, 0.0..24.0. The sun rises at 6 and sets at 18.
+    pub hour: f32,
+    /// Hours advanced per second, 0.0 to hold the current time.
+    pub speed: f32,
+    /// Hours per second when holding the `[` and `]` keys.
+    pub scrub_speed: f32,
+}
+
+impl TimeOfDay {
+    pub fn new(hour: f32) -> Self {
+        Self {
+            hour: hour.rem_euclid(24.0),
+            speed: 0.0,
+            scrub_speed: 2.0,
+        }
+    }
+
+    /// Angle of the sun along its path, 0 at sunrise, PI at sunset, negative or > PI at night.
+    pub fn sun_angle(&self) -> f32 {
+        (self.hour - 6.0) / 12.0 * PI
+    }
+
+    /// Sine of the sun's elevation, clamped to 0 below the horizon.
+    pub fn sun_height(&self) -> f32 {
+        self.sun_angle().sin().max(0.0)
+    }
+
+    pub fn sun_rotation(&self) -> Quat {
+        Quat::from_rotation_y(SUN_AZIMUTH) * Quat::from_rotation_x(-self.sun_angle())
+    }
+
+    /// Approximate sun color temperature, warm near the horizon and neutral at noon.
+    pub fn sun_temperature(&self) -> f32 {
+        2000.0 + 4500.0 * self.sun_height().sqrt()
+    }
+
+    pub fn sky_color(&self) -> Vec3 {
+        let height = self.sun_height();
+        // Fade in sunset colors near the horizon, and out to night below it
+        let sunset = SUNSET_SKY.lerp(DAY_SKY, (height * 3.0).min(1.0));
+        let daylight = (self.sun_angle().sin() * 8.0 + 1.0).clamp(0.0, 1.0);
+        NIGHT_SKY.lerp(sunset, daylight)
+    }
+}
+
+/// Converts a color temperature in kelvin to an sRGB color.
+/// Based on Tanner Helland's approximation of blackbody colors.
+pub fn kelvin_to_color(kelvin: f32) -> Color {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let r = if t <= 66.0 {
+        255.0
+    } else {
+        329.69873 * (t - 60.0).powf(-0.13320476)
+    };
+    let g = if t <= 66.0 {
+        99.4708 * t.ln() - 161.11957
+    } else {
+        288.12216 * (t - 60.0).powf(-0.07551485)
+    };
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (t - 10.0).ln() - 305.0448
+    };
+    Color::rgb(
+        (r / 255.0).clamp(0.0, 1.0),
+        (g / 255.0).clamp(0.0, 1.0),
+        (b / 255.0).clamp(0.0, 1.0),
+    )
+}
+
+pub struct TimeOfDayPlugin;
+impl Plugin for TimeOfDayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (time_of_day_input, apply_time_of_day).chain());
+    }
+}
+
+fn time_of_day_input(
+    input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    time_of_day: Option<ResMut<TimeOfDay>>,
+) {
+    let Some(mut time_of_day) = time_of_day else {
+        return;
+    };
+    let dt = time.delta_seconds();
+    let mut hour = time_of_day.hour + time_of_day.speed * dt;
+    if input.pressed(KeyCode::BracketLeft) {
+        hour -= time_of_day.scrub_speed * dt;
+    }
+    if input.pressed(KeyCode::BracketRight) {
+        hour += time_of_day.scrub_speed * dt;
+    }
+    if input.just_released(KeyCode::BracketLeft) || input.just_released(KeyCode::BracketRight) {
+        info!("Time of day: {:.2}", hour.rem_euclid(24.0));
+    }
+    if hour != time_of_day.hour {
+        time_of_day.hour = hour.rem_euclid(24.0);
+    }
+}
+
+fn apply_time_of_day(
+    time_of_day: Option<Res<TimeOfDay>>,
+    mut suns: Query<(&mut Transform, &mut DirectionalLight), With<GrifLight>>,
+    mut clear_color: ResMut<ClearColor>,
+    mut ambient: ResMut<AmbientLight>,
+) {
+    let Some(time_of_day) = time_of_day else {
+        return;
+    };
+    if !time_of_day.is_changed() {
+        return;
+    }
+    let height = time_of_day.sun_height();
+    for (mut transform, mut light) in &mut suns {
+        transform.rotation = time_of_day.sun_rotation();
+        light.illuminance = NIGHT_ILLUMINANCE + NOON_ILLUMINANCE * height;
+        light.color = kelvin_to_color(time_of_day.sun_temperature());
+    }
+    let sky = time_of_day.sky_color();
+    clear_color.0 = Color::rgb(sky.x, sky.y, sky.z);
+    ambient.brightness = NIGHT_AMBIENT + (DAY_AMBIENT - NIGHT_AMBIENT) * height.sqrt();
+}
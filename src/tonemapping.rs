@@ -0,0 +1,72 @@
+use bevy::{core_pipeline::tonemapping::Tonemapping, prelude::*, render::camera::Exposure};
+
+/// Tonemappers that can be picked with `--tonemapping` and cycled through with T.
+const TONEMAPPERS: [(&str, Tonemapping); 4] = [
+    ("tony_mc_mapface", Tonemapping::TonyMcMapface),
+    ("agx", Tonemapping::AgX),
+    ("reinhard", Tonemapping::Reinhard),
+    ("none", Tonemapping::None),
+];
+
+/// EV100 change per press of `-` or `=`.
+const EXPOSURE_STEP: f32 = 0.5;
+
+pub fn parse_tonemapping(value: &str) -> Result<Tonemapping, String> {
+    TONEMAPPERS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, tonemapping)| *tonemapping)
+        .ok_or_else(|| {
+            let names: Vec<_> = TONEMAPPERS.iter().map(|(name, _)| *name).collect();
+            format!("Unknown tonemapper {value}, expected {}", names.join(", "))
+        })
+}
+
+fn tonemapping_name(tonemapping: Tonemapping) -> &'static str {
+    TONEMAPPERS
+        .iter()
+        .find(|(_, t)| *t == tonemapping)
+        .map_or("other", |(name, _)| name)
+}
+
+pub struct TonemappingPlugin;
+impl Plugin for TonemappingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (cycle_tonemapping, adjust_exposure));
+    }
+}
+
+fn cycle_tonemapping(
+    input: Res<ButtonInput<KeyCode>>,
+    mut cameras: Query<&mut Tonemapping, With<Camera3d>>,
+) {
+    if !input.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+    for mut tonemapping in &mut cameras {
+        let current = TONEMAPPERS
+            .iter()
+            .position(|(_, t)| *t == *tonemapping)
+            .unwrap_or(TONEMAPPERS.len() - 1);
+        *tonemapping = TONEMAPPERS[(current + 1) % TONEMAPPERS.len()].1;
+        println!("Tonemapping: {}", tonemapping_name(*tonemapping));
+    }
+}
+
+fn adjust_exposure(
+    input: Res<ButtonInput<KeyCode>>,
+    mut cameras: Query<&mut Exposure, With<Camera3d>>,
+) {
+    let step = if input.just_pressed(KeyCode::Equal) {
+        EXPOSURE_STEP
+    } else if input.just_pressed(KeyCode::Minus) {
+        -EXPOSURE_STEP
+    } else {
+        return;
+    };
+    for mut exposure in &mut cameras {
+        // A higher EV100 lets in less light
+        exposure.ev100 -= step;
+        println!("Exposure: {:.1} EV100", exposure.ev100);
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        texture::{CompressedImageFormats, ImageSampler, ImageType},
+    },
+};
+
+use crate::{
+    compression::CompressionFormat,
+    convert::{texture_roles, ConvertManifest, ConvertQuality, TextureRole, SMALLEST_MIP_SIZE},
+    gltf_file::GltfFile,
+};
+
+/// Checks that every texture the materials of the `gltfs` in `dir` use is a KTX2 file that
+/// decodes, has a full mip chain, uses `format`, and is sRGB only if used as a color. Without a
+/// `format`, the one recorded in the conversion manifest is expected, or any supported
+/// compressed format if there's no manifest. When `quality` is given the manifest has to record
+/// that preset. Prints a report and returns false if there were any problems.
+pub fn validate_assets(
+    dir: &Path,
+    gltfs: &[PathBuf],
+    format: Option<CompressionFormat>,
+    quality: Option<ConvertQuality>,
+) -> bool {
+    let mut checked = 0;
+    let mut problems = 0;
+    let manifest = ConvertManifest::load(dir);
+    match (&manifest, quality) {
+        (Ok(manifest), Some(quality)) if manifest.quality != quality => {
+            println!(
+                "Converted at {} quality, expected {}",
+                manifest.quality.name(),
+                quality.name()
+            );
+            problems += 1;
+        }
+        (Err(e), Some(_)) => {
+            println!("{}: {e:#}", ConvertManifest::path(dir).display());
+            problems += 1;
+        }
+        _ => (),
+    }
+    #[cfg(not(feature = "basis_universal"))]
+    if manifest.as_ref().is_ok_and(|m| m.format == "uastc") {
+        println!("The textures are UASTC, decoding them needs the basis_universal feature: cargo run --features basis_universal -- --validate");
+    }
+    // UASTC is transcoded to any of the formats when decoded
+    let format = format.or_else(|| manifest.ok().and_then(|m| m.format.parse().ok()));
+    for gltf in gltfs {
+        let gltf_path = dir.join(gltf);
+        let gltf_dir = gltf_path.parent().unwrap_or(dir);
+        let doc = match GltfFile::read(&gltf_path) {
+            Ok(doc) => doc,
+            Err(e) => {
+                println!("{e:#}");
+                problems += 1;
+                continue;
+            }
+        };
+        // Only the images materials use are converted, the same set is checked here
+        let mut roles: Vec<_> = texture_roles(&doc.json).into_iter().collect();
+        roles.sort_unstable_by_key(|(index, _)| *index);
+        for (index, role) in roles {
+            checked += 1;
+            let Some(uri) = doc.json["images"][index]["uri"].as_str() else {
+                println!(
+                    "{}: image {index} is embedded, not converted to KTX2",
+                    gltf_path.display()
+                );
+                problems += 1;
+                continue;
+            };
+            let path = gltf_dir.join(uri);
+            if let Err(e) = validate_texture(&path, format, role) {
+                println!("{}: {e:#}", path.display());
+                problems += 1;
+            }
+        }
+    }
+    println!(
+        "Checked {checked} textures in {}, {problems} problems",
+        dir.display()
+    );
+    problems == 0
+}
+
+fn validate_texture(
+    path: &Path,
+    format: Option<CompressionFormat>,
+    role: TextureRole,
+) -> anyhow::Result<()> {
+    if path.extension().unwrap_or_default() != "ktx2" {
+        return Err(anyhow!("Not converted to KTX2"));
+    }
+    let bytes = fs::read(path).context("Missing")?;
+    let image = Image::from_buffer(
+        &bytes,
+        ImageType::Extension("ktx2"),
+        CompressedImageFormats::all(),
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::default(),
+    )
+    .map_err(|e| anyhow!("Failed to decode: {e}"))?;
+
+    let descriptor = &image.texture_descriptor;
+    let formats = match format {
+        Some(format) => vec![format],
+        None => vec![
+            CompressionFormat::Bc1,
+            CompressionFormat::Bc7,
+            CompressionFormat::Astc4x4,
+            CompressionFormat::Astc8x8,
+        ],
+    };
+    if !formats.iter().any(|f| {
+        [
+            f.texture_format(true),
+            f.texture_format(false),
+            f.for_normal_map().texture_format(false),
+        ]
+        .contains(&descriptor.format)
+    }) {
+        return Err(anyhow!("Unexpected format {:?}", descriptor.format));
+    }
+
+    // sRGB data textures wash out, roughness ends up far from what was authored
+    if descriptor.format.is_srgb() != role.srgb() {
+        return Err(anyhow!(
+            "{:?} is {}, but used as {role:?}",
+            descriptor.format,
+            if role.srgb() { "linear" } else { "sRGB" }
+        ));
+    }
+
+    let last_level = descriptor.mip_level_count.max(1) - 1;
+    let smallest = (descriptor.size.width.min(descriptor.size.height) >> last_level).max(1);
+    if smallest > SMALLEST_MIP_SIZE {
+        return Err(anyhow!(
+            "Incomplete mip chain, {} levels for {}x{}",
+            descriptor.mip_level_count,
+            descriptor.size.width,
+            descriptor.size.height
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,314 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{anyhow, Context};
+use bevy::{
+    app::AppExit, prelude::*, render::view::screenshot::ScreenshotManager, window::PrimaryWindow,
+};
+use image::{DynamicImage, Rgb32FImage};
+
+use crate::{
+    camera_controller::{CameraController, CameraPath},
+    frame_pacing::FixedTimestep,
+    loading::SceneReady,
+    screenshot::CAPTURE_DELAY_FRAMES,
+};
+
+/// Output files with these extensions are encoded by ffmpeg instead of written as images.
+const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "mkv", "mov", "webm"];
+
+/// Image format of the numbered frames written by `--render-video`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameFormat {
+    #[default]
+    Png,
+    /// Linear 32 bit float, decoded from the window's sRGB.
+    Exr,
+}
+
+impl FromStr for FrameFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(FrameFormat::Png),
+            "exr" => Ok(FrameFormat::Exr),
+            _ => Err(anyhow!("Unknown frame format {s}, expected png or exr")),
+        }
+    }
+}
+
+impl FrameFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            FrameFormat::Png => "png",
+            FrameFormat::Exr => "exr",
+        }
+    }
+}
+
+enum VideoOutput {
+    Frames { dir: PathBuf, format: FrameFormat },
+    Ffmpeg { path: PathBuf, child: Option<Child> },
+}
+
+/// ffmpeg's stdin, shared with the screenshot callbacks. They run as separate tasks that can
+/// finish out of order, so each frame is held until the ones before it have been written.
+#[derive(Default)]
+struct FfmpegInput {
+    stdin: Option<ChildStdin>,
+    next_frame: u32,
+    /// RGBA pixels by frame index, `None` for frames that couldn't be converted.
+    pending: BTreeMap<u32, Option<Vec<u8>>>,
+}
+
+impl FfmpegInput {
+    fn push(&mut self, frame: u32, pixels: Option<Vec<u8>>) {
+        self.pending.insert(frame, pixels);
+        while let Some(pixels) = self.pending.remove(&self.next_frame) {
+            if let (Some(stdin), Some(pixels)) = (self.stdin.as_mut(), pixels) {
+                if let Err(e) = stdin.write_all(&pixels) {
+                    error!("Failed to write frame {} to ffmpeg: {e}", self.next_frame);
+                }
+            }
+            self.next_frame += 1;
+        }
+    }
+}
+
+/// Renders a [`CameraPath`] frame by frame at a fixed timestep and exits, see `--render-video`.
+/// Each frame is saved to a numbered image, or piped to ffmpeg as raw RGBA when the output is a
+/// video file. Rendering waits for every frame, so the result is smooth however slow the GPU.
+#[derive(Resource)]
+pub struct VideoRender {
+    path: CameraPath,
+    fps: f32,
+    frame_count: u32,
+    output: VideoOutput,
+    /// Shared with the screenshot callbacks, which run once the frame has been rendered.
+    ffmpeg_input: Arc<Mutex<FfmpegInput>>,
+    frames_requested: u32,
+    frames_saved: Arc<AtomicU32>,
+}
+
+impl VideoRender {
+    /// `duration` defaults to the length of the camera path.
+    pub fn new(
+        output: &str,
+        path: CameraPath,
+        fps: f32,
+        duration: Option<f32>,
+        format: FrameFormat,
+    ) -> anyhow::Result<Self> {
+        let fps = fps.max(1.0);
+        let duration = duration.unwrap_or_else(|| path.duration());
+        let frame_count = ((duration * fps).ceil() as u32).max(1);
+        let output_path = PathBuf::from(output);
+        let is_video = output_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        let output = if is_video {
+            if let Some(parent) = output_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            VideoOutput::Ffmpeg {
+                path: output_path,
+                child: None,
+            }
+        } else {
+            fs::create_dir_all(&output_path)
+                .with_context(|| format!("Failed to create {}", output_path.display()))?;
+            VideoOutput::Frames {
+                dir: output_path,
+                format,
+            }
+        };
+        Ok(Self {
+            path,
+            fps,
+            frame_count,
+            output,
+            ffmpeg_input: default(),
+            frames_requested: 0,
+            frames_saved: Arc::new(AtomicU32::new(0)),
+        })
+    }
+
+    pub fn fixed_timestep(&self) -> FixedTimestep {
+        FixedTimestep::new(self.fps)
+    }
+
+    /// Starts ffmpeg reading raw frames of the window's size from stdin.
+    fn spawn_ffmpeg(path: &Path, fps: f32, width: u32, height: u32) -> anyhow::Result<Child> {
+        Command::new("ffmpeg")
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "rawvideo",
+                "-pix_fmt",
+                "rgba",
+            ])
+            .args(["-s", &format!("{width}x{height}"), "-r", &fps.to_string()])
+            .args([
+                "-i", "-", "-c:v", "libx264", "-pix_fmt", "yuv420p", "-crf", "16",
+            ])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("Failed to start ffmpeg, is it installed and on the PATH?")
+    }
+}
+
+pub struct VideoRenderPlugin;
+impl Plugin for VideoRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, render_video);
+    }
+}
+
+fn render_video(
+    video: Option<ResMut<VideoRender>>,
+    scene_ready: Res<SceneReady>,
+    window: Query<(Entity, &Window), With<PrimaryWindow>>,
+    mut camera: Query<(&mut Transform, Option<&mut CameraController>), With<Camera3d>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+    mut app_exit: EventWriter<AppExit>,
+    mut frames_since_ready: Local<u32>,
+) {
+    let Some(mut video) = video else {
+        return;
+    };
+    let video = &mut *video;
+    if !scene_ready.ready {
+        return;
+    }
+    let Ok((window, window_size)) = window.get_single() else {
+        return;
+    };
+    let Ok((mut transform, options)) = camera.get_single_mut() else {
+        return;
+    };
+
+    if video.frames_requested == video.frame_count {
+        if video.frames_saved.load(Ordering::Acquire) < video.frame_count {
+            return;
+        }
+        // Closing stdin lets ffmpeg finish the file
+        video.ffmpeg_input.lock().unwrap().stdin.take();
+        if let VideoOutput::Ffmpeg { path, child } = &mut video.output {
+            if let Some(mut child) = child.take() {
+                match child.wait() {
+                    Ok(status) if status.success() => {}
+                    Ok(status) => error!("ffmpeg exited with {status}"),
+                    Err(e) => error!("Failed to wait for ffmpeg: {e}"),
+                }
+            }
+            info!("Saved video to {}", path.display());
+        }
+        info!("Rendered {} frames", video.frame_count);
+        app_exit.send(AppExit);
+        return;
+    }
+
+    // Hold the first frame while TAA and auto exposure settle
+    let frame = video.frames_requested;
+    let t = if *frames_since_ready < CAPTURE_DELAY_FRAMES {
+        *frames_since_ready += 1;
+        0.0
+    } else {
+        frame as f32 / video.fps
+    };
+    if let Some(sampled) = video.path.sample(t) {
+        *transform = sampled;
+        if let Some(mut options) = options {
+            options.initialized = false;
+        }
+    }
+    if *frames_since_ready < CAPTURE_DELAY_FRAMES {
+        return;
+    }
+
+    let fps = video.fps;
+    let (width, height) = (window_size.physical_width(), window_size.physical_height());
+    let save: Box<dyn FnOnce(Option<DynamicImage>) + Send + Sync> = match &mut video.output {
+        VideoOutput::Frames { dir, format } => {
+            let path = dir.join(format!("frame_{frame:05}.{}", format.extension()));
+            let format = *format;
+            Box::new(move |image| {
+                let Some(image) = image else {
+                    return;
+                };
+                let result = match format {
+                    FrameFormat::Png => image.to_rgb8().save(&path),
+                    FrameFormat::Exr => linear(&image).save(&path),
+                };
+                if let Err(e) = result {
+                    error!("Failed to save frame {}: {}", path.display(), e);
+                }
+            })
+        }
+        VideoOutput::Ffmpeg { path, child } => {
+            if child.is_none() {
+                match VideoRender::spawn_ffmpeg(path, fps, width, height) {
+                    Ok(mut spawned) => {
+                        video.ffmpeg_input.lock().unwrap().stdin = spawned.stdin.take();
+                        *child = Some(spawned);
+                    }
+                    Err(e) => {
+                        error!("{e:#}");
+                        app_exit.send(AppExit);
+                        return;
+                    }
+                }
+            }
+            let input = video.ffmpeg_input.clone();
+            Box::new(move |image| {
+                let pixels = image.map(|image| image.to_rgba8().into_raw());
+                input.lock().unwrap().push(frame, pixels);
+            })
+        }
+    };
+    let saved = video.frames_saved.clone();
+    let result = screenshot_manager.take_screenshot(window, move |image: Image| {
+        match image.try_into_dynamic() {
+            Ok(dyn_image) => save(Some(dyn_image)),
+            Err(e) => {
+                error!("Failed to convert frame {frame}: {:?}", e);
+                save(None);
+            }
+        }
+        saved.fetch_add(1, Ordering::Release);
+    });
+    match result {
+        Ok(_) => video.frames_requested += 1,
+        // Try the same frame again next time
+        Err(e) => warn!("Frame {frame} not captured: {:?}", e),
+    }
+}
+
+/// EXR holds linear values, the window holds sRGB.
+fn linear(image: &DynamicImage) -> Rgb32FImage {
+    let mut linear = image.to_rgb32f();
+    for c in linear.iter_mut() {
+        *c = if *c <= 0.04045 {
+            *c / 12.92
+        } else {
+            ((*c + 0.055) / 1.055).powf(2.4)
+        };
+    }
+    linear
+}
@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    camera_controller::{camera_controller, CameraTransition},
+    loading::SceneReady,
+    props::Prop,
+};
+
+const GRAVITY: f32 = 9.81;
+const JUMP_SPEED: f32 = 4.0;
+const CAPSULE_RADIUS: f32 = 0.3;
+/// Moves further than this in a frame are jumps to a bookmark or benchmark position, which
+/// aren't blocked by the walls in between.
+const TELEPORT_DISTANCE: f32 = 1.0;
+
+/// Walks the camera at human height, falling with gravity and colliding with the scene instead
+/// of flying through it. Toggled with V, or start in it with `--walk`.
+#[derive(Resource, Clone, Debug)]
+pub struct WalkMode {
+    pub enabled: bool,
+    /// Height of the camera above the ground.
+    pub eye_height: f32,
+    vertical_speed: f32,
+    /// Where the walk left the camera last frame, so movement from the camera controller can be
+    /// picked up and pushed through the collision.
+    last_position: Option<Vec3>,
+}
+
+/// Keeps the flying camera from passing through walls and floors by sliding a sphere around
+/// it along the scene colliders. Toggled with C, or start with it on using `--camera-collision`.
+#[derive(Resource, Clone, Debug)]
+pub struct CameraCollision {
+    pub enabled: bool,
+    /// Radius of the sphere kept clear around the camera.
+    pub radius: f32,
+    last_position: Option<Vec3>,
+}
+
+impl Default for CameraCollision {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            radius: 0.2,
+            last_position: None,
+        }
+    }
+}
+
+impl CameraCollision {
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            ..default()
+        }
+    }
+}
+
+/// Triangle mesh colliders for the static scene, built once they're first needed since it takes
+/// a few seconds for large scenes.
+#[derive(Resource, Default)]
+pub struct SceneColliders {
+    pub wanted: bool,
+    pub built: bool,
+}
+
+impl Default for WalkMode {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            eye_height: 1.7,
+            vertical_speed: 0.0,
+            last_position: None,
+        }
+    }
+}
+
+impl WalkMode {
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            ..default()
+        }
+    }
+}
+
+/// Triangle mesh colliders for the scene, a walk mode, and collision for the flying camera.
+/// Needs the `physics` feature.
+pub struct WalkPlugin;
+impl Plugin for WalkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+            .init_resource::<WalkMode>()
+            .init_resource::<CameraCollision>()
+            .init_resource::<SceneColliders>()
+            .add_systems(
+                Update,
+                (
+                    toggle_walk,
+                    toggle_camera_collision,
+                    build_scene_colliders,
+                    walk.after(camera_controller),
+                    collide_camera.after(camera_controller),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn toggle_walk(input: Res<ButtonInput<KeyCode>>, mut walk: ResMut<WalkMode>) {
+    if input.just_pressed(KeyCode::KeyV) {
+        walk.enabled = !walk.enabled;
+        walk.last_position = None;
+        walk.vertical_speed = 0.0;
+        info!("Walk mode {}", if walk.enabled { "on" } else { "off" });
+    }
+}
+
+fn toggle_camera_collision(
+    input: Res<ButtonInput<KeyCode>>,
+    mut collision: ResMut<CameraCollision>,
+) {
+    if input.just_pressed(KeyCode::KeyC) {
+        collision.enabled = !collision.enabled;
+        collision.last_position = None;
+        info!(
+            "Camera collision {}",
+            if collision.enabled { "on" } else { "off" }
+        );
+    }
+}
+
+fn build_scene_colliders(
+    mut commands: Commands,
+    walk: Res<WalkMode>,
+    collision: Res<CameraCollision>,
+    mut colliders: ResMut<SceneColliders>,
+    scene_ready: Res<SceneReady>,
+    meshes: Res<Assets<Mesh>>,
+    scene_meshes: Query<(Entity, &Handle<Mesh>), (Without<Collider>, Without<Prop>)>,
+) {
+    if !(walk.enabled || collision.enabled || colliders.wanted)
+        || colliders.built
+        || !scene_ready.ready
+    {
+        return;
+    }
+    let mut count = 0;
+    for (entity, handle) in &scene_meshes {
+        let Some(mesh) = meshes.get(handle) else {
+            continue;
+        };
+        if let Some(collider) = Collider::from_bevy_mesh(mesh, &ComputedColliderShape::TriMesh) {
+            commands.entity(entity).insert((RigidBody::Fixed, collider));
+            count += 1;
+        }
+    }
+    info!("Built {count} scene colliders");
+    colliders.built = true;
+}
+
+fn walk(
+    time: Res<Time>,
+    input: Res<ButtonInput<KeyCode>>,
+    rapier: Res<RapierContext>,
+    colliders: Res<SceneColliders>,
+    mut walk: ResMut<WalkMode>,
+    mut camera: Query<&mut Transform, With<Camera3d>>,
+) {
+    let Ok(mut transform) = camera.get_single_mut() else {
+        return;
+    };
+    if !walk.enabled || !colliders.built {
+        return;
+    }
+    let dt = time.delta_seconds();
+    let start = walk.last_position.unwrap_or(transform.translation);
+
+    // Keep the camera controller's horizontal movement, gravity takes care of the rest
+    let mut movement = transform.translation - start;
+    walk.vertical_speed -= GRAVITY * dt;
+    movement.y = walk.vertical_speed * dt;
+
+    // The capsule stands on the ground, with the camera at eye height
+    let half_height = (walk.eye_height - CAPSULE_RADIUS * 2.0) * 0.5;
+    let capsule = Collider::capsule_y(half_height, CAPSULE_RADIUS);
+    let center = start - Vec3::Y * (walk.eye_height - half_height - CAPSULE_RADIUS);
+    let output = rapier.move_shape(
+        movement,
+        &capsule,
+        center,
+        Quat::IDENTITY,
+        70.0,
+        &MoveShapeOptions {
+            autostep: Some(CharacterAutostep {
+                max_height: CharacterLength::Absolute(0.35),
+                min_width: CharacterLength::Absolute(0.2),
+                include_dynamic_bodies: false,
+            }),
+            snap_to_ground: Some(CharacterLength::Absolute(0.2)),
+            ..default()
+        },
+        QueryFilter::default(),
+        |_| {},
+    );
+    if output.grounded {
+        walk.vertical_speed = if input.just_pressed(KeyCode::Space) {
+            JUMP_SPEED
+        } else {
+            0.0
+        };
+    }
+    transform.translation = start + output.effective_translation;
+    walk.last_position = Some(transform.translation);
+}
+
+fn collide_camera(
+    rapier: Res<RapierContext>,
+    colliders: Res<SceneColliders>,
+    walk: Res<WalkMode>,
+    mut collision: ResMut<CameraCollision>,
+    mut camera: Query<&mut Transform, (With<Camera3d>, Without<CameraTransition>)>,
+) {
+    // Walk mode has its own collision
+    if !collision.enabled || walk.enabled || !colliders.built {
+        collision.last_position = None;
+        return;
+    }
+    let Ok(mut transform) = camera.get_single_mut() else {
+        collision.last_position = None;
+        return;
+    };
+    let start = collision.last_position.unwrap_or(transform.translation);
+    let movement = transform.translation - start;
+    if movement.length() > TELEPORT_DISTANCE {
+        collision.last_position = Some(transform.translation);
+        return;
+    }
+    let sphere = Collider::ball(collision.radius);
+    let output = rapier.move_shape(
+        movement,
+        &sphere,
+        start,
+        Quat::IDENTITY,
+        1.0,
+        &MoveShapeOptions {
+            // Fly freely, only slide along what's hit
+            up: Vec3::Y,
+            slide: true,
+            autostep: None,
+            snap_to_ground: None,
+            max_slope_climb_angle: std::f32::consts::PI,
+            min_slope_slide_angle: std::f32::consts::PI,
+            ..default()
+        },
+        QueryFilter::default(),
+        |_| {},
+    );
+    transform.translation = start + output.effective_translation;
+    collision.last_position = Some(transform.translation);
+}
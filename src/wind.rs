@@ -0,0 +1,168 @@
+use bevy::{
+    asset::load_internal_asset,
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::{
+        primitives::Aabb,
+        render_resource::{AsBindGroup, ShaderRef, ShaderType},
+    },
+};
+
+use crate::{all_children, loading::SceneReady};
+
+const WIND_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(84316207951846330172945618802);
+const WIND_VERTEX_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(84316207951846330172945618803);
+const WIND_PREPASS_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(84316207951846330172945618804);
+
+/// Curtains hang along the length of Sponza, so they sway across it.
+const WIND_DIRECTION: Vec3 = Vec3::Z;
+
+/// Sways the curtains with a noise function, so there is something moving to judge TAA ghosting
+/// against. Only active when the resource is present, see `--wind`.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Wind {
+    pub strength: f32,
+}
+
+#[derive(Clone, Copy, Default, Debug, ShaderType, Reflect)]
+pub struct WindParams {
+    direction: Vec3,
+    strength: f32,
+    /// World space height of the top of the mesh, where it hangs from.
+    top: f32,
+    height: f32,
+    time: f32,
+    previous_time: f32,
+}
+
+#[derive(Asset, AsBindGroup, Reflect, Clone, Debug)]
+pub struct WindExtension {
+    #[uniform(100)]
+    params: WindParams,
+}
+
+impl MaterialExtension for WindExtension {
+    fn vertex_shader() -> ShaderRef {
+        WIND_VERTEX_SHADER_HANDLE.into()
+    }
+
+    fn prepass_vertex_shader() -> ShaderRef {
+        WIND_PREPASS_SHADER_HANDLE.into()
+    }
+}
+
+pub type WindMaterial = ExtendedMaterial<StandardMaterial, WindExtension>;
+
+pub struct WindPlugin;
+impl Plugin for WindPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, WIND_SHADER_HANDLE, "wind.wgsl", Shader::from_wgsl);
+        load_internal_asset!(
+            app,
+            WIND_VERTEX_SHADER_HANDLE,
+            "wind_vertex.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            WIND_PREPASS_SHADER_HANDLE,
+            "wind_prepass.wgsl",
+            Shader::from_wgsl
+        );
+        app.add_plugins(MaterialPlugin::<WindMaterial>::default())
+            .add_systems(Update, (apply_wind, update_wind_time));
+    }
+}
+
+/// Swaps the materials of every mesh in the curtain scenes once they've loaded. Each mesh gets
+/// its own material so it can be pinned at its own top.
+#[allow(clippy::too_many_arguments)]
+fn apply_wind(
+    mut commands: Commands,
+    wind: Option<Res<Wind>>,
+    scene_ready: Res<SceneReady>,
+    asset_server: Res<AssetServer>,
+    scenes: Query<(Entity, &Handle<Scene>)>,
+    children_query: Query<&Children>,
+    meshes: Query<(&Handle<StandardMaterial>, &Aabb, &GlobalTransform)>,
+    standard_materials: Res<Assets<StandardMaterial>>,
+    mut wind_materials: ResMut<Assets<WindMaterial>>,
+    mut applied: Local<bool>,
+) {
+    let Some(wind) = wind else {
+        return;
+    };
+    if *applied || !scene_ready.ready {
+        return;
+    }
+    *applied = true;
+
+    let mut count = 0;
+    for (root, scene) in &scenes {
+        let is_curtains = asset_server
+            .get_path(scene)
+            .is_some_and(|path| path.to_string().to_lowercase().contains("curtain"));
+        if !is_curtains {
+            continue;
+        }
+        let Ok(children) = children_query.get(root) else {
+            continue;
+        };
+        all_children(children, &children_query, &mut |entity| {
+            let Ok((handle, aabb, transform)) = meshes.get(entity) else {
+                return;
+            };
+            let Some(base) = standard_materials.get(handle) else {
+                return;
+            };
+            let (mut bottom, mut top) = (f32::MAX, f32::MIN);
+            for corner in 0..8 {
+                let sign = Vec3::new(
+                    if corner & 1 == 0 { -1.0 } else { 1.0 },
+                    if corner & 2 == 0 { -1.0 } else { 1.0 },
+                    if corner & 4 == 0 { -1.0 } else { 1.0 },
+                );
+                let corner = Vec3::from(aabb.center) + Vec3::from(aabb.half_extents) * sign;
+                let y = transform.transform_point(corner).y;
+                bottom = bottom.min(y);
+                top = top.max(y);
+            }
+            let material = wind_materials.add(WindMaterial {
+                base: base.clone(),
+                extension: WindExtension {
+                    params: WindParams {
+                        direction: WIND_DIRECTION,
+                        strength: wind.strength,
+                        top,
+                        height: top - bottom,
+                        ..default()
+                    },
+                },
+            });
+            commands
+                .entity(entity)
+                .remove::<Handle<StandardMaterial>>()
+                .insert(material);
+            count += 1;
+        });
+    }
+    info!("Wind is blowing on {count} meshes");
+}
+
+fn update_wind_time(
+    wind: Option<Res<Wind>>,
+    time: Res<Time>,
+    mut materials: ResMut<Assets<WindMaterial>>,
+) {
+    if wind.is_none() {
+        return;
+    }
+    let t = time.elapsed_seconds_wrapped();
+    for (_, material) in materials.iter_mut() {
+        let params = &mut material.extension.params;
+        params.previous_time = params.time;
+        params.time = t;
+    }
+}
@@ -0,0 +1,21 @@
+#define_import_path sponza::wind
+
+struct WindParams {
+    direction: vec3<f32>,
+    strength: f32,
+    top: f32,
+    height: f32,
+    time: f32,
+    previous_time: f32,
+};
+
+@group(2) @binding(100) var<uniform> wind: WindParams;
+
+// How far the cloth is pushed at a world position. The top of the mesh is pinned to its rail
+// and the bottom moves the most.
+fn wind_offset(world_position: vec3<f32>, time: f32) -> vec3<f32> {
+    let weight = clamp((wind.top - world_position.y) / max(wind.height, 0.001), 0.0, 1.0);
+    let phase = dot(world_position.xz, vec2(0.7, 0.4)) * 1.3 + time * 1.7;
+    let gust = sin(phase) + 0.5 * sin(phase * 2.3 + 1.1) + 0.25 * sin(world_position.y * 3.0 + time * 3.1);
+    return wind.direction * wind.strength * weight * weight * gust * 0.1;
+}
@@ -0,0 +1,63 @@
+// Bevy's prepass.wgsl vertex shader, with the wind applied in world space. The previous
+// position uses last frame's time so TAA gets correct motion vectors for the cloth.
+#import bevy_pbr::{
+    prepass_io::{Vertex, VertexOutput},
+    mesh_functions,
+    view_transformations::position_world_to_clip,
+}
+#import bevy_render::instance_index::get_instance_index
+#import sponza::wind::{wind, wind_offset}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    var model = mesh_functions::get_model_matrix(vertex.instance_index);
+
+    out.world_position = mesh_functions::mesh_position_local_to_world(model, vec4<f32>(vertex.position, 1.0));
+    out.world_position = vec4(out.world_position.xyz + wind_offset(out.world_position.xyz, wind.time), 1.0);
+    out.position = position_world_to_clip(out.world_position.xyz);
+#ifdef DEPTH_CLAMP_ORTHO
+    out.clip_position_unclamped = out.position;
+    out.position.z = min(out.position.z, 1.0);
+#endif
+
+#ifdef VERTEX_UVS
+    out.uv = vertex.uv;
+#endif
+
+#ifdef VERTEX_UVS_B
+    out.uv_b = vertex.uv_b;
+#endif
+
+#ifdef NORMAL_PREPASS_OR_DEFERRED_PREPASS
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(
+        vertex.normal,
+        get_instance_index(vertex.instance_index)
+    );
+#ifdef VERTEX_TANGENTS
+    out.world_tangent = mesh_functions::mesh_tangent_local_to_world(
+        model,
+        vertex.tangent,
+        get_instance_index(vertex.instance_index)
+    );
+#endif
+#endif
+
+#ifdef MOTION_VECTOR_PREPASS
+    let previous = mesh_functions::mesh_position_local_to_world(
+        mesh_functions::get_previous_model_matrix(vertex.instance_index),
+        vec4<f32>(vertex.position, 1.0)
+    );
+    out.previous_world_position = vec4(previous.xyz + wind_offset(previous.xyz, wind.previous_time), 1.0);
+#endif
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = get_instance_index(vertex.instance_index);
+#endif
+
+#ifdef VERTEX_COLORS
+    out.color = vertex.color;
+#endif
+
+    return out;
+}
@@ -0,0 +1,184 @@
+use bevy::{
+    pbr::wireframe::{WireframeColor, WireframeConfig, WireframePlugin},
+    prelude::*,
+    render::{mesh::VertexAttributeValues, render_resource::WgpuFeatures, renderer::RenderDevice},
+};
+
+use crate::{
+    picking::{pick_mesh, PickableMeshes},
+    split_screen::ComparisonCamera,
+};
+
+/// Vertices further than this from the point that was hit don't get their normals drawn.
+const NORMALS_RADIUS: f32 = 1.0;
+const NORMAL_LENGTH: f32 = 0.1;
+/// Keeps dense meshes readable.
+const MAX_NORMALS: usize = 2000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WireframeMode {
+    #[default]
+    Off,
+    White,
+    /// A different color for every mesh, to see where one ends and the next begins.
+    PerMesh,
+}
+
+impl WireframeMode {
+    fn next(self) -> Self {
+        match self {
+            WireframeMode::Off => WireframeMode::White,
+            WireframeMode::White => WireframeMode::PerMesh,
+            WireframeMode::PerMesh => WireframeMode::Off,
+        }
+    }
+}
+
+/// Wireframes over the scene, cycled with F, and the vertex normals and tangents of the mesh
+/// at the center of the screen, toggled with H.
+#[derive(Resource, Default)]
+pub struct MeshDebug {
+    pub wireframe: WireframeMode,
+    pub normals: bool,
+}
+
+pub struct MeshDebugPlugin;
+impl Plugin for MeshDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(WireframePlugin)
+            .init_resource::<MeshDebug>()
+            .add_systems(
+                Update,
+                (toggle_mesh_debug, color_wireframes, draw_normals).chain(),
+            );
+    }
+}
+
+fn toggle_mesh_debug(
+    input: Res<ButtonInput<KeyCode>>,
+    mut mesh_debug: ResMut<MeshDebug>,
+    mut config: ResMut<WireframeConfig>,
+    render_device: Res<RenderDevice>,
+) {
+    if input.just_pressed(KeyCode::KeyF) {
+        // WebGL and some mobile GPUs can't draw lines instead of triangles
+        if !render_device
+            .features()
+            .contains(WgpuFeatures::POLYGON_MODE_LINE)
+        {
+            warn!("Wireframes aren't supported by this GPU");
+            return;
+        }
+        mesh_debug.wireframe = mesh_debug.wireframe.next();
+        config.global = mesh_debug.wireframe != WireframeMode::Off;
+        config.default_color = Color::WHITE;
+        info!("Wireframe: {:?}", mesh_debug.wireframe);
+    }
+    if input.just_pressed(KeyCode::KeyH) {
+        mesh_debug.normals = !mesh_debug.normals;
+        info!(
+            "Normals and tangents at the center of the screen: {}",
+            if mesh_debug.normals { "on" } else { "off" }
+        );
+    }
+}
+
+fn color_wireframes(
+    mut commands: Commands,
+    mesh_debug: Res<MeshDebug>,
+    uncolored: Query<Entity, (With<Handle<Mesh>>, Without<WireframeColor>)>,
+    colored: Query<Entity, With<WireframeColor>>,
+) {
+    if mesh_debug.wireframe == WireframeMode::PerMesh {
+        for entity in &uncolored {
+            // Spread the hues so neighbouring entities stand apart
+            let hue = (entity.index() as f32 * 137.508) % 360.0;
+            commands.entity(entity).insert(WireframeColor {
+                color: Color::hsl(hue, 0.9, 0.6),
+            });
+        }
+    } else if mesh_debug.is_changed() {
+        for entity in &colored {
+            commands.entity(entity).remove::<WireframeColor>();
+        }
+    }
+}
+
+/// Draws the normals (blue), tangents (red), and bitangents (green) of the vertices around the
+/// point at the center of the screen, and outlines the triangle that was hit in yellow.
+fn draw_normals(
+    mesh_debug: Res<MeshDebug>,
+    camera: Query<&GlobalTransform, (With<Camera3d>, Without<ComparisonCamera>)>,
+    meshes: PickableMeshes,
+    mesh_assets: Res<Assets<Mesh>>,
+    mut gizmos: Gizmos,
+) {
+    if !mesh_debug.normals {
+        return;
+    }
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let origin = camera.translation();
+    let direction = camera.forward();
+
+    let Some(hit) = pick_mesh(origin, direction, &meshes, &mesh_assets) else {
+        return;
+    };
+    let Some((mesh, transform)) = meshes
+        .get(hit.entity)
+        .ok()
+        .and_then(|(_, handle, _, transform, _)| Some((mesh_assets.get(handle)?, transform)))
+    else {
+        return;
+    };
+    let triangle = hit.triangle;
+    let hit = origin + direction * hit.distance;
+    let Some(positions) = mesh
+        .attribute(Mesh::ATTRIBUTE_POSITION)
+        .and_then(VertexAttributeValues::as_float3)
+    else {
+        return;
+    };
+    let affine = transform.affine();
+    let corners = triangle.map(|i| affine.transform_point3(Vec3::from(positions[i])));
+    gizmos.linestrip(
+        [corners[0], corners[1], corners[2], corners[0]],
+        Color::YELLOW,
+    );
+
+    let normals = mesh
+        .attribute(Mesh::ATTRIBUTE_NORMAL)
+        .and_then(VertexAttributeValues::as_float3);
+    let tangents = match mesh.attribute(Mesh::ATTRIBUTE_TANGENT) {
+        Some(VertexAttributeValues::Float32x4(tangents)) => Some(tangents),
+        _ => None,
+    };
+    // Normals transform by the inverse transpose so they stay perpendicular under uneven scale
+    let normal_matrix = affine.matrix3.inverse().transpose();
+    let mut drawn = 0;
+    for (i, position) in positions.iter().enumerate() {
+        let position = affine.transform_point3(Vec3::from(*position));
+        if position.distance(hit) > NORMALS_RADIUS {
+            continue;
+        }
+        drawn += 1;
+        if drawn > MAX_NORMALS {
+            break;
+        }
+        let normal =
+            normals.map(|normals| (normal_matrix * Vec3::from(normals[i])).normalize_or_zero());
+        if let Some(normal) = normal {
+            gizmos.line(position, position + normal * NORMAL_LENGTH, Color::BLUE);
+        }
+        if let Some(tangent) = tangents.map(|tangents| tangents[i]) {
+            let direction = (affine.matrix3 * Vec3::from_slice(&tangent)).normalize_or_zero();
+            gizmos.line(position, position + direction * NORMAL_LENGTH, Color::RED);
+            if let Some(normal) = normal {
+                // The sign in w flips the bitangent for mirrored UVs
+                let bitangent = normal.cross(direction) * tangent[3];
+                gizmos.line(position, position + bitangent * NORMAL_LENGTH, Color::GREEN);
+            }
+        }
+    }
+}